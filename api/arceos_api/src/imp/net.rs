@@ -1,7 +1,11 @@
 use crate::io::AxPollState;
 use axerrno::AxResult;
-use axnet::{UdpSocket, TcpSocket};
+use axnet::{UdpSocket, TcpSocket, UnixDatagramSocket, UnixStreamSocket};
 use core::net::{IpAddr, SocketAddr};
+use core::time::Duration;
+
+pub use axnet::UnixSocketAddr as AxUnixSocketAddr;
+pub use axnet::{InterfaceInfo as AxNetInterfaceInfo, RouteInfo as AxNetRoute};
 
 /// A handle to a TCP socket.
 pub struct AxTcpSocketHandle(TcpSocket);
@@ -9,6 +13,12 @@ pub struct AxTcpSocketHandle(TcpSocket);
 /// A handle to a UDP socket.
 pub struct AxUdpSocketHandle(UdpSocket);
 
+/// A handle to a Unix domain stream socket.
+pub struct AxUnixStreamHandle(UnixStreamSocket);
+
+/// A handle to a Unix domain datagram socket.
+pub struct AxUnixDatagramHandle(UnixDatagramSocket);
+
 ////////////////////////////////////////////////////////////////////////////////
 // TCP socket
 ////////////////////////////////////////////////////////////////////////////////
@@ -64,6 +74,30 @@ pub fn ax_tcp_shutdown(socket: &AxTcpSocketHandle) -> AxResult {
     socket.0.shutdown()
 }
 
+pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> Option<Duration> {
+    socket.0.recv_timeout()
+}
+
+pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_send_timeout(timeout)
+}
+
+pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> Option<Duration> {
+    socket.0.send_timeout()
+}
+
+pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) {
+    socket.0.set_nodelay(nodelay)
+}
+
+pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> bool {
+    socket.0.nodelay()
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // UDP socket
 ////////////////////////////////////////////////////////////////////////////////
@@ -117,6 +151,189 @@ pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState> {
     socket.0.poll()
 }
 
+pub fn ax_udp_set_read_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_udp_read_timeout(socket: &AxUdpSocketHandle) -> Option<Duration> {
+    socket.0.recv_timeout()
+}
+
+pub fn ax_udp_set_write_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_send_timeout(timeout)
+}
+
+pub fn ax_udp_write_timeout(socket: &AxUdpSocketHandle) -> Option<Duration> {
+    socket.0.send_timeout()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Unix domain stream socket
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_unix_stream_socket() -> AxUnixStreamHandle {
+    AxUnixStreamHandle(UnixStreamSocket::new())
+}
+
+pub fn ax_unix_stream_pair() -> (AxUnixStreamHandle, AxUnixStreamHandle) {
+    let (socket1, socket2) = UnixStreamSocket::pair();
+    (AxUnixStreamHandle(socket1), AxUnixStreamHandle(socket2))
+}
+
+pub fn ax_unix_stream_local_addr(socket: &AxUnixStreamHandle) -> AxResult<AxUnixSocketAddr> {
+    socket.0.local_addr()
+}
+
+pub fn ax_unix_stream_peer_addr(socket: &AxUnixStreamHandle) -> AxResult<AxUnixSocketAddr> {
+    socket.0.peer_addr()
+}
+
+pub fn ax_unix_stream_set_nonblocking(socket: &AxUnixStreamHandle, nonblocking: bool) -> AxResult {
+    socket.0.set_nonblocking(nonblocking);
+    Ok(())
+}
+
+pub fn ax_unix_stream_bind(socket: &AxUnixStreamHandle, addr: AxUnixSocketAddr) -> AxResult {
+    socket.0.bind(addr)
+}
+
+pub fn ax_unix_stream_listen(socket: &AxUnixStreamHandle) -> AxResult {
+    socket.0.listen()
+}
+
+pub fn ax_unix_stream_accept(socket: &AxUnixStreamHandle) -> AxResult<AxUnixStreamHandle> {
+    socket.0.accept().map(AxUnixStreamHandle)
+}
+
+pub fn ax_unix_stream_connect(socket: &AxUnixStreamHandle, addr: AxUnixSocketAddr) -> AxResult {
+    socket.0.connect(addr)
+}
+
+pub fn ax_unix_stream_send(socket: &AxUnixStreamHandle, buf: &[u8]) -> AxResult<usize> {
+    socket.0.send(buf)
+}
+
+pub fn ax_unix_stream_recv(socket: &AxUnixStreamHandle, buf: &mut [u8]) -> AxResult<usize> {
+    socket.0.recv(buf)
+}
+
+pub fn ax_unix_stream_poll(socket: &AxUnixStreamHandle) -> AxResult<AxPollState> {
+    socket.0.poll()
+}
+
+pub fn ax_unix_stream_shutdown(socket: &AxUnixStreamHandle) -> AxResult {
+    socket.0.shutdown()
+}
+
+pub fn ax_unix_stream_set_read_timeout(
+    socket: &AxUnixStreamHandle,
+    timeout: Option<Duration>,
+) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_unix_stream_read_timeout(socket: &AxUnixStreamHandle) -> Option<Duration> {
+    socket.0.recv_timeout()
+}
+
+pub fn ax_unix_stream_set_write_timeout(
+    socket: &AxUnixStreamHandle,
+    timeout: Option<Duration>,
+) -> AxResult {
+    socket.0.set_send_timeout(timeout)
+}
+
+pub fn ax_unix_stream_write_timeout(socket: &AxUnixStreamHandle) -> Option<Duration> {
+    socket.0.send_timeout()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Unix domain datagram socket
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_unix_dgram_socket() -> AxUnixDatagramHandle {
+    AxUnixDatagramHandle(UnixDatagramSocket::new())
+}
+
+pub fn ax_unix_dgram_pair() -> (AxUnixDatagramHandle, AxUnixDatagramHandle) {
+    let (socket1, socket2) = UnixDatagramSocket::pair();
+    (AxUnixDatagramHandle(socket1), AxUnixDatagramHandle(socket2))
+}
+
+pub fn ax_unix_dgram_local_addr(socket: &AxUnixDatagramHandle) -> AxResult<AxUnixSocketAddr> {
+    socket.0.local_addr()
+}
+
+pub fn ax_unix_dgram_peer_addr(socket: &AxUnixDatagramHandle) -> AxResult<AxUnixSocketAddr> {
+    socket.0.peer_addr()
+}
+
+pub fn ax_unix_dgram_set_nonblocking(socket: &AxUnixDatagramHandle, nonblocking: bool) -> AxResult {
+    socket.0.set_nonblocking(nonblocking);
+    Ok(())
+}
+
+pub fn ax_unix_dgram_bind(socket: &AxUnixDatagramHandle, addr: AxUnixSocketAddr) -> AxResult {
+    socket.0.bind(addr)
+}
+
+pub fn ax_unix_dgram_connect(socket: &AxUnixDatagramHandle, addr: AxUnixSocketAddr) -> AxResult {
+    socket.0.connect(addr)
+}
+
+pub fn ax_unix_dgram_send_to(
+    socket: &AxUnixDatagramHandle,
+    buf: &[u8],
+    addr: AxUnixSocketAddr,
+) -> AxResult<usize> {
+    socket.0.send_to(buf, addr)
+}
+
+pub fn ax_unix_dgram_recv_from(
+    socket: &AxUnixDatagramHandle,
+    buf: &mut [u8],
+) -> AxResult<(usize, AxUnixSocketAddr)> {
+    socket.0.recv_from(buf)
+}
+
+pub fn ax_unix_dgram_send(socket: &AxUnixDatagramHandle, buf: &[u8]) -> AxResult<usize> {
+    socket.0.send(buf)
+}
+
+pub fn ax_unix_dgram_recv(socket: &AxUnixDatagramHandle, buf: &mut [u8]) -> AxResult<usize> {
+    socket.0.recv(buf)
+}
+
+pub fn ax_unix_dgram_poll(socket: &AxUnixDatagramHandle) -> AxResult<AxPollState> {
+    socket.0.poll()
+}
+
+pub fn ax_unix_dgram_shutdown(socket: &AxUnixDatagramHandle) -> AxResult {
+    socket.0.shutdown()
+}
+
+pub fn ax_unix_dgram_set_read_timeout(
+    socket: &AxUnixDatagramHandle,
+    timeout: Option<Duration>,
+) -> AxResult {
+    socket.0.set_recv_timeout(timeout)
+}
+
+pub fn ax_unix_dgram_read_timeout(socket: &AxUnixDatagramHandle) -> Option<Duration> {
+    socket.0.recv_timeout()
+}
+
+pub fn ax_unix_dgram_set_write_timeout(
+    socket: &AxUnixDatagramHandle,
+    timeout: Option<Duration>,
+) -> AxResult {
+    socket.0.set_send_timeout(timeout)
+}
+
+pub fn ax_unix_dgram_write_timeout(socket: &AxUnixDatagramHandle) -> Option<Duration> {
+    socket.0.send_timeout()
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Miscellaneous
 ////////////////////////////////////////////////////////////////////////////////
@@ -129,3 +346,23 @@ pub fn ax_poll_interfaces() -> AxResult {
     axnet::poll_interfaces();
     Ok(())
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// Interfaces and routes
+////////////////////////////////////////////////////////////////////////////////
+
+pub fn ax_net_interfaces() -> alloc::vec::Vec<AxNetInterfaceInfo> {
+    axnet::interfaces()
+}
+
+pub fn ax_net_routes() -> alloc::vec::Vec<AxNetRoute> {
+    axnet::routes()
+}
+
+pub fn ax_net_add_route(dest: IpAddr, prefix_len: u8, gateway: IpAddr) -> AxResult {
+    axnet::add_route(dest, prefix_len, gateway)
+}
+
+pub fn ax_net_del_route(dest: IpAddr, prefix_len: u8) -> AxResult {
+    axnet::del_route(dest, prefix_len)
+}
@@ -16,16 +16,32 @@ pub fn ax_yield_now() {
     }
 }
 
+pub fn ax_terminate() -> ! {
+    #[cfg(feature = "fs")]
+    axfs::sync_filesystems().ok();
+    axhal::misc::terminate()
+}
+
 pub fn ax_exit(_exit_code: i32) -> ! {
     #[cfg(feature = "multitask")]
-    axtask::exit(_exit_code);
+    {
+        // only the exit of the main task terminates the system
+        #[cfg(feature = "fs")]
+        if axtask::current().is_init() {
+            axfs::sync_filesystems().ok();
+        }
+        axtask::exit(_exit_code);
+    }
     #[cfg(not(feature = "multitask"))]
-    axhal::misc::terminate();
+    ax_terminate();
 }
 
 cfg_task! {
+    use core::sync::atomic::AtomicU32;
     use core::time::Duration;
 
+    pub use axtask::AxCpuMask;
+
     /// A handle to a task.
     pub struct AxTaskHandle {
         inner: axtask::AxTaskRef,
@@ -67,6 +83,25 @@ cfg_task! {
         }
     }
 
+    pub fn ax_spawn_with_affinity<F>(
+        f: F,
+        name: alloc::string::String,
+        stack_size: usize,
+        cpumask: AxCpuMask,
+    ) -> crate::AxResult<AxTaskHandle>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if cpumask.is_empty() {
+            return axerrno::ax_err!(InvalidInput, "ax_spawn_with_affinity: empty CPU mask");
+        }
+        let inner = axtask::spawn_raw_with_affinity(f, name, stack_size, cpumask);
+        Ok(AxTaskHandle {
+            id: inner.id().as_u64(),
+            inner,
+        })
+    }
+
     pub fn ax_wait_for_exit(task: AxTaskHandle) -> Option<i32> {
         task.inner.join()
     }
@@ -82,6 +117,17 @@ cfg_task! {
         }
     }
 
+    pub fn ax_set_current_affinity(cpumask: AxCpuMask) -> crate::AxResult {
+        if axtask::set_current_affinity(cpumask) {
+            Ok(())
+        } else {
+            axerrno::ax_err!(
+                InvalidInput,
+                "ax_set_current_affinity: empty CPU mask"
+            )
+        }
+    }
+
     pub fn ax_wait_queue_wait(
         wq: &AxWaitQueueHandle,
         until_condition: impl Fn() -> bool,
@@ -92,13 +138,40 @@ cfg_task! {
             return wq.0.wait_timeout_until(dur, until_condition);
         }
 
-        if timeout.is_some() {
-            axlog::warn!("ax_wait_queue_wait: the `timeout` argument is ignored without the `irq` feature");
+        #[cfg(not(feature = "irq"))]
+        if let Some(dur) = timeout {
+            // no timers to wake us up, poll the condition instead
+            let deadline = axhal::time::current_time() + dur;
+            while !until_condition() {
+                if axhal::time::current_time() >= deadline {
+                    return true;
+                }
+                axtask::yield_now();
+            }
+            return false;
         }
+
         wq.0.wait_until(until_condition);
         false
     }
 
+    pub fn ax_futex_wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+        let key = axtask::FutexKey::kernel(futex);
+        if let Some(dur) = timeout {
+            let deadline = axhal::time::current_time() + dur;
+            axtask::futex_wait_timeout(key, futex, expected, deadline, false)
+                == axtask::FutexWaitResult::TimedOut
+        } else {
+            axtask::futex_wait(key, futex, expected, false);
+            false
+        }
+    }
+
+    pub fn ax_futex_wake(futex: &AtomicU32, count: u32) {
+        let count = if count == u32::MAX { usize::MAX } else { count as usize };
+        axtask::futex_wake(axtask::FutexKey::kernel(futex), count);
+    }
+
     pub fn ax_wait_queue_wake(wq: &AxWaitQueueHandle, count: u32) {
         if count == u32::MAX {
             wq.0.notify_all(true);
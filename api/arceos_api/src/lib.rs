@@ -85,6 +85,7 @@ pub mod task {
         @cfg "multitask";
         pub type AxTaskHandle;
         pub type AxWaitQueueHandle;
+        pub type AxCpuMask;
     }
 
     define_api! {
@@ -114,15 +115,32 @@ pub mod task {
             name: alloc::string::String,
             stack_size: usize
         ) -> AxTaskHandle;
+        /// Spawns a new task that only runs on CPUs in `cpumask`.
+        ///
+        /// Returns [`AxError::InvalidInput`] if `cpumask` is empty.
+        ///
+        /// [`AxError::InvalidInput`]: crate::AxError::InvalidInput
+        pub fn ax_spawn_with_affinity(
+            f: impl FnOnce() + Send + 'static,
+            name: alloc::string::String,
+            stack_size: usize,
+            cpumask: AxCpuMask,
+        ) -> crate::AxResult<AxTaskHandle>;
         /// Waits for the given task to exit, and returns its exit code (the
         /// argument of [`ax_exit`]).
         pub fn ax_wait_for_exit(task: AxTaskHandle) -> Option<i32>;
         /// Sets the priority of the current task.
         pub fn ax_set_current_priority(prio: isize) -> crate::AxResult;
+        /// Sets the CPU affinity mask of the current task, it's moved to an
+        /// allowed CPU immediately if necessary.
+        pub fn ax_set_current_affinity(cpumask: AxCpuMask) -> crate::AxResult;
 
         /// Blocks the current task and put it into the wait queue, until the
         /// given condition becomes true, or the the given duration has elapsed
-        /// (if specified).
+        /// (if specified). Returns `true` if it timed out.
+        ///
+        /// If the `irq` feature is not enabled, the timed wait keeps yielding
+        /// and polling the condition until the deadline.
         pub fn ax_wait_queue_wait(
             wq: &AxWaitQueueHandle,
             until_condition: impl Fn() -> bool,
@@ -133,6 +151,27 @@ pub mod task {
         /// The maximum number of tasks to wake up is specified by `count`. If
         /// `count` is `u32::MAX`, it will wake up all tasks in the wait queue.
         pub fn ax_wait_queue_wake(wq: &AxWaitQueueHandle, count: u32);
+
+        /// Blocks the current task if the futex word `futex` contains
+        /// `expected`, until it's woken up by [`ax_futex_wake`], or the given
+        /// duration has elapsed (if specified). Returns `true` if it timed
+        /// out.
+        ///
+        /// Spurious wakeups are possible, so the caller should check its
+        /// condition in a loop.
+        ///
+        /// If the `irq` feature is not enabled, the timed wait keeps yielding
+        /// and polling the futex word until the deadline.
+        pub fn ax_futex_wait(
+            futex: &core::sync::atomic::AtomicU32,
+            expected: u32,
+            timeout: Option<core::time::Duration>,
+        ) -> bool;
+        /// Wakes up one or more tasks blocked on the futex word `futex`.
+        ///
+        /// The maximum number of tasks to wake up is specified by `count`. If
+        /// `count` is `u32::MAX`, it will wake up all tasks blocked on it.
+        pub fn ax_futex_wake(futex: &core::sync::atomic::AtomicU32, count: u32);
     }
 }
 
@@ -150,6 +189,7 @@ pub mod fs {
         pub type AxFilePerm;
         pub type AxDirEntry;
         pub type AxSeekFrom;
+        pub type AxFileSystem;
         #[cfg(feature = "myfs")]
         pub type AxDisk;
         #[cfg(feature = "myfs")]
@@ -213,6 +253,21 @@ pub mod fs {
         ///
         /// It will delete the original file if `old` already exists.
         pub fn ax_rename(old: &str, new: &str) -> AxResult;
+        /// Creates a new symbolic link `link` which points to `original`.
+        pub fn ax_symlink(original: &str, link: &str) -> AxResult;
+        /// Returns the target path of the symbolic link.
+        pub fn ax_read_link(path: &str) -> AxResult<alloc::string::String>;
+        /// Returns attributes of the file at the given path, without following
+        /// the symbolic link.
+        pub fn ax_symlink_attr(path: &str) -> AxResult<AxFileAttr>;
+
+        /// Mounts the filesystem `fs` at the given path.
+        ///
+        /// The mount point is created if it does not exist, and it can be
+        /// inside another mounted filesystem.
+        pub fn ax_mount(path: &str, fs: AxFileSystem) -> AxResult;
+        /// Unmounts the filesystem mounted at the given path.
+        pub fn ax_umount(path: &str) -> AxResult;
 
         /// Returns the current working directory.
         pub fn ax_current_dir() -> AxResult<alloc::string::String>;
@@ -221,7 +276,7 @@ pub mod fs {
     }
 }
 
-/// Networking primitives for TCP/UDP communication.
+/// Networking primitives for TCP/UDP communication, and Unix domain sockets.
 pub mod net {
     use crate::{io::AxPollState, AxResult};
     use core::net::{IpAddr, SocketAddr};
@@ -230,6 +285,11 @@ pub mod net {
         @cfg "net";
         pub type AxTcpSocketHandle;
         pub type AxUdpSocketHandle;
+        pub type AxNetInterfaceInfo;
+        pub type AxNetRoute;
+        pub type AxUnixSocketAddr;
+        pub type AxUnixStreamHandle;
+        pub type AxUnixDatagramHandle;
     }
 
     define_api! {
@@ -267,6 +327,18 @@ pub mod net {
         pub fn ax_tcp_poll(socket: &AxTcpSocketHandle) -> AxResult<AxPollState>;
         /// Closes the connection on the TCP socket.
         pub fn ax_tcp_shutdown(socket: &AxTcpSocketHandle) -> AxResult;
+        /// Sets the read timeout of the TCP socket. `None` means blocking forever.
+        pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the read timeout of the TCP socket.
+        pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> Option<core::time::Duration>;
+        /// Sets the write timeout of the TCP socket. `None` means blocking forever.
+        pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the write timeout of the TCP socket.
+        pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> Option<core::time::Duration>;
+        /// Enables or disables the Nagle's algorithm (`TCP_NODELAY`) on the TCP socket.
+        pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool);
+        /// Returns whether the Nagle's algorithm is disabled on the TCP socket.
+        pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> bool;
 
         // UDP socket
 
@@ -303,6 +375,103 @@ pub mod net {
         pub fn ax_udp_recv(socket: &AxUdpSocketHandle, buf: &mut [u8]) -> AxResult<usize>;
         /// Returns whether the UDP socket is readable or writable.
         pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState>;
+        /// Sets the read timeout of the UDP socket. `None` means blocking forever.
+        pub fn ax_udp_set_read_timeout(socket: &AxUdpSocketHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the read timeout of the UDP socket.
+        pub fn ax_udp_read_timeout(socket: &AxUdpSocketHandle) -> Option<core::time::Duration>;
+        /// Sets the write timeout of the UDP socket. `None` means blocking forever.
+        pub fn ax_udp_set_write_timeout(socket: &AxUdpSocketHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the write timeout of the UDP socket.
+        pub fn ax_udp_write_timeout(socket: &AxUdpSocketHandle) -> Option<core::time::Duration>;
+
+        // Unix domain stream socket
+
+        /// Creates a new Unix domain stream socket.
+        pub fn ax_unix_stream_socket() -> AxUnixStreamHandle;
+        /// Creates a pair of connected unnamed Unix domain stream sockets.
+        pub fn ax_unix_stream_pair() -> (AxUnixStreamHandle, AxUnixStreamHandle);
+        /// Returns the local address of the Unix domain stream socket.
+        pub fn ax_unix_stream_local_addr(socket: &AxUnixStreamHandle) -> AxResult<AxUnixSocketAddr>;
+        /// Returns the address of the peer of the Unix domain stream socket.
+        pub fn ax_unix_stream_peer_addr(socket: &AxUnixStreamHandle) -> AxResult<AxUnixSocketAddr>;
+        /// Moves this Unix domain stream socket into or out of nonblocking mode.
+        pub fn ax_unix_stream_set_nonblocking(socket: &AxUnixStreamHandle, nonblocking: bool) -> AxResult;
+
+        /// Binds the Unix domain stream socket to the given address.
+        pub fn ax_unix_stream_bind(socket: &AxUnixStreamHandle, addr: AxUnixSocketAddr) -> AxResult;
+        /// Starts listening on the bound address.
+        pub fn ax_unix_stream_listen(socket: &AxUnixStreamHandle) -> AxResult;
+        /// Accepts a new connection on the Unix domain stream socket.
+        ///
+        /// This function will block the calling thread until a new connection
+        /// is established. When established, a new socket is returned.
+        pub fn ax_unix_stream_accept(socket: &AxUnixStreamHandle) -> AxResult<AxUnixStreamHandle>;
+        /// Connects the Unix domain stream socket to the given address.
+        pub fn ax_unix_stream_connect(socket: &AxUnixStreamHandle, addr: AxUnixSocketAddr) -> AxResult;
+
+        /// Transmits data in the given buffer on the Unix domain stream socket.
+        pub fn ax_unix_stream_send(socket: &AxUnixStreamHandle, buf: &[u8]) -> AxResult<usize>;
+        /// Receives data on the Unix domain stream socket, and stores it in
+        /// the given buffer. On success, returns the number of bytes read.
+        pub fn ax_unix_stream_recv(socket: &AxUnixStreamHandle, buf: &mut [u8]) -> AxResult<usize>;
+        /// Returns whether the Unix domain stream socket is readable or writable.
+        pub fn ax_unix_stream_poll(socket: &AxUnixStreamHandle) -> AxResult<AxPollState>;
+        /// Shuts down the connection on the Unix domain stream socket.
+        pub fn ax_unix_stream_shutdown(socket: &AxUnixStreamHandle) -> AxResult;
+        /// Sets the read timeout of the Unix domain stream socket. `None` means
+        /// blocking forever.
+        pub fn ax_unix_stream_set_read_timeout(socket: &AxUnixStreamHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the read timeout of the Unix domain stream socket.
+        pub fn ax_unix_stream_read_timeout(socket: &AxUnixStreamHandle) -> Option<core::time::Duration>;
+        /// Sets the write timeout of the Unix domain stream socket. `None`
+        /// means blocking forever.
+        pub fn ax_unix_stream_set_write_timeout(socket: &AxUnixStreamHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the write timeout of the Unix domain stream socket.
+        pub fn ax_unix_stream_write_timeout(socket: &AxUnixStreamHandle) -> Option<core::time::Duration>;
+
+        // Unix domain datagram socket
+
+        /// Creates a new Unix domain datagram socket.
+        pub fn ax_unix_dgram_socket() -> AxUnixDatagramHandle;
+        /// Creates a pair of connected unnamed Unix domain datagram sockets.
+        pub fn ax_unix_dgram_pair() -> (AxUnixDatagramHandle, AxUnixDatagramHandle);
+        /// Returns the local address of the Unix domain datagram socket.
+        pub fn ax_unix_dgram_local_addr(socket: &AxUnixDatagramHandle) -> AxResult<AxUnixSocketAddr>;
+        /// Returns the address of the peer of the Unix domain datagram socket.
+        pub fn ax_unix_dgram_peer_addr(socket: &AxUnixDatagramHandle) -> AxResult<AxUnixSocketAddr>;
+        /// Moves this Unix domain datagram socket into or out of nonblocking mode.
+        pub fn ax_unix_dgram_set_nonblocking(socket: &AxUnixDatagramHandle, nonblocking: bool) -> AxResult;
+
+        /// Binds the Unix domain datagram socket to the given address.
+        pub fn ax_unix_dgram_bind(socket: &AxUnixDatagramHandle, addr: AxUnixSocketAddr) -> AxResult;
+        /// Connects the Unix domain datagram socket to the given address, so
+        /// that `send` sends datagrams to it.
+        pub fn ax_unix_dgram_connect(socket: &AxUnixDatagramHandle, addr: AxUnixSocketAddr) -> AxResult;
+        /// Sends a datagram on the Unix domain datagram socket to the given
+        /// address.
+        pub fn ax_unix_dgram_send_to(socket: &AxUnixDatagramHandle, buf: &[u8], addr: AxUnixSocketAddr) -> AxResult<usize>;
+        /// Receives a datagram on the Unix domain datagram socket, and returns
+        /// the number of bytes read and the source address.
+        pub fn ax_unix_dgram_recv_from(socket: &AxUnixDatagramHandle, buf: &mut [u8]) -> AxResult<(usize, AxUnixSocketAddr)>;
+        /// Sends a datagram on the Unix domain datagram socket to the
+        /// connected address.
+        pub fn ax_unix_dgram_send(socket: &AxUnixDatagramHandle, buf: &[u8]) -> AxResult<usize>;
+        /// Receives a datagram on the Unix domain datagram socket.
+        pub fn ax_unix_dgram_recv(socket: &AxUnixDatagramHandle, buf: &mut [u8]) -> AxResult<usize>;
+        /// Returns whether the Unix domain datagram socket is readable or writable.
+        pub fn ax_unix_dgram_poll(socket: &AxUnixDatagramHandle) -> AxResult<AxPollState>;
+        /// Shuts down receiving on the Unix domain datagram socket.
+        pub fn ax_unix_dgram_shutdown(socket: &AxUnixDatagramHandle) -> AxResult;
+        /// Sets the read timeout of the Unix domain datagram socket. `None`
+        /// means blocking forever.
+        pub fn ax_unix_dgram_set_read_timeout(socket: &AxUnixDatagramHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the read timeout of the Unix domain datagram socket.
+        pub fn ax_unix_dgram_read_timeout(socket: &AxUnixDatagramHandle) -> Option<core::time::Duration>;
+        /// Sets the write timeout of the Unix domain datagram socket. `None`
+        /// means blocking forever.
+        pub fn ax_unix_dgram_set_write_timeout(socket: &AxUnixDatagramHandle, timeout: Option<core::time::Duration>) -> AxResult;
+        /// Returns the write timeout of the Unix domain datagram socket.
+        pub fn ax_unix_dgram_write_timeout(socket: &AxUnixDatagramHandle) -> Option<core::time::Duration>;
 
         // Miscellaneous
 
@@ -313,6 +482,18 @@ pub mod net {
         /// It may receive packets from the NIC and process them, and transmit queued
         /// packets to the NIC.
         pub fn ax_poll_interfaces() -> AxResult;
+
+        // Interfaces and routes
+
+        /// Returns all network interfaces and their addresses.
+        pub fn ax_net_interfaces() -> alloc::vec::Vec<AxNetInterfaceInfo>;
+        /// Returns all entries of the routing table.
+        pub fn ax_net_routes() -> alloc::vec::Vec<AxNetRoute>;
+        /// Adds a route to the network `dest/prefix_len` via `gateway`. The
+        /// egress interface is the one directly connected to `gateway`.
+        pub fn ax_net_add_route(dest: IpAddr, prefix_len: u8, gateway: IpAddr) -> AxResult;
+        /// Removes the route to the network `dest/prefix_len`.
+        pub fn ax_net_del_route(dest: IpAddr, prefix_len: u8) -> AxResult;
     }
 }
 
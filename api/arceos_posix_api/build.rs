@@ -2,16 +2,8 @@ fn main() {
     use std::io::Write;
 
     fn gen_pthread_mutex(out_file: &str) -> std::io::Result<()> {
-        // TODO: generate size and initial content automatically.
-        let (mutex_size, mutex_init) = if cfg!(feature = "multitask") {
-            if cfg!(feature = "smp") {
-                (6, "{0, 8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 6]>(axsync::Mutex::new(()))
-            } else {
-                (5, "{8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 5]>(axsync::Mutex::new(()))
-            }
-        } else {
-            (1, "{0}")
-        };
+        // The mutex is an unlocked futex word, see `PthreadMutex`.
+        let (mutex_size, mutex_init) = (1, "{0}");
 
         let mut output = Vec::new();
         writeln!(
@@ -48,25 +40,48 @@ typedef struct {{
             "pthread_attr_t",
             "pthread_mutex_t",
             "pthread_mutexattr_t",
+            "pthread_cond_t",
+            "pthread_condattr_t",
+            "pthread_rwlock_t",
+            "pthread_rwlockattr_t",
+            "sem_t",
             "epoll_event",
+            "pollfd",
+            "nfds_t",
             "iovec",
             "clockid_t",
             "rlimit",
             "aibuf",
+            "cpu_set_t",
+            "sigaction",
+            "sigset_t",
+            "linger",
         ];
         let allow_vars = [
             "O_.*",
             "AF_.*",
             "SOCK_.*",
             "IPPROTO_.*",
+            "SOL_.*",
+            "SO_.*",
+            "TCP_.*",
             "FD_.*",
             "F_.*",
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
+            "POLL.*",
             "RLIMIT_.*",
             "EAI_.*",
             "MAXADDRS",
+            "SEM_.*",
+            "PROT_.*",
+            "MAP_.*",
+            "MS_.*",
+            "AT_.*",
+            "SIG.*",
+            "SA_.*",
+            "SI_.*",
         ];
 
         #[derive(Debug)]
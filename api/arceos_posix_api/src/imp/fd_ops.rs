@@ -1,7 +1,10 @@
 use alloc::sync::Arc;
 use core::ffi::c_int;
+#[cfg(feature = "epoll")]
+use core::task::Waker;
 
 use axerrno::{LinuxError, LinuxResult};
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 use axio::PollState;
 use flatten_objects::FlattenObjects;
 use spin::RwLock;
@@ -16,37 +19,72 @@ pub trait FileLike: Send + Sync {
     fn write(&self, buf: &[u8]) -> LinuxResult<usize>;
     fn stat(&self) -> LinuxResult<ctypes::stat>;
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+
+    /// Registers `waker` to be woken up once the result of [`poll`] may
+    /// change. The waker is woken up at most once, and should be registered
+    /// again after that.
+    ///
+    /// Returns `false` if the file does not support readiness notifications,
+    /// it should be polled instead.
+    ///
+    /// [`poll`]: FileLike::poll
+    #[cfg(feature = "epoll")]
+    fn register_waker(&self, _waker: &Waker) -> bool {
+        false
+    }
+}
+
+pub type FdTable = RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>>;
+
+pub fn new_fd_table() -> FdTable {
+    let mut fd_table = FlattenObjects::new();
+    fd_table.add_at(0, Arc::new(stdin()) as _).unwrap(); // stdin
+    fd_table.add_at(1, Arc::new(stdout()) as _).unwrap(); // stdout
+    fd_table.add_at(2, Arc::new(stdout()) as _).unwrap(); // stderr
+    RwLock::new(fd_table)
+}
+
+/// Creates a new file descriptor table with the same files as `table`.
+#[cfg(feature = "uspace")]
+pub fn fork_fd_table(table: &FdTable) -> FdTable {
+    let table = table.read();
+    let mut new_table = FlattenObjects::new();
+    for fd in 0..AX_FILE_LIMIT {
+        if let Some(f) = table.get(fd) {
+            new_table.add_at(fd, f.clone());
+        }
+    }
+    RwLock::new(new_table)
 }
 
 lazy_static::lazy_static! {
-    static ref FD_TABLE: RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>> = {
-        let mut fd_table = FlattenObjects::new();
-        fd_table.add_at(0, Arc::new(stdin()) as _).unwrap(); // stdin
-        fd_table.add_at(1, Arc::new(stdout()) as _).unwrap(); // stdout
-        fd_table.add_at(2, Arc::new(stdout()) as _).unwrap(); // stderr
-        RwLock::new(fd_table)
-    };
+    static ref FD_TABLE: FdTable = new_fd_table();
+}
+
+/// Runs `f` with the file descriptor table of the current process, or the
+/// global one if the current task is not a user process.
+fn with_fd_table<R>(f: impl FnOnce(&FdTable) -> R) -> R {
+    #[cfg(feature = "uspace")]
+    if let Some(data) = super::process::current_data() {
+        return f(&data.fd_table);
+    }
+    f(&FD_TABLE)
 }
 
 pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
-    FD_TABLE
-        .read()
-        .get(fd as usize)
-        .cloned()
-        .ok_or(LinuxError::EBADF)
+    with_fd_table(|table| table.read().get(fd as usize).cloned()).ok_or(LinuxError::EBADF)
 }
 
 pub fn add_file_like(f: Arc<dyn FileLike>) -> LinuxResult<c_int> {
-    Ok(FD_TABLE.write().add(f).ok_or(LinuxError::EMFILE)? as c_int)
+    let fd = with_fd_table(|table| table.write().add(f)).ok_or(LinuxError::EMFILE)?;
+    Ok(fd as c_int)
 }
 
 pub fn close_file_like(fd: c_int) -> LinuxResult {
-    let f = FD_TABLE
-        .write()
-        .remove(fd as usize)
-        .ok_or(LinuxError::EBADF)?;
+    let f = with_fd_table(|table| table.write().remove(fd as usize)).ok_or(LinuxError::EBADF)?;
     drop(f);
     Ok(())
 }
@@ -91,9 +129,7 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
         }
 
         let f = get_file_like(old_fd)?;
-        FD_TABLE
-            .write()
-            .add_at(new_fd as usize, f)
+        with_fd_table(|table| table.write().add_at(new_fd as usize, f))
             .ok_or(LinuxError::EMFILE)?;
 
         Ok(new_fd)
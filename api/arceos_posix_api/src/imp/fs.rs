@@ -2,8 +2,10 @@ use alloc::sync::Arc;
 use core::ffi::{c_char, c_int};
 
 use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
-use axio::{PollState, SeekFrom};
+use axfs::fops::{FileAttr, OpenOptions};
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
+use axio::PollState;
+use axio::SeekFrom;
 use axsync::Mutex;
 
 use super::fd_ops::{get_file_like, FileLike};
@@ -24,7 +26,7 @@ impl File {
         super::fd_ops::add_file_like(Arc::new(self))
     }
 
-    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+    pub(crate) fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
         let f = super::fd_ops::get_file_like(fd)?;
         f.into_any()
             .downcast::<Self>()
@@ -42,31 +44,19 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        Ok(attr_to_stat(&self.inner.lock().get_attr()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
         self
     }
 
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     fn poll(&self) -> LinuxResult<PollState> {
         Ok(PollState {
             readable: true,
             writable: true,
+            hangup: false,
         })
     }
 
@@ -75,6 +65,35 @@ impl FileLike for File {
     }
 }
 
+#[cfg(feature = "mmap")]
+impl axmm::MmapFile for File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> axerrno::AxResult<usize> {
+        self.inner.lock().read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> axerrno::AxResult<usize> {
+        self.inner.lock().write_at(offset, buf)
+    }
+}
+
+/// Convert file attributes to [`ctypes::stat`].
+fn attr_to_stat(metadata: &FileAttr) -> ctypes::stat {
+    let ty = metadata.file_type() as u8;
+    let perm = metadata.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode,
+        st_uid: 1000,
+        st_gid: 1000,
+        st_size: metadata.size() as _,
+        st_blocks: metadata.blocks() as _,
+        st_blksize: 512,
+        ..Default::default()
+    }
+}
+
 /// Convert open flags to [`OpenOptions`].
 fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
@@ -177,11 +196,54 @@ pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        let attr = axfs::fops::symlink_attr(path?)?;
+        unsafe { *buf = attr_to_stat(&attr) };
+        Ok(0)
+    })
+}
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if success.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_str(target)?;
+        let linkpath = char_ptr_to_str(linkpath)?;
+        debug!(
+            "sys_symlink <= target: {:?}, linkpath: {:?}",
+            target, linkpath
+        );
+        axfs::api::symlink(target, linkpath)?;
         Ok(0)
     })
 }
 
+/// Read the target of the symbolic link `path` into `buf`.
+///
+/// The content is truncated if `buf` is too small, and no NUL terminator is
+/// appended. Return the number of bytes placed in `buf`.
+pub unsafe fn sys_readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsiz: usize,
+) -> ctypes::ssize_t {
+    let path = char_ptr_to_str(path);
+    debug!("sys_readlink <= {:?} {:#x} {}", path, buf as usize, bufsiz);
+    syscall_body!(sys_readlink, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        if bufsiz == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let target = axfs::api::read_link(path?)?;
+        let len = target.len().min(bufsiz);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len)
+    })
+}
+
 /// Get the path of the current directory.
 pub fn sys_getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
     debug!("sys_getcwd <= {:#x} {}", buf as usize, size);
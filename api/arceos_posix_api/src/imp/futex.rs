@@ -0,0 +1,135 @@
+use core::ffi::c_int;
+use core::sync::atomic::AtomicU32;
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{FutexKey, FutexWaitResult};
+
+use crate::ctypes;
+
+pub(crate) const FUTEX_WAIT: c_int = 0;
+const FUTEX_WAKE: c_int = 1;
+const FUTEX_REQUEUE: c_int = 3;
+const FUTEX_CMP_REQUEUE: c_int = 4;
+
+const FUTEX_PRIVATE_FLAG: c_int = 128;
+const FUTEX_CLOCK_REALTIME: c_int = 256;
+pub(crate) const FUTEX_CMD_MASK: c_int = !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+
+/// Returns the futex word at `uaddr` and its key.
+///
+/// The futexes of user processes are keyed by their address spaces, so the
+/// same address in different processes refers to different futexes. The
+/// futex word of user processes must be in readable user memory, and it's
+/// populated here, as it's read with locks held when waiting.
+fn futex_word<'a>(uaddr: *const u32) -> LinuxResult<(&'a AtomicU32, FutexKey)> {
+    if uaddr.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    if uaddr as usize % core::mem::align_of::<AtomicU32>() != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    #[cfg(feature = "uspace")]
+    let space = match axprocess::current_aspace() {
+        Some(aspace) => {
+            crate::syscall::uaccess::check_ptr(uaddr)?;
+            let root = aspace.lock().page_table_root();
+            root.as_usize()
+        }
+        None => 0,
+    };
+    #[cfg(not(feature = "uspace"))]
+    let space = 0;
+    let word = unsafe { &*(uaddr as *const AtomicU32) };
+    Ok((word, FutexKey::new(space, uaddr as usize)))
+}
+
+/// Wait on, or wake up the tasks waiting on the futex word at `uaddr`.
+///
+/// The supported operations are:
+///
+/// - `FUTEX_WAIT`: blocks if the futex word contains `val`, until it's woken
+///   up, or the relative `timeout` (if not NULL) has elapsed.
+/// - `FUTEX_WAKE`: wakes up at most `val` waiters, returns the number of
+///   waiters woken up.
+/// - `FUTEX_REQUEUE`: wakes up at most `val` waiters, and moves at most
+///   `val2` (passed in `timeout`) of the remaining ones to the futex at
+///   `uaddr2`. Returns the number of waiters woken up or requeued.
+/// - `FUTEX_CMP_REQUEUE`: like `FUTEX_REQUEUE`, but fails with `EAGAIN` if
+///   the futex word does not contain `val3`.
+///
+/// All futexes are private to the process, so `FUTEX_PRIVATE_FLAG` makes no
+/// difference.
+pub unsafe fn sys_futex(
+    uaddr: *mut u32,
+    op: c_int,
+    val: u32,
+    timeout: *const ctypes::timespec,
+    uaddr2: *mut u32,
+    val3: u32,
+) -> c_int {
+    debug!(
+        "sys_futex <= {:#x} {} {} {:#x} {:#x} {}",
+        uaddr as usize, op, val, timeout as usize, uaddr2 as usize, val3
+    );
+    syscall_body!(sys_futex, {
+        if op & FUTEX_CLOCK_REALTIME != 0 {
+            return Err(LinuxError::ENOSYS);
+        }
+        let (word, key) = futex_word(uaddr)?;
+        let cmd = op & FUTEX_CMD_MASK;
+        match cmd {
+            FUTEX_WAIT => {
+                let res = if timeout.is_null() {
+                    axtask::futex_wait(key, word, val, true)
+                } else {
+                    let ts = unsafe { *timeout };
+                    if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec > 999999999 {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    let deadline = axhal::time::current_time() + Duration::from(ts);
+                    axtask::futex_wait_timeout(key, word, val, deadline, true)
+                };
+                match res {
+                    FutexWaitResult::Woken => Ok(0),
+                    FutexWaitResult::Mismatch => Err(LinuxError::EAGAIN),
+                    FutexWaitResult::TimedOut => Err(LinuxError::ETIMEDOUT),
+                    FutexWaitResult::Interrupted => Err(LinuxError::EINTR),
+                }
+            }
+            FUTEX_WAKE => Ok(axtask::futex_wake(key, val as usize)),
+            FUTEX_REQUEUE | FUTEX_CMP_REQUEUE => {
+                let (_, key2) = futex_word(uaddr2)?;
+                let requeue_count = timeout as usize as u32 as usize;
+                let cmp = (cmd == FUTEX_CMP_REQUEUE).then_some((word, val3));
+                axtask::futex_requeue(key, val as usize, key2, requeue_count, cmp)
+                    .ok_or(LinuxError::EAGAIN)
+            }
+            _ => Err(LinuxError::ENOSYS),
+        }
+    })
+}
+
+/// Blocks the current task if the futex word contains `expected`, until it's
+/// woken up or the timeout has elapsed. Returns `true` if it timed out.
+///
+/// Unlike `FUTEX_WAIT`, it's not interrupted by signals.
+pub(crate) fn futex_wait(word: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+    let key = FutexKey::kernel(word);
+    match timeout {
+        Some(dur) => {
+            let deadline = axhal::time::current_time() + dur;
+            axtask::futex_wait_timeout(key, word, expected, deadline, false)
+                == FutexWaitResult::TimedOut
+        }
+        None => {
+            axtask::futex_wait(key, word, expected, false);
+            false
+        }
+    }
+}
+
+/// Wakes up at most `count` tasks blocked on the futex word.
+pub(crate) fn futex_wake(word: &AtomicU32, count: usize) {
+    axtask::futex_wake(FutexKey::kernel(word), count);
+}
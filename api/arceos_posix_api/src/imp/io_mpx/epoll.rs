@@ -1,21 +1,88 @@
 //! `epoll` implementation.
 //!
-//! TODO: do not support `EPOLLET` flag
+//! Each epoll instance registers a [`Waker`] to the files it's interested in,
+//! which adds the file descriptor to the ready list of the instance when the
+//! file may become ready. `epoll_wait` only polls the files in the ready list,
+//! and sleeps until the list is not empty.
+//!
+//! Files that do not support readiness notifications (see
+//! [`FileLike::register_waker`]) are polled on each `epoll_wait` iteration,
+//! and `epoll_wait` keeps yielding the CPU instead of sleeping if there are any.
 
 use alloc::collections::btree_map::Entry;
-use alloc::collections::BTreeMap;
-use alloc::sync::Arc;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::{Arc, Weak};
+use alloc::task::Wake;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::Waker;
 use core::{ffi::c_int, time::Duration};
 
 use axerrno::{LinuxError, LinuxResult};
-use axhal::time::current_time;
+use axhal::time::{current_time, TimeValue};
 use axsync::Mutex;
 
 use crate::ctypes;
 use crate::imp::fd_ops::{add_file_like, get_file_like, FileLike};
 
+/// Events that are always reported, even if not requested.
+const EPOLL_ALWAYS: u32 = ctypes::EPOLLERR | ctypes::EPOLLHUP;
+
+/// File descriptors that may be ready, shared with the wakers.
+struct ReadyList {
+    fds: Mutex<BTreeSet<usize>>,
+    /// Increased on each notification, the futex word on which `epoll_wait`
+    /// sleeps.
+    seq: AtomicU32,
+}
+
+impl ReadyList {
+    fn push(&self, fd: usize) {
+        self.fds.lock().insert(fd);
+        self.seq.fetch_add(1, Ordering::Release);
+        #[cfg(feature = "multitask")]
+        crate::imp::futex::futex_wake(&self.seq, usize::MAX);
+    }
+}
+
+struct EpollWaker {
+    ready: Weak<ReadyList>,
+    fd: usize,
+}
+
+impl Wake for EpollWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if let Some(ready) = self.ready.upgrade() {
+            ready.push(self.fd);
+        }
+    }
+}
+
+struct EpollInterest {
+    file: Weak<dyn FileLike>,
+    event: ctypes::epoll_event,
+    waker: Waker,
+    /// Events reported last time, to detect edges of the files that are
+    /// polled.
+    last_events: u32,
+    /// Disabled after an event is reported with `EPOLLONESHOT`, until it's
+    /// modified again.
+    disabled: bool,
+}
+
+#[derive(Default)]
+struct EpollState {
+    interests: BTreeMap<usize, EpollInterest>,
+    /// File descriptors that do not support readiness notifications.
+    polled: BTreeSet<usize>,
+}
+
 pub struct EpollInstance {
-    events: Mutex<BTreeMap<usize, ctypes::epoll_event>>,
+    state: Mutex<EpollState>,
+    ready: Arc<ReadyList>,
 }
 
 unsafe impl Send for ctypes::epoll_event {}
@@ -25,7 +92,11 @@ impl EpollInstance {
     // TODO: parse flags
     pub fn new(_flags: usize) -> Self {
         Self {
-            events: Mutex::new(BTreeMap::new()),
+            state: Mutex::new(EpollState::default()),
+            ready: Arc::new(ReadyList {
+                fds: Mutex::new(BTreeSet::new()),
+                seq: AtomicU32::new(0),
+            }),
         }
     }
 
@@ -36,72 +107,195 @@ impl EpollInstance {
             .map_err(|_| LinuxError::EINVAL)
     }
 
-    fn control(&self, op: usize, fd: usize, event: &ctypes::epoll_event) -> LinuxResult<usize> {
-        match get_file_like(fd as c_int) {
-            Ok(_) => {}
-            Err(e) => return Err(e),
+    fn control(&self, op: usize, fd: usize, event: Option<&ctypes::epoll_event>) -> LinuxResult {
+        let file = get_file_like(fd as c_int)?;
+        if Arc::as_ptr(&file) as *const () == self as *const Self as *const () {
+            return Err(LinuxError::EINVAL); // cannot watch itself
         }
 
+        let mut state = self.state.lock();
         match op as u32 {
             ctypes::EPOLL_CTL_ADD => {
-                if let Entry::Vacant(e) = self.events.lock().entry(fd) {
-                    e.insert(*event);
+                let event = event.ok_or(LinuxError::EFAULT)?;
+                if let Entry::Vacant(e) = state.interests.entry(fd) {
+                    let waker = Waker::from(Arc::new(EpollWaker {
+                        ready: Arc::downgrade(&self.ready),
+                        fd,
+                    }));
+                    e.insert(EpollInterest {
+                        file: Arc::downgrade(&file),
+                        event: *event,
+                        waker,
+                        last_events: 0,
+                        disabled: false,
+                    });
                 } else {
                     return Err(LinuxError::EEXIST);
                 }
             }
             ctypes::EPOLL_CTL_MOD => {
-                let mut events = self.events.lock();
-                if let Entry::Occupied(mut ocp) = events.entry(fd) {
-                    ocp.insert(*event);
+                let event = event.ok_or(LinuxError::EFAULT)?;
+                if let Some(interest) = state.interests.get_mut(&fd) {
+                    interest.event = *event;
+                    interest.last_events = 0;
+                    interest.disabled = false;
                 } else {
                     return Err(LinuxError::ENOENT);
                 }
             }
             ctypes::EPOLL_CTL_DEL => {
-                let mut events = self.events.lock();
-                if let Entry::Occupied(ocp) = events.entry(fd) {
-                    ocp.remove_entry();
-                } else {
+                if state.interests.remove(&fd).is_none() {
                     return Err(LinuxError::ENOENT);
                 }
+                state.polled.remove(&fd);
+                return Ok(());
             }
             _ => {
                 return Err(LinuxError::EINVAL);
             }
         }
-        Ok(0)
+        drop(state);
+        // check the current state in the next `epoll_wait`
+        self.ready.push(fd);
+        Ok(())
     }
 
-    fn poll_all(&self, events: &mut [ctypes::epoll_event]) -> LinuxResult<usize> {
-        let ready_list = self.events.lock();
+    /// Polls the files in the ready list and the files that do not support
+    /// notifications, and fills `events` with the ready ones.
+    fn poll_ready(&self, events: &mut [ctypes::epoll_event]) -> usize {
+        let mut state = self.state.lock();
+        let EpollState { interests, polled } = &mut *state;
+        let mut pending = core::mem::take(&mut *self.ready.fds.lock());
+        pending.extend(polled.iter());
+
         let mut events_num = 0;
+        let mut requeue = BTreeSet::new();
+        let mut pending = pending.into_iter();
+        for fd in pending.by_ref() {
+            let Some(interest) = interests.get_mut(&fd) else {
+                continue; // deleted
+            };
+            if interest.disabled {
+                polled.remove(&fd);
+                continue;
+            }
+            let Some(file) = interest.file.upgrade() else {
+                // closed, remove it like Linux does
+                interests.remove(&fd);
+                polled.remove(&fd);
+                continue;
+            };
 
-        for (infd, ev) in ready_list.iter() {
-            match get_file_like(*infd as c_int)?.poll() {
-                Err(_) => {
-                    if (ev.events & ctypes::EPOLLERR) != 0 {
-                        events[events_num].events = ctypes::EPOLLERR;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
+            // Register before polling, so no notifications are missed.
+            let notified = file.register_waker(&interest.waker);
+            let mut revents = match file.poll() {
+                Ok(s) => {
+                    let mut revents = 0;
+                    if s.readable {
+                        revents |= ctypes::EPOLLIN;
                     }
-                }
-                Ok(state) => {
-                    if state.readable && (ev.events & ctypes::EPOLLIN != 0) {
-                        events[events_num].events = ctypes::EPOLLIN;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
+                    if s.writable {
+                        revents |= ctypes::EPOLLOUT;
                     }
-
-                    if state.writable && (ev.events & ctypes::EPOLLOUT != 0) {
-                        events[events_num].events = ctypes::EPOLLOUT;
-                        events[events_num].data = ev.data;
-                        events_num += 1;
+                    if s.hangup {
+                        revents |= ctypes::EPOLLHUP;
                     }
+                    revents
                 }
+                Err(e) => {
+                    debug!("    error: {} {:?}", fd, e);
+                    ctypes::EPOLLERR
+                }
+            };
+            revents &= interest.event.events | EPOLL_ALWAYS;
+
+            let edge_triggered = interest.event.events & ctypes::EPOLLET != 0;
+            if edge_triggered && !notified {
+                // no notifications, find the edges by ourselves
+                let new_events = revents & !interest.last_events;
+                interest.last_events = revents;
+                revents = new_events;
+            }
+            if revents != 0 {
+                events[events_num].events = revents;
+                events[events_num].data = interest.event.data;
+                events_num += 1;
+                if interest.event.events & ctypes::EPOLLONESHOT != 0 {
+                    interest.disabled = true;
+                } else if !edge_triggered && notified {
+                    // level-triggered, report it again if it's still ready
+                    requeue.insert(fd);
+                }
+            }
+
+            if notified {
+                polled.remove(&fd);
+            } else {
+                polled.insert(fd);
+            }
+            if events_num == events.len() {
+                break;
+            }
+        }
+
+        // Not polled yet as `events` is full.
+        requeue.extend(pending);
+        if !requeue.is_empty() {
+            self.ready.fds.lock().append(&mut requeue);
+        }
+        events_num
+    }
+
+    /// Whether there are files that do not support notifications, then we
+    /// cannot sleep.
+    fn busy_poll(&self) -> bool {
+        !self.state.lock().polled.is_empty()
+    }
+
+    fn wait(
+        &self,
+        events: &mut [ctypes::epoll_event],
+        deadline: Option<TimeValue>,
+    ) -> LinuxResult<usize> {
+        loop {
+            let seq = self.ready.seq.load(Ordering::Acquire);
+            #[cfg(feature = "net")]
+            if self.busy_poll() {
+                axnet::poll_interfaces();
+            }
+            let events_num = self.poll_ready(events);
+            if events_num > 0 {
+                return Ok(events_num);
+            }
+
+            if deadline.map_or(false, |ddl| current_time() >= ddl) {
+                debug!("    timeout!");
+                return Ok(0);
+            }
+            if crate::imp::task::signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
+            if self.busy_poll() {
+                crate::sys_sched_yield();
+                continue;
+            }
+
+            // Sleep until notified, i.e., `seq` is changed.
+            #[cfg(feature = "multitask")]
+            {
+                use axtask::FutexKey;
+                let key = FutexKey::kernel(&self.ready.seq);
+                match deadline {
+                    Some(ddl) => axtask::futex_wait_timeout(key, &self.ready.seq, seq, ddl, true),
+                    None => axtask::futex_wait(key, &self.ready.seq, seq, true),
+                };
+            }
+            #[cfg(not(feature = "multitask"))]
+            {
+                let _ = seq;
+                crate::sys_sched_yield();
             }
         }
-        Ok(events_num)
     }
 }
 
@@ -152,6 +346,8 @@ pub fn sys_epoll_create(size: c_int) -> c_int {
 }
 
 /// Control interface for an epoll file descriptor
+///
+/// `EPOLLET` (edge-triggered) and `EPOLLONESHOT` are supported in the events.
 pub unsafe fn sys_epoll_ctl(
     epfd: c_int,
     op: c_int,
@@ -160,14 +356,16 @@ pub unsafe fn sys_epoll_ctl(
 ) -> c_int {
     debug!("sys_epoll_ctl <= epfd: {} op: {} fd: {}", epfd, op, fd);
     syscall_body!(sys_epoll_ctl, {
-        let ret = unsafe {
-            EpollInstance::from_fd(epfd)?.control(op as usize, fd as usize, &(*event))? as c_int
-        };
-        Ok(ret)
+        let event = unsafe { event.as_ref() };
+        EpollInstance::from_fd(epfd)?.control(op as usize, fd as usize, event)?;
+        Ok(0)
     })
 }
 
 /// Waits for events on the epoll instance referred to by the file descriptor epfd.
+///
+/// It sleeps until any of the files is ready, the `timeout` (in milliseconds,
+/// negative for infinite) expires, or it's interrupted by signals.
 pub unsafe fn sys_epoll_wait(
     epfd: c_int,
     events: *mut ctypes::epoll_event,
@@ -187,19 +385,6 @@ pub unsafe fn sys_epoll_wait(
         let deadline = (!timeout.is_negative())
             .then(|| current_time() + Duration::from_millis(timeout as u64));
         let epoll_instance = EpollInstance::from_fd(epfd)?;
-        loop {
-            #[cfg(feature = "net")]
-            axnet::poll_interfaces();
-            let events_num = epoll_instance.poll_all(events)?;
-            if events_num > 0 {
-                return Ok(events_num as c_int);
-            }
-
-            if deadline.map_or(false, |ddl| current_time() >= ddl) {
-                debug!("    timeout!");
-                return Ok(0);
-            }
-            crate::sys_sched_yield();
-        }
+        Ok(epoll_instance.wait(events, deadline)? as c_int)
     })
 }
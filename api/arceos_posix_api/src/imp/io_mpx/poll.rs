@@ -0,0 +1,145 @@
+use core::ffi::{c_int, c_short};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{current_time, TimeValue};
+
+use crate::{ctypes, imp::fd_ops::get_file_like};
+
+/// Events that are always reported, even if not requested.
+const POLL_ALWAYS: c_short = (ctypes::POLLERR | ctypes::POLLHUP | ctypes::POLLNVAL) as c_short;
+
+/// Polls each file descriptor in `fds` once, fills in the `revents` fields,
+/// and returns the number of file descriptors with events.
+fn poll_all(fds: &mut [ctypes::pollfd]) -> usize {
+    let mut res_num = 0;
+    for pfd in fds.iter_mut() {
+        pfd.revents = 0;
+        if pfd.fd < 0 {
+            continue;
+        }
+        let revents = match get_file_like(pfd.fd) {
+            Err(_) => ctypes::POLLNVAL as c_short,
+            Ok(f) => match f.poll() {
+                Ok(state) => {
+                    let mut revents = 0;
+                    if state.readable {
+                        revents |= ctypes::POLLIN as c_short;
+                    }
+                    if state.writable {
+                        revents |= ctypes::POLLOUT as c_short;
+                    }
+                    if state.hangup {
+                        revents |= ctypes::POLLHUP as c_short;
+                    }
+                    revents
+                }
+                Err(e) => {
+                    debug!("    error: {} {:?}", pfd.fd, e);
+                    ctypes::POLLERR as c_short
+                }
+            },
+        };
+        pfd.revents = revents & (pfd.events | POLL_ALWAYS);
+        if pfd.revents != 0 {
+            res_num += 1;
+        }
+    }
+    res_num
+}
+
+fn do_poll(fds: &mut [ctypes::pollfd], deadline: Option<TimeValue>) -> LinuxResult<c_int> {
+    loop {
+        #[cfg(feature = "net")]
+        axnet::poll_interfaces();
+        let res = poll_all(fds);
+        if res > 0 {
+            return Ok(res as c_int);
+        }
+
+        if deadline.map_or(false, |ddl| current_time() >= ddl) {
+            debug!("    timeout!");
+            return Ok(0);
+        }
+        if crate::imp::task::signal_pending() {
+            return Err(LinuxError::EINTR);
+        }
+        crate::sys_sched_yield();
+    }
+}
+
+unsafe fn fds_from_raw<'a>(
+    fds: *mut ctypes::pollfd,
+    nfds: ctypes::nfds_t,
+) -> LinuxResult<&'a mut [ctypes::pollfd]> {
+    if nfds as usize > crate::imp::fd_ops::AX_FILE_LIMIT {
+        return Err(LinuxError::EINVAL);
+    }
+    if nfds == 0 {
+        return Ok(&mut []);
+    }
+    if fds.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(unsafe { core::slice::from_raw_parts_mut(fds, nfds as usize) })
+}
+
+/// Wait for one of the file descriptors in `fds` to become ready to perform
+/// I/O.
+///
+/// The `timeout` is in milliseconds, a negative value means an infinite
+/// timeout. Errors and hangups are always reported in `revents`, and negative
+/// file descriptors are ignored.
+pub unsafe fn sys_poll(fds: *mut ctypes::pollfd, nfds: ctypes::nfds_t, timeout: c_int) -> c_int {
+    debug!("sys_poll <= {:#x} {} {}", fds as usize, nfds, timeout);
+    syscall_body!(sys_poll, {
+        let fds = unsafe { fds_from_raw(fds, nfds)? };
+        let deadline = (!timeout.is_negative())
+            .then(|| current_time() + Duration::from_millis(timeout as u64));
+        do_poll(fds, deadline)
+    })
+}
+
+/// Like `poll`, but with a `timespec` timeout (NULL for infinite), and the
+/// signal mask is replaced by `sigmask` (if not NULL) while waiting.
+pub unsafe fn sys_ppoll(
+    fds: *mut ctypes::pollfd,
+    nfds: ctypes::nfds_t,
+    timeout: *const ctypes::timespec,
+    sigmask: *const ctypes::sigset_t,
+) -> c_int {
+    debug!(
+        "sys_ppoll <= {:#x} {} {:#x} {:#x}",
+        fds as usize, nfds, timeout as usize, sigmask as usize
+    );
+    syscall_body!(sys_ppoll, {
+        let fds = unsafe { fds_from_raw(fds, nfds)? };
+        let deadline = match unsafe { timeout.as_ref() } {
+            Some(ts) => {
+                if ts.tv_sec < 0 || ts.tv_nsec < 0 || ts.tv_nsec > 999999999 {
+                    return Err(LinuxError::EINVAL);
+                }
+                Some(current_time() + Duration::from(*ts))
+            }
+            None => None,
+        };
+
+        #[cfg(feature = "multitask")]
+        if let Some(mask) = unsafe { sigmask.as_ref() } {
+            use crate::imp::signal::{handle_pending_signals, UNBLOCKABLE};
+            let mask: u64 = mask.__bits[0] as _;
+            let curr = axtask::current();
+            let old = curr.set_blocked_signals(mask & !UNBLOCKABLE);
+            let res = do_poll(fds, deadline);
+            if res.is_err() {
+                // deliver the signals with the temporary mask
+                handle_pending_signals();
+            }
+            curr.set_blocked_signals(old);
+            return res;
+        }
+        #[cfg(not(feature = "multitask"))]
+        let _ = sigmask;
+        do_poll(fds, deadline)
+    })
+}
@@ -145,6 +145,9 @@ pub unsafe fn sys_select(
                 debug!("    timeout!");
                 return Ok(0);
             }
+            if crate::imp::task::signal_pending() {
+                return Err(LinuxError::EINTR);
+            }
             crate::sys_sched_yield();
         }
     })
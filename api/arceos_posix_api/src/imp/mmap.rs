@@ -0,0 +1,200 @@
+use core::ffi::{c_int, c_void};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::mem::{VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::MappingFlags;
+use axmm::AddrSpace;
+
+use crate::ctypes;
+
+/// Runs `f` on the address space where the memory mappings are created.
+///
+/// It's the address space of the current user task if any, otherwise the
+/// kernel address space.
+fn with_current_aspace<R>(f: impl FnOnce(&mut AddrSpace) -> R) -> R {
+    #[cfg(feature = "uspace")]
+    if let Some(aspace) = axprocess::current_aspace() {
+        return f(&mut aspace.lock());
+    }
+    f(&mut axmm::kernel_aspace().lock())
+}
+
+/// Returns the extra mapping flags for the current task, i.e., `USER` for
+/// user tasks.
+fn current_task_flags() -> MappingFlags {
+    #[cfg(feature = "uspace")]
+    if axprocess::current_aspace().is_some() {
+        return MappingFlags::USER;
+    }
+    MappingFlags::empty()
+}
+
+fn is_aligned_4k(addr: usize) -> bool {
+    VirtAddr::from(addr).is_aligned_4k()
+}
+
+/// Rounds up `len` to the page size, returns `None` if it's too large.
+fn page_align_len(len: usize) -> Option<usize> {
+    len.checked_add(PAGE_SIZE_4K - 1)
+        .map(|len| len & !(PAGE_SIZE_4K - 1))
+}
+
+fn prot_to_flags(prot: c_int) -> LinuxResult<MappingFlags> {
+    let prot = prot as u32;
+    if prot & !(ctypes::PROT_READ | ctypes::PROT_WRITE | ctypes::PROT_EXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut flags = MappingFlags::empty();
+    if prot & ctypes::PROT_READ != 0 {
+        flags |= MappingFlags::READ;
+    }
+    if prot & ctypes::PROT_WRITE != 0 {
+        flags |= MappingFlags::WRITE;
+    }
+    if prot & ctypes::PROT_EXEC != 0 {
+        flags |= MappingFlags::EXECUTE;
+    }
+    Ok(flags)
+}
+
+/// Checks the range `[addr, addr + len)` and returns the start address and
+/// the page-aligned length.
+fn check_range(addr: *mut c_void, len: ctypes::size_t) -> LinuxResult<(VirtAddr, usize)> {
+    let start = addr as usize;
+    if !is_aligned_4k(start) || len == 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let size = page_align_len(len).ok_or(LinuxError::ENOMEM)?;
+    if start.checked_add(size).is_none() {
+        return Err(LinuxError::ENOMEM);
+    }
+    Ok((VirtAddr::from(start), size))
+}
+
+/// Map files or devices into memory.
+///
+/// Return the start address of the mapping, or a negated error number
+/// (e.g., `-ENOMEM`) casted to a pointer if failed.
+///
+/// The pages are populated lazily on page faults.
+pub fn sys_mmap(
+    addr: *mut c_void,
+    len: ctypes::size_t,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    offset: ctypes::off_t,
+) -> *mut c_void {
+    debug!(
+        "sys_mmap <= addr: {:#x}, len: {:#x}, prot: {:#x}, flags: {:#x}, fd: {}, offset: {:#x}",
+        addr as usize, len, prot, flags, fd, offset
+    );
+    syscall_body!(sys_mmap, {
+        let map_flags = flags as u32;
+        let shared = match map_flags & ctypes::MAP_TYPE {
+            ctypes::MAP_SHARED | ctypes::MAP_SHARED_VALIDATE => true,
+            ctypes::MAP_PRIVATE => false,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let anonymous = map_flags & ctypes::MAP_ANONYMOUS != 0;
+        let fixed = map_flags & ctypes::MAP_FIXED != 0;
+        if len == 0 || offset < 0 || !is_aligned_4k(offset as usize) {
+            return Err(LinuxError::EINVAL);
+        }
+        let mapping_flags = prot_to_flags(prot)? | current_task_flags();
+        let size = page_align_len(len).ok_or(LinuxError::ENOMEM)?;
+        #[cfg(feature = "fs")]
+        let file = if anonymous {
+            None
+        } else {
+            Some(super::fs::File::from_fd(fd).map_err(|_| LinuxError::EBADF)?)
+        };
+        #[cfg(not(feature = "fs"))]
+        if !anonymous {
+            let _ = (fd, shared);
+            return Err(LinuxError::ENODEV);
+        }
+
+        let (start, writeback) = with_current_aspace(|aspace| {
+            let (start, writeback) = if fixed {
+                let (start, size) = check_range(addr, size as _)?;
+                if !aspace.contains_range(start, size) {
+                    return Err(LinuxError::ENOMEM);
+                }
+                (start, Some(aspace.unmap(start, size)?))
+            } else {
+                // use the hint if possible, otherwise search from the middle
+                // of the address space to keep away from the kernel image
+                let hint = if addr.is_null() {
+                    aspace.base() + aspace.size() / 2
+                } else {
+                    VirtAddr::from(addr as usize).align_down_4k()
+                };
+                let start = aspace
+                    .find_free_area(hint, size)
+                    .or_else(|| aspace.find_free_area(aspace.base() + PAGE_SIZE_4K, size))
+                    .ok_or(LinuxError::ENOMEM)?;
+                (start, None)
+            };
+
+            #[cfg(feature = "fs")]
+            if let Some(file) = file {
+                aspace.map_file(start, size, mapping_flags, file, offset as u64, shared)?;
+                return Ok((start.as_usize(), writeback));
+            }
+            aspace.map_alloc(start, size, mapping_flags, false)?;
+            Ok((start.as_usize(), writeback))
+        })?;
+        // the replaced pages are written back with the address space unlocked
+        if let Some(writeback) = writeback {
+            writeback.write()?;
+        }
+        Ok(start)
+    })
+}
+
+/// Unmap the pages in the range `[addr, addr + len)`.
+///
+/// Shared file mappings are written back to the file before unmapping.
+pub fn sys_munmap(addr: *mut c_void, len: ctypes::size_t) -> c_int {
+    debug!("sys_munmap <= addr: {:#x}, len: {:#x}", addr as usize, len);
+    syscall_body!(sys_munmap, {
+        let (start, size) = check_range(addr, len)?;
+        with_current_aspace(|aspace| aspace.unmap(start, size))?.write()?;
+        Ok(0)
+    })
+}
+
+/// Change the access protections of the pages in the range
+/// `[addr, addr + len)`.
+pub fn sys_mprotect(addr: *mut c_void, len: ctypes::size_t, prot: c_int) -> c_int {
+    debug!(
+        "sys_mprotect <= addr: {:#x}, len: {:#x}, prot: {:#x}",
+        addr as usize, len, prot
+    );
+    syscall_body!(sys_mprotect, {
+        let (start, size) = check_range(addr, len)?;
+        let flags = prot_to_flags(prot)? | current_task_flags();
+        with_current_aspace(|aspace| aspace.protect(start, size, flags))?;
+        Ok(0)
+    })
+}
+
+/// Write the modified pages of shared file mappings in the range
+/// `[addr, addr + len)` back to the file.
+pub fn sys_msync(addr: *mut c_void, len: ctypes::size_t, flags: c_int) -> c_int {
+    debug!(
+        "sys_msync <= addr: {:#x}, len: {:#x}, flags: {:#x}",
+        addr as usize, len, flags
+    );
+    syscall_body!(sys_msync, {
+        let flags = flags as u32;
+        let valid = ctypes::MS_ASYNC | ctypes::MS_SYNC | ctypes::MS_INVALIDATE;
+        if flags & !valid != 0 || (flags & ctypes::MS_ASYNC != 0 && flags & ctypes::MS_SYNC != 0) {
+            return Err(LinuxError::EINVAL);
+        }
+        let (start, size) = check_range(addr, len)?;
+        with_current_aspace(|aspace| aspace.sync(start, size))?.write()?;
+        Ok(0)
+    })
+}
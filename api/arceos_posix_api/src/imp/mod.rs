@@ -10,11 +10,21 @@ pub mod time;
 pub mod fd_ops;
 #[cfg(feature = "fs")]
 pub mod fs;
-#[cfg(any(feature = "select", feature = "epoll"))]
+#[cfg(feature = "multitask")]
+pub mod futex;
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 pub mod io_mpx;
+#[cfg(feature = "mmap")]
+pub mod mmap;
 #[cfg(feature = "net")]
 pub mod net;
 #[cfg(feature = "pipe")]
 pub mod pipe;
+#[cfg(feature = "uspace")]
+pub mod process;
 #[cfg(feature = "multitask")]
 pub mod pthread;
+#[cfg(feature = "multitask")]
+pub mod semaphore;
+#[cfg(feature = "multitask")]
+pub mod signal;
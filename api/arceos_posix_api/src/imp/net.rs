@@ -1,11 +1,15 @@
 use alloc::{sync::Arc, vec, vec::Vec};
 use core::ffi::{c_char, c_int, c_void};
-use core::mem::size_of;
-use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::mem::{offset_of, size_of, size_of_val};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+#[cfg(feature = "epoll")]
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{LinuxError, LinuxResult};
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 use axio::PollState;
-use axnet::{TcpSocket, UdpSocket};
+use axnet::{TcpSocket, UdpSocket, UnixDatagramSocket, UnixSocketAddr, UnixStreamSocket};
 use axsync::Mutex;
 
 use super::fd_ops::FileLike;
@@ -15,6 +19,31 @@ use crate::utils::char_ptr_to_str;
 pub enum Socket {
     Udp(Mutex<UdpSocket>),
     Tcp(Mutex<TcpSocket>),
+    UnixStream(UnixStreamSocket),
+    UnixDatagram(UnixDatagramSocket),
+}
+
+/// A socket address of any supported family.
+#[derive(Debug)]
+enum SockAddr {
+    Inet(SocketAddr),
+    Unix(UnixSocketAddr),
+}
+
+impl SockAddr {
+    fn into_inet(self) -> LinuxResult<SocketAddr> {
+        match self {
+            SockAddr::Inet(addr) => Ok(addr),
+            SockAddr::Unix(_) => Err(LinuxError::EAFNOSUPPORT),
+        }
+    }
+
+    fn into_unix(self) -> LinuxResult<UnixSocketAddr> {
+        match self {
+            SockAddr::Unix(addr) => Ok(addr),
+            SockAddr::Inet(_) => Err(LinuxError::EAFNOSUPPORT),
+        }
+    }
 }
 
 impl Socket {
@@ -33,6 +62,8 @@ impl Socket {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().send(buf)?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().send(buf)?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.send(buf)?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.send(buf)?),
         }
     }
 
@@ -40,77 +71,241 @@ impl Socket {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.recv(buf)?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.recv(buf)?),
         }
     }
 
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     pub fn poll(&self) -> LinuxResult<PollState> {
         match self {
             Socket::Udp(udpsocket) => Ok(udpsocket.lock().poll()?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().poll()?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.poll()?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.poll()?),
         }
     }
 
-    fn local_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().local_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?),
-        }
+    fn local_addr(&self) -> LinuxResult<SockAddr> {
+        Ok(match self {
+            Socket::Udp(udpsocket) => SockAddr::Inet(udpsocket.lock().local_addr()?),
+            Socket::Tcp(tcpsocket) => SockAddr::Inet(tcpsocket.lock().local_addr()?),
+            Socket::UnixStream(unixsocket) => SockAddr::Unix(unixsocket.local_addr()?),
+            Socket::UnixDatagram(unixsocket) => SockAddr::Unix(unixsocket.local_addr()?),
+        })
     }
 
-    fn peer_addr(&self) -> LinuxResult<SocketAddr> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().peer_addr()?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?),
-        }
+    fn peer_addr(&self) -> LinuxResult<SockAddr> {
+        Ok(match self {
+            Socket::Udp(udpsocket) => SockAddr::Inet(udpsocket.lock().peer_addr()?),
+            Socket::Tcp(tcpsocket) => SockAddr::Inet(tcpsocket.lock().peer_addr()?),
+            Socket::UnixStream(unixsocket) => SockAddr::Unix(unixsocket.peer_addr()?),
+            Socket::UnixDatagram(unixsocket) => SockAddr::Unix(unixsocket.peer_addr()?),
+        })
     }
 
-    fn bind(&self, addr: SocketAddr) -> LinuxResult {
+    fn bind(&self, addr: SockAddr) -> LinuxResult {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().bind(addr.into_inet()?)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(addr.into_inet()?)?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.bind(addr.into_unix()?)?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.bind(addr.into_unix()?)?),
         }
     }
 
-    fn connect(&self, addr: SocketAddr) -> LinuxResult {
+    fn connect(&self, addr: SockAddr) -> LinuxResult {
         match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr)?),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().connect(addr.into_inet()?)?),
+            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().connect(addr.into_inet()?)?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.connect(addr.into_unix()?)?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.connect(addr.into_unix()?)?),
         }
     }
 
-    fn sendto(&self, buf: &[u8], addr: SocketAddr) -> LinuxResult<usize> {
+    fn sendto(&self, buf: &[u8], addr: SockAddr) -> LinuxResult<usize> {
         match self {
             // diff: must bind before sendto
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
-            Socket::Tcp(_) => Err(LinuxError::EISCONN),
+            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr.into_inet()?)?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.send_to(buf, addr.into_unix()?)?),
+            Socket::Tcp(_) | Socket::UnixStream(_) => Err(LinuxError::EISCONN),
         }
     }
 
-    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SocketAddr>)> {
+    fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SockAddr>)> {
         match self {
             // diff: must bind before recvfrom
             Socket::Udp(udpsocket) => Ok(udpsocket
                 .lock()
                 .recv_from(buf)
-                .map(|res| (res.0, Some(res.1)))?),
+                .map(|res| (res.0, Some(SockAddr::Inet(res.1))))?),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.recv(buf).map(|res| (res, None))?),
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket
+                .recv_from(buf)
+                .map(|res| (res.0, Some(SockAddr::Unix(res.1))))?),
         }
     }
 
     fn listen(&self) -> LinuxResult {
         match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Udp(_) | Socket::UnixDatagram(_) => Err(LinuxError::EOPNOTSUPP),
             Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+            Socket::UnixStream(unixsocket) => Ok(unixsocket.listen()?),
         }
     }
 
-    fn accept(&self) -> LinuxResult<TcpSocket> {
+    fn accept(&self) -> LinuxResult<Socket> {
         match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+            Socket::Udp(_) | Socket::UnixDatagram(_) => Err(LinuxError::EOPNOTSUPP),
+            Socket::Tcp(tcpsocket) => Ok(Socket::Tcp(Mutex::new(tcpsocket.lock().accept()?))),
+            Socket::UnixStream(unixsocket) => Ok(Socket::UnixStream(unixsocket.accept()?)),
         }
     }
 
+    fn setsockopt(&self, level: u32, optname: u32, optval: &[u8]) -> LinuxResult {
+        match (self, level, optname) {
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => udpsocket
+                .lock()
+                .set_reuse_address(read_optval::<c_int>(optval)? != 0),
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => tcpsocket
+                .lock()
+                .set_reuse_address(read_optval::<c_int>(optval)? != 0),
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                udpsocket.lock().set_recv_timeout(read_timeout(optval)?)?
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                tcpsocket.lock().set_recv_timeout(read_timeout(optval)?)?
+            }
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                udpsocket.lock().set_send_timeout(read_timeout(optval)?)?
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                tcpsocket.lock().set_send_timeout(read_timeout(optval)?)?
+            }
+            (Socket::Udp(_), ctypes::SOL_SOCKET, ctypes::SO_RCVBUF | ctypes::SO_SNDBUF) => {
+                // the buffers of UDP sockets are fixed
+                read_buf_size(optval)?;
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => tcpsocket
+                .lock()
+                .set_recv_buffer_size(read_buf_size(optval)?),
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => tcpsocket
+                .lock()
+                .set_send_buffer_size(read_buf_size(optval)?),
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE) => tcpsocket
+                .lock()
+                .set_keep_alive(read_optval::<c_int>(optval)? != 0),
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = read_optval::<ctypes::linger>(optval)?;
+                tcpsocket.lock().set_linger(
+                    (linger.l_onoff != 0)
+                        .then(|| Duration::from_secs(linger.l_linger.max(0) as u64)),
+                )
+            }
+            (Socket::Tcp(tcpsocket), ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY) => tcpsocket
+                .lock()
+                .set_nodelay(read_optval::<c_int>(optval)? != 0),
+            (Socket::UnixStream(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                unixsocket.set_recv_timeout(read_timeout(optval)?)?
+            }
+            (Socket::UnixDatagram(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                unixsocket.set_recv_timeout(read_timeout(optval)?)?
+            }
+            (Socket::UnixStream(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                unixsocket.set_send_timeout(read_timeout(optval)?)?
+            }
+            (Socket::UnixDatagram(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                unixsocket.set_send_timeout(read_timeout(optval)?)?
+            }
+            (
+                Socket::UnixStream(_) | Socket::UnixDatagram(_),
+                ctypes::SOL_SOCKET,
+                ctypes::SO_RCVBUF | ctypes::SO_SNDBUF,
+            ) => {
+                // the buffers of Unix domain sockets are fixed
+                read_buf_size(optval)?;
+            }
+            _ => return Err(LinuxError::ENOPROTOOPT),
+        }
+        Ok(())
+    }
+
+    fn getsockopt(&self, level: u32, optname: u32) -> LinuxResult<SockOptVal> {
+        let bool_val = |val: bool| SockOptVal::Int(val as c_int);
+        let timeout_val = |timeout: Option<Duration>| {
+            SockOptVal::Timeval(timeout.unwrap_or(Duration::ZERO).into())
+        };
+        let size_val = |size: usize| SockOptVal::Int(size.min(c_int::MAX as usize) as c_int);
+        Ok(match (self, level, optname) {
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                bool_val(udpsocket.lock().reuse_address())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR) => {
+                bool_val(tcpsocket.lock().reuse_address())
+            }
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                timeout_val(udpsocket.lock().recv_timeout())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                timeout_val(tcpsocket.lock().recv_timeout())
+            }
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                timeout_val(udpsocket.lock().send_timeout())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                timeout_val(tcpsocket.lock().send_timeout())
+            }
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                size_val(udpsocket.lock().recv_buffer_size())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVBUF) => {
+                size_val(tcpsocket.lock().recv_buffer_size())
+            }
+            (Socket::Udp(udpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                size_val(udpsocket.lock().send_buffer_size())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDBUF) => {
+                size_val(tcpsocket.lock().send_buffer_size())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE) => {
+                bool_val(tcpsocket.lock().keep_alive())
+            }
+            (Socket::Tcp(tcpsocket), ctypes::SOL_SOCKET, ctypes::SO_LINGER) => {
+                let linger = tcpsocket.lock().linger();
+                SockOptVal::Linger(ctypes::linger {
+                    l_onoff: linger.is_some() as c_int,
+                    l_linger: linger.map_or(0, |linger| linger.as_secs() as c_int),
+                })
+            }
+            (Socket::Tcp(tcpsocket), ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY) => {
+                bool_val(tcpsocket.lock().nodelay())
+            }
+            (Socket::UnixStream(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                timeout_val(unixsocket.recv_timeout())
+            }
+            (Socket::UnixDatagram(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO) => {
+                timeout_val(unixsocket.recv_timeout())
+            }
+            (Socket::UnixStream(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                timeout_val(unixsocket.send_timeout())
+            }
+            (Socket::UnixDatagram(unixsocket), ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO) => {
+                timeout_val(unixsocket.send_timeout())
+            }
+            (
+                Socket::UnixStream(unixsocket),
+                ctypes::SOL_SOCKET,
+                ctypes::SO_RCVBUF | ctypes::SO_SNDBUF,
+            ) => size_val(unixsocket.buffer_size()),
+            (
+                Socket::UnixDatagram(unixsocket),
+                ctypes::SOL_SOCKET,
+                ctypes::SO_RCVBUF | ctypes::SO_SNDBUF,
+            ) => size_val(unixsocket.buffer_size()),
+            _ => return Err(LinuxError::ENOPROTOOPT),
+        })
+    }
+
     fn shutdown(&self) -> LinuxResult {
         match self {
             Socket::Udp(udpsocket) => {
@@ -126,6 +321,12 @@ impl Socket {
                 tcpsocket.shutdown()?;
                 Ok(())
             }
+            Socket::UnixStream(unixsocket) => {
+                unixsocket.peer_addr()?;
+                unixsocket.shutdown()?;
+                Ok(())
+            }
+            Socket::UnixDatagram(unixsocket) => Ok(unixsocket.shutdown()?),
         }
     }
 }
@@ -157,14 +358,27 @@ impl FileLike for Socket {
         self
     }
 
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     fn poll(&self) -> LinuxResult<PollState> {
         self.poll()
     }
 
+    #[cfg(feature = "epoll")]
+    fn register_waker(&self, waker: &Waker) -> bool {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().register_poll_waker(waker),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().register_poll_waker(waker),
+            Socket::UnixStream(unixsocket) => unixsocket.register_poll_waker(waker),
+            Socket::UnixDatagram(unixsocket) => unixsocket.register_poll_waker(waker),
+        }
+    }
+
     fn set_nonblocking(&self, nonblock: bool) -> LinuxResult {
         match self {
             Socket::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
             Socket::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
+            Socket::UnixStream(unixsocket) => unixsocket.set_nonblocking(nonblock),
+            Socket::UnixDatagram(unixsocket) => unixsocket.set_nonblocking(nonblock),
         }
         Ok(())
     }
@@ -194,34 +408,191 @@ impl From<ctypes::sockaddr_in> for SocketAddrV4 {
     }
 }
 
-fn into_sockaddr(addr: SocketAddr) -> (ctypes::sockaddr, ctypes::socklen_t) {
-    debug!("    Sockaddr: {}", addr);
+impl From<SocketAddrV6> for ctypes::sockaddr_in6 {
+    fn from(addr: SocketAddrV6) -> ctypes::sockaddr_in6 {
+        ctypes::sockaddr_in6 {
+            sin6_family: ctypes::AF_INET6 as u16,
+            sin6_port: addr.port().to_be(),
+            sin6_flowinfo: addr.flowinfo().to_be(),
+            sin6_addr: ctypes::in6_addr {
+                __in6_union: ctypes::in6_addr__bindgen_ty_1 {
+                    __s6_addr: addr.ip().octets(),
+                },
+            },
+            sin6_scope_id: addr.scope_id(),
+        }
+    }
+}
+
+impl From<ctypes::sockaddr_in6> for SocketAddrV6 {
+    fn from(addr: ctypes::sockaddr_in6) -> SocketAddrV6 {
+        SocketAddrV6::new(
+            Ipv6Addr::from(unsafe { addr.sin6_addr.__in6_union.__s6_addr }),
+            u16::from_be(addr.sin6_port),
+            u32::from_be(addr.sin6_flowinfo),
+            addr.sin6_scope_id,
+        )
+    }
+}
+
+/// The value of a socket option.
+enum SockOptVal {
+    Int(c_int),
+    Timeval(ctypes::timeval),
+    Linger(ctypes::linger),
+}
+
+fn read_optval<T: Copy>(optval: &[u8]) -> LinuxResult<T> {
+    if optval.len() < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(unsafe { (optval.as_ptr() as *const T).read_unaligned() })
+}
+
+/// Reads a timeout, where zero means no timeout.
+fn read_timeout(optval: &[u8]) -> LinuxResult<Option<Duration>> {
+    let tv = read_optval::<ctypes::timeval>(optval)?;
+    if tv.tv_sec < 0 || !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EDOM);
+    }
+    let timeout = Duration::from(tv);
+    Ok((!timeout.is_zero()).then_some(timeout))
+}
+
+fn read_buf_size(optval: &[u8]) -> LinuxResult<usize> {
+    match read_optval::<c_int>(optval)? {
+        size if size < 0 => Err(LinuxError::EINVAL),
+        size => Ok(size as usize),
+    }
+}
+
+/// Copy the value `src` to `dst`, truncated to `*dst_len` bytes, and store
+/// the copied size in `*dst_len`.
+unsafe fn copy_optval<T>(src: &T, dst: *mut c_void, dst_len: *mut ctypes::socklen_t) {
+    let len = (*dst_len as usize).min(size_of::<T>());
+    core::ptr::copy_nonoverlapping(src as *const T as *const u8, dst as *mut u8, len);
+    *dst_len = len as _;
+}
+
+/// Copy the first `len` bytes of the socket address `src` to `dst`, truncated
+/// to `*dst_len` bytes, and store `len` in `*dst_len`.
+unsafe fn copy_sockaddr<T>(
+    src: &T,
+    len: usize,
+    dst: *mut ctypes::sockaddr,
+    dst_len: *mut ctypes::socklen_t,
+) {
+    let copy_len = (*dst_len as usize).min(len);
+    core::ptr::copy_nonoverlapping(src as *const T as *const u8, dst as *mut u8, copy_len);
+    *dst_len = len as _;
+}
+
+unsafe fn write_sockaddr(
+    addr: SockAddr,
+    dst: *mut ctypes::sockaddr,
+    dst_len: *mut ctypes::socklen_t,
+) {
+    debug!("    Sockaddr: {:?}", addr);
     match addr {
-        SocketAddr::V4(addr) => (
-            unsafe { *(&ctypes::sockaddr_in::from(addr) as *const _ as *const ctypes::sockaddr) },
-            size_of::<ctypes::sockaddr>() as _,
-        ),
-        SocketAddr::V6(_) => panic!("IPv6 is not supported"),
+        SockAddr::Inet(SocketAddr::V4(addr)) => {
+            let addr = ctypes::sockaddr_in::from(addr);
+            copy_sockaddr(&addr, size_of_val(&addr), dst, dst_len)
+        }
+        SockAddr::Inet(SocketAddr::V6(addr)) => {
+            let addr = ctypes::sockaddr_in6::from(addr);
+            copy_sockaddr(&addr, size_of_val(&addr), dst, dst_len)
+        }
+        SockAddr::Unix(addr) => {
+            let (addr, len) = to_sockaddr_un(&addr);
+            copy_sockaddr(&addr, len, dst, dst_len)
+        }
+    }
+}
+
+/// Converts a Unix domain socket address to `sockaddr_un`, and returns it with
+/// its length.
+fn to_sockaddr_un(addr: &UnixSocketAddr) -> (ctypes::sockaddr_un, usize) {
+    let mut sun = ctypes::sockaddr_un {
+        sun_family: ctypes::AF_UNIX as u16,
+        sun_path: [0; 108],
+    };
+    let name_len = match addr {
+        UnixSocketAddr::Unnamed => 0,
+        // null-terminated
+        UnixSocketAddr::Pathname(path) => path.len() + 1,
+        // starts with a null byte
+        UnixSocketAddr::Abstract(name) => name.len() + 1,
+    }
+    .min(sun.sun_path.len());
+    let name = match addr {
+        UnixSocketAddr::Unnamed => &[][..],
+        UnixSocketAddr::Pathname(path) => path.as_bytes(),
+        UnixSocketAddr::Abstract(name) => name.as_slice(),
+    };
+    let start = matches!(addr, UnixSocketAddr::Abstract(_)) as usize;
+    for (dst, &src) in sun.sun_path[start..name_len].iter_mut().zip(name) {
+        *dst = src as _;
     }
+    (sun, offset_of!(ctypes::sockaddr_un, sun_path) + name_len)
+}
+
+fn from_sockaddr_un(
+    addr: *const ctypes::sockaddr,
+    addrlen: ctypes::socklen_t,
+) -> LinuxResult<UnixSocketAddr> {
+    let path_offset = offset_of!(ctypes::sockaddr_un, sun_path);
+    if addrlen as usize > size_of::<ctypes::sockaddr_un>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let sun = unsafe { &*(addr as *const ctypes::sockaddr_un) };
+    let path = unsafe {
+        core::slice::from_raw_parts(
+            sun.sun_path.as_ptr() as *const u8,
+            addrlen as usize - path_offset,
+        )
+    };
+    Ok(match path {
+        [] => UnixSocketAddr::Unnamed,
+        [0, name @ ..] => UnixSocketAddr::Abstract(name.to_vec()),
+        path => {
+            let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+            let path = core::str::from_utf8(&path[..len]).map_err(|_| LinuxError::EINVAL)?;
+            UnixSocketAddr::Pathname(path.into())
+        }
+    })
 }
 
 fn from_sockaddr(
     addr: *const ctypes::sockaddr,
     addrlen: ctypes::socklen_t,
-) -> LinuxResult<SocketAddr> {
+) -> LinuxResult<SockAddr> {
     if addr.is_null() {
         return Err(LinuxError::EFAULT);
     }
-    if addrlen != size_of::<ctypes::sockaddr>() as _ {
+    if (addrlen as usize) < size_of::<ctypes::sa_family_t>() {
         return Err(LinuxError::EINVAL);
     }
 
-    let mid = unsafe { *(addr as *const ctypes::sockaddr_in) };
-    if mid.sin_family != ctypes::AF_INET as u16 {
-        return Err(LinuxError::EINVAL);
-    }
-
-    let res = SocketAddr::V4(mid.into());
+    let res = match unsafe { (*addr).sa_family } as u32 {
+        ctypes::AF_INET => {
+            if (addrlen as usize) < size_of::<ctypes::sockaddr_in>() {
+                return Err(LinuxError::EINVAL);
+            }
+            SockAddr::Inet(SocketAddr::V4(
+                unsafe { *(addr as *const ctypes::sockaddr_in) }.into(),
+            ))
+        }
+        ctypes::AF_INET6 => {
+            if (addrlen as usize) < size_of::<ctypes::sockaddr_in6>() {
+                return Err(LinuxError::EINVAL);
+            }
+            SockAddr::Inet(SocketAddr::V6(
+                unsafe { *(addr as *const ctypes::sockaddr_in6) }.into(),
+            ))
+        }
+        ctypes::AF_UNIX => SockAddr::Unix(from_sockaddr_un(addr, addrlen)?),
+        _ => return Err(LinuxError::EINVAL),
+    };
     debug!("    load sockaddr:{:#x} => {:?}", addr as usize, res);
     Ok(res)
 }
@@ -234,19 +605,63 @@ pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
     let (domain, socktype, protocol) = (domain as u32, socktype as u32, protocol as u32);
     syscall_body!(sys_socket, {
         match (domain, socktype, protocol) {
-            (ctypes::AF_INET, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
-            | (ctypes::AF_INET, ctypes::SOCK_STREAM, 0) => {
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
+            | (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_STREAM, 0) => {
                 Socket::Tcp(Mutex::new(TcpSocket::new())).add_to_fd_table()
             }
-            (ctypes::AF_INET, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
-            | (ctypes::AF_INET, ctypes::SOCK_DGRAM, 0) => {
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
+            | (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_DGRAM, 0) => {
                 Socket::Udp(Mutex::new(UdpSocket::new())).add_to_fd_table()
             }
+            (ctypes::AF_UNIX, ctypes::SOCK_STREAM, 0) => {
+                Socket::UnixStream(UnixStreamSocket::new()).add_to_fd_table()
+            }
+            (ctypes::AF_UNIX, ctypes::SOCK_DGRAM, 0) => {
+                Socket::UnixDatagram(UnixDatagramSocket::new()).add_to_fd_table()
+            }
             _ => Err(LinuxError::EINVAL),
         }
     })
 }
 
+/// Create a pair of connected sockets.
+///
+/// Only `AF_UNIX` is supported. Return 0 if success.
+pub fn sys_socketpair(domain: c_int, socktype: c_int, protocol: c_int, fds: &mut [c_int]) -> c_int {
+    debug!(
+        "sys_socketpair <= {} {} {} {:#x}",
+        domain,
+        socktype,
+        protocol,
+        fds.as_ptr() as usize
+    );
+    let (domain, socktype, protocol) = (domain as u32, socktype as u32, protocol as u32);
+    syscall_body!(sys_socketpair, {
+        if fds.len() != 2 {
+            return Err(LinuxError::EFAULT);
+        }
+        let (socket1, socket2) = match (domain, socktype, protocol) {
+            (ctypes::AF_UNIX, ctypes::SOCK_STREAM, 0) => {
+                let (socket1, socket2) = UnixStreamSocket::pair();
+                (Socket::UnixStream(socket1), Socket::UnixStream(socket2))
+            }
+            (ctypes::AF_UNIX, ctypes::SOCK_DGRAM, 0) => {
+                let (socket1, socket2) = UnixDatagramSocket::pair();
+                (Socket::UnixDatagram(socket1), Socket::UnixDatagram(socket2))
+            }
+            (ctypes::AF_UNIX, _, _) => return Err(LinuxError::EINVAL),
+            _ => return Err(LinuxError::EOPNOTSUPP),
+        };
+        let fd1 = socket1.add_to_fd_table()?;
+        let fd2 = socket2.add_to_fd_table().inspect_err(|_| {
+            super::fd_ops::close_file_like(fd1).ok();
+        })?;
+        fds[0] = fd1;
+        fds[1] = fd2;
+        Ok(0)
+    })
+}
+
 /// Bind a address to a socket.
 ///
 /// Return 0 if success.
@@ -356,9 +771,7 @@ pub unsafe fn sys_recvfrom(
 
         let res = socket.recvfrom(buf)?;
         if let Some(addr) = res.1 {
-            unsafe {
-                (*socket_addr, *addrlen) = into_sockaddr(addr);
-            }
+            unsafe { write_sockaddr(addr, socket_addr, addrlen) };
         }
         Ok(res.0)
     })
@@ -419,10 +832,8 @@ pub unsafe fn sys_accept(
         let socket = Socket::from_fd(socket_fd)?;
         let new_socket = socket.accept()?;
         let addr = new_socket.peer_addr()?;
-        let new_fd = Socket::add_to_fd_table(Socket::Tcp(Mutex::new(new_socket)))?;
-        unsafe {
-            (*socket_addr, *socket_len) = into_sockaddr(addr);
-        }
+        let new_fd = new_socket.add_to_fd_table()?;
+        unsafe { write_sockaddr(addr, socket_addr, socket_len) };
         Ok(new_fd)
     })
 }
@@ -443,14 +854,14 @@ pub fn sys_shutdown(
 
 /// Query addresses for a domain name.
 ///
-/// Only IPv4. Ports are always 0. Ignore servname and hint.
-/// Results' ai_flags and ai_canonname are 0 or NULL.
+/// Only the `ai_family` of `hints` is respected. Results' ai_flags and
+/// ai_canonname are 0 or NULL.
 ///
 /// Return address number if success.
 pub unsafe fn sys_getaddrinfo(
     nodename: *const c_char,
     servname: *const c_char,
-    _hints: *const ctypes::addrinfo,
+    hints: *const ctypes::addrinfo,
     res: *mut *mut ctypes::addrinfo,
 ) -> c_int {
     let name = char_ptr_to_str(nodename);
@@ -465,7 +876,7 @@ pub unsafe fn sys_getaddrinfo(
         }
 
         let port = port.map_or(0, |p| p.parse::<u16>().unwrap_or(0));
-        let ip_addrs = if let Ok(domain) = name {
+        let mut ip_addrs = if let Ok(domain) = name {
             if let Ok(a) = domain.parse::<IpAddr>() {
                 vec![a]
             } else {
@@ -474,6 +885,13 @@ pub unsafe fn sys_getaddrinfo(
         } else {
             vec![Ipv4Addr::LOCALHOST.into()]
         };
+        if !hints.is_null() {
+            match unsafe { (*hints).ai_family } as u32 {
+                ctypes::AF_INET => ip_addrs.retain(IpAddr::is_ipv4),
+                ctypes::AF_INET6 => ip_addrs.retain(IpAddr::is_ipv6),
+                _ => {}
+            }
+        }
 
         let len = ip_addrs.len().min(ctypes::MAXADDRS as usize);
         if len == 0 {
@@ -482,31 +900,42 @@ pub unsafe fn sys_getaddrinfo(
 
         let mut out: Vec<ctypes::aibuf> = Vec::with_capacity(len);
         for (i, &ip) in ip_addrs.iter().enumerate().take(len) {
-            let buf = match ip {
-                IpAddr::V4(ip) => ctypes::aibuf {
-                    ai: ctypes::addrinfo {
-                        ai_family: ctypes::AF_INET as _,
-                        // TODO: This is a hard-code part, only return TCP parameters
-                        ai_socktype: ctypes::SOCK_STREAM as _,
-                        ai_protocol: ctypes::IPPROTO_TCP as _,
-                        ai_addrlen: size_of::<ctypes::sockaddr_in>() as _,
-                        ai_addr: core::ptr::null_mut(),
-                        ai_canonname: core::ptr::null_mut(),
-                        ai_next: core::ptr::null_mut(),
-                        ai_flags: 0,
-                    },
-                    sa: ctypes::aibuf_sa {
+            let (ai_family, ai_addrlen, sa) = match ip {
+                IpAddr::V4(ip) => (
+                    ctypes::AF_INET,
+                    size_of::<ctypes::sockaddr_in>(),
+                    ctypes::aibuf_sa {
                         sin: SocketAddrV4::new(ip, port).into(),
                     },
-                    slot: i as i16,
-                    lock: [0],
-                    ref_: 0,
+                ),
+                IpAddr::V6(ip) => (
+                    ctypes::AF_INET6,
+                    size_of::<ctypes::sockaddr_in6>(),
+                    ctypes::aibuf_sa {
+                        sin6: SocketAddrV6::new(ip, port, 0, 0).into(),
+                    },
+                ),
+            };
+            let buf = ctypes::aibuf {
+                ai: ctypes::addrinfo {
+                    ai_family: ai_family as _,
+                    // TODO: This is a hard-code part, only return TCP parameters
+                    ai_socktype: ctypes::SOCK_STREAM as _,
+                    ai_protocol: ctypes::IPPROTO_TCP as _,
+                    ai_addrlen: ai_addrlen as _,
+                    ai_addr: core::ptr::null_mut(),
+                    ai_canonname: core::ptr::null_mut(),
+                    ai_next: core::ptr::null_mut(),
+                    ai_flags: 0,
                 },
-                _ => panic!("IPv6 is not supported"),
+                sa,
+                slot: i as i16,
+                lock: [0],
+                ref_: 0,
             };
             out.push(buf);
             out[i].ai.ai_addr =
-                unsafe { core::ptr::addr_of_mut!(out[i].sa.sin) as *mut ctypes::sockaddr };
+                unsafe { core::ptr::addr_of_mut!(out[i].sa) as *mut ctypes::sockaddr };
             if i > 0 {
                 out[i - 1].ai.ai_next = core::ptr::addr_of_mut!(out[i].ai);
             }
@@ -549,9 +978,8 @@ pub unsafe fn sys_getsockname(
         if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
             return Err(LinuxError::EINVAL);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.local_addr()?);
-        }
+        let local_addr = Socket::from_fd(sock_fd)?.local_addr()?;
+        unsafe { write_sockaddr(local_addr, addr, addrlen) };
         Ok(0)
     })
 }
@@ -573,8 +1001,58 @@ pub unsafe fn sys_getpeername(
         if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
             return Err(LinuxError::EINVAL);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.peer_addr()?);
+        let peer_addr = Socket::from_fd(sock_fd)?.peer_addr()?;
+        unsafe { write_sockaddr(peer_addr, addr, addrlen) };
+        Ok(0)
+    })
+}
+
+/// Set options on a socket.
+///
+/// Return 0 if success.
+pub fn sys_setsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_setsockopt <= {} {} {} {:#x} {}",
+        socket_fd, level, optname, optval as usize, optlen
+    );
+    syscall_body!(sys_setsockopt, {
+        if optval.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let optval = unsafe { core::slice::from_raw_parts(optval as *const u8, optlen as usize) };
+        Socket::from_fd(socket_fd)?.setsockopt(level as u32, optname as u32, optval)?;
+        Ok(0)
+    })
+}
+
+/// Get options on a socket.
+///
+/// Return 0 if success.
+pub unsafe fn sys_getsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_getsockopt <= {} {} {} {:#x} {:#x}",
+        socket_fd, level, optname, optval as usize, optlen as usize
+    );
+    syscall_body!(sys_getsockopt, {
+        if optval.is_null() || optlen.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        match Socket::from_fd(socket_fd)?.getsockopt(level as u32, optname as u32)? {
+            SockOptVal::Int(val) => unsafe { copy_optval(&val, optval, optlen) },
+            SockOptVal::Timeval(val) => unsafe { copy_optval(&val, optval, optlen) },
+            SockOptVal::Linger(val) => unsafe { copy_optval(&val, optval, optlen) },
         }
         Ok(0)
     })
@@ -1,7 +1,9 @@
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
 use core::ffi::c_int;
+use core::task::Waker;
 
 use axerrno::{LinuxError, LinuxResult};
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 use axio::PollState;
 use axsync::Mutex;
 
@@ -22,6 +24,9 @@ pub struct PipeRingBuffer {
     head: usize,
     tail: usize,
     status: RingBufferStatus,
+    write_end_closed: bool,
+    /// Wakers to be woken up once the buffer is read, written, or closed.
+    wakers: Vec<Waker>,
 }
 
 impl PipeRingBuffer {
@@ -31,6 +36,8 @@ impl PipeRingBuffer {
             head: 0,
             tail: 0,
             status: RingBufferStatus::Empty,
+            write_end_closed: false,
+            wakers: Vec::new(),
         }
     }
 
@@ -72,6 +79,19 @@ impl PipeRingBuffer {
             RING_BUFFER_SIZE - self.available_read()
         }
     }
+
+    #[cfg(feature = "epoll")]
+    fn register_waker(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
 }
 
 pub struct Pipe {
@@ -100,9 +120,15 @@ impl Pipe {
     pub const fn writable(&self) -> bool {
         !self.readable
     }
+}
 
-    pub fn write_end_close(&self) -> bool {
-        Arc::strong_count(&self.buffer) == 1
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let mut ring_buffer = self.buffer.lock();
+        if self.writable() {
+            ring_buffer.write_end_closed = true;
+        }
+        ring_buffer.wake_all();
     }
 }
 
@@ -117,21 +143,29 @@ impl FileLike for Pipe {
             let mut ring_buffer = self.buffer.lock();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
-                if self.write_end_close() {
+                if ring_buffer.write_end_closed {
                     return Ok(read_size);
                 }
                 drop(ring_buffer);
+                if super::task::signal_pending() {
+                    return if read_size > 0 {
+                        Ok(read_size)
+                    } else {
+                        Err(LinuxError::EINTR)
+                    };
+                }
                 // Data not ready, wait for write end
                 crate::sys_sched_yield(); // TODO: use synconize primitive
                 continue;
             }
-            for _ in 0..loop_read {
-                if read_size == max_len {
-                    return Ok(read_size);
-                }
+            for _ in 0..loop_read.min(max_len - read_size) {
                 buf[read_size] = ring_buffer.read_byte();
                 read_size += 1;
             }
+            ring_buffer.wake_all();
+            if read_size == max_len {
+                return Ok(read_size);
+            }
         }
     }
 
@@ -146,17 +180,25 @@ impl FileLike for Pipe {
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
                 drop(ring_buffer);
+                if super::task::signal_pending() {
+                    return if write_size > 0 {
+                        Ok(write_size)
+                    } else {
+                        Err(LinuxError::EINTR)
+                    };
+                }
                 // Buffer is full, wait for read end to consume
                 crate::sys_sched_yield(); // TODO: use synconize primitive
                 continue;
             }
-            for _ in 0..loop_write {
-                if write_size == max_len {
-                    return Ok(write_size);
-                }
+            for _ in 0..loop_write.min(max_len - write_size) {
                 ring_buffer.write_byte(buf[write_size]);
                 write_size += 1;
             }
+            ring_buffer.wake_all();
+            if write_size == max_len {
+                return Ok(write_size);
+            }
         }
     }
 
@@ -177,17 +219,27 @@ impl FileLike for Pipe {
         self
     }
 
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     fn poll(&self) -> LinuxResult<PollState> {
         let buf = self.buffer.lock();
+        // reading at EOF does not block
+        let hangup = self.readable() && buf.write_end_closed;
         Ok(PollState {
-            readable: self.readable() && buf.available_read() > 0,
+            readable: self.readable() && (buf.available_read() > 0 || hangup),
             writable: self.writable() && buf.available_write() > 0,
+            hangup,
         })
     }
 
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    #[cfg(feature = "epoll")]
+    fn register_waker(&self, waker: &Waker) -> bool {
+        self.buffer.lock().register_waker(waker);
+        true
+    }
 }
 
 /// Create a pipe
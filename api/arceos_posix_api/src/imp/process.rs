@@ -0,0 +1,61 @@
+//! Per-process data of user processes.
+
+use alloc::sync::Arc;
+use core::any::Any;
+use core::ops::Deref;
+
+use axprocess::{Process, ProcessExt};
+
+use super::fd_ops::{self, FdTable};
+use super::signal::{self, SigActions};
+
+/// The data of a user process kept by the POSIX layer, which is stored in
+/// the [`Process`].
+pub struct ProcessData {
+    pub fd_table: FdTable,
+    pub sig_actions: SigActions,
+}
+
+impl ProcessExt for ProcessData {
+    fn fork(&self) -> Arc<dyn ProcessExt> {
+        Arc::new(Self {
+            fd_table: fd_ops::fork_fd_table(&self.fd_table),
+            sig_actions: SigActions::new(*self.sig_actions.lock()),
+        })
+    }
+
+    fn exec(&self) {
+        signal::reset_on_exec(&mut self.sig_actions.lock());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// A reference to the [`ProcessData`] of a process.
+pub struct ProcessDataRef(Arc<dyn ProcessExt>);
+
+impl Deref for ProcessDataRef {
+    type Target = ProcessData;
+
+    fn deref(&self) -> &ProcessData {
+        self.0.as_any().downcast_ref().unwrap()
+    }
+}
+
+/// Returns the data of `process`, which is initialized on the first access.
+pub fn process_data(process: &Process) -> ProcessDataRef {
+    ProcessDataRef(process.ext_or_init(|| {
+        Arc::new(ProcessData {
+            fd_table: fd_ops::new_fd_table(),
+            sig_actions: signal::new_sig_actions(),
+        })
+    }))
+}
+
+/// Returns the data of the current process, or `None` if the current task is
+/// not a user process.
+pub fn current_data() -> Option<ProcessDataRef> {
+    axprocess::current().map(|process| process_data(&process))
+}
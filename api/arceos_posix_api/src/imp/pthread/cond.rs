@@ -0,0 +1,138 @@
+use crate::imp::futex::{futex_wait, futex_wake};
+use crate::imp::time::timeout_from_abstime;
+use crate::{ctypes, utils::check_null_mut_ptr, utils::check_null_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use super::mutex::PthreadMutex;
+
+static_assertions::const_assert!(size_of::<PthreadCond>() <= size_of::<ctypes::pthread_cond_t>());
+
+/// A condition variable built on a futex word.
+///
+/// Each notification bumps a sequence number, waiting tasks are blocked on
+/// it until the sequence number is changed.
+#[repr(C)]
+pub struct PthreadCond(AtomicU32);
+
+impl PthreadCond {
+    const fn new() -> Self {
+        Self(AtomicU32::new(0))
+    }
+
+    fn wait(&self, mutex: &PthreadMutex, timeout: Option<Duration>) -> LinuxResult {
+        // The mutex is locked by the caller, and remains locked on return.
+        let seq = self.0.load(Ordering::Acquire);
+        mutex.unlock()?;
+        let timed_out = futex_wait(&self.0, seq, timeout);
+        mutex.lock()?;
+        if timed_out {
+            Err(LinuxError::ETIMEDOUT)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn notify(&self, count: usize) {
+        self.0.fetch_add(1, Ordering::Release);
+        futex_wake(&self.0, count);
+    }
+}
+
+/// Initialize a condition variable.
+pub fn sys_pthread_cond_init(
+    cond: *mut ctypes::pthread_cond_t,
+    _attr: *const ctypes::pthread_condattr_t,
+) -> c_int {
+    debug!("sys_pthread_cond_init <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_init, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            cond.cast::<PthreadCond>().write(PthreadCond::new());
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy a condition variable.
+pub fn sys_pthread_cond_destroy(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_destroy <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_destroy, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            cond.cast::<PthreadCond>().drop_in_place();
+        }
+        Ok(0)
+    })
+}
+
+/// Wait on the condition variable, the `mutex` is unlocked during the wait.
+pub fn sys_pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_wait <= {:#x}, {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_wait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).wait(&*mutex.cast::<PthreadMutex>(), None)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Wait on the condition variable until the absolute time `abstime`, the
+/// `mutex` is unlocked during the wait.
+pub unsafe fn sys_pthread_cond_timedwait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!(
+        "sys_pthread_cond_timedwait <= {:#x}, {:#x}",
+        cond as usize, mutex as usize
+    );
+    syscall_body!(sys_pthread_cond_timedwait, {
+        check_null_mut_ptr(cond)?;
+        check_null_mut_ptr(mutex)?;
+        check_null_ptr(abstime)?;
+        let timeout = timeout_from_abstime(unsafe { &*abstime })?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).wait(&*mutex.cast::<PthreadMutex>(), Some(timeout))?;
+        }
+        Ok(0)
+    })
+}
+
+/// Wake up one task waiting on the condition variable.
+pub fn sys_pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_signal <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_signal, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).notify(1);
+        }
+        Ok(0)
+    })
+}
+
+/// Wake up all tasks waiting on the condition variable.
+pub fn sys_pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    debug!("sys_pthread_cond_broadcast <= {:#x}", cond as usize);
+    syscall_body!(sys_pthread_cond_broadcast, {
+        check_null_mut_ptr(cond)?;
+        unsafe {
+            (*cond.cast::<PthreadCond>()).notify(usize::MAX);
+        }
+        Ok(0)
+    })
+}
@@ -8,7 +8,9 @@ use spin::RwLock;
 
 use crate::ctypes;
 
+pub mod cond;
 pub mod mutex;
+pub mod rwlock;
 
 lazy_static::lazy_static! {
     static ref TID_TO_PTHREAD: RwLock<BTreeMap<u64, ForceSendSync<ctypes::pthread_t>>> = {
@@ -147,6 +149,16 @@ pub unsafe fn sys_pthread_join(thread: ctypes::pthread_t, retval: *mut *mut c_vo
     })
 }
 
+/// Sends signal `sig` to the given thread.
+pub fn sys_pthread_kill(thread: ctypes::pthread_t, sig: c_int) -> c_int {
+    debug!("sys_pthread_kill <= {:#x} {}", thread as usize, sig);
+    syscall_body!(sys_pthread_kill, {
+        let thread = unsafe { (thread as *const Pthread).as_ref() }.ok_or(LinuxError::ESRCH)?;
+        super::signal::send_signal(&thread.inner, sig)?;
+        Ok(0)
+    })
+}
+
 #[derive(Clone, Copy)]
 struct ForceSendSync<T>(T);
 
@@ -1,31 +1,51 @@
+use crate::imp::futex::{futex_wait, futex_wake};
 use crate::{ctypes, utils::check_null_mut_ptr};
 
-use axerrno::LinuxResult;
-use axsync::Mutex;
+use axerrno::{LinuxError, LinuxResult};
 
 use core::ffi::c_int;
-use core::mem::{size_of, ManuallyDrop};
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
 
-static_assertions::const_assert_eq!(
-    size_of::<PthreadMutex>(),
-    size_of::<ctypes::pthread_mutex_t>()
-);
+static_assertions::const_assert!(size_of::<PthreadMutex>() <= size_of::<ctypes::pthread_mutex_t>());
 
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked, and there may be other tasks waiting for it.
+const CONTENDED: u32 = 2;
+
+/// A mutex built on a futex word, the uncontended lock and unlock are single
+/// atomic operations.
 #[repr(C)]
-pub struct PthreadMutex(Mutex<()>);
+pub struct PthreadMutex(AtomicU32);
 
 impl PthreadMutex {
     const fn new() -> Self {
-        Self(Mutex::new(()))
+        Self(AtomicU32::new(UNLOCKED))
+    }
+
+    fn try_lock(&self) -> bool {
+        self.0
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
     }
 
-    fn lock(&self) -> LinuxResult {
-        let _guard = ManuallyDrop::new(self.0.lock());
+    pub(super) fn lock(&self) -> LinuxResult {
+        if !self.try_lock() {
+            // Mark it as contended, so the owner will wake us up on unlock.
+            while self.0.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+                futex_wait(&self.0, CONTENDED, None);
+            }
+        }
         Ok(())
     }
 
-    fn unlock(&self) -> LinuxResult {
-        unsafe { self.0.force_unlock() };
+    pub(super) fn unlock(&self) -> LinuxResult {
+        match self.0.swap(UNLOCKED, Ordering::Release) {
+            UNLOCKED => return Err(LinuxError::EPERM),
+            CONTENDED => futex_wake(&self.0, 1),
+            _ => {}
+        }
         Ok(())
     }
 }
@@ -57,6 +77,19 @@ pub fn sys_pthread_mutex_lock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
     })
 }
 
+/// Try to lock the given mutex, returns `EBUSY` if it's already locked.
+pub fn sys_pthread_mutex_trylock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
+    debug!("sys_pthread_mutex_trylock <= {:#x}", mutex as usize);
+    syscall_body!(sys_pthread_mutex_trylock, {
+        check_null_mut_ptr(mutex)?;
+        if unsafe { (*mutex.cast::<PthreadMutex>()).try_lock() } {
+            Ok(0)
+        } else {
+            Err(LinuxError::EBUSY)
+        }
+    })
+}
+
 /// Unlock the given mutex.
 pub fn sys_pthread_mutex_unlock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
     debug!("sys_pthread_mutex_unlock <= {:#x}", mutex as usize);
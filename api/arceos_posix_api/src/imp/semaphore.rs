@@ -0,0 +1,126 @@
+use crate::imp::time::timeout_from_abstime;
+use crate::{ctypes, utils::check_null_mut_ptr, utils::check_null_ptr};
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Semaphore;
+
+use core::ffi::{c_int, c_uint};
+use core::mem::size_of;
+
+static_assertions::const_assert!(size_of::<Sem>() <= size_of::<ctypes::sem_t>());
+
+#[repr(C)]
+pub struct Sem(Semaphore);
+
+impl Sem {
+    const fn new(value: usize) -> Self {
+        Self(Semaphore::new(value))
+    }
+
+    fn post(&self) -> LinuxResult {
+        if self.0.available_permits() >= ctypes::SEM_VALUE_MAX as usize {
+            return Err(LinuxError::EOVERFLOW);
+        }
+        self.0.release();
+        Ok(())
+    }
+}
+
+/// Initialize an unnamed semaphore with the given value.
+///
+/// All tasks share the same address space, so `pshared` is ignored.
+pub fn sys_sem_init(sem: *mut ctypes::sem_t, _pshared: c_int, value: c_uint) -> c_int {
+    debug!("sys_sem_init <= {:#x}, {}", sem as usize, value);
+    syscall_body!(sys_sem_init, {
+        check_null_mut_ptr(sem)?;
+        if value > ctypes::SEM_VALUE_MAX as c_uint {
+            return Err(LinuxError::EINVAL);
+        }
+        unsafe {
+            sem.cast::<Sem>().write(Sem::new(value as usize));
+        }
+        Ok(0)
+    })
+}
+
+/// Destroy an unnamed semaphore.
+pub fn sys_sem_destroy(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_destroy <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_destroy, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            sem.cast::<Sem>().drop_in_place();
+        }
+        Ok(0)
+    })
+}
+
+/// Decrement the semaphore, blocks until its value is greater than zero.
+pub fn sys_sem_wait(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_wait <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_wait, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            (*sem.cast::<Sem>()).0.acquire();
+        }
+        Ok(0)
+    })
+}
+
+/// Decrement the semaphore if its value is greater than zero, returns
+/// `EAGAIN` otherwise.
+pub fn sys_sem_trywait(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_trywait <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_trywait, {
+        check_null_mut_ptr(sem)?;
+        if unsafe { (*sem.cast::<Sem>()).0.try_acquire() } {
+            Ok(0)
+        } else {
+            Err(LinuxError::EAGAIN)
+        }
+    })
+}
+
+/// Decrement the semaphore, blocks until its value is greater than zero or
+/// the absolute time `abstime` has passed.
+pub unsafe fn sys_sem_timedwait(
+    sem: *mut ctypes::sem_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    debug!("sys_sem_timedwait <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_timedwait, {
+        check_null_mut_ptr(sem)?;
+        check_null_ptr(abstime)?;
+        let timeout = timeout_from_abstime(unsafe { &*abstime })?;
+        if unsafe { (*sem.cast::<Sem>()).0.acquire_timeout(timeout) } {
+            Ok(0)
+        } else {
+            Err(LinuxError::ETIMEDOUT)
+        }
+    })
+}
+
+/// Increment the semaphore and wake up a task waiting on it.
+pub fn sys_sem_post(sem: *mut ctypes::sem_t) -> c_int {
+    debug!("sys_sem_post <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_post, {
+        check_null_mut_ptr(sem)?;
+        unsafe {
+            (*sem.cast::<Sem>()).post()?;
+        }
+        Ok(0)
+    })
+}
+
+/// Get the current value of the semaphore.
+pub unsafe fn sys_sem_getvalue(sem: *mut ctypes::sem_t, sval: *mut c_int) -> c_int {
+    debug!("sys_sem_getvalue <= {:#x}", sem as usize);
+    syscall_body!(sys_sem_getvalue, {
+        check_null_mut_ptr(sem)?;
+        check_null_mut_ptr(sval)?;
+        unsafe {
+            *sval = (*sem.cast::<Sem>()).0.available_permits() as c_int;
+        }
+        Ok(0)
+    })
+}
@@ -0,0 +1,402 @@
+//! Signals.
+//!
+//! Each task has its own pending and blocked signal sets (kept by `axtask`),
+//! while the signal actions are shared by all tasks of the kernel, or by the
+//! task of a user process.
+//!
+//! For tasks of the kernel, pending signals are delivered when they return
+//! from the functions of this crate. For user processes, they are delivered
+//! when returning to user space. Blocking functions (e.g., `sleep`, `select`
+//! and `wait4`) return `EINTR` if they are interrupted by signals, the
+//! `SA_RESTART` flag is not supported. Blocking socket operations are not
+//! interrupted.
+
+use core::ffi::{c_int, c_void};
+
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{AxTaskRef, WaitQueue};
+
+use crate::ctypes;
+
+/// The number of signals, which are numbered from `1` to `NSIG`.
+pub const NSIG: usize = 64;
+
+/// The handler of the default action.
+pub const SIG_DFL: usize = 0;
+/// The handler to ignore the signal.
+pub const SIG_IGN: usize = 1;
+
+const SIGKILL: usize = ctypes::SIGKILL as _;
+const SIGSTOP: usize = ctypes::SIGSTOP as _;
+const SIGCONT: usize = ctypes::SIGCONT as _;
+
+/// Returns the bit of signal `signo` in signal sets.
+pub const fn sigbit(signo: usize) -> u64 {
+    1 << (signo - 1)
+}
+
+/// Signals that cannot be caught, blocked or ignored.
+pub const UNBLOCKABLE: u64 = sigbit(SIGKILL) | sigbit(SIGSTOP);
+
+/// Signals whose default action is to stop the task.
+const STOP_SIGNALS: u64 = sigbit(SIGSTOP)
+    | sigbit(ctypes::SIGTSTP as _)
+    | sigbit(ctypes::SIGTTIN as _)
+    | sigbit(ctypes::SIGTTOU as _);
+
+/// Signals whose default action is to ignore them.
+const IGNORED_SIGNALS: u64 = sigbit(ctypes::SIGCHLD as _)
+    | sigbit(ctypes::SIGURG as _)
+    | sigbit(ctypes::SIGWINCH as _)
+    | sigbit(SIGCONT);
+
+/// Notified when `SIGCONT` or `SIGKILL` is sent, to wake up stopped tasks.
+static STOP_WQ: WaitQueue = WaitQueue::new();
+
+/// Signal actions of the tasks of the kernel.
+static SIG_ACTIONS: SigActions = SigActions::new([SigAction::DEFAULT; NSIG]);
+
+/// The action taken on a signal, as in `struct sigaction`.
+#[derive(Debug, Clone, Copy)]
+pub struct SigAction {
+    /// The address of the handler, or `SIG_DFL` or `SIG_IGN`.
+    pub handler: usize,
+    /// `SA_*` flags.
+    pub flags: u32,
+    /// Signals blocked during the execution of the handler.
+    pub mask: u64,
+    /// The function that the handler returns to, used with `SA_RESTORER`.
+    pub restorer: usize,
+}
+
+impl SigAction {
+    const DEFAULT: Self = Self {
+        handler: SIG_DFL,
+        flags: 0,
+        mask: 0,
+        restorer: 0,
+    };
+
+    /// Whether the signal is discarded on delivery, either by `SIG_IGN` or
+    /// by the default action.
+    fn is_ignored(&self, signo: usize) -> bool {
+        self.handler == SIG_IGN || (self.handler == SIG_DFL && IGNORED_SIGNALS & sigbit(signo) != 0)
+    }
+}
+
+impl From<&ctypes::sigaction> for SigAction {
+    fn from(act: &ctypes::sigaction) -> Self {
+        Self {
+            handler: unsafe { act.__sa_handler.sa_handler }.map_or(SIG_DFL, |f| f as usize),
+            flags: act.sa_flags as _,
+            mask: act.sa_mask.__bits[0] as _,
+            restorer: act.sa_restorer.map_or(0, |f| f as usize),
+        }
+    }
+}
+
+impl From<SigAction> for ctypes::sigaction {
+    fn from(act: SigAction) -> Self {
+        let mut sa = ctypes::sigaction::default();
+        sa.__sa_handler.sa_handler = unsafe {
+            core::mem::transmute::<usize, Option<unsafe extern "C" fn(c_int)>>(act.handler)
+        };
+        sa.sa_flags = act.flags as _;
+        sa.sa_mask.__bits[0] = act.mask as _;
+        sa.sa_restorer =
+            unsafe { core::mem::transmute::<usize, Option<unsafe extern "C" fn()>>(act.restorer) };
+        sa
+    }
+}
+
+/// The actions of all signals, indexed by the signal number minus one.
+pub type SigActions = spin::Mutex<[SigAction; NSIG]>;
+
+/// Creates the actions of all signals, which are all `SIG_DFL`.
+#[cfg(feature = "uspace")]
+pub fn new_sig_actions() -> SigActions {
+    SigActions::new([SigAction::DEFAULT; NSIG])
+}
+
+/// Resets the handled signals to `SIG_DFL` on `execve`, while the ignored
+/// signals stay ignored.
+#[cfg(feature = "uspace")]
+pub fn reset_on_exec(actions: &mut [SigAction; NSIG]) {
+    for act in actions.iter_mut().filter(|act| act.handler != SIG_IGN) {
+        *act = SigAction::DEFAULT;
+    }
+}
+
+/// The signal information passed to handlers with `SA_SIGINFO`, in the
+/// layout of `siginfo_t`.
+///
+/// Only the signal number is filled, with `si_code` set to `SI_USER`.
+#[repr(C)]
+pub struct SigInfo {
+    signo: i32,
+    errno: i32,
+    code: i32,
+    __pad: [u8; 128 - 3 * 4],
+}
+
+impl SigInfo {
+    /// Creates the information of signal `signo` sent by `kill`.
+    pub fn new(signo: usize) -> Self {
+        Self {
+            signo: signo as _,
+            errno: 0,
+            code: ctypes::SI_USER as _,
+            __pad: [0; 128 - 3 * 4],
+        }
+    }
+}
+
+/// Runs `f` with the signal actions of the task `tid`, which are the ones of
+/// its process if it's a user process.
+fn with_sig_actions<R>(tid: u64, f: impl FnOnce(&mut [SigAction; NSIG]) -> R) -> R {
+    #[cfg(feature = "uspace")]
+    if let Some(process) = axprocess::find(tid) {
+        return f(&mut super::process::process_data(&process).sig_actions.lock());
+    }
+    let _ = tid;
+    f(&mut SIG_ACTIONS.lock())
+}
+
+fn check_signo(signum: c_int) -> LinuxResult<usize> {
+    if (1..=NSIG as c_int).contains(&signum) {
+        Ok(signum as usize)
+    } else {
+        Err(LinuxError::EINVAL)
+    }
+}
+
+/// Returns the action of signal `signo` to deliver to the current task, and
+/// resets it to `SIG_DFL` if `SA_RESETHAND` is set.
+pub fn take_sig_action(signo: usize) -> SigAction {
+    with_sig_actions(axtask::current().id().as_u64(), |actions| {
+        let act = actions[signo - 1];
+        if act.handler > SIG_IGN && act.flags & ctypes::SA_RESETHAND != 0 {
+            actions[signo - 1] = SigAction::DEFAULT;
+        }
+        act
+    })
+}
+
+/// Sets the action of signal `signo` of the current task if `act` is not
+/// `None`, and returns the old action.
+pub fn sigaction(signo: usize, act: Option<SigAction>) -> LinuxResult<SigAction> {
+    if act.is_some() && UNBLOCKABLE & sigbit(signo) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = axtask::current();
+    let old = with_sig_actions(curr.id().as_u64(), |actions| {
+        let old = actions[signo - 1];
+        if let Some(act) = act {
+            actions[signo - 1] = act;
+        }
+        old
+    });
+    if act.is_some_and(|act| act.is_ignored(signo)) {
+        curr.discard_signals(sigbit(signo));
+    }
+    Ok(old)
+}
+
+/// Changes the blocked signals of the current task by `how` (`SIG_BLOCK`,
+/// `SIG_UNBLOCK` or `SIG_SETMASK`) if `set` is not `None`, and returns the
+/// old ones.
+pub fn sigprocmask(how: c_int, set: Option<u64>) -> LinuxResult<u64> {
+    let curr = axtask::current();
+    let old = curr.blocked_signals();
+    if let Some(set) = set {
+        let blocked = match how as u32 {
+            ctypes::SIG_BLOCK => old | set,
+            ctypes::SIG_UNBLOCK => old & !set,
+            ctypes::SIG_SETMASK => set,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        curr.set_blocked_signals(blocked & !UNBLOCKABLE);
+    }
+    Ok(old)
+}
+
+/// Sends signal `signum` to `task`. If `signum` is `0`, no signal is sent,
+/// which can be used to check the existence of the task.
+///
+/// The signal is discarded if it's ignored and not blocked by the task.
+pub fn send_signal(task: &AxTaskRef, signum: c_int) -> LinuxResult {
+    if signum == 0 {
+        return Ok(());
+    }
+    let signo = check_signo(signum)?;
+    let bit = sigbit(signo);
+    if signo == SIGCONT {
+        task.discard_signals(STOP_SIGNALS);
+    } else if STOP_SIGNALS & bit != 0 {
+        task.discard_signals(sigbit(SIGCONT));
+    }
+    let ignored = with_sig_actions(task.id().as_u64(), |actions| {
+        actions[signo - 1].is_ignored(signo)
+    });
+    if !ignored || task.blocked_signals() & bit != 0 {
+        axtask::send_signal(task, signo);
+    }
+    if signo == SIGCONT || signo == SIGKILL {
+        STOP_WQ.notify_all(true);
+    }
+    Ok(())
+}
+
+/// Runs the default action of signal `signo` on the current task, i.e.,
+/// terminates it, stops it, or ignores the signal.
+///
+/// Terminating a task of the kernel terminates the whole system.
+pub fn default_action(signo: usize) {
+    let bit = sigbit(signo);
+    if IGNORED_SIGNALS & bit != 0 {
+        return;
+    }
+    if STOP_SIGNALS & bit != 0 {
+        let curr = axtask::current();
+        debug!("task {} stopped by signal {}", curr.id_name(), signo);
+        STOP_WQ.wait_until(|| curr.pending_signals() & (sigbit(SIGCONT) | sigbit(SIGKILL)) != 0);
+        return;
+    }
+    #[cfg(feature = "uspace")]
+    if axprocess::current().is_some() {
+        axprocess::kill_current(signo as _);
+    }
+    info!("killed by signal {}", signo);
+    #[cfg(feature = "fs")]
+    axfs::sync_filesystems().ok();
+    axhal::misc::terminate();
+}
+
+/// Delivers the pending signals of the current task if it's a task of the
+/// kernel, i.e., runs the handlers or the default actions.
+///
+/// It's called when returning from the functions of this crate. Signals of
+/// user processes are delivered when returning to user space instead.
+pub fn handle_pending_signals() {
+    let Some(curr) = axtask::current_may_uninit() else {
+        return;
+    };
+    if !curr.has_pending_signal() {
+        return;
+    }
+    #[cfg(feature = "uspace")]
+    if axprocess::current().is_some() {
+        return;
+    }
+    while let Some(signo) = curr.take_pending_signal() {
+        let act = take_sig_action(signo);
+        match act.handler {
+            SIG_IGN => {}
+            SIG_DFL => default_action(signo),
+            handler => {
+                let mut blocked = curr.blocked_signals() | act.mask;
+                if act.flags & ctypes::SA_NODEFER == 0 {
+                    blocked |= sigbit(signo);
+                }
+                let old = curr.set_blocked_signals(blocked & !UNBLOCKABLE);
+                if act.flags & ctypes::SA_SIGINFO != 0 {
+                    let handler: extern "C" fn(c_int, *mut SigInfo, *mut c_void) =
+                        unsafe { core::mem::transmute(handler) };
+                    let mut info = SigInfo::new(signo);
+                    handler(signo as _, &mut info, core::ptr::null_mut());
+                } else {
+                    let handler: extern "C" fn(c_int) = unsafe { core::mem::transmute(handler) };
+                    handler(signo as _);
+                }
+                curr.set_blocked_signals(old);
+            }
+        }
+    }
+}
+
+/// Examine and change the action of signal `signum`.
+pub unsafe fn sys_sigaction(
+    signum: c_int,
+    act: *const ctypes::sigaction,
+    oldact: *mut ctypes::sigaction,
+) -> c_int {
+    debug!("sys_sigaction <= {} {:#x}", signum, act as usize);
+    syscall_body!(sys_sigaction, {
+        let signo = check_signo(signum)?;
+        let act = unsafe { act.as_ref() }.map(SigAction::from);
+        let old = sigaction(signo, act)?;
+        if let Some(oldact) = unsafe { oldact.as_mut() } {
+            *oldact = old.into();
+        }
+        Ok(0)
+    })
+}
+
+/// Examine and change the blocked signals of the current thread.
+pub unsafe fn sys_sigprocmask(
+    how: c_int,
+    set: *const ctypes::sigset_t,
+    oldset: *mut ctypes::sigset_t,
+) -> c_int {
+    syscall_body!(sys_sigprocmask, {
+        let set = unsafe { set.as_ref() }.map(|set| set.__bits[0] as _);
+        let old = sigprocmask(how, set)?;
+        if let Some(oldset) = unsafe { oldset.as_mut() } {
+            *oldset = ctypes::sigset_t::default();
+            oldset.__bits[0] = old as _;
+        }
+        Ok(0)
+    })
+}
+
+/// Get the pending signals of the current thread.
+pub unsafe fn sys_sigpending(set: *mut ctypes::sigset_t) -> c_int {
+    syscall_body!(sys_sigpending, {
+        let set = unsafe { set.as_mut() }.ok_or(LinuxError::EFAULT)?;
+        *set = ctypes::sigset_t::default();
+        set.__bits[0] = axtask::current().pending_signals() as _;
+        Ok(0)
+    })
+}
+
+/// Send signal `sig` to the thread `pid`, or the current thread if `pid` is
+/// `0`.
+///
+/// Process groups are not supported, so `pid` cannot be negative.
+pub fn sys_kill(pid: c_int, sig: c_int) -> c_int {
+    debug!("sys_kill <= {} {}", pid, sig);
+    syscall_body!(sys_kill, {
+        if pid < 0 {
+            return Err(LinuxError::ESRCH);
+        }
+        let task = super::task::find_task(pid)?;
+        send_signal(&task, sig)?;
+        Ok(0)
+    })
+}
+
+/// Send signal `sig` to the current thread.
+pub fn sys_raise(sig: c_int) -> c_int {
+    debug!("sys_raise <= {}", sig);
+    syscall_body!(sys_raise, {
+        send_signal(axtask::current().as_task_ref(), sig)?;
+        Ok(0)
+    })
+}
+
+/// Replace the blocked signals of the current thread with `mask`, and wait
+/// for a signal that runs a handler or terminates the thread.
+///
+/// It always returns `-EINTR`.
+pub unsafe fn sys_sigsuspend(mask: *const ctypes::sigset_t) -> c_int {
+    syscall_body!(sys_sigsuspend, {
+        let mask: u64 = unsafe { mask.as_ref() }.ok_or(LinuxError::EFAULT)?.__bits[0] as _;
+        let curr = axtask::current();
+        let old = curr.set_blocked_signals(mask & !UNBLOCKABLE);
+        WaitQueue::new().wait_until_interruptible(|| false);
+        // deliver the signals with the temporary mask
+        handle_pending_signals();
+        curr.set_blocked_signals(old);
+        Err::<c_int, _>(LinuxError::EINTR)
+    })
+}
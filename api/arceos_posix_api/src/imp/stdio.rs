@@ -2,8 +2,10 @@ use axerrno::AxResult;
 use axio::{prelude::*, BufReader};
 use axsync::Mutex;
 
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
+use axio::PollState;
 #[cfg(feature = "fd")]
-use {alloc::sync::Arc, axerrno::LinuxError, axerrno::LinuxResult, axio::PollState};
+use {alloc::sync::Arc, axerrno::LinuxError, axerrno::LinuxResult};
 
 fn console_read_bytes() -> Option<u8> {
     axhal::console::getchar().map(|c| if c == b'\r' { b'\n' } else { c })
@@ -121,10 +123,12 @@ impl super::fd_ops::FileLike for Stdin {
         self
     }
 
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     fn poll(&self) -> LinuxResult<PollState> {
         Ok(PollState {
             readable: true,
             writable: true,
+            hangup: false,
         })
     }
 
@@ -157,10 +161,12 @@ impl super::fd_ops::FileLike for Stdout {
         self
     }
 
+    #[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
     fn poll(&self) -> LinuxResult<PollState> {
         Ok(PollState {
             readable: true,
             writable: true,
+            hangup: false,
         })
     }
 
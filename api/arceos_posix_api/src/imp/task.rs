@@ -1,4 +1,9 @@
-use core::ffi::c_int;
+use core::ffi::{c_int, c_ulong};
+
+use axerrno::LinuxError;
+
+use crate::ctypes;
+use crate::utils::{check_null_mut_ptr, check_null_ptr};
 
 /// Relinquish the CPU, and switches to another task.
 ///
@@ -16,9 +21,14 @@ pub fn sys_sched_yield() -> c_int {
     0
 }
 
-/// Get current thread ID.
+/// Get current process ID, or the current thread ID if it's not a user
+/// process.
 pub fn sys_getpid() -> c_int {
     syscall_body!(sys_getpid,
+        #[cfg(feature = "uspace")]
+        if let Some(process) = axprocess::current() {
+            return Ok(process.pid() as c_int);
+        }
         #[cfg(feature = "multitask")]
         {
             Ok(axtask::current().id().as_u64() as c_int)
@@ -30,11 +40,133 @@ pub fn sys_getpid() -> c_int {
     )
 }
 
+/// Whether the current task has pending signals that are not blocked, so
+/// blocking functions should return `EINTR`.
+#[cfg(any(
+    feature = "pipe",
+    feature = "select",
+    feature = "poll",
+    feature = "epoll"
+))]
+pub fn signal_pending() -> bool {
+    #[cfg(feature = "multitask")]
+    {
+        axtask::current().has_pending_signal()
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        false
+    }
+}
+
 /// Exit current task
 pub fn sys_exit(exit_code: c_int) -> ! {
     debug!("sys_exit <= {}", exit_code);
     #[cfg(feature = "multitask")]
-    axtask::exit(exit_code);
+    {
+        // only the exit of the main task terminates the system
+        #[cfg(feature = "fs")]
+        if axtask::current().is_init() {
+            axfs::sync_filesystems().ok();
+        }
+        axtask::exit(exit_code);
+    }
     #[cfg(not(feature = "multitask"))]
-    axhal::misc::terminate();
+    {
+        #[cfg(feature = "fs")]
+        axfs::sync_filesystems().ok();
+        axhal::misc::terminate();
+    }
+}
+
+/// Returns the task with ID `pid`, or the current task if `pid` is `0`.
+#[cfg(feature = "multitask")]
+pub fn find_task(pid: c_int) -> axerrno::LinuxResult<axtask::AxTaskRef> {
+    if pid == 0 {
+        return Ok(axtask::current().as_task_ref().clone());
+    }
+    axtask::all_tasks()
+        .into_iter()
+        .find(|t| t.id().as_u64() == pid as u64)
+        .ok_or(LinuxError::ESRCH)
+}
+
+/// Set the CPU affinity mask of the thread whose ID is `pid` (0 for the
+/// current thread).
+///
+/// Only the first `unsigned long` of the mask is used. For single-threaded
+/// configuration, the mask must contain the current CPU.
+pub unsafe fn sys_sched_setaffinity(
+    pid: c_int,
+    cpusetsize: usize,
+    mask: *const ctypes::cpu_set_t,
+) -> c_int {
+    debug!(
+        "sys_sched_setaffinity <= {} {} {:#x}",
+        pid, cpusetsize, mask as usize
+    );
+    syscall_body!(sys_sched_setaffinity, {
+        check_null_ptr(mask)?;
+        if cpusetsize < core::mem::size_of::<c_ulong>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let bits = unsafe { (*mask).__bits[0] } as usize;
+        #[cfg(feature = "multitask")]
+        {
+            let cpumask = axtask::AxCpuMask::from_raw_bits(bits);
+            if cpumask.is_empty() {
+                return Err(LinuxError::EINVAL);
+            }
+            let task = find_task(pid)?;
+            if task.id() == axtask::current().id() {
+                axtask::set_current_affinity(cpumask);
+            } else {
+                task.set_cpumask(cpumask);
+            }
+        }
+        #[cfg(not(feature = "multitask"))]
+        {
+            if pid != 0 && pid != 2 {
+                return Err(LinuxError::ESRCH);
+            }
+            if bits & (1 << axhal::cpu::this_cpu_id()) == 0 {
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Get the CPU affinity mask of the thread whose ID is `pid` (0 for the
+/// current thread).
+pub unsafe fn sys_sched_getaffinity(
+    pid: c_int,
+    cpusetsize: usize,
+    mask: *mut ctypes::cpu_set_t,
+) -> c_int {
+    debug!(
+        "sys_sched_getaffinity <= {} {} {:#x}",
+        pid, cpusetsize, mask as usize
+    );
+    syscall_body!(sys_sched_getaffinity, {
+        check_null_mut_ptr(mask)?;
+        if cpusetsize < core::mem::size_of::<c_ulong>() {
+            return Err(LinuxError::EINVAL);
+        }
+        #[cfg(feature = "multitask")]
+        let bits = find_task(pid)?.cpumask().bits();
+        #[cfg(not(feature = "multitask"))]
+        let bits = {
+            if pid != 0 && pid != 2 {
+                return Err(LinuxError::ESRCH);
+            }
+            1usize << axhal::cpu::this_cpu_id()
+        };
+        let len = cpusetsize.min(core::mem::size_of::<ctypes::cpu_set_t>());
+        unsafe {
+            core::ptr::write_bytes(mask as *mut u8, 0, len);
+            (*mask).__bits[0] = bits as c_ulong;
+        }
+        Ok(0)
+    })
 }
@@ -34,6 +34,17 @@ impl From<Duration> for ctypes::timeval {
     }
 }
 
+/// Converts the absolute time `abstime` (e.g., the deadline of timed waits) to
+/// the duration from now, which is zero if the time has passed.
+#[cfg(feature = "multitask")]
+pub(crate) fn timeout_from_abstime(abstime: &ctypes::timespec) -> axerrno::LinuxResult<Duration> {
+    if abstime.tv_sec < 0 || abstime.tv_nsec < 0 || abstime.tv_nsec > 999999999 {
+        return Err(LinuxError::EINVAL);
+    }
+    let deadline = Duration::from(*abstime);
+    Ok(deadline.saturating_sub(axhal::time::current_time()))
+}
+
 /// Get clock time since booting
 pub unsafe fn sys_clock_gettime(_clk: ctypes::clockid_t, ts: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_clock_gettime, {
@@ -49,7 +60,8 @@ pub unsafe fn sys_clock_gettime(_clk: ctypes::clockid_t, ts: *mut ctypes::timesp
 
 /// Sleep some nanoseconds
 ///
-/// TODO: should be woken by signals, and set errno
+/// If it's interrupted by signals, returns `-EINTR` with the remaining time
+/// written to `rem`.
 pub unsafe fn sys_nanosleep(req: *const ctypes::timespec, rem: *mut ctypes::timespec) -> c_int {
     syscall_body!(sys_nanosleep, {
         unsafe {
@@ -66,7 +78,7 @@ pub unsafe fn sys_nanosleep(req: *const ctypes::timespec, rem: *mut ctypes::time
         let now = axhal::time::current_time();
 
         #[cfg(feature = "multitask")]
-        axtask::sleep(dur);
+        axtask::sleep_until_interruptible(now + dur);
         #[cfg(not(feature = "multitask"))]
         axhal::time::busy_wait(dur);
 
@@ -1,5 +1,8 @@
 //! POSIX-compatible APIs for [ArceOS] modules
 //!
+//! With the `uspace` feature, it also handles the Linux system calls from
+//! user programs, by routing them to these APIs.
+//!
 //! [ArceOS]: https://github.com/rcore-os/arceos
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -20,6 +23,8 @@ extern crate alloc;
 mod utils;
 
 mod imp;
+#[cfg(feature = "uspace")]
+mod syscall;
 
 /// Platform-specific constants and parameters.
 pub mod config {
@@ -35,28 +40,62 @@ pub mod ctypes;
 pub use imp::io::{sys_read, sys_write, sys_writev};
 pub use imp::resources::{sys_getrlimit, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
-pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
+pub use imp::task::{
+    sys_exit, sys_getpid, sys_sched_getaffinity, sys_sched_setaffinity, sys_sched_yield,
+};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
 
 #[cfg(feature = "fd")]
 pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_readlink, sys_rename, sys_stat,
+    sys_symlink,
+};
+#[cfg(feature = "multitask")]
+pub use imp::futex::sys_futex;
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
 pub use imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+#[cfg(feature = "poll")]
+pub use imp::io_mpx::{sys_poll, sys_ppoll};
+#[cfg(feature = "mmap")]
+pub use imp::mmap::{sys_mmap, sys_mprotect, sys_msync, sys_munmap};
 #[cfg(feature = "net")]
 pub use imp::net::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
-    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_shutdown,
-    sys_socket,
+    sys_getsockname, sys_getsockopt, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto,
+    sys_setsockopt, sys_shutdown, sys_socket, sys_socketpair,
 };
 #[cfg(feature = "pipe")]
 pub use imp::pipe::sys_pipe;
 #[cfg(feature = "multitask")]
+pub use imp::pthread::cond::{
+    sys_pthread_cond_broadcast, sys_pthread_cond_destroy, sys_pthread_cond_init,
+    sys_pthread_cond_signal, sys_pthread_cond_timedwait, sys_pthread_cond_wait,
+};
+#[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
-    sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
+    sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_trylock,
+    sys_pthread_mutex_unlock,
 };
 #[cfg(feature = "multitask")]
-pub use imp::pthread::{sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_self};
+pub use imp::pthread::rwlock::{
+    sys_pthread_rwlock_destroy, sys_pthread_rwlock_init, sys_pthread_rwlock_rdlock,
+    sys_pthread_rwlock_tryrdlock, sys_pthread_rwlock_trywrlock, sys_pthread_rwlock_unlock,
+    sys_pthread_rwlock_wrlock,
+};
+#[cfg(feature = "multitask")]
+pub use imp::pthread::{
+    sys_pthread_create, sys_pthread_exit, sys_pthread_join, sys_pthread_kill, sys_pthread_self,
+};
+#[cfg(feature = "multitask")]
+pub use imp::semaphore::{
+    sys_sem_destroy, sys_sem_getvalue, sys_sem_init, sys_sem_post, sys_sem_timedwait,
+    sys_sem_trywait, sys_sem_wait,
+};
+#[cfg(feature = "multitask")]
+pub use imp::signal::{
+    sys_kill, sys_raise, sys_sigaction, sys_sigpending, sys_sigprocmask, sys_sigsuspend,
+};
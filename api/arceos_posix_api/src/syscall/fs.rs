@@ -0,0 +1,243 @@
+//! File system related system calls whose Linux ABI differs from the POSIX
+//! functions.
+
+use alloc::ffi::CString;
+use alloc::format;
+use core::ffi::{c_char, c_int};
+
+use axerrno::{LinuxError, LinuxResult};
+
+use crate::ctypes;
+use crate::utils::char_ptr_to_str;
+
+/// The `struct stat` used by the Linux kernel on x86_64.
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct KernelStat {
+    st_dev: u64,
+    st_ino: u64,
+    st_nlink: u64,
+    st_mode: u32,
+    st_uid: u32,
+    st_gid: u32,
+    __pad0: u32,
+    st_rdev: u64,
+    st_size: i64,
+    st_blksize: i64,
+    st_blocks: i64,
+    st_atime: ctypes::timespec,
+    st_mtime: ctypes::timespec,
+    st_ctime: ctypes::timespec,
+    __unused: [i64; 3],
+}
+
+/// The generic `struct stat` used by the Linux kernel on riscv64 and
+/// aarch64.
+#[cfg(not(target_arch = "x86_64"))]
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct KernelStat {
+    st_dev: u64,
+    st_ino: u64,
+    st_mode: u32,
+    st_nlink: u32,
+    st_uid: u32,
+    st_gid: u32,
+    st_rdev: u64,
+    __pad1: u64,
+    st_size: i64,
+    st_blksize: i32,
+    __pad2: i32,
+    st_blocks: i64,
+    st_atime: ctypes::timespec,
+    st_mtime: ctypes::timespec,
+    st_ctime: ctypes::timespec,
+    __unused: [u32; 2],
+}
+
+impl From<ctypes::stat> for KernelStat {
+    fn from(st: ctypes::stat) -> Self {
+        Self {
+            st_dev: st.st_dev as _,
+            st_ino: st.st_ino as _,
+            st_nlink: st.st_nlink as _,
+            st_mode: st.st_mode as _,
+            st_uid: st.st_uid as _,
+            st_gid: st.st_gid as _,
+            st_rdev: st.st_rdev as _,
+            st_size: st.st_size as _,
+            st_blksize: st.st_blksize as _,
+            st_blocks: st.st_blocks as _,
+            st_atime: st.st_atime,
+            st_mtime: st.st_mtime,
+            st_ctime: st.st_ctime,
+            ..Default::default()
+        }
+    }
+}
+
+/// Resolves `path` relative to the directory `dirfd` to an absolute path.
+///
+/// Relative paths are resolved against the working directory of the current
+/// process if `dirfd` is `AT_FDCWD`. Paths relative to other directory file
+/// descriptors are not supported.
+pub(super) fn resolve_path(dirfd: c_int, path: *const c_char) -> LinuxResult<CString> {
+    let path = char_ptr_to_str(path)?;
+    let abs_path = if path.starts_with('/') {
+        path.into()
+    } else if path.is_empty() {
+        return Err(LinuxError::ENOENT);
+    } else if dirfd != ctypes::AT_FDCWD {
+        return Err(LinuxError::EOPNOTSUPP);
+    } else {
+        let cwd = axprocess::current().ok_or(LinuxError::ESRCH)?.cwd();
+        if cwd.ends_with('/') {
+            format!("{}{}", cwd, path)
+        } else {
+            format!("{}/{}", cwd, path)
+        }
+    };
+    CString::new(abs_path).map_err(|_| LinuxError::EINVAL)
+}
+
+/// Converts the result of `f` that fills a `ctypes::stat`, and writes it to
+/// `buf` in the kernel layout.
+unsafe fn stat_to_kernel(
+    buf: *mut KernelStat,
+    f: impl FnOnce(*mut ctypes::stat) -> c_int,
+) -> c_int {
+    if buf.is_null() {
+        return -LinuxError::EFAULT.code();
+    }
+    let mut st = ctypes::stat::default();
+    let ret = f(&mut st);
+    if ret == 0 {
+        unsafe { buf.write(st.into()) };
+    }
+    ret
+}
+
+/// Open a file relative to the directory `dirfd`.
+pub fn sys_openat(dirfd: c_int, path: *const c_char, flags: c_int, mode: ctypes::mode_t) -> c_int {
+    match resolve_path(dirfd, path) {
+        Ok(path) => crate::sys_open(path.as_ptr(), flags, mode),
+        Err(e) => -e.code(),
+    }
+}
+
+/// Get the file status of `path` relative to the directory `dirfd`, or of
+/// `dirfd` itself if `path` is empty and `AT_EMPTY_PATH` is set.
+pub unsafe fn sys_newfstatat(
+    dirfd: c_int,
+    path: *const c_char,
+    buf: *mut KernelStat,
+    flags: c_int,
+) -> c_int {
+    let flags = flags as u32;
+    if flags & ctypes::AT_EMPTY_PATH != 0 && !path.is_null() && unsafe { *path } == 0 {
+        return unsafe { sys_fstat(dirfd, buf) };
+    }
+    let path = match resolve_path(dirfd, path) {
+        Ok(path) => path,
+        Err(e) => return -e.code(),
+    };
+    unsafe {
+        stat_to_kernel(buf, |st| {
+            if flags & ctypes::AT_SYMLINK_NOFOLLOW != 0 {
+                crate::sys_lstat(path.as_ptr(), st) as _
+            } else {
+                crate::sys_stat(path.as_ptr(), st)
+            }
+        })
+    }
+}
+
+/// Get the file status of the file descriptor `fd`.
+pub unsafe fn sys_fstat(fd: c_int, buf: *mut KernelStat) -> c_int {
+    unsafe { stat_to_kernel(buf, |st| crate::sys_fstat(fd, st)) }
+}
+
+/// Read the target of the symbolic link `path` relative to the directory
+/// `dirfd`.
+pub unsafe fn sys_readlinkat(
+    dirfd: c_int,
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsiz: usize,
+) -> ctypes::ssize_t {
+    match resolve_path(dirfd, path) {
+        Ok(path) => unsafe { crate::sys_readlink(path.as_ptr(), buf, bufsiz) },
+        Err(e) => -e.code() as _,
+    }
+}
+
+/// Create the symbolic link `linkpath` relative to the directory `dirfd`.
+pub fn sys_symlinkat(target: *const c_char, dirfd: c_int, linkpath: *const c_char) -> c_int {
+    match resolve_path(dirfd, linkpath) {
+        Ok(linkpath) => crate::sys_symlink(target, linkpath.as_ptr()),
+        Err(e) => -e.code(),
+    }
+}
+
+/// Rename `old` relative to `old_dirfd` to `new` relative to `new_dirfd`.
+///
+/// No `flags` (e.g., `RENAME_NOREPLACE`) are supported.
+pub fn sys_renameat2(
+    old_dirfd: c_int,
+    old: *const c_char,
+    new_dirfd: c_int,
+    new: *const c_char,
+    flags: c_int,
+) -> c_int {
+    if flags != 0 {
+        return -LinuxError::EINVAL.code();
+    }
+    match (resolve_path(old_dirfd, old), resolve_path(new_dirfd, new)) {
+        (Ok(old), Ok(new)) => crate::sys_rename(old.as_ptr(), new.as_ptr()),
+        (Err(e), _) | (_, Err(e)) => -e.code(),
+    }
+}
+
+/// Get the working directory of the current process.
+///
+/// Unlike `getcwd` in libc, it returns the length of the path including the
+/// NUL terminator.
+pub fn sys_getcwd(buf: *mut c_char, size: usize) -> isize {
+    syscall_body!(sys_getcwd, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let cwd = axprocess::current().ok_or(LinuxError::ESRCH)?.cwd();
+        let len = cwd.len() + 1;
+        if len > size {
+            return Err(LinuxError::ERANGE);
+        }
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst[..cwd.len()].copy_from_slice(cwd.as_bytes());
+        dst[cwd.len()] = 0;
+        Ok(len as isize)
+    })
+}
+
+/// Change the working directory of the current process to `path`.
+pub fn sys_chdir(path: *const c_char) -> c_int {
+    syscall_body!(sys_chdir, {
+        let path = resolve_path(ctypes::AT_FDCWD, path)?;
+        let path = axfs::api::canonicalize(path.to_str().map_err(|_| LinuxError::EINVAL)?)?;
+        if !axfs::api::metadata(&path)?.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        debug!("sys_chdir <= {:?}", path);
+        axprocess::current().ok_or(LinuxError::ESRCH)?.set_cwd(path);
+        Ok(0)
+    })
+}
+
+/// Duplicate `old_fd` to `new_fd`, which must be different.
+pub fn sys_dup3(old_fd: c_int, new_fd: c_int, flags: c_int) -> c_int {
+    if old_fd == new_fd || flags as u32 & !ctypes::O_CLOEXEC != 0 {
+        return -LinuxError::EINVAL.code();
+    }
+    crate::sys_dup2(old_fd, new_fd)
+}
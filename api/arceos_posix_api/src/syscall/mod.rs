@@ -0,0 +1,320 @@
+//! Linux-compatible system call interface for user programs.
+//!
+//! System calls trapped from user space are dispatched by the Linux syscall
+//! numbers of the current architecture, and routed to the POSIX functions of
+//! this crate. Unknown or disabled system calls return `ENOSYS`.
+
+mod fs;
+mod signal;
+mod sysno;
+mod task;
+pub(crate) mod uaccess;
+
+use core::ffi::c_int;
+
+use axerrno::LinuxError;
+use axhal::arch::TrapFrame;
+use axhal::trap::SyscallHandler;
+
+use crate::ctypes;
+use crate::imp::{fd_ops::*, fs::sys_lseek, io::*, mmap::*, resources::*, task::*, time::*};
+
+#[cfg(feature = "select")]
+use crate::imp::io_mpx::sys_select;
+#[cfg(feature = "epoll")]
+use crate::imp::io_mpx::{sys_epoll_create, sys_epoll_ctl, sys_epoll_wait};
+#[cfg(feature = "poll")]
+use crate::imp::io_mpx::{sys_poll, sys_ppoll};
+#[cfg(feature = "net")]
+use crate::imp::net::*;
+
+/// Create a pipe with `flags`, the file descriptors are written to `fds`.
+///
+/// The flags (e.g., `O_NONBLOCK`) are ignored.
+#[cfg(feature = "pipe")]
+unsafe fn sys_pipe2(fds: *mut c_int, _flags: c_int) -> c_int {
+    if fds.is_null() {
+        return -LinuxError::EFAULT.code();
+    }
+    crate::sys_pipe(unsafe { core::slice::from_raw_parts_mut(fds, 2) })
+}
+
+/// Get and set the resource limits of the process `pid`.
+unsafe fn sys_prlimit64(
+    pid: c_int,
+    resource: c_int,
+    new_limit: *const ctypes::rlimit,
+    old_limit: *mut ctypes::rlimit,
+) -> c_int {
+    if pid != 0 && pid != sys_getpid() {
+        return -LinuxError::ESRCH.code();
+    }
+    if !old_limit.is_null() {
+        let ret = unsafe { sys_getrlimit(resource, old_limit) };
+        if ret < 0 {
+            return ret;
+        }
+    }
+    if !new_limit.is_null() {
+        return unsafe { sys_setrlimit(resource, new_limit as _) };
+    }
+    0
+}
+
+/// Get the CPU affinity mask of the thread `pid`.
+///
+/// Unlike the libc function, it returns the size of the mask in bytes.
+unsafe fn sys_sched_getaffinity_linux(
+    pid: c_int,
+    cpusetsize: usize,
+    mask: *mut ctypes::cpu_set_t,
+) -> isize {
+    match unsafe { sys_sched_getaffinity(pid, cpusetsize, mask) } {
+        0 => core::mem::size_of::<ctypes::cpu_set_t>().min(cpusetsize) as isize,
+        e => e as isize,
+    }
+}
+
+/// Like `select`, but with a `timespec` timeout. The signal mask is ignored.
+#[cfg(feature = "select")]
+unsafe fn sys_pselect6(
+    nfds: c_int,
+    readfds: *mut ctypes::fd_set,
+    writefds: *mut ctypes::fd_set,
+    exceptfds: *mut ctypes::fd_set,
+    timeout: *const ctypes::timespec,
+) -> c_int {
+    let mut tv = ctypes::timeval::default();
+    let tv_ptr = if timeout.is_null() {
+        core::ptr::null_mut()
+    } else {
+        let ts = unsafe { *timeout };
+        tv.tv_sec = ts.tv_sec;
+        tv.tv_usec = ts.tv_nsec / 1000;
+        &mut tv as *mut _
+    };
+    unsafe { sys_select(nfds, readfds, writefds, exceptfds, tv_ptr) }
+}
+
+/// Create an epoll instance with `flags`, which can only be `EPOLL_CLOEXEC`.
+#[cfg(feature = "epoll")]
+fn sys_epoll_create1(flags: c_int) -> c_int {
+    if flags as u32 & !ctypes::EPOLL_CLOEXEC != 0 {
+        return -LinuxError::EINVAL.code();
+    }
+    sys_epoll_create(1)
+}
+
+#[cfg(target_arch = "x86_64")]
+const ARCH_SET_FS: c_int = 0x1002;
+#[cfg(target_arch = "x86_64")]
+const ARCH_GET_FS: c_int = 0x1003;
+
+/// Set or get the architecture-specific thread state, only the FS base is
+/// supported.
+#[cfg(target_arch = "x86_64")]
+fn sys_arch_prctl(code: c_int, addr: usize) -> c_int {
+    debug!("sys_arch_prctl <= {:#x} {:#x}", code, addr);
+    syscall_body!(sys_arch_prctl, {
+        match code {
+            ARCH_SET_FS => unsafe { axhal::arch::write_thread_pointer(addr) },
+            ARCH_GET_FS => {
+                crate::utils::check_null_mut_ptr(addr as *mut usize)?;
+                unsafe { *(addr as *mut usize) = axhal::arch::read_thread_pointer() };
+            }
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
+
+/// Get the thread ID, i.e., the task ID.
+fn sys_gettid() -> isize {
+    axtask::current().id().as_u64() as isize
+}
+
+/// Dispatches the system call `sysno` with arguments `a`, returns `None` if
+/// it's not supported.
+///
+/// `tf` is the trap frame of the system call, which is copied by `clone`,
+/// replaced by `execve`, and changed to run signal handlers. The user memory
+/// passed in the arguments is checked before dispatching.
+unsafe fn dispatch(tf: &mut TrapFrame, sysno: usize, a: [usize; 6]) -> Option<isize> {
+    if let Err(e) = unsafe { uaccess::check_args(sysno, &a) } {
+        return Some(-e.code() as _);
+    }
+    let ret = unsafe {
+        match sysno {
+            sysno::read => sys_read(a[0] as _, a[1] as _, a[2]) as _,
+            sysno::write => sys_write(a[0] as _, a[1] as _, a[2]) as _,
+            sysno::writev => sys_writev(a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::lseek => sys_lseek(a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::close => sys_close(a[0] as _) as _,
+            sysno::dup => sys_dup(a[0] as _) as _,
+            sysno::dup3 => fs::sys_dup3(a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::fcntl => sys_fcntl(a[0] as _, a[1] as _, a[2]) as _,
+            sysno::openat => fs::sys_openat(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _,
+            sysno::newfstatat => {
+                fs::sys_newfstatat(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _
+            }
+            sysno::fstat => fs::sys_fstat(a[0] as _, a[1] as _) as _,
+            sysno::readlinkat => fs::sys_readlinkat(a[0] as _, a[1] as _, a[2] as _, a[3]) as _,
+            sysno::symlinkat => fs::sys_symlinkat(a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::renameat => {
+                fs::sys_renameat2(a[0] as _, a[1] as _, a[2] as _, a[3] as _, 0) as _
+            }
+            sysno::renameat2 => {
+                fs::sys_renameat2(a[0] as _, a[1] as _, a[2] as _, a[3] as _, a[4] as _) as _
+            }
+            sysno::getcwd => fs::sys_getcwd(a[0] as _, a[1]),
+            sysno::chdir => fs::sys_chdir(a[0] as _) as _,
+            #[cfg(feature = "pipe")]
+            sysno::pipe2 => sys_pipe2(a[0] as _, a[1] as _) as _,
+            #[cfg(feature = "select")]
+            sysno::pselect6 => {
+                sys_pselect6(a[0] as _, a[1] as _, a[2] as _, a[3] as _, a[4] as _) as _
+            }
+            #[cfg(feature = "poll")]
+            sysno::ppoll => sys_ppoll(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _,
+            #[cfg(feature = "epoll")]
+            sysno::epoll_create1 => sys_epoll_create1(a[0] as _) as _,
+            #[cfg(feature = "epoll")]
+            sysno::epoll_ctl => sys_epoll_ctl(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _,
+            #[cfg(feature = "epoll")]
+            sysno::epoll_pwait => sys_epoll_wait(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _,
+            sysno::mmap => {
+                sys_mmap(a[0] as _, a[1], a[2] as _, a[3] as _, a[4] as _, a[5] as _) as _
+            }
+            sysno::munmap => sys_munmap(a[0] as _, a[1]) as _,
+            sysno::mprotect => sys_mprotect(a[0] as _, a[1], a[2] as _) as _,
+            sysno::msync => sys_msync(a[0] as _, a[1], a[2] as _) as _,
+            sysno::exit | sysno::exit_group => task::sys_exit_group(a[0] as _),
+            sysno::kill => signal::sys_kill(a[0] as _, a[1] as _) as _,
+            sysno::tkill => signal::sys_tkill(a[0] as _, a[1] as _) as _,
+            sysno::tgkill => signal::sys_tgkill(a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::rt_sigaction => {
+                signal::sys_rt_sigaction(a[0] as _, a[1] as _, a[2] as _, a[3]) as _
+            }
+            sysno::rt_sigprocmask => {
+                signal::sys_rt_sigprocmask(a[0] as _, a[1] as _, a[2] as _, a[3]) as _
+            }
+            sysno::rt_sigpending => signal::sys_rt_sigpending(a[0] as _, a[1]) as _,
+            sysno::rt_sigsuspend => signal::sys_rt_sigsuspend(tf, a[0] as _, a[1]),
+            sysno::rt_sigreturn => signal::sys_rt_sigreturn(tf),
+            sysno::clone => task::sys_clone(tf, a[0], a[1]) as _,
+            sysno::execve => task::sys_execve(tf, a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::wait4 => task::sys_wait4(a[0] as _, a[1] as _, a[2] as _) as _,
+            sysno::set_tid_address => task::sys_set_tid_address(a[0] as _) as _,
+            sysno::gettid => sys_gettid(),
+            sysno::getpid => sys_getpid() as _,
+            sysno::getppid => task::sys_getppid() as _,
+            sysno::futex => crate::imp::futex::sys_futex(
+                a[0] as _, a[1] as _, a[2] as _, a[3] as _, a[4] as _, a[5] as _,
+            ) as _,
+            sysno::sched_yield => sys_sched_yield() as _,
+            sysno::sched_setaffinity => sys_sched_setaffinity(a[0] as _, a[1], a[2] as _) as _,
+            sysno::sched_getaffinity => sys_sched_getaffinity_linux(a[0] as _, a[1], a[2] as _),
+            sysno::nanosleep => sys_nanosleep(a[0] as _, a[1] as _) as _,
+            sysno::clock_gettime => sys_clock_gettime(a[0] as _, a[1] as _) as _,
+            sysno::prlimit64 => sys_prlimit64(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::socket => sys_socket(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::bind => sys_bind(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::connect => sys_connect(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::listen => sys_listen(a[0] as _, a[1] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::accept | sysno::accept4 => sys_accept(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::shutdown => sys_shutdown(a[0] as _, a[1] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::sendto => {
+                sys_sendto(a[0] as _, a[1] as _, a[2], a[3] as _, a[4] as _, a[5] as _) as _
+            }
+            #[cfg(feature = "net")]
+            sysno::recvfrom => {
+                sys_recvfrom(a[0] as _, a[1] as _, a[2], a[3] as _, a[4] as _, a[5] as _) as _
+            }
+            #[cfg(feature = "net")]
+            sysno::getsockname => sys_getsockname(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(feature = "net")]
+            sysno::getpeername => sys_getpeername(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::open => fs::sys_openat(ctypes::AT_FDCWD, a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::stat => fs::sys_newfstatat(ctypes::AT_FDCWD, a[0] as _, a[1] as _, 0) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::lstat => fs::sys_newfstatat(
+                ctypes::AT_FDCWD,
+                a[0] as _,
+                a[1] as _,
+                ctypes::AT_SYMLINK_NOFOLLOW as _,
+            ) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::readlink => {
+                fs::sys_readlinkat(ctypes::AT_FDCWD, a[0] as _, a[1] as _, a[2]) as _
+            }
+            #[cfg(target_arch = "x86_64")]
+            sysno::symlink => fs::sys_symlinkat(a[0] as _, ctypes::AT_FDCWD, a[1] as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::rename => {
+                fs::sys_renameat2(ctypes::AT_FDCWD, a[0] as _, ctypes::AT_FDCWD, a[1] as _, 0) as _
+            }
+            #[cfg(target_arch = "x86_64")]
+            sysno::dup2 => sys_dup2(a[0] as _, a[1] as _) as _,
+            #[cfg(all(target_arch = "x86_64", feature = "pipe"))]
+            sysno::pipe => sys_pipe2(a[0] as _, 0) as _,
+            #[cfg(all(target_arch = "x86_64", feature = "select"))]
+            sysno::select => sys_select(a[0] as _, a[1] as _, a[2] as _, a[3] as _, a[4] as _) as _,
+            #[cfg(all(target_arch = "x86_64", feature = "poll"))]
+            sysno::poll => sys_poll(a[0] as _, a[1] as _, a[2] as _) as _,
+            #[cfg(all(target_arch = "x86_64", feature = "epoll"))]
+            sysno::epoll_create => sys_epoll_create(a[0] as _) as _,
+            #[cfg(all(target_arch = "x86_64", feature = "epoll"))]
+            sysno::epoll_wait => sys_epoll_wait(a[0] as _, a[1] as _, a[2] as _, a[3] as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::getrlimit => sys_getrlimit(a[0] as _, a[1] as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::setrlimit => sys_setrlimit(a[0] as _, a[1] as _) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::arch_prctl => sys_arch_prctl(a[0] as _, a[1]) as _,
+            #[cfg(target_arch = "x86_64")]
+            sysno::fork | sysno::vfork => task::sys_clone(tf, 0, 0) as _,
+            _ => return None,
+        }
+    };
+    Some(ret)
+}
+
+struct SyscallHandlerImpl;
+
+#[crate_interface::impl_interface]
+impl SyscallHandler for SyscallHandlerImpl {
+    fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
+        let args = [
+            tf.arg0(),
+            tf.arg1(),
+            tf.arg2(),
+            tf.arg3(),
+            tf.arg4(),
+            tf.arg5(),
+        ];
+        let Some(name) = sysno::syscall_name(syscall_num) else {
+            warn!("unsupported syscall: {}, args: {:x?}", syscall_num, args);
+            return -LinuxError::ENOSYS.code() as _;
+        };
+        trace!("syscall {} <= {:x?}", name, args);
+        let ret = unsafe { dispatch(tf, syscall_num, args) }.unwrap_or_else(|| {
+            warn!("unsupported syscall: {}, args: {:x?}", name, args);
+            -LinuxError::ENOSYS.code() as _
+        });
+        trace!("syscall {} => {}", name, ret);
+        ret
+    }
+
+    fn handle_user_return(tf: &mut TrapFrame) {
+        signal::handle_user_return(tf);
+    }
+}
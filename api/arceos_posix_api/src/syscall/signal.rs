@@ -0,0 +1,328 @@
+//! Signal related system calls, and the delivery of signals to user
+//! processes.
+//!
+//! When a signal handler is run, a [`SignalFrame`] with the interrupted
+//! context is saved on the user stack, and the handler returns to the
+//! restorer (`SA_RESTORER`) or the [trampoline] that calls `rt_sigreturn` to
+//! restore the context. The FP/SIMD registers are not saved, and the
+//! alternate signal stack (`SA_ONSTACK`) is not supported.
+//!
+//! [trampoline]: axprocess::sigreturn_trampoline
+
+use core::ffi::{c_int, c_ulong};
+use core::mem::size_of;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::arch::{TrapFrame, UspaceContext};
+use axtask::{AxTaskRef, WaitQueue};
+
+use super::uaccess::{check_ptr, check_region};
+use crate::ctypes;
+use crate::imp::signal::{
+    self, default_action, sigbit, take_sig_action, SigAction, SigInfo, SIG_DFL, SIG_IGN,
+    UNBLOCKABLE,
+};
+
+/// The `struct sigaction` used by the Linux kernel.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KernelSigAction {
+    handler: usize,
+    flags: c_ulong,
+    #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+    restorer: usize,
+    mask: u64,
+}
+
+impl From<KernelSigAction> for SigAction {
+    fn from(act: KernelSigAction) -> Self {
+        Self {
+            handler: act.handler,
+            flags: act.flags as _,
+            mask: act.mask,
+            #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+            restorer: act.restorer,
+            #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+            restorer: 0,
+        }
+    }
+}
+
+impl From<SigAction> for KernelSigAction {
+    fn from(act: SigAction) -> Self {
+        Self {
+            handler: act.handler,
+            flags: act.flags as _,
+            #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+            restorer: act.restorer,
+            mask: act.mask,
+        }
+    }
+}
+
+/// The frame pushed onto the user stack when running a signal handler.
+#[repr(C)]
+struct SignalFrame {
+    info: SigInfo,
+    /// The interrupted context.
+    uctx: UspaceContext,
+    /// The blocked signals to restore.
+    blocked: u64,
+}
+
+fn check_sigsetsize(sigsetsize: usize) -> LinuxResult {
+    if sigsetsize == size_of::<u64>() {
+        Ok(())
+    } else {
+        Err(LinuxError::EINVAL)
+    }
+}
+
+/// Returns the task of the user process `pid`.
+fn find_process_task(pid: c_int) -> LinuxResult<AxTaskRef> {
+    let process = if pid == 0 {
+        axprocess::current()
+    } else if pid > 0 {
+        axprocess::find(pid as _)
+    } else {
+        None
+    };
+    process.map(|p| p.task().clone()).ok_or(LinuxError::ESRCH)
+}
+
+/// Sets up `tf` to run the handler of signal `signo`, and saves the current
+/// context and the blocked signals `blocked` on the user stack.
+///
+/// The process is killed by `SIGSEGV` if the user stack is not writable.
+fn setup_frame(tf: &mut TrapFrame, signo: usize, act: &SigAction, blocked: u64) {
+    let mut uctx = UspaceContext::from(&*tf);
+    // skip the red zone on x86_64
+    let sp = uctx
+        .get_sp()
+        .wrapping_sub(if cfg!(target_arch = "x86_64") { 128 } else { 0 });
+    let frame_addr = sp.wrapping_sub(size_of::<SignalFrame>()) & !0xf;
+    let frame = SignalFrame {
+        info: SigInfo::new(signo),
+        uctx: UspaceContext::from(&*tf),
+        blocked,
+    };
+    let restorer = if act.flags & ctypes::SA_RESTORER != 0 && act.restorer != 0 {
+        act.restorer
+    } else {
+        axprocess::sigreturn_trampoline().as_usize()
+    };
+
+    // push the return address on x86_64, so that the stack is aligned as on
+    // a function call
+    #[cfg(target_arch = "x86_64")]
+    let sp = frame_addr.wrapping_sub(size_of::<usize>());
+    #[cfg(not(target_arch = "x86_64"))]
+    let sp = frame_addr;
+    let size = frame_addr - sp + size_of::<SignalFrame>();
+    if check_region(sp, size, true).is_err() {
+        warn!(
+            "failed to set up the frame for signal {} @ {:#x}",
+            signo, sp
+        );
+        axprocess::kill_current(ctypes::SIGSEGV as _);
+    }
+    // the checked memory is mapped in the current page table
+    unsafe { (frame_addr as *mut SignalFrame).write(frame) };
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        (sp as *mut usize).write(restorer)
+    };
+
+    #[cfg(not(target_arch = "x86_64"))]
+    uctx.set_ra(restorer);
+    uctx.set_ip(act.handler);
+    uctx.set_sp(sp);
+    let info_ptr = if act.flags & ctypes::SA_SIGINFO != 0 {
+        frame_addr
+    } else {
+        0
+    };
+    uctx.set_args(signo, info_ptr, 0);
+    *tf = uctx.into();
+}
+
+/// Delivers the pending signals of the current process on returning to user
+/// space with `tf`. `blocked` is the signal mask to restore after the
+/// handler returns.
+///
+/// At most one handler is set up, returns `true` if there is one.
+fn deliver_signals(tf: &mut TrapFrame, blocked: u64) -> bool {
+    let curr = axtask::current();
+    while let Some(signo) = curr.take_pending_signal() {
+        let act = take_sig_action(signo);
+        match act.handler {
+            SIG_DFL => default_action(signo),
+            SIG_IGN => {}
+            _ => {
+                debug!("deliver signal {} to task {}", signo, curr.id_name());
+                setup_frame(tf, signo, &act, blocked);
+                let mut handler_blocked = curr.blocked_signals() | act.mask;
+                if act.flags & ctypes::SA_NODEFER == 0 {
+                    handler_blocked |= sigbit(signo);
+                }
+                curr.set_blocked_signals(handler_blocked & !UNBLOCKABLE);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Delivers the pending signals before returning to user space with `tf`.
+pub(super) fn handle_user_return(tf: &mut TrapFrame) {
+    let curr = axtask::current();
+    if curr.has_pending_signal() && axprocess::current().is_some() {
+        deliver_signals(tf, curr.blocked_signals());
+    }
+}
+
+/// Examine and change the action of signal `signum`, in the kernel layout.
+pub unsafe fn sys_rt_sigaction(
+    signum: c_int,
+    act: *const KernelSigAction,
+    oldact: *mut KernelSigAction,
+    sigsetsize: usize,
+) -> c_int {
+    debug!("sys_rt_sigaction <= {} {:#x}", signum, act as usize);
+    syscall_body!(sys_rt_sigaction, {
+        check_sigsetsize(sigsetsize)?;
+        if !(1..=signal::NSIG as c_int).contains(&signum) {
+            return Err(LinuxError::EINVAL);
+        }
+        let act = unsafe { act.as_ref() }.map(|act| SigAction::from(*act));
+        let old = signal::sigaction(signum as usize, act)?;
+        if let Some(oldact) = unsafe { oldact.as_mut() } {
+            *oldact = old.into();
+        }
+        Ok(0)
+    })
+}
+
+/// Examine and change the blocked signals, in the kernel layout.
+pub unsafe fn sys_rt_sigprocmask(
+    how: c_int,
+    set: *const u64,
+    oldset: *mut u64,
+    sigsetsize: usize,
+) -> c_int {
+    syscall_body!(sys_rt_sigprocmask, {
+        check_sigsetsize(sigsetsize)?;
+        let old = signal::sigprocmask(how, unsafe { set.as_ref() }.copied())?;
+        if let Some(oldset) = unsafe { oldset.as_mut() } {
+            *oldset = old;
+        }
+        Ok(0)
+    })
+}
+
+/// Get the pending signals, in the kernel layout.
+pub unsafe fn sys_rt_sigpending(set: *mut u64, sigsetsize: usize) -> c_int {
+    syscall_body!(sys_rt_sigpending, {
+        check_sigsetsize(sigsetsize)?;
+        let set = unsafe { set.as_mut() }.ok_or(LinuxError::EFAULT)?;
+        *set = axtask::current().pending_signals();
+        Ok(0)
+    })
+}
+
+/// Replace the blocked signals with `mask` and wait for a signal.
+///
+/// The handler is set up here with the original blocked signals saved, so
+/// that they are restored by `rt_sigreturn`. Returns the value to write to
+/// the return value register.
+pub unsafe fn sys_rt_sigsuspend(tf: &mut TrapFrame, mask: *const u64, sigsetsize: usize) -> isize {
+    if let Err(e) = check_sigsetsize(sigsetsize) {
+        return -e.code() as _;
+    }
+    let Some(&mask) = (unsafe { mask.as_ref() }) else {
+        return -LinuxError::EFAULT.code() as _;
+    };
+    debug!("sys_rt_sigsuspend <= {:#x}", mask);
+    let curr = axtask::current();
+    let old = curr.set_blocked_signals(mask & !UNBLOCKABLE);
+    WaitQueue::new().wait_until_interruptible(|| false);
+
+    let mut uctx = UspaceContext::from(&*tf);
+    uctx.set_retval(-LinuxError::EINTR.code() as _);
+    *tf = uctx.into();
+    if !deliver_signals(tf, old) {
+        curr.set_blocked_signals(old);
+    }
+    UspaceContext::from(&*tf).get_retval() as _
+}
+
+/// Return from the signal handler, and restore the context saved in the
+/// signal frame on the user stack.
+///
+/// Returns the value of the return value register of the restored context.
+pub fn sys_rt_sigreturn(tf: &mut TrapFrame) -> isize {
+    let mut uctx = UspaceContext::from(&*tf);
+    // the return address has been popped on x86_64
+    let frame_addr = uctx.get_sp();
+    let frame_ptr = frame_addr as *const SignalFrame;
+    if frame_ptr.is_null() || check_ptr(frame_ptr).is_err() {
+        warn!("bad signal frame @ {:#x}", frame_addr);
+        axprocess::kill_current(ctypes::SIGSEGV as _);
+    }
+    // the frame may be misaligned if the user stack pointer is changed
+    let frame = unsafe { frame_ptr.read_unaligned() };
+    debug!("sys_rt_sigreturn @ {:#x}", frame.uctx.get_ip());
+    // returning to a non-canonical address faults in the kernel on x86_64
+    #[cfg(target_arch = "x86_64")]
+    if ((frame.uctx.get_ip() << 16) as isize >> 16) as usize != frame.uctx.get_ip() {
+        warn!("bad return address @ {:#x}", frame.uctx.get_ip());
+        axprocess::kill_current(ctypes::SIGSEGV as _);
+    }
+    uctx.restore_user_regs(&frame.uctx);
+    axtask::current().set_blocked_signals(frame.blocked & !UNBLOCKABLE);
+    *tf = uctx.into();
+    UspaceContext::from(&*tf).get_retval() as _
+}
+
+/// Send signal `sig` to the process `pid`, or the current process if `pid`
+/// is `0`.
+///
+/// Process groups are not supported, so `pid` cannot be negative.
+pub fn sys_kill(pid: c_int, sig: c_int) -> c_int {
+    debug!("sys_kill <= {} {}", pid, sig);
+    syscall_body!(sys_kill, {
+        signal::send_signal(&find_process_task(pid)?, sig)?;
+        Ok(0)
+    })
+}
+
+/// Send signal `sig` to the thread `tid`.
+///
+/// Each process has only one thread, whose ID is the process ID.
+pub fn sys_tkill(tid: c_int, sig: c_int) -> c_int {
+    debug!("sys_tkill <= {} {}", tid, sig);
+    syscall_body!(sys_tkill, {
+        if tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        signal::send_signal(&find_process_task(tid)?, sig)?;
+        Ok(0)
+    })
+}
+
+/// Send signal `sig` to the thread `tid` of the process `tgid`.
+///
+/// Each process has only one thread, whose ID is the process ID.
+pub fn sys_tgkill(tgid: c_int, tid: c_int, sig: c_int) -> c_int {
+    debug!("sys_tgkill <= {} {} {}", tgid, tid, sig);
+    syscall_body!(sys_tgkill, {
+        if tgid <= 0 || tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if tgid != tid {
+            return Err(LinuxError::ESRCH);
+        }
+        signal::send_signal(&find_process_task(tid)?, sig)?;
+        Ok(0)
+    })
+}
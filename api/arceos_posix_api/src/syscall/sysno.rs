@@ -0,0 +1,169 @@
+//! Linux system call numbers of the supported system calls.
+
+#![allow(dead_code, non_upper_case_globals)]
+
+macro_rules! define_sysno {
+    ($($name: ident = $num: literal,)*) => {
+        $(pub const $name: usize = $num;)*
+
+        /// Returns the name of the system call `sysno`, or `None` if it's
+        /// not in the table.
+        pub fn syscall_name(sysno: usize) -> Option<&'static str> {
+            match sysno {
+                $($num => Some(stringify!($name)),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+// The generic table in `include/uapi/asm-generic/unistd.h`.
+#[cfg(not(target_arch = "x86_64"))]
+define_sysno! {
+    getcwd = 17,
+    epoll_create1 = 20,
+    epoll_ctl = 21,
+    epoll_pwait = 22,
+    dup = 23,
+    dup3 = 24,
+    fcntl = 25,
+    symlinkat = 36,
+    renameat = 38,
+    chdir = 49,
+    openat = 56,
+    close = 57,
+    pipe2 = 59,
+    lseek = 62,
+    read = 63,
+    write = 64,
+    writev = 66,
+    pselect6 = 72,
+    ppoll = 73,
+    readlinkat = 78,
+    newfstatat = 79,
+    fstat = 80,
+    exit = 93,
+    exit_group = 94,
+    set_tid_address = 96,
+    futex = 98,
+    nanosleep = 101,
+    clock_gettime = 113,
+    sched_setaffinity = 122,
+    sched_getaffinity = 123,
+    sched_yield = 124,
+    kill = 129,
+    tkill = 130,
+    tgkill = 131,
+    rt_sigsuspend = 133,
+    rt_sigaction = 134,
+    rt_sigprocmask = 135,
+    rt_sigpending = 136,
+    rt_sigreturn = 139,
+    getpid = 172,
+    getppid = 173,
+    gettid = 178,
+    socket = 198,
+    bind = 200,
+    listen = 201,
+    accept = 202,
+    connect = 203,
+    getsockname = 204,
+    getpeername = 205,
+    sendto = 206,
+    recvfrom = 207,
+    shutdown = 210,
+    munmap = 215,
+    clone = 220,
+    execve = 221,
+    mmap = 222,
+    mprotect = 226,
+    msync = 227,
+    accept4 = 242,
+    wait4 = 260,
+    prlimit64 = 261,
+    renameat2 = 276,
+}
+
+// The table in `arch/x86/entry/syscalls/syscall_64.tbl`.
+#[cfg(target_arch = "x86_64")]
+define_sysno! {
+    read = 0,
+    write = 1,
+    open = 2,
+    close = 3,
+    stat = 4,
+    fstat = 5,
+    lstat = 6,
+    poll = 7,
+    lseek = 8,
+    mmap = 9,
+    mprotect = 10,
+    munmap = 11,
+    rt_sigaction = 13,
+    rt_sigprocmask = 14,
+    rt_sigreturn = 15,
+    writev = 20,
+    pipe = 22,
+    select = 23,
+    sched_yield = 24,
+    msync = 26,
+    dup = 32,
+    dup2 = 33,
+    nanosleep = 35,
+    getpid = 39,
+    socket = 41,
+    connect = 42,
+    accept = 43,
+    sendto = 44,
+    recvfrom = 45,
+    shutdown = 48,
+    bind = 49,
+    listen = 50,
+    getsockname = 51,
+    getpeername = 52,
+    clone = 56,
+    fork = 57,
+    vfork = 58,
+    execve = 59,
+    exit = 60,
+    wait4 = 61,
+    kill = 62,
+    fcntl = 72,
+    getcwd = 79,
+    chdir = 80,
+    rename = 82,
+    symlink = 88,
+    readlink = 89,
+    getrlimit = 97,
+    getppid = 110,
+    rt_sigpending = 127,
+    rt_sigsuspend = 130,
+    arch_prctl = 158,
+    setrlimit = 160,
+    gettid = 186,
+    tkill = 200,
+    futex = 202,
+    sched_setaffinity = 203,
+    sched_getaffinity = 204,
+    epoll_create = 213,
+    set_tid_address = 218,
+    clock_gettime = 228,
+    exit_group = 231,
+    epoll_wait = 232,
+    epoll_ctl = 233,
+    tgkill = 234,
+    openat = 257,
+    newfstatat = 262,
+    renameat = 264,
+    symlinkat = 266,
+    readlinkat = 267,
+    pselect6 = 270,
+    ppoll = 271,
+    epoll_pwait = 281,
+    accept4 = 288,
+    epoll_create1 = 291,
+    dup3 = 292,
+    pipe2 = 293,
+    prlimit64 = 302,
+    renameat2 = 316,
+}
@@ -0,0 +1,136 @@
+//! Process management system calls.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int};
+
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axhal::arch::{TrapFrame, UspaceContext};
+use axprocess::{Pid, Process};
+
+use super::uaccess::{check_ptr, check_str};
+use crate::ctypes;
+use crate::utils::char_ptr_to_str;
+
+const CSIGNAL: usize = 0xff;
+const CLONE_VM: usize = 0x100;
+const CLONE_VFORK: usize = 0x4000;
+
+const WNOHANG: c_int = 1;
+
+fn current_process() -> LinuxResult<Arc<Process>> {
+    axprocess::current().ok_or(LinuxError::ESRCH)
+}
+
+/// Reads the NULL-terminated array of C strings at `ptr` in user memory.
+unsafe fn str_array(ptr: *const *const c_char) -> LinuxResult<Vec<String>> {
+    let mut strs = Vec::new();
+    if ptr.is_null() {
+        return Ok(strs);
+    }
+    loop {
+        let entry = unsafe { ptr.add(strs.len()) };
+        check_ptr(entry)?;
+        let s = unsafe { *entry };
+        if s.is_null() {
+            break;
+        }
+        check_str(s)?;
+        strs.push(char_ptr_to_str(s)?.into());
+    }
+    Ok(strs)
+}
+
+/// Create a child process, which returns to user space with the stack
+/// pointer `stack` if it's not zero.
+///
+/// Only the flags of `fork` and `vfork` are supported. The parent is not
+/// suspended on `vfork`, i.e., it's the same as `fork`.
+pub fn sys_clone(tf: &TrapFrame, flags: usize, stack: usize) -> c_int {
+    debug!("sys_clone <= {:#x} {:#x}", flags, stack);
+    syscall_body!(sys_clone, {
+        const VFORK_FLAGS: usize = CLONE_VM | CLONE_VFORK;
+        let clone_flags = flags & !CSIGNAL;
+        if clone_flags != 0 && clone_flags != VFORK_FLAGS {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut uctx = UspaceContext::from(tf);
+        if stack != 0 {
+            uctx.set_sp(stack);
+        }
+        Ok(current_process()?.fork(uctx)? as c_int)
+    })
+}
+
+/// Replace the program of the current process with the executable at
+/// `path`, the arguments and environment variables are read from `argv` and
+/// `envp`.
+///
+/// On success, `tf` is replaced to enter the new program.
+pub unsafe fn sys_execve(
+    tf: &mut TrapFrame,
+    path: *const c_char,
+    argv: *const *const c_char,
+    envp: *const *const c_char,
+) -> c_int {
+    syscall_body!(sys_execve, {
+        let path = super::fs::resolve_path(ctypes::AT_FDCWD, path)?;
+        let path = path.to_str().map_err(|_| LinuxError::EINVAL)?;
+        let args = unsafe { str_array(argv)? };
+        let envs = unsafe { str_array(envp)? };
+        debug!("sys_execve <= {:?} {:?} {:?}", path, args, envs);
+        let uctx = current_process()?.exec(path, &args, &envs)?;
+        *tf = uctx.into();
+        Ok(0)
+    })
+}
+
+/// Wait for the child process `pid` (or any child if `pid` is not positive)
+/// to exit, and write its wait status to `wstatus`.
+///
+/// Only the `WNOHANG` option is supported. Process groups are not
+/// supported, so waiting for a process group is the same as waiting for any
+/// child.
+pub unsafe fn sys_wait4(pid: c_int, wstatus: *mut c_int, options: c_int) -> c_int {
+    debug!("sys_wait4 <= {} {:#x}", pid, options);
+    syscall_body!(sys_wait4, {
+        let pid = if pid > 0 { Some(pid as Pid) } else { None };
+        match current_process()?.wait_child(pid, options & WNOHANG != 0) {
+            Ok(Some((pid, status))) => {
+                if !wstatus.is_null() {
+                    unsafe { *wstatus = status };
+                }
+                Ok(pid as c_int)
+            }
+            Ok(None) => Ok(0),
+            Err(AxError::NotFound) => Err(LinuxError::ECHILD),
+            Err(e) => Err(e.into()),
+        }
+    })
+}
+
+/// Set the address to clear when the current process exits, returns the
+/// thread ID.
+///
+/// On exit, `0` is written to the address, and a waiter of the futex there is
+/// woken up.
+pub fn sys_set_tid_address(tidptr: *mut c_int) -> c_int {
+    debug!("sys_set_tid_address <= {:#x}", tidptr as usize);
+    syscall_body!(sys_set_tid_address, {
+        let process = current_process()?;
+        process.set_clear_child_tid(tidptr as usize);
+        Ok(process.pid() as c_int)
+    })
+}
+
+/// Get the parent process ID, or `0` if the process is spawned by the
+/// kernel.
+pub fn sys_getppid() -> c_int {
+    syscall_body!(sys_getppid, Ok(current_process()?.parent_pid() as c_int))
+}
+
+/// Terminate the current process with `exit_code`.
+pub fn sys_exit_group(exit_code: c_int) -> ! {
+    axprocess::exit_current(exit_code)
+}
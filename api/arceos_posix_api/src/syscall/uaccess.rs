@@ -0,0 +1,249 @@
+//! Checks of the user memory accessed by system calls.
+//!
+//! The pointers passed by user programs are checked before they are accessed
+//! by the kernel: the memory must be mapped in the address space of the
+//! current process with `USER` and the required permissions, otherwise
+//! `EFAULT` is returned. The pages are also populated by the checks, so the
+//! kernel does not fault on them later, e.g., with locks held. Each process
+//! is run by a single task, so the checked memory cannot be unmapped before
+//! the system call returns.
+//!
+//! NULL pointers are not checked here, they are rejected (or treated as
+//! absent arguments) by the system calls themselves.
+
+use core::ffi::{c_char, c_int};
+use core::mem::size_of;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::mem::PAGE_SIZE_4K;
+use axhal::paging::MappingFlags;
+
+use super::fs::KernelStat;
+use super::signal::KernelSigAction;
+use super::sysno;
+use crate::ctypes;
+use crate::imp::futex::{FUTEX_CMD_MASK, FUTEX_WAIT};
+
+/// Checks the user memory `[addr, addr + len)` can be read, or written if
+/// `write` is `true`.
+pub fn check_region(addr: usize, len: usize, write: bool) -> LinuxResult {
+    let access = if write {
+        MappingFlags::WRITE
+    } else {
+        MappingFlags::READ
+    };
+    let aspace = axprocess::current_aspace().ok_or(LinuxError::EFAULT)?;
+    axmm::populate(&aspace, addr.into(), len, access | MappingFlags::USER)
+        .map_err(|_| LinuxError::EFAULT)
+}
+
+/// Checks `ptr` points to `len` readable objects of type `T`.
+pub fn check_slice<T>(ptr: *const T, len: usize) -> LinuxResult {
+    if ptr.is_null() {
+        return Ok(());
+    }
+    let size = len.checked_mul(size_of::<T>()).ok_or(LinuxError::EFAULT)?;
+    check_region(ptr as usize, size, false)
+}
+
+/// Checks `ptr` points to `len` writable objects of type `T`.
+pub fn check_slice_mut<T>(ptr: *mut T, len: usize) -> LinuxResult {
+    if ptr.is_null() {
+        return Ok(());
+    }
+    let size = len.checked_mul(size_of::<T>()).ok_or(LinuxError::EFAULT)?;
+    check_region(ptr as usize, size, true)
+}
+
+/// Checks `ptr` points to a readable object of type `T`.
+pub fn check_ptr<T>(ptr: *const T) -> LinuxResult {
+    check_slice(ptr, 1)
+}
+
+/// Checks `ptr` points to a writable object of type `T`.
+pub fn check_ptr_mut<T>(ptr: *mut T) -> LinuxResult {
+    check_slice_mut(ptr, 1)
+}
+
+/// Checks the NUL-terminated string at `ptr` is readable.
+pub fn check_str(ptr: *const c_char) -> LinuxResult {
+    if ptr.is_null() {
+        return Ok(());
+    }
+    let mut addr = ptr as usize;
+    loop {
+        check_region(addr, 1, false)?;
+        let page_end = (addr & !(PAGE_SIZE_4K - 1))
+            .checked_add(PAGE_SIZE_4K)
+            .ok_or(LinuxError::EFAULT)?;
+        let chunk = unsafe { core::slice::from_raw_parts(addr as *const u8, page_end - addr) };
+        if chunk.contains(&0) {
+            return Ok(());
+        }
+        addr = page_end;
+    }
+}
+
+/// Checks the socket address buffer `addr` whose length is at `addrlen`,
+/// which are written by `accept`, `recvfrom`, etc.
+unsafe fn check_sockaddr_mut(
+    addr: *mut ctypes::sockaddr,
+    addrlen: *mut ctypes::socklen_t,
+) -> LinuxResult {
+    check_ptr_mut(addrlen)?;
+    if addrlen.is_null() {
+        return Ok(());
+    }
+    check_slice_mut(addr as *mut u8, unsafe { *addrlen } as usize)
+}
+
+/// Checks the user memory accessed by the system call `sysno` with
+/// arguments `a`, before it's dispatched.
+///
+/// Only the memory that can be determined from the arguments is checked
+/// here, the others (e.g., `argv` of `execve`) are checked by the system
+/// calls.
+pub unsafe fn check_args(sysno: usize, a: &[usize; 6]) -> LinuxResult {
+    match sysno {
+        sysno::read => check_slice_mut(a[1] as *mut u8, a[2]),
+        sysno::write => check_slice(a[1] as *const u8, a[2]),
+        sysno::writev => {
+            let (iov, iocnt) = (a[1] as *const ctypes::iovec, a[2] as c_int);
+            if !(0..=1024).contains(&iocnt) {
+                // rejected by `writev`
+                return Ok(());
+            }
+            check_slice(iov, iocnt as usize)?;
+            if iov.is_null() {
+                return Ok(());
+            }
+            let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
+            for iov in iovs {
+                check_slice(iov.iov_base as *const u8, iov.iov_len)?;
+            }
+            Ok(())
+        }
+        sysno::openat => check_str(a[1] as _),
+        sysno::newfstatat => {
+            check_str(a[1] as _)?;
+            check_ptr_mut(a[2] as *mut KernelStat)
+        }
+        sysno::fstat => check_ptr_mut(a[1] as *mut KernelStat),
+        sysno::readlinkat => {
+            check_str(a[1] as _)?;
+            check_slice_mut(a[2] as *mut u8, a[3])
+        }
+        sysno::symlinkat => {
+            check_str(a[0] as _)?;
+            check_str(a[2] as _)
+        }
+        sysno::renameat | sysno::renameat2 => {
+            check_str(a[1] as _)?;
+            check_str(a[3] as _)
+        }
+        sysno::getcwd => check_slice_mut(a[0] as *mut u8, a[1]),
+        sysno::chdir => check_str(a[0] as _),
+        sysno::pipe2 => check_slice_mut(a[0] as *mut c_int, 2),
+        sysno::pselect6 => {
+            for fds in &a[1..4] {
+                check_ptr_mut(*fds as *mut ctypes::fd_set)?;
+            }
+            check_ptr(a[4] as *const ctypes::timespec)
+        }
+        sysno::ppoll => {
+            check_slice_mut(a[0] as *mut ctypes::pollfd, a[1])?;
+            check_ptr(a[2] as *const ctypes::timespec)
+        }
+        sysno::epoll_ctl => check_ptr(a[3] as *const ctypes::epoll_event),
+        sysno::epoll_pwait => check_slice_mut(
+            a[1] as *mut ctypes::epoll_event,
+            (a[2] as c_int).max(0) as _,
+        ),
+        sysno::rt_sigaction => {
+            check_ptr(a[1] as *const KernelSigAction)?;
+            check_ptr_mut(a[2] as *mut KernelSigAction)
+        }
+        sysno::rt_sigprocmask => {
+            check_ptr(a[1] as *const u64)?;
+            check_ptr_mut(a[2] as *mut u64)
+        }
+        sysno::rt_sigpending => check_ptr_mut(a[0] as *mut u64),
+        sysno::rt_sigsuspend => check_ptr(a[0] as *const u64),
+        sysno::execve => check_str(a[0] as _),
+        sysno::wait4 => check_ptr_mut(a[1] as *mut c_int),
+        // the futex words are checked by `futex`, and `timeout` is a value
+        // for the operations other than `FUTEX_WAIT`
+        sysno::futex if a[1] as c_int & FUTEX_CMD_MASK == FUTEX_WAIT => {
+            check_ptr(a[3] as *const ctypes::timespec)
+        }
+        sysno::sched_setaffinity => check_slice(a[2] as *const u8, a[1]),
+        sysno::sched_getaffinity => {
+            check_slice_mut(a[2] as *mut u8, a[1].min(size_of::<ctypes::cpu_set_t>()))
+        }
+        sysno::nanosleep => {
+            check_ptr(a[0] as *const ctypes::timespec)?;
+            check_ptr_mut(a[1] as *mut ctypes::timespec)
+        }
+        sysno::clock_gettime => check_ptr_mut(a[1] as *mut ctypes::timespec),
+        sysno::prlimit64 => {
+            check_ptr(a[2] as *const ctypes::rlimit)?;
+            check_ptr_mut(a[3] as *mut ctypes::rlimit)
+        }
+        sysno::bind | sysno::connect => {
+            check_slice(a[1] as *const u8, a[2] as ctypes::socklen_t as _)
+        }
+        sysno::accept | sysno::accept4 | sysno::getsockname | sysno::getpeername => unsafe {
+            check_sockaddr_mut(a[1] as _, a[2] as _)
+        },
+        sysno::sendto => {
+            check_slice(a[1] as *const u8, a[2])?;
+            check_slice(a[4] as *const u8, a[5] as ctypes::socklen_t as _)
+        }
+        sysno::recvfrom => {
+            check_slice_mut(a[1] as *mut u8, a[2])?;
+            unsafe { check_sockaddr_mut(a[4] as _, a[5] as _) }
+        }
+        #[cfg(target_arch = "x86_64")]
+        sysno::open => check_str(a[0] as _),
+        #[cfg(target_arch = "x86_64")]
+        sysno::stat | sysno::lstat => {
+            check_str(a[0] as _)?;
+            check_ptr_mut(a[1] as *mut KernelStat)
+        }
+        #[cfg(target_arch = "x86_64")]
+        sysno::readlink => {
+            check_str(a[0] as _)?;
+            check_slice_mut(a[1] as *mut u8, a[2])
+        }
+        #[cfg(target_arch = "x86_64")]
+        sysno::symlink | sysno::rename => {
+            check_str(a[0] as _)?;
+            check_str(a[1] as _)
+        }
+        #[cfg(target_arch = "x86_64")]
+        sysno::pipe => check_slice_mut(a[0] as *mut c_int, 2),
+        #[cfg(target_arch = "x86_64")]
+        sysno::select => {
+            for fds in &a[1..4] {
+                check_ptr_mut(*fds as *mut ctypes::fd_set)?;
+            }
+            check_ptr(a[4] as *const ctypes::timeval)
+        }
+        #[cfg(target_arch = "x86_64")]
+        sysno::poll => check_slice_mut(a[0] as *mut ctypes::pollfd, a[1]),
+        #[cfg(target_arch = "x86_64")]
+        sysno::epoll_wait => check_slice_mut(
+            a[1] as *mut ctypes::epoll_event,
+            (a[2] as c_int).max(0) as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        sysno::getrlimit => check_ptr_mut(a[1] as *mut ctypes::rlimit),
+        #[cfg(target_arch = "x86_64")]
+        sysno::setrlimit => check_ptr(a[1] as *const ctypes::rlimit),
+        #[cfg(target_arch = "x86_64")]
+        sysno::arch_prctl if a[0] as c_int == super::ARCH_GET_FS => {
+            check_ptr_mut(a[1] as *mut usize)
+        }
+        _ => Ok(()),
+    }
+}
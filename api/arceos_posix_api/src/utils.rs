@@ -38,6 +38,8 @@ macro_rules! syscall_body {
             Ok(_) | Err(axerrno::LinuxError::EAGAIN) => debug!(concat!(stringify!($fn), " => {:?}"),  res),
             Err(_) => info!(concat!(stringify!($fn), " => {:?}"), res),
         }
+        #[cfg(feature = "multitask")]
+        crate::imp::signal::handle_pending_signals();
         match res {
             Ok(v) => v as _,
             Err(e) => {
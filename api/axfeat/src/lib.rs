@@ -14,6 +14,7 @@
 //!     - `alloc-buddy`: Use the buddy system allocator.
 //!     - `paging`: Enable page table manipulation.
 //!     - `tls`: Enable thread-local storage.
+//!     - `uspace`: Enable user-space execution and the syscall entry.
 //! - Task management
 //!     - `multitask`: Enable multi-threading support.
 //!     - `sched_fifo`: Use the FIFO cooperative scheduler.
@@ -22,7 +23,10 @@
 //! - Upperlayer stacks (fs, net, display)
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext2`: Use the ext2 filesystem as the root filesystem instead of FAT.
 //!     - `net`: Enable networking support.
+//!     - `net-busy-poll`: Poll the NIC in a loop instead of sleeping on NIC interrupts.
+//!     - `net-dhcp`: Configure the network by DHCP, fall back to the static configuration.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
@@ -41,8 +41,14 @@ pub enum AxError {
     ConnectionRefused,
     /// The connection was reset by the remote server.
     ConnectionReset,
+    /// A link or rename crosses filesystems (mount points).
+    CrossesDevices,
     /// A non-empty directory was specified where an empty directory was expected.
     DirectoryNotEmpty,
+    /// Too many levels of symbolic links were encountered while resolving a path.
+    FilesystemLoop,
+    /// The operation was interrupted, e.g., by a signal.
+    Interrupted,
     /// Data not valid for the operation were encountered.
     ///
     /// Unlike [`InvalidInput`], this typically means that the operation
@@ -195,7 +201,10 @@ impl AxError {
             AlreadyExists => "Entity already exists",
             ConnectionRefused => "Connection refused",
             ConnectionReset => "Connection reset",
+            CrossesDevices => "Cross-device link or rename",
             DirectoryNotEmpty => "Directory not empty",
+            FilesystemLoop => "Too many levels of symbolic links",
+            Interrupted => "Operation interrupted",
             InvalidData => "Invalid data",
             InvalidInput => "Invalid input parameter",
             Io => "I/O error",
@@ -248,7 +257,10 @@ impl From<AxError> for LinuxError {
             BadAddress | BadState => LinuxError::EFAULT,
             ConnectionRefused => LinuxError::ECONNREFUSED,
             ConnectionReset => LinuxError::ECONNRESET,
+            CrossesDevices => LinuxError::EXDEV,
             DirectoryNotEmpty => LinuxError::ENOTEMPTY,
+            FilesystemLoop => LinuxError::ELOOP,
+            Interrupted => LinuxError::EINTR,
             InvalidInput | InvalidData => LinuxError::EINVAL,
             Io => LinuxError::EIO,
             IsADirectory => LinuxError::EISDIR,
@@ -284,7 +296,7 @@ mod tests {
     #[test]
     fn test_try_from() {
         let max_code = core::mem::variant_count::<AxError>() as i32;
-        assert_eq!(max_code, 22);
+        assert_eq!(max_code, 25);
         assert_eq!(max_code, AxError::WriteZero.code());
 
         assert_eq!(AxError::AddrInUse.code(), 1);
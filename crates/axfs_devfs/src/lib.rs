@@ -59,6 +59,10 @@ impl VfsOps for DeviceFileSystem {
         Ok(())
     }
 
+    fn fs_type(&self) -> &str {
+        "devfs"
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
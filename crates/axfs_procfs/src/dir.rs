@@ -0,0 +1,226 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+use spin::RwLock;
+
+use crate::file::{ProcFile, ReadFn};
+
+type ListFn = Box<dyn Fn() -> Vec<(String, VfsNodeType)> + Send + Sync>;
+type LookupFn = Box<dyn Fn(&Arc<ProcDir>, &str) -> Option<VfsNodeRef> + Send + Sync>;
+
+/// The directory node in the process information filesystem.
+///
+/// Besides the nodes added in advance, a directory can have dynamic entries
+/// (e.g., one subdirectory for each task). Listing the directory only asks
+/// for their names and types, and a node is built only when its name is
+/// looked up.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct ProcDir {
+    this: Weak<ProcDir>,
+    parent: RwLock<Weak<dyn VfsNodeOps>>,
+    children: RwLock<BTreeMap<&'static str, Entry>>,
+    dynamic: RwLock<Option<(ListFn, LookupFn)>>,
+}
+
+enum Entry {
+    Node(VfsNodeRef),
+    File(ReadFn),
+}
+
+impl Entry {
+    fn node(&self) -> VfsNodeRef {
+        match self {
+            Self::Node(node) => node.clone(),
+            // a new node for each lookup, to keep the content per handle
+            Self::File(read) => Arc::new(ProcFile::from_fn(read.clone())),
+        }
+    }
+
+    fn file_type(&self) -> VfsResult<VfsNodeType> {
+        match self {
+            Self::Node(node) => Ok(node.get_attr()?.file_type()),
+            Self::File(_) => Ok(VfsNodeType::File),
+        }
+    }
+}
+
+impl ProcDir {
+    pub(super) fn new(parent: Option<&VfsNodeRef>) -> Arc<Self> {
+        let parent = parent.map_or(Weak::<Self>::new() as _, Arc::downgrade);
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            parent: RwLock::new(parent),
+            children: RwLock::new(BTreeMap::new()),
+            dynamic: RwLock::new(None),
+        })
+    }
+
+    pub(super) fn set_parent(&self, parent: Option<&VfsNodeRef>) {
+        *self.parent.write() = parent.map_or(Weak::<Self>::new() as _, Arc::downgrade);
+    }
+
+    /// Create a subdirectory at this directory.
+    pub fn mkdir(self: &Arc<Self>, name: &'static str) -> Arc<Self> {
+        let node = self.new_child();
+        self.add(name, node.clone());
+        node
+    }
+
+    /// Create an empty directory whose parent is this directory, but do not
+    /// add it to this directory.
+    ///
+    /// It's used to build the directories returned by dynamic entries.
+    pub fn new_child(self: &Arc<Self>) -> Arc<Self> {
+        let parent = self.clone() as VfsNodeRef;
+        Self::new(Some(&parent))
+    }
+
+    /// Add a node to this directory.
+    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
+        self.children.write().insert(name, Entry::Node(node));
+    }
+
+    /// Add a read-only file to this directory, whose content is generated by
+    /// `read` each time the file is read from the beginning.
+    pub fn add_file<F>(&self, name: &'static str, read: F)
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.children
+            .write()
+            .insert(name, Entry::File(Arc::new(read)));
+    }
+
+    /// Set the generators of dynamic entries of this directory.
+    ///
+    /// `list` returns the names and types of all dynamic entries, and is
+    /// called when listing this directory. `lookup` is called with this
+    /// directory (to be used as the parent of generated directories) and a
+    /// name, and returns the node of that entry, or `None` if it does not
+    /// exist. Nodes added by [`ProcDir::add`] take precedence over dynamic
+    /// entries of the same name.
+    pub fn set_dynamic<L, F>(&self, list: L, lookup: F)
+    where
+        L: Fn() -> Vec<(String, VfsNodeType)> + Send + Sync + 'static,
+        F: Fn(&Arc<ProcDir>, &str) -> Option<VfsNodeRef> + Send + Sync + 'static,
+    {
+        *self.dynamic.write() = Some((Box::new(list), Box::new(lookup)));
+    }
+
+    fn dynamic_entries(&self) -> Vec<(String, VfsNodeType)> {
+        match self.dynamic.read().as_ref() {
+            Some((list, _)) => list()
+                .into_iter()
+                .filter(|(name, _)| !self.children.read().contains_key(name.as_str()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn find_child(&self, name: &str) -> VfsResult<VfsNodeRef> {
+        if let Some(entry) = self.children.read().get(name) {
+            return Ok(entry.node());
+        }
+        match (self.dynamic.read().as_ref(), self.this.upgrade()) {
+            (Some((_, lookup)), Some(this)) => lookup(&this, name).ok_or(VfsError::NotFound),
+            _ => Err(VfsError::NotFound),
+        }
+    }
+}
+
+impl VfsNodeOps for ProcDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o555),
+            VfsNodeType::Dir,
+            0,
+            0,
+        ))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.read().upgrade()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => Ok(self.clone() as VfsNodeRef),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self.find_child(name),
+        }?;
+
+        if let Some(rest) = rest {
+            node.lookup(rest)
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let mut children = self
+            .children
+            .read()
+            .iter()
+            .map(|(name, entry)| Ok((String::from(*name), entry.file_type()?)))
+            .collect::<VfsResult<Vec<_>>>()?;
+        children.extend(self.dynamic_entries());
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, ty)) = children.next() {
+                        *ent = VfsDirEntry::new(name, *ty);
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        log::debug!("create {:?} at procfs: {}", ty, path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.create(rest, ty),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
+                _ => self.find_child(name)?.create(rest, ty),
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Ok(()) // already exists
+        } else {
+            Err(VfsError::PermissionDenied) // do not support to create nodes dynamically
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        log::debug!("remove at procfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.remove(rest),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.remove(rest),
+                _ => self.find_child(name)?.remove(rest),
+            }
+        } else {
+            Err(VfsError::PermissionDenied) // do not support to remove nodes dynamically
+        }
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
@@ -0,0 +1,73 @@
+use alloc::{string::String, sync::Arc};
+use axfs_vfs::VfsError;
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use spin::Mutex;
+
+pub(crate) type ReadFn = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// The read-only file node in the process information filesystem.
+///
+/// The content is generated by a callback when the file is read from offset
+/// 0, and is kept for the following reads, so that reading the file in small
+/// chunks sees a consistent snapshot. The file size is always reported as 0,
+/// the same as Linux.
+///
+/// Files added by [`ProcDir::add_file`](crate::ProcDir::add_file) get a new
+/// node on each lookup, so every opened handle has its own content.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct ProcFile {
+    read: ReadFn,
+    content: Mutex<Option<String>>,
+}
+
+impl ProcFile {
+    /// Create a new file whose content is generated by `read`.
+    pub fn new<F>(read: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Self::from_fn(Arc::new(read))
+    }
+
+    pub(crate) fn from_fn(read: ReadFn) -> Self {
+        Self {
+            read,
+            content: Mutex::new(None),
+        }
+    }
+}
+
+impl VfsNodeOps for ProcFile {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o444),
+            VfsNodeType::File,
+            0,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut content = self.content.lock();
+        if offset == 0 || content.is_none() {
+            *content = Some((self.read)());
+        }
+        let content = content.as_deref().unwrap_or_default().as_bytes();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(start + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
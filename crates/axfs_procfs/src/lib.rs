@@ -0,0 +1,93 @@
+//! Process information pseudo-filesystem used by [ArceOS](https://github.com/rcore-os/arceos).
+//!
+//! Unlike a RAM filesystem, the files do not store any data. Their content is
+//! generated by callbacks each time they are read from the beginning, so that
+//! it always reflects the current state of the kernel.
+//!
+//! The implementation is based on [`axfs_vfs`].
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod dir;
+mod file;
+mod symlink;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::dir::ProcDir;
+pub use self::file::ProcFile;
+pub use self::symlink::ProcSymlink;
+
+use alloc::{string::String, sync::Arc};
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use spin::once::Once;
+
+/// A process information filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct ProcFileSystem {
+    parent: Once<VfsNodeRef>,
+    root: Arc<ProcDir>,
+}
+
+impl ProcFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            parent: Once::new(),
+            root: ProcDir::new(None),
+        }
+    }
+
+    /// Returns the root directory node in [`Arc<ProcDir>`](ProcDir).
+    pub fn root_dir_node(&self) -> Arc<ProcDir> {
+        self.root.clone()
+    }
+
+    /// Create a subdirectory at the root directory.
+    pub fn mkdir(&self, name: &'static str) -> Arc<ProcDir> {
+        self.root.mkdir(name)
+    }
+
+    /// Add a node to the root directory.
+    ///
+    /// The node must implement [`axfs_vfs::VfsNodeOps`], and be wrapped in [`Arc`].
+    pub fn add(&self, name: &'static str, node: VfsNodeRef) {
+        self.root.add(name, node);
+    }
+
+    /// Add a read-only file to the root directory, whose content is generated
+    /// by `read` each time the file is read from the beginning.
+    pub fn add_file<F>(&self, name: &'static str, read: F)
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.root.add_file(name, read);
+    }
+}
+
+impl VfsOps for ProcFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        if let Some(parent) = mount_point.parent() {
+            self.root.set_parent(Some(self.parent.call_once(|| parent)));
+        } else {
+            self.root.set_parent(None);
+        }
+        Ok(())
+    }
+
+    fn fs_type(&self) -> &str {
+        "proc"
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+impl Default for ProcFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,39 @@
+use alloc::{boxed::Box, string::String};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsResult};
+
+/// The symbolic link node in the process information filesystem, such as
+/// `/proc/self`.
+///
+/// The target is generated by a callback each time the link is resolved.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct ProcSymlink {
+    target: Box<dyn Fn() -> String + Send + Sync>,
+}
+
+impl ProcSymlink {
+    /// Create a new symbolic link whose target is generated by `target`.
+    pub fn new<F>(target: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Self {
+            target: Box::new(target),
+        }
+    }
+}
+
+impl VfsNodeOps for ProcSymlink {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_symlink((self.target)().len() as u64))
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let target = (self.target)();
+        let len = target.len().min(buf.len());
+        buf[..len].copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
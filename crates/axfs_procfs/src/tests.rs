@@ -0,0 +1,161 @@
+use std::string::ToString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+
+use crate::*;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+fn read_all(node: &VfsNodeRef) -> VfsResult<String> {
+    let mut buf = [0; 64];
+    let mut content = Vec::new();
+    loop {
+        let n = node.read_at(content.len() as _, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        content.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8(content).unwrap())
+}
+
+fn test_procfs_ops(procfs: &ProcFileSystem) -> VfsResult {
+    let root = procfs.root_dir();
+    assert!(root.get_attr()?.is_dir());
+
+    // the content is generated on each read from offset 0
+    let node = root.clone().lookup("counter")?;
+    assert_eq!(node.get_attr()?.file_type(), VfsNodeType::File);
+    assert_eq!(node.get_attr()?.size(), 0);
+    let mut buf = [0; 8];
+    assert_eq!(node.read_at(0, &mut buf)?, 2);
+    assert_eq!(&buf[..2], b"1\n");
+    assert_eq!(node.read_at(0, &mut buf)?, 2);
+    assert_eq!(&buf[..2], b"2\n");
+    assert_eq!(node.read_at(10, &mut buf)?, 0);
+    assert_eq!(node.read_at(1, &mut buf)?, 1);
+    assert_eq!(&buf[..1], b"\n");
+    assert_eq!(
+        node.write_at(0, b"3").err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(node.truncate(0).err(), Some(VfsError::PermissionDenied));
+
+    // each lookup gets its own content, which is kept until the next read
+    // from offset 0
+    let other = root.clone().lookup("counter")?;
+    assert_eq!(other.read_at(1, &mut buf)?, 1);
+    assert_eq!(&buf[..1], b"\n");
+    assert_eq!(other.read_at(0, &mut buf)?, 2);
+    assert_eq!(&buf[..2], b"4\n");
+    assert_eq!(node.read_at(0, &mut buf[..1])?, 1);
+    assert_eq!(node.read_at(1, &mut buf[1..])?, 1);
+    assert_eq!(&buf[..2], b"5\n");
+
+    // read in small chunks
+    let node = root.clone().lookup("./sys/long")?;
+    assert_eq!(read_all(&node)?, "0123456789".repeat(20));
+
+    // dynamic entries
+    LOOKUPS.store(0, Ordering::Relaxed);
+    let node = root.clone().lookup("tasks/2/name")?;
+    assert_eq!(read_all(&node)?, "task 2\n");
+    assert_eq!(LOOKUPS.load(Ordering::Relaxed), 1);
+    assert!(Arc::ptr_eq(
+        &root.clone().lookup("tasks/1/..")?,
+        &root.clone().lookup("tasks")?,
+    ));
+    assert_eq!(
+        root.clone().lookup("tasks/4").err(),
+        Some(VfsError::NotFound)
+    );
+    let node = root.clone().lookup("tasks/self")?;
+    assert!(node.get_attr()?.is_symlink());
+    let mut buf = [0; 8];
+    assert_eq!(node.readlink(&mut buf)?, 1);
+    assert_eq!(&buf[..1], b"3");
+
+    let tasks = root.clone().lookup("tasks")?;
+    let mut entries = [VfsDirEntry::default(), VfsDirEntry::default()];
+    let mut names = Vec::new();
+    let mut idx = 0;
+    loop {
+        let n = tasks.read_dir(idx, &mut entries)?;
+        if n == 0 {
+            break;
+        }
+        for ent in &entries[..n] {
+            names.push(String::from_utf8(ent.name_as_bytes().to_vec()).unwrap());
+        }
+        idx += n;
+    }
+    assert_eq!(names, [".", "..", "self", "1", "2", "3"]);
+
+    // cannot create or remove nodes
+    assert_eq!(
+        root.create("foo", VfsNodeType::File).err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(
+        root.remove("counter").err(),
+        Some(VfsError::PermissionDenied)
+    );
+    assert_eq!(
+        root.remove("tasks/1/name").err(),
+        Some(VfsError::PermissionDenied)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_procfs() {
+    // .
+    // ├── counter
+    // ├── sys
+    // │   └── long
+    // └── tasks
+    //     ├── self -> 3
+    //     ├── 1
+    //     │   └── name
+    //     ├── 2
+    //     │   └── name
+    //     └── 3
+    //         └── name
+
+    let procfs = ProcFileSystem::new();
+    procfs.add_file("counter", || {
+        (COUNTER.fetch_add(1, Ordering::Relaxed) + 1).to_string() + "\n"
+    });
+    let sys = procfs.mkdir("sys");
+    sys.add_file("long", || "0123456789".repeat(20));
+
+    let tasks = procfs.mkdir("tasks");
+    tasks.add("self", Arc::new(ProcSymlink::new(|| "3".into())));
+    tasks.set_dynamic(
+        || {
+            (1..=3)
+                .map(|id| (id.to_string(), VfsNodeType::Dir))
+                // hidden by the static entry with the same name
+                .chain([("self".into(), VfsNodeType::File)])
+                .collect()
+        },
+        |parent, name| {
+            LOOKUPS.fetch_add(1, Ordering::Relaxed);
+            if name == "self" {
+                return Some(Arc::new(ProcFile::new(String::new)));
+            }
+            let id = name
+                .parse::<usize>()
+                .ok()
+                .filter(|id| (1..=3).contains(id))?;
+            let dir = parent.new_child();
+            dir.add_file("name", move || format!("task {}\n", id));
+            Some(dir)
+        },
+    );
+
+    test_procfs_ops(&procfs).unwrap();
+}
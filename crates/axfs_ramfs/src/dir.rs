@@ -7,6 +7,7 @@ use axfs_vfs::{VfsError, VfsResult};
 use spin::RwLock;
 
 use crate::file::FileNode;
+use crate::symlink::SymlinkNode;
 
 /// The directory node in the RAM filesystem.
 ///
@@ -56,6 +57,18 @@ impl DirNode {
         Ok(())
     }
 
+    /// Creates a new symbolic link with the given name in this directory,
+    /// which points to `target`.
+    pub fn create_symlink(&self, name: &str, target: &str) -> VfsResult {
+        if self.exist(name) {
+            log::error!("AlreadyExists {}", name);
+            return Err(VfsError::AlreadyExists);
+        }
+        let node = Arc::new(SymlinkNode::new(target));
+        self.children.write().insert(name.into(), node);
+        Ok(())
+    }
+
     /// Removes a node by the given name in this directory.
     pub fn remove_node(&self, name: &str) -> VfsResult {
         let mut children = self.children.write();
@@ -166,6 +179,33 @@ impl VfsNodeOps for DirNode {
         }
     }
 
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at ramfs: {} -> {}", path, target);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.symlink(rest, target),
+                ".." => self
+                    .parent()
+                    .ok_or(VfsError::NotFound)?
+                    .symlink(rest, target),
+                _ => {
+                    let subdir = self
+                        .children
+                        .read()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.symlink(rest, target)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            self.create_symlink(name, target)
+        }
+    }
+
     axfs_vfs::impl_vfs_dir_default! {}
 }
 
@@ -8,12 +8,14 @@ extern crate alloc;
 
 mod dir;
 mod file;
+mod symlink;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::dir::DirNode;
 pub use self::file::FileNode;
+pub use self::symlink::SymlinkNode;
 
 use alloc::sync::Arc;
 use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
@@ -51,6 +53,10 @@ impl VfsOps for RamFileSystem {
         Ok(())
     }
 
+    fn fs_type(&self) -> &str {
+        "ramfs"
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
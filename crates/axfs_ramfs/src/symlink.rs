@@ -0,0 +1,36 @@
+use alloc::string::String;
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+
+/// The symbolic link node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct SymlinkNode {
+    target: String,
+}
+
+impl SymlinkNode {
+    pub(super) fn new(target: &str) -> Self {
+        Self {
+            target: target.into(),
+        }
+    }
+
+    /// Returns the target path of the symbolic link.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_symlink(self.target.len() as _))
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let len = self.target.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.target.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    impl_vfs_non_dir_default! {}
+}
@@ -134,3 +134,61 @@ fn test_ramfs() {
     assert_eq!(root.remove("./foo"), Ok(()));
     assert!(ramfs.root_dir_node().get_entries().is_empty());
 }
+
+#[test]
+fn test_ramfs_symlink() {
+    let ramfs = RamFileSystem::new();
+    let root = ramfs.root_dir();
+    root.create("foo", VfsNodeType::Dir).unwrap();
+    root.create("foo/f1", VfsNodeType::File).unwrap();
+
+    assert_eq!(root.symlink("l1", "foo/f1"), Ok(()));
+    assert_eq!(root.symlink("./foo//l2", "/not/exist"), Ok(()));
+    assert_eq!(
+        root.symlink("l1", "foo").err(),
+        Some(VfsError::AlreadyExists)
+    );
+    assert_eq!(
+        root.symlink("bar/l3", "foo").err(),
+        Some(VfsError::NotFound)
+    );
+    assert_eq!(
+        root.clone()
+            .lookup("foo/f1")
+            .unwrap()
+            .symlink("l4", "foo")
+            .err(),
+        Some(VfsError::NotADirectory)
+    );
+
+    // lookup doesn't follow the link
+    let link = root.clone().lookup("l1").unwrap();
+    let attr = link.get_attr().unwrap();
+    assert!(attr.is_symlink());
+    assert_eq!(attr.file_type(), VfsNodeType::SymLink);
+    assert_eq!(attr.size(), 6);
+
+    let mut buf = [0; 32];
+    assert_eq!(link.readlink(&mut buf), Ok(6));
+    assert_eq!(&buf[..6], b"foo/f1");
+    assert_eq!(link.readlink(&mut buf[..3]), Ok(3));
+    assert_eq!(&buf[..3], b"foo");
+
+    let link = root.clone().lookup("foo/l2").unwrap();
+    assert_eq!(link.readlink(&mut buf), Ok(10));
+    assert_eq!(&buf[..10], b"/not/exist");
+    assert_eq!(
+        root.clone().lookup("foo").unwrap().readlink(&mut buf).err(),
+        Some(VfsError::InvalidInput)
+    );
+
+    let mut entries = ramfs.root_dir_node().get_entries();
+    entries.sort();
+    assert_eq!(entries, ["foo", "l1"]);
+
+    assert_eq!(root.remove("l1"), Ok(()));
+    assert_eq!(root.remove("foo/l2"), Ok(()));
+    assert_eq!(root.remove("foo/f1"), Ok(()));
+    assert_eq!(root.remove("foo"), Ok(()));
+    assert!(ramfs.root_dir_node().get_entries().is_empty());
+}
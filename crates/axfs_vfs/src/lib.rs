@@ -1,8 +1,8 @@
 //! Virtual filesystem interfaces used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
-//! A filesystem is a set of files and directories (symbol links are not
-//! supported currently), collectively referred to as **nodes**, which are
-//! conceptually similar to [inodes] in Linux. A file system needs to implement
+//! A filesystem is a set of files, directories and symbolic links,
+//! collectively referred to as **nodes**, which are conceptually similar to
+//! [inodes] in Linux. A file system needs to implement
 //! the [`VfsOps`] trait, its files and directories need to implement the
 //! [`VfsNodeOps`] trait.
 //!
@@ -14,10 +14,10 @@
 //! - [`statfs()`](VfsOps::statfs): Get the attributes of the filesystem.
 //! - [`root_dir()`](VfsOps::root_dir): Get root directory of the filesystem.
 //!
-//! The [`VfsNodeOps`] trait provides the following operations on a file or a
-//! directory:
+//! The [`VfsNodeOps`] trait provides the following operations on a file, a
+//! directory or a symbolic link:
 //!
-//! | Operation | Description | file/directory |
+//! | Operation | Description | file/directory/symlink |
 //! | --- | --- | --- |
 //! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | both |
 //! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | both |
@@ -31,6 +31,8 @@
 //! | [`create()`](VfsNodeOps::create) | Create a new node with the given path | directory |
 //! | [`remove()`](VfsNodeOps::remove) | Remove the node with the given path | directory |
 //! | [`read_dir()`](VfsNodeOps::read_dir) | Read directory entries | directory |
+//! | [`symlink()`](VfsNodeOps::symlink) | Create a symbolic link with the given path | directory |
+//! | [`readlink()`](VfsNodeOps::readlink) | Read the target of the symbolic link | symlink |
 //!
 //! [inodes]: https://en.wikipedia.org/wiki/Inode
 
@@ -69,6 +71,11 @@ pub trait VfsOps: Send + Sync {
         Ok(())
     }
 
+    /// Write all cached data of the filesystem to its device.
+    fn sync(&self) -> VfsResult {
+        Ok(())
+    }
+
     /// Format the filesystem.
     fn format(&self) -> VfsResult {
         ax_err!(Unsupported)
@@ -79,6 +86,11 @@ pub trait VfsOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Get the name of the filesystem type, such as `"ext2"`.
+    fn fs_type(&self) -> &str {
+        "unknown"
+    }
+
     /// Get the root directory of the filesystem.
     fn root_dir(&self) -> VfsNodeRef;
 }
@@ -133,7 +145,8 @@ pub trait VfsNodeOps: Send + Sync {
 
     /// Lookup the node with given `path` in the directory.
     ///
-    /// Return the node if found.
+    /// Return the node if found. Symbolic links are not followed, the link
+    /// node itself is returned if the last component of `path` is a link.
     fn lookup(self: Arc<Self>, _path: &str) -> VfsResult<VfsNodeRef> {
         ax_err!(Unsupported)
     }
@@ -160,6 +173,24 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Create a symbolic link with the given `path` in the directory, which
+    /// points to `target`.
+    ///
+    /// The `target` is stored as is, it's not required to exist.
+    fn symlink(&self, _path: &str, _target: &str) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    // symbolic link operations:
+
+    /// Read the target path of the symbolic link into `buf`.
+    ///
+    /// Return the number of bytes read. The content is truncated if `buf` is
+    /// too small, and no NUL terminator is appended.
+    fn readlink(&self, _buf: &mut [u8]) -> VfsResult<usize> {
+        ax_err!(InvalidInput)
+    }
+
     /// Convert `&self` to [`&dyn Any`][1] that can use
     /// [`Any::downcast_ref`][2].
     ///
@@ -58,6 +58,10 @@ macro_rules! impl_vfs_non_dir_default {
             $crate::__priv::ax_err!(NotADirectory)
         }
 
+        fn symlink(&self, _path: &str, _target: &str) -> $crate::VfsResult {
+            $crate::__priv::ax_err!(NotADirectory)
+        }
+
         #[inline]
         fn as_any(&self) -> &dyn core::any::Any {
             self
@@ -89,6 +89,14 @@ impl VfsNodePerm {
         Self::from_bits_truncate(0o755)
     }
 
+    /// Returns the default permission for a symbolic link.
+    ///
+    /// The default permission is `0o777`, the permission of a link is never
+    /// used, the permission of its target is checked instead.
+    pub const fn default_symlink() -> Self {
+        Self::from_bits_truncate(0o777)
+    }
+
     /// Returns the underlying raw `st_mode` bits that contain the standard
     /// Unix permissions for this file.
     pub const fn mode(&self) -> u32 {
@@ -231,6 +239,17 @@ impl VfsNodeAttr {
         }
     }
 
+    /// Creates a new `VfsNodeAttr` for a symbolic link, with the default
+    /// symbolic link permission. The `size` is the length of the target path.
+    pub const fn new_symlink(size: u64) -> Self {
+        Self {
+            mode: VfsNodePerm::default_symlink(),
+            ty: VfsNodeType::SymLink,
+            size,
+            blocks: 0,
+        }
+    }
+
     /// Returns the size of the node.
     pub const fn size(&self) -> u64 {
         self.size
@@ -265,6 +284,11 @@ impl VfsNodeAttr {
     pub const fn is_dir(&self) -> bool {
         self.ty.is_dir()
     }
+
+    /// Whether the node is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.ty.is_symlink()
+    }
 }
 
 impl VfsDirEntry {
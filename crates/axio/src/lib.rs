@@ -257,4 +257,7 @@ pub struct PollState {
     pub readable: bool,
     /// Object can be writen now.
     pub writable: bool,
+    /// The other end is closed, e.g., the peer has shut down the connection,
+    /// or the write end of a pipe is closed.
+    pub hangup: bool,
 }
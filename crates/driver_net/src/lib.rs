@@ -8,6 +8,8 @@
 #[cfg(feature = "ixgbe")]
 /// ixgbe NIC device driver.
 pub mod ixgbe;
+/// Software loopback device.
+pub mod loopback;
 mod net_buf;
 
 use core::ptr::NonNull;
@@ -64,6 +66,21 @@ pub trait NetDriverOps: BaseDriverOps {
     /// Allocate a memory buffer of a specified size for network transmission,
     /// returns [`DevResult`]
     fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr>;
+
+    /// The IRQ number of the NIC, or `None` if the NIC does not support
+    /// interrupts and can only be polled.
+    fn irq_num(&self) -> Option<usize> {
+        None
+    }
+
+    /// Enables or disables the RX/TX completion interrupts of the NIC.
+    fn set_irq_enabled(&mut self, _enabled: bool) {}
+
+    /// Acknowledges a pending interrupt of the NIC, returns whether there was
+    /// an interrupt pending.
+    fn ack_irq(&mut self) -> bool {
+        false
+    }
 }
 
 /// A raw buffer struct for network device.
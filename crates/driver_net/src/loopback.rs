@@ -0,0 +1,109 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use driver_common::{BaseDriverOps, DevError, DevResult, DeviceType};
+
+use crate::{EthernetAddress, NetBuf, NetBufBox, NetBufPool, NetBufPtr, NetDriverOps};
+
+extern crate alloc;
+
+const QUEUE_SIZE: usize = 64;
+const BUFFER_LEN: usize = 1526;
+
+/// A software loopback device.
+///
+/// Every packet transmitted is queued and received back by the device itself.
+/// Packets are dropped if the queue is full.
+pub struct LoopbackDevice {
+    queue: VecDeque<NetBufBox>,
+    pool: Arc<NetBufPool>,
+}
+
+impl LoopbackDevice {
+    /// Creates a new loopback device.
+    pub fn new() -> Self {
+        // one more for the buffer being received, and one for being
+        // transmitted.
+        let pool = NetBufPool::new(QUEUE_SIZE + 2, BUFFER_LEN).unwrap();
+        Self {
+            queue: VecDeque::with_capacity(QUEUE_SIZE),
+            pool,
+        }
+    }
+
+    /// Frees a buffer allocated by [`NetDriverOps::alloc_tx_buffer`] without
+    /// transmitting it.
+    pub fn free_tx_buffer(&mut self, tx_buf: NetBufPtr) {
+        drop(unsafe { NetBuf::from_buf_ptr(tx_buf) });
+    }
+}
+
+impl Default for LoopbackDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BaseDriverOps for LoopbackDevice {
+    fn device_name(&self) -> &str {
+        "loopback"
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Net
+    }
+}
+
+impl NetDriverOps for LoopbackDevice {
+    fn mac_address(&self) -> EthernetAddress {
+        EthernetAddress([0; 6])
+    }
+
+    fn can_transmit(&self) -> bool {
+        self.queue.len() < QUEUE_SIZE
+    }
+
+    fn can_receive(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    fn rx_queue_size(&self) -> usize {
+        QUEUE_SIZE
+    }
+
+    fn tx_queue_size(&self) -> usize {
+        QUEUE_SIZE
+    }
+
+    fn recycle_rx_buffer(&mut self, rx_buf: NetBufPtr) -> DevResult {
+        // return the buffer to the pool
+        drop(unsafe { NetBuf::from_buf_ptr(rx_buf) });
+        Ok(())
+    }
+
+    fn recycle_tx_buffers(&mut self) -> DevResult {
+        Ok(())
+    }
+
+    fn transmit(&mut self, tx_buf: NetBufPtr) -> DevResult {
+        let tx_buf = unsafe { NetBuf::from_buf_ptr(tx_buf) };
+        if self.queue.len() >= QUEUE_SIZE {
+            return Err(DevError::Again);
+        }
+        self.queue.push_back(tx_buf);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> DevResult<NetBufPtr> {
+        let rx_buf = self.queue.pop_front().ok_or(DevError::Again)?;
+        Ok(rx_buf.into_buf_ptr())
+    }
+
+    fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr> {
+        if size > self.pool.buffer_len() {
+            return Err(DevError::InvalidParam);
+        }
+        let mut tx_buf = self.pool.alloc_boxed().ok_or(DevError::NoMemory)?;
+        tx_buf.set_packet_len(size);
+        Ok(tx_buf.into_buf_ptr())
+    }
+}
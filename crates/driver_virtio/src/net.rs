@@ -17,6 +17,7 @@ pub struct VirtIoNetDev<H: Hal, T: Transport, const QS: usize> {
     free_tx_bufs: Vec<NetBufBox>,
     buf_pool: Arc<NetBufPool>,
     inner: InnerDev<H, T, QS>,
+    irq_num: Option<usize>,
 }
 
 unsafe impl<H: Hal, T: Transport, const QS: usize> Send for VirtIoNetDev<H, T, QS> {}
@@ -25,7 +26,11 @@ unsafe impl<H: Hal, T: Transport, const QS: usize> Sync for VirtIoNetDev<H, T, Q
 impl<H: Hal, T: Transport, const QS: usize> VirtIoNetDev<H, T, QS> {
     /// Creates a new driver instance and initializes the device, or returns
     /// an error if any step fails.
-    pub fn try_new(transport: T) -> DevResult<Self> {
+    ///
+    /// `irq_num` is the interrupt line of the device, or `None` if it can only
+    /// be polled. Interrupts are disabled until [`NetDriverOps::set_irq_enabled`]
+    /// is called.
+    pub fn try_new(transport: T, irq_num: Option<usize>) -> DevResult<Self> {
         // 0. Create a new driver instance.
         const NONE_BUF: Option<NetBufBox> = None;
         let inner = InnerDev::new(transport).map_err(as_dev_err)?;
@@ -40,7 +45,9 @@ impl<H: Hal, T: Transport, const QS: usize> VirtIoNetDev<H, T, QS> {
             tx_buffers,
             free_tx_bufs,
             buf_pool,
+            irq_num,
         };
+        dev.inner.disable_interrupts();
 
         // 1. Fill all rx buffers.
         for (i, rx_buf_place) in dev.rx_buffers.iter_mut().enumerate() {
@@ -190,4 +197,22 @@ impl<H: Hal, T: Transport, const QS: usize> NetDriverOps for VirtIoNetDev<H, T,
         // 2. Return the buffer.
         Ok(net_buf.into_buf_ptr())
     }
+
+    #[inline]
+    fn irq_num(&self) -> Option<usize> {
+        self.irq_num
+    }
+
+    fn set_irq_enabled(&mut self, enabled: bool) {
+        if enabled {
+            self.inner.enable_interrupts();
+        } else {
+            self.inner.disable_interrupts();
+        }
+    }
+
+    #[inline]
+    fn ack_irq(&mut self) -> bool {
+        self.inner.ack_interrupt()
+    }
 }
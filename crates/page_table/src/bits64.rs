@@ -224,6 +224,37 @@ impl<M: PagingMetaData, PTE: GenericPTE, IF: PagingIf> PageTable64<M, PTE, IF> {
         Ok(())
     }
 
+    /// Copies the root-level entries that cover the virtual memory region
+    /// `[start, start + size)` from another page table.
+    ///
+    /// After copying, the two page tables share the same lower-level tables
+    /// in this region, so the mappings of the region made later in `other`
+    /// are also visible in `self`. The shared tables are still owned by
+    /// `other`, they will not be deallocated when `self` is dropped.
+    ///
+    /// It's typically used to share the kernel half of the address space
+    /// with user page tables. The region is extended to the granularity of
+    /// the root-level entries.
+    pub fn copy_from(&mut self, other: &Self, start: VirtAddr, size: usize) {
+        if size == 0 {
+            return;
+        }
+        let root_index = |vaddr| {
+            if M::LEVELS == 3 {
+                p3_index(vaddr)
+            } else if M::LEVELS == 4 {
+                p4_index(vaddr)
+            } else {
+                unreachable!()
+            }
+        };
+        let start_idx = root_index(start);
+        let end_idx = root_index(start + (size - 1)) + 1;
+        let src_table = &self.table_of(other.root_paddr())[start_idx..end_idx];
+        let dst_table = &mut self.table_of_mut(self.root_paddr())[start_idx..end_idx];
+        dst_table.copy_from_slice(src_table);
+    }
+
     /// Walk the page table recursively.
     ///
     /// When reaching the leaf page table, call `func` on the current page table
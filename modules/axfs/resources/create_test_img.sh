@@ -37,5 +37,41 @@ create_test_img() {
 	sudo umount mnt
 }
 
+create_ext2_img() {
+	local name=$1
+	local blkcount=$2
+	local root=`mktemp -d`
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"$root/long.txt"
+	done
+	echo "Rust is cool!" >>"$root/short.txt"
+	mkdir -p "$root/very/long/path"
+	echo "Rust is cool!" >>"$root/very/long/path/test.txt"
+	mkdir -p "$root/very-long-dir-name"
+	echo "Rust is cool!" >>"$root/very-long-dir-name/very-long-file-name.txt"
+
+    mkdir -p "$root/Program/Rust"
+    mkdir -p "$root/Program/Linux"
+    mkdir -p "$root/Program/Python"
+    mkdir -p "$root/Program/JavaScript"
+
+    echo "Rust is very cool!" >> "$root/Program/Rust/rust.txt"
+    echo "Linux is very cool!" >> "$root/Program/Linux/linux.txt"
+    echo "Python is very cool!" >> "$root/Program/Python/python.txt"
+    echo "JavaScript is very cool!" >> "$root/Program/JavaScript/javascript.txt"
+
+	rm -f "$name"
+	mkfs.ext2 -b 1024 -L "Test!" -U 12345678-1234-1234-1234-123456789abc \
+	  -E root_owner=0:0 -d "$root" "$name" $blkcount
+	rm -rf "$root"
+}
+
+# the ext2 image is generated before testing, which doesn't need root
+if [ "$1" == "ext2" ]; then
+	create_ext2_img "$CUR_DIR/ext2.img" 2500
+	exit
+fi
+
 create_test_img "$CUR_DIR/fat16.img" 2500 16
 create_test_img "$CUR_DIR/fat32.img" 34000 32
+create_ext2_img "$CUR_DIR/ext2.img" 2500
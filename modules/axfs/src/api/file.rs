@@ -16,7 +16,7 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(fops::FileAttr);
+pub struct Metadata(pub(super) fops::FileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
@@ -90,6 +90,11 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.0.is_symlink()
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -118,6 +123,7 @@ impl fmt::Debug for Metadata {
             .field("file_type", &self.file_type())
             .field("is_dir", &self.is_dir())
             .field("is_file", &self.is_file())
+            .field("is_symlink", &self.is_symlink())
             .field("permissions", &self.permissions())
             .finish_non_exhaustive()
     }
@@ -9,6 +9,8 @@ pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
 use alloc::{string::String, vec::Vec};
 use axio::{self as io, prelude::*};
 
+use crate::fops::FileSystem;
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -59,6 +61,23 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Query the metadata about a file without following symlinks.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    crate::fops::symlink_attr(path).map(Metadata)
+}
+
+/// Reads a symbolic link, returning the file that the link points to.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(None, path)
+}
+
+/// Creates a new symbolic link on the filesystem.
+///
+/// The `link` path will be a symbolic link pointing to the `original` path.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    crate::root::create_symlink(None, original, link)
+}
+
 /// Creates a new, empty directory at the provided path.
 pub fn create_dir(path: &str) -> io::Result<()> {
     DirBuilder::new().create(path)
@@ -81,9 +100,27 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 }
 
 /// Rename a file or directory to a new name.
-/// Delete the original file if `old` already exists.
+/// Replaces the file at `new` if it already exists.
 ///
-/// This only works then the new path is in the same mounted fs.
+/// This only works when the new path is in the same mounted fs, otherwise
+/// [`CrossesDevices`](axerrno::AxError::CrossesDevices) is returned.
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     crate::root::rename(old, new)
 }
+
+/// Mounts the filesystem `fs` at the given path.
+///
+/// The mount point is created if it does not exist. It can be inside another
+/// mounted filesystem, and the path is resolved by the longest matching mount
+/// point.
+pub fn mount(path: &str, fs: FileSystem) -> io::Result<()> {
+    crate::root::mount(path, fs)
+}
+
+/// Unmounts the filesystem mounted at the given path.
+///
+/// Fails if the path is not a mount point, or if other filesystems are
+/// mounted inside it, or if the current directory is inside it.
+pub fn umount(path: &str) -> io::Result<()> {
+    crate::root::umount(path)
+}
@@ -0,0 +1,161 @@
+//! A bounded LRU cache of disk blocks.
+//!
+//! Writes are buffered in the cache (write-back), and dirty blocks are written
+//! to the device when they are evicted, or when [`BlockCache::flush`] is
+//! called.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use axdriver::prelude::*;
+
+/// Size of blocks in the cache, the same as the block size of the device.
+pub const BLOCK_SIZE: usize = 512;
+
+/// Number of blocks cached by default (512 KiB).
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Statistics of a [`BlockCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockCacheStats {
+    /// Number of accesses served from the cache.
+    pub hits: u64,
+    /// Number of accesses that had to read the device.
+    pub misses: u64,
+    /// Number of dirty blocks written to the device.
+    pub writebacks: u64,
+    /// Number of blocks currently cached.
+    pub cached: usize,
+    /// Number of dirty blocks currently cached.
+    pub dirty: usize,
+    /// Maximum number of cached blocks.
+    pub capacity: usize,
+}
+
+struct CachedBlock {
+    data: Box<[u8; BLOCK_SIZE]>,
+    dirty: bool,
+    /// The last access time, the key in [`BlockCache::lru`].
+    stamp: u64,
+}
+
+/// A write-back LRU cache over a block device.
+pub struct BlockCache {
+    dev: AxBlockDevice,
+    capacity: usize,
+    /// Cached blocks, indexed by block ID.
+    blocks: BTreeMap<u64, CachedBlock>,
+    /// Block IDs ordered by the last access time, the first is the least
+    /// recently used.
+    lru: BTreeMap<u64, u64>,
+    clock: u64,
+    stats: BlockCacheStats,
+}
+
+impl BlockCache {
+    /// Creates a cache over the device which holds at most `capacity` blocks.
+    pub fn new(dev: AxBlockDevice, capacity: usize) -> Self {
+        assert_eq!(BLOCK_SIZE, dev.block_size());
+        let capacity = capacity.max(1);
+        Self {
+            dev,
+            capacity,
+            blocks: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            stats: BlockCacheStats {
+                capacity,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The number of blocks in the device.
+    pub fn num_blocks(&self) -> u64 {
+        self.dev.num_blocks()
+    }
+
+    /// Returns the statistics of the cache.
+    pub fn stats(&self) -> BlockCacheStats {
+        BlockCacheStats {
+            cached: self.blocks.len(),
+            dirty: self.blocks.values().filter(|b| b.dirty).count(),
+            ..self.stats
+        }
+    }
+
+    /// Reads the block `block_id` into `buf`, which must be [`BLOCK_SIZE`]
+    /// bytes.
+    pub fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        let block = self.get(block_id, true)?;
+        buf.copy_from_slice(&block.data[..]);
+        Ok(())
+    }
+
+    /// Writes `buf` to the block `block_id` in the cache, which must be
+    /// [`BLOCK_SIZE`] bytes. The block is written to the device later.
+    pub fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        // the whole block is overwritten, no need to read it from the device
+        let block = self.get(block_id, false)?;
+        block.data.copy_from_slice(buf);
+        block.dirty = true;
+        Ok(())
+    }
+
+    /// Writes all dirty blocks to the device, and flushes the device.
+    pub fn flush(&mut self) -> DevResult {
+        for (&block_id, block) in self.blocks.iter_mut() {
+            if block.dirty {
+                self.dev.write_block(block_id, &block.data[..])?;
+                block.dirty = false;
+                self.stats.writebacks += 1;
+            }
+        }
+        self.dev.flush()
+    }
+
+    /// Gets the cached block, loads it from the device if `fill` is `true`
+    /// and it's not in the cache.
+    fn get(&mut self, block_id: u64, fill: bool) -> DevResult<&mut CachedBlock> {
+        if block_id >= self.dev.num_blocks() {
+            return Err(DevError::Io);
+        }
+        self.clock += 1;
+        let stamp = self.clock;
+        if let Some(block) = self.blocks.get_mut(&block_id) {
+            self.stats.hits += 1;
+            self.lru.remove(&block.stamp);
+            self.lru.insert(stamp, block_id);
+            block.stamp = stamp;
+            return Ok(self.blocks.get_mut(&block_id).unwrap());
+        }
+
+        self.stats.misses += 1;
+        if self.blocks.len() >= self.capacity {
+            self.evict()?;
+        }
+        let mut data = Box::new([0; BLOCK_SIZE]);
+        if fill {
+            self.dev.read_block(block_id, &mut data[..])?;
+        }
+        self.lru.insert(stamp, block_id);
+        Ok(self.blocks.entry(block_id).or_insert(CachedBlock {
+            data,
+            dirty: false,
+            stamp,
+        }))
+    }
+
+    /// Evicts the least recently used block, writes it back if it's dirty.
+    fn evict(&mut self) -> DevResult {
+        let Some((&stamp, &block_id)) = self.lru.first_key_value() else {
+            return Ok(());
+        };
+        let block = &self.blocks[&block_id];
+        if block.dirty {
+            self.dev.write_block(block_id, &block.data[..])?;
+            self.stats.writebacks += 1;
+        }
+        self.lru.remove(&stamp);
+        self.blocks.remove(&block_id);
+        Ok(())
+    }
+}
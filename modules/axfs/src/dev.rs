@@ -1,28 +1,47 @@
+use alloc::sync::Arc;
 use axdriver::prelude::*;
+use axsync::Mutex;
 
-const BLOCK_SIZE: usize = 512;
+use crate::cache::{BlockCache, BlockCacheStats, BLOCK_SIZE, DEFAULT_CAPACITY};
 
 /// A disk device with a cursor.
+///
+/// All accesses go through a [`BlockCache`], call [`Disk::flush`] to write
+/// the cached data to the device.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    cache: Arc<Mutex<BlockCache>>,
 }
 
 impl Disk {
     /// Create a new disk.
     pub fn new(dev: AxBlockDevice) -> Self {
-        assert_eq!(BLOCK_SIZE, dev.block_size());
+        Self::with_cache_capacity(dev, DEFAULT_CAPACITY)
+    }
+
+    /// Create a new disk, whose cache holds at most `capacity` blocks.
+    pub fn with_cache_capacity(dev: AxBlockDevice, capacity: usize) -> Self {
         Self {
             block_id: 0,
             offset: 0,
-            dev,
+            cache: Arc::new(Mutex::new(BlockCache::new(dev, capacity))),
         }
     }
 
+    /// Get the block cache of the disk.
+    pub(crate) fn cache(&self) -> Arc<Mutex<BlockCache>> {
+        self.cache.clone()
+    }
+
+    /// Get the statistics of the block cache.
+    pub fn cache_stats(&self) -> BlockCacheStats {
+        self.cache.lock().stats()
+    }
+
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
-        self.dev.num_blocks() * BLOCK_SIZE as u64
+        self.cache.lock().num_blocks() * BLOCK_SIZE as u64
     }
 
     /// Get the position of the cursor.
@@ -40,7 +59,8 @@ impl Disk {
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
         let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
             // whole block
-            self.dev
+            self.cache
+                .lock()
                 .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
             self.block_id += 1;
             BLOCK_SIZE
@@ -50,7 +70,7 @@ impl Disk {
             let start = self.offset;
             let count = buf.len().min(BLOCK_SIZE - self.offset);
 
-            self.dev.read_block(self.block_id, &mut data)?;
+            self.cache.lock().read_block(self.block_id, &mut data)?;
             buf[..count].copy_from_slice(&data[start..start + count]);
 
             self.offset += count;
@@ -67,7 +87,9 @@ impl Disk {
     pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
         let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
             // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
+            self.cache
+                .lock()
+                .write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
             self.block_id += 1;
             BLOCK_SIZE
         } else {
@@ -76,9 +98,10 @@ impl Disk {
             let start = self.offset;
             let count = buf.len().min(BLOCK_SIZE - self.offset);
 
-            self.dev.read_block(self.block_id, &mut data)?;
+            let mut cache = self.cache.lock();
+            cache.read_block(self.block_id, &mut data)?;
             data[start..start + count].copy_from_slice(&buf[..count]);
-            self.dev.write_block(self.block_id, &data)?;
+            cache.write_block(self.block_id, &data)?;
 
             self.offset += count;
             if self.offset >= BLOCK_SIZE {
@@ -89,4 +112,9 @@ impl Disk {
         };
         Ok(write_size)
     }
+
+    /// Write all cached dirty blocks to the device.
+    pub fn flush(&mut self) -> DevResult {
+        self.cache.lock().flush()
+    }
 }
@@ -19,6 +19,9 @@ pub type DirEntry = axfs_vfs::VfsDirEntry;
 pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
+/// A reference to a mountable filesystem, i.e. [`axfs_vfs::VfsOps`] trait
+/// objects.
+pub type FileSystem = alloc::sync::Arc<dyn axfs_vfs::VfsOps>;
 
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
@@ -330,14 +333,22 @@ impl Directory {
     }
 
     /// Rename a file or directory to a new name.
-    /// Delete the original file if `old` already exists.
+    /// Replaces the file at `new` if it already exists.
     ///
-    /// This only works then the new path is in the same mounted fs.
+    /// This only works when the new path is in the same mounted fs, otherwise
+    /// [`CrossesDevices`](axerrno::AxError::CrossesDevices) is returned.
     pub fn rename(&self, old: &str, new: &str) -> AxResult {
         crate::root::rename(old, new)
     }
 }
 
+/// Gets the attributes of the node at the path relative to the current
+/// directory. If the node is a symbolic link, returns the attributes of the
+/// link itself.
+pub fn symlink_attr(path: &str) -> AxResult<FileAttr> {
+    crate::root::lookup_no_follow(None, path)?.get_attr()
+}
+
 impl Drop for File {
     fn drop(&mut self) {
         unsafe { self.node.access_unchecked().release().ok() };
@@ -0,0 +1,311 @@
+//! On-disk structures of the ext2 filesystem.
+//!
+//! All fields are stored in little-endian. The structures keep the raw bytes
+//! read from the disk, so that fields we don't care about are preserved when
+//! writing them back.
+
+use alloc::{vec, vec::Vec};
+use axfs_vfs::VfsNodeType;
+
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+pub const SUPERBLOCK_SIZE: usize = 1024;
+pub const EXT2_MAGIC: u16 = 0xef53;
+pub const GROUP_DESC_SIZE: usize = 32;
+pub const GOOD_OLD_INODE_SIZE: usize = 128;
+pub const GOOD_OLD_FIRST_INO: u32 = 11;
+
+pub const ROOT_INO: u32 = 2;
+
+/// Number of direct blocks in `i_block`.
+pub const NDIR_BLOCKS: usize = 12;
+pub const IND_BLOCK: usize = 12;
+pub const DIND_BLOCK: usize = 13;
+pub const TIND_BLOCK: usize = 14;
+/// Size of `i_block` in bytes, symbolic links shorter than it are stored
+/// inside the inode (fast symlinks).
+pub const INODE_BLOCK_BYTES: usize = 60;
+
+pub const FEATURE_INCOMPAT_FILETYPE: u32 = 0x0002;
+pub const FEATURE_RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+pub const FEATURE_RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+pub const SUPPORTED_INCOMPAT: u32 = FEATURE_INCOMPAT_FILETYPE;
+pub const SUPPORTED_RO_COMPAT: u32 = FEATURE_RO_COMPAT_SPARSE_SUPER | FEATURE_RO_COMPAT_LARGE_FILE;
+
+/// Hash indexed directory, the index is not maintained by us.
+pub const INDEX_FL: u32 = 0x1000;
+
+pub const S_IFMT: u16 = 0o170000;
+pub const S_IFSOCK: u16 = 0o140000;
+pub const S_IFLNK: u16 = 0o120000;
+pub const S_IFREG: u16 = 0o100000;
+pub const S_IFBLK: u16 = 0o060000;
+pub const S_IFDIR: u16 = 0o040000;
+pub const S_IFCHR: u16 = 0o020000;
+pub const S_IFIFO: u16 = 0o010000;
+
+pub const FT_REG_FILE: u8 = 1;
+pub const FT_DIR: u8 = 2;
+pub const FT_SYMLINK: u8 = 7;
+
+/// Generates getters and setters of little-endian fields at fixed offsets of
+/// `self.raw`, in the form `getter / setter: type @ offset;`. Either the getter
+/// or the setter can be omitted.
+macro_rules! le_fields {
+    ($( $($get:ident)? $(/ $set:ident)?: $ty:ident @ $off:literal; )+) => {
+        $(
+            $(
+                pub fn $get(&self) -> $ty {
+                    const N: usize = core::mem::size_of::<$ty>();
+                    <$ty>::from_le_bytes(self.raw[$off..$off + N].try_into().unwrap())
+                }
+            )?
+            $(
+                pub fn $set(&mut self, val: $ty) {
+                    const N: usize = core::mem::size_of::<$ty>();
+                    self.raw[$off..$off + N].copy_from_slice(&val.to_le_bytes());
+                }
+            )?
+        )+
+    };
+}
+
+pub fn read_le32(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+pub fn write_le32(buf: &mut [u8], off: usize, val: u32) {
+    buf[off..off + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+pub struct Superblock {
+    pub raw: [u8; SUPERBLOCK_SIZE],
+}
+
+impl Superblock {
+    le_fields! {
+        inodes_count / set_inodes_count: u32 @ 0;
+        blocks_count / set_blocks_count: u32 @ 4;
+        free_blocks_count / set_free_blocks_count: u32 @ 12;
+        free_inodes_count / set_free_inodes_count: u32 @ 16;
+        first_data_block / set_first_data_block: u32 @ 20;
+        log_block_size / set_log_block_size: u32 @ 24;
+        blocks_per_group / set_blocks_per_group: u32 @ 32;
+        / set_frags_per_group: u32 @ 36;
+        inodes_per_group / set_inodes_per_group: u32 @ 40;
+        wtime: u32 @ 48;
+        / set_max_mnt_count: u16 @ 54;
+        magic / set_magic: u16 @ 56;
+        / set_state: u16 @ 58;
+        / set_errors: u16 @ 60;
+        rev_level / set_rev_level: u32 @ 76;
+        raw_first_ino / set_first_ino: u32 @ 84;
+        raw_inode_size / set_inode_size: u16 @ 88;
+        / set_block_group_nr: u16 @ 90;
+        feature_incompat / set_feature_incompat: u32 @ 96;
+        feature_ro_compat / set_feature_ro_compat: u32 @ 100;
+    }
+
+    pub const fn new() -> Self {
+        Self {
+            raw: [0; SUPERBLOCK_SIZE],
+        }
+    }
+
+    pub fn block_size(&self) -> usize {
+        1024 << self.log_block_size()
+    }
+
+    pub fn first_ino(&self) -> u32 {
+        if self.rev_level() == 0 {
+            GOOD_OLD_FIRST_INO
+        } else {
+            self.raw_first_ino()
+        }
+    }
+
+    pub fn inode_size(&self) -> usize {
+        if self.rev_level() == 0 {
+            GOOD_OLD_INODE_SIZE
+        } else {
+            self.raw_inode_size() as usize
+        }
+    }
+}
+
+pub struct GroupDesc {
+    pub raw: [u8; GROUP_DESC_SIZE],
+}
+
+impl GroupDesc {
+    le_fields! {
+        block_bitmap / set_block_bitmap: u32 @ 0;
+        inode_bitmap / set_inode_bitmap: u32 @ 4;
+        inode_table / set_inode_table: u32 @ 8;
+        free_blocks_count / set_free_blocks_count: u16 @ 12;
+        free_inodes_count / set_free_inodes_count: u16 @ 14;
+        used_dirs_count / set_used_dirs_count: u16 @ 16;
+    }
+
+    pub const fn new() -> Self {
+        Self {
+            raw: [0; GROUP_DESC_SIZE],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Inode {
+    pub raw: Vec<u8>,
+}
+
+impl Inode {
+    le_fields! {
+        mode / set_mode: u16 @ 0;
+        size_lo / set_size_lo: u32 @ 4;
+        / set_dtime: u32 @ 20;
+        links_count / set_links_count: u16 @ 26;
+        blocks / set_blocks: u32 @ 28;
+        flags / set_flags: u32 @ 32;
+        file_acl: u32 @ 104;
+        size_high / set_size_high: u32 @ 108;
+        / set_extra_isize: u16 @ 128;
+    }
+
+    /// Creates a zeroed inode of `inode_size` bytes with the given mode.
+    pub fn new(inode_size: usize, mode: u16) -> Self {
+        let mut inode = Self {
+            raw: vec![0; inode_size],
+        };
+        inode.set_mode(mode);
+        // the same as `mke2fs`, fields after the good old inode are valid.
+        if inode_size >= GOOD_OLD_INODE_SIZE + 32 {
+            inode.set_extra_isize(32);
+        }
+        inode
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode() & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode() & S_IFMT == S_IFLNK
+    }
+
+    pub fn node_type(&self) -> VfsNodeType {
+        match self.mode() & S_IFMT {
+            S_IFIFO => VfsNodeType::Fifo,
+            S_IFCHR => VfsNodeType::CharDevice,
+            S_IFDIR => VfsNodeType::Dir,
+            S_IFBLK => VfsNodeType::BlockDevice,
+            S_IFLNK => VfsNodeType::SymLink,
+            S_IFSOCK => VfsNodeType::Socket,
+            _ => VfsNodeType::File,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        if self.mode() & S_IFMT == S_IFREG {
+            (self.size_high() as u64) << 32 | self.size_lo() as u64
+        } else {
+            self.size_lo() as u64
+        }
+    }
+
+    pub fn set_size(&mut self, size: u64) {
+        self.set_size_lo(size as u32);
+        if self.mode() & S_IFMT == S_IFREG {
+            self.set_size_high((size >> 32) as u32);
+        }
+    }
+
+    pub fn block(&self, idx: usize) -> u32 {
+        read_le32(&self.raw, 40 + idx * 4)
+    }
+
+    pub fn set_block(&mut self, idx: usize, blk: u32) {
+        write_le32(&mut self.raw, 40 + idx * 4, blk)
+    }
+
+    /// The `i_block` array, which stores the target of fast symlinks.
+    pub fn block_bytes(&self) -> &[u8] {
+        &self.raw[40..40 + INODE_BLOCK_BYTES]
+    }
+
+    pub fn block_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.raw[40..40 + INODE_BLOCK_BYTES]
+    }
+}
+
+/// Converts the node type to the file type stored in directory entries.
+pub const fn dir_entry_type(ty: VfsNodeType) -> u8 {
+    match ty {
+        VfsNodeType::File => FT_REG_FILE,
+        VfsNodeType::Dir => FT_DIR,
+        VfsNodeType::CharDevice => 3,
+        VfsNodeType::BlockDevice => 4,
+        VfsNodeType::Fifo => 5,
+        VfsNodeType::Socket => 6,
+        VfsNodeType::SymLink => FT_SYMLINK,
+    }
+}
+
+/// A directory entry header.
+pub struct DirEntryHeader {
+    pub ino: u32,
+    pub rec_len: usize,
+    pub name_len: usize,
+}
+
+impl DirEntryHeader {
+    pub const SIZE: usize = 8;
+
+    /// Parses the entry at `off` of the directory block `buf`.
+    pub fn parse(buf: &[u8], off: usize, has_file_type: bool) -> Option<Self> {
+        if off + Self::SIZE > buf.len() {
+            return None;
+        }
+        let rec_len = u16::from_le_bytes([buf[off + 4], buf[off + 5]]) as usize;
+        let name_len = if has_file_type {
+            buf[off + 6] as usize
+        } else {
+            u16::from_le_bytes([buf[off + 6], buf[off + 7]]) as usize
+        };
+        if rec_len < Self::SIZE || rec_len % 4 != 0 || off + rec_len > buf.len() {
+            return None;
+        }
+        if Self::SIZE + name_len > rec_len {
+            return None;
+        }
+        Some(Self {
+            ino: read_le32(buf, off),
+            rec_len,
+            name_len,
+        })
+    }
+
+    /// The minimum record length of an entry with the name length.
+    pub const fn min_rec_len(name_len: usize) -> usize {
+        (Self::SIZE + name_len + 3) & !3
+    }
+
+    pub fn name<'a>(&self, buf: &'a [u8], off: usize) -> &'a [u8] {
+        &buf[off + Self::SIZE..off + Self::SIZE + self.name_len]
+    }
+
+    pub fn write(buf: &mut [u8], off: usize, ino: u32, rec_len: usize, name: &[u8], ty: u8) {
+        write_le32(buf, off, ino);
+        buf[off + 4..off + 6].copy_from_slice(&(rec_len as u16).to_le_bytes());
+        buf[off + 6] = name.len() as u8;
+        buf[off + 7] = ty;
+        buf[off + Self::SIZE..off + Self::SIZE + name.len()].copy_from_slice(name);
+    }
+
+    pub fn set_ino(buf: &mut [u8], off: usize, ino: u32) {
+        write_le32(buf, off, ino);
+    }
+
+    pub fn set_rec_len(buf: &mut [u8], off: usize, rec_len: usize) {
+        buf[off + 4..off + 6].copy_from_slice(&(rec_len as u16).to_le_bytes());
+    }
+}
@@ -0,0 +1,1147 @@
+//! The [ext2] filesystem, built on [`Disk`].
+//!
+//! Supports reading and writing regular files, directories and symbolic
+//! links. Filesystems with incompatible features (e.g., ext3 journals that
+//! need recovery, or ext4 extents) are rejected, and those with unknown
+//! read-only compatible features are mounted read-only.
+//!
+//! [ext2]: https://www.nongnu.org/ext2-doc/ext2.html
+
+mod layout;
+mod node;
+
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use axerrno::{ax_err, ax_err_type};
+use axfs_vfs::{VfsError, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use self::layout::*;
+use self::node::Ext2Node;
+use crate::dev::Disk;
+
+/// The ext2 filesystem.
+pub struct Ext2FileSystem {
+    inner: Arc<Mutex<Ext2>>,
+}
+
+impl Ext2FileSystem {
+    #[cfg(feature = "use-ramdisk")]
+    pub fn new(disk: Disk) -> Self {
+        let mut fs = Ext2::new(disk);
+        fs.format().expect("failed to format volume");
+        Self::new_inner(fs)
+    }
+
+    #[cfg(not(feature = "use-ramdisk"))]
+    pub fn new(disk: Disk) -> Self {
+        Self::new_inner(Ext2::new(disk))
+    }
+
+    fn new_inner(mut fs: Ext2) -> Self {
+        fs.load().expect("failed to initialize ext2 filesystem");
+        Self {
+            inner: Arc::new(Mutex::new(fs)),
+        }
+    }
+}
+
+impl VfsOps for Ext2FileSystem {
+    fn umount(&self) -> VfsResult {
+        self.inner.lock().sync()
+    }
+
+    fn sync(&self) -> VfsResult {
+        self.inner.lock().sync()
+    }
+
+    fn format(&self) -> VfsResult {
+        let mut fs = self.inner.lock();
+        fs.format()?;
+        fs.load()
+    }
+
+    fn fs_type(&self) -> &str {
+        "ext2"
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        Arc::new(Ext2Node::new(self.inner.clone(), ROOT_INO))
+    }
+}
+
+/// The state of a mounted ext2 filesystem.
+struct Ext2 {
+    disk: Disk,
+    sb: Superblock,
+    groups: Vec<GroupDesc>,
+    block_size: usize,
+    read_only: bool,
+}
+
+impl Ext2 {
+    /// Creates an unloaded filesystem on the disk, [`Ext2::load`] or
+    /// [`Ext2::format`] must be called before other operations.
+    fn new(disk: Disk) -> Self {
+        Self {
+            disk,
+            sb: Superblock::new(),
+            groups: Vec::new(),
+            block_size: 1024,
+            read_only: false,
+        }
+    }
+
+    /// Reads the superblock and group descriptors from the disk.
+    fn load(&mut self) -> VfsResult {
+        let mut sb = Superblock::new();
+        self.read_bytes(SUPERBLOCK_OFFSET, &mut sb.raw)?;
+        if sb.magic() != EXT2_MAGIC {
+            return ax_err!(InvalidData, "not an ext2 filesystem");
+        }
+        if sb.log_block_size() > 6 || sb.blocks_per_group() == 0 || sb.inodes_per_group() == 0 {
+            return ax_err!(InvalidData, "corrupted ext2 superblock");
+        }
+        let incompat = sb.feature_incompat() & !SUPPORTED_INCOMPAT;
+        if incompat != 0 {
+            warn!("unsupported ext2 incompatible features: {:#x}", incompat);
+            return ax_err!(Unsupported);
+        }
+        let ro_compat = sb.feature_ro_compat() & !SUPPORTED_RO_COMPAT;
+        self.read_only = ro_compat != 0;
+        if self.read_only {
+            warn!(
+                "unsupported ext2 read-only compatible features: {:#x}, mount read-only",
+                ro_compat
+            );
+        }
+
+        self.block_size = sb.block_size();
+        let group_count =
+            (sb.blocks_count() - sb.first_data_block()).div_ceil(sb.blocks_per_group());
+        let gdt_pos = (sb.first_data_block() as u64 + 1) * self.block_size as u64;
+        let mut groups = Vec::with_capacity(group_count as usize);
+        for i in 0..group_count as u64 {
+            let mut desc = GroupDesc::new();
+            self.read_bytes(gdt_pos + i * GROUP_DESC_SIZE as u64, &mut desc.raw)?;
+            groups.push(desc);
+        }
+        info!(
+            "ext2: block size {}, {} blocks, {} inodes, {} groups",
+            self.block_size,
+            sb.blocks_count(),
+            sb.inodes_count(),
+            group_count
+        );
+        self.sb = sb;
+        self.groups = groups;
+        Ok(())
+    }
+
+    /// Writes all metadata and cached blocks to the disk.
+    fn sync(&mut self) -> VfsResult {
+        if self.read_only {
+            return Ok(());
+        }
+        self.write_super()?;
+        for i in 0..self.groups.len() {
+            self.write_group(i)?;
+        }
+        self.disk.flush().map_err(|_| VfsError::Io)
+    }
+
+    fn check_writable(&self) -> VfsResult {
+        if self.read_only {
+            ax_err!(PermissionDenied, "ext2 filesystem is read-only")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn has_file_type(&self) -> bool {
+        self.sb.feature_incompat() & FEATURE_INCOMPAT_FILETYPE != 0
+    }
+
+    // disk I/O
+
+    fn read_bytes(&mut self, pos: u64, mut buf: &mut [u8]) -> VfsResult {
+        self.disk.set_position(pos);
+        while !buf.is_empty() {
+            match self.disk.read_one(buf) {
+                Ok(0) => return ax_err!(UnexpectedEof),
+                Ok(n) => buf = &mut buf[n..],
+                Err(_) => return ax_err!(Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, pos: u64, mut buf: &[u8]) -> VfsResult {
+        self.disk.set_position(pos);
+        while !buf.is_empty() {
+            match self.disk.write_one(buf) {
+                Ok(0) => return ax_err!(WriteZero),
+                Ok(n) => buf = &buf[n..],
+                Err(_) => return ax_err!(Io),
+            }
+        }
+        Ok(())
+    }
+
+    fn block_pos(&self, blk: u32) -> u64 {
+        blk as u64 * self.block_size as u64
+    }
+
+    fn read_block(&mut self, blk: u32, buf: &mut [u8]) -> VfsResult {
+        self.read_bytes(self.block_pos(blk), &mut buf[..self.block_size])
+    }
+
+    fn write_block(&mut self, blk: u32, buf: &[u8]) -> VfsResult {
+        self.write_bytes(self.block_pos(blk), &buf[..self.block_size])
+    }
+
+    fn write_super(&mut self) -> VfsResult {
+        let raw = self.sb.raw;
+        self.write_bytes(SUPERBLOCK_OFFSET, &raw)
+    }
+
+    fn write_group(&mut self, group: usize) -> VfsResult {
+        let gdt_pos = (self.sb.first_data_block() as u64 + 1) * self.block_size as u64;
+        let raw = self.groups[group].raw;
+        self.write_bytes(gdt_pos + (group * GROUP_DESC_SIZE) as u64, &raw)
+    }
+
+    // inodes
+
+    fn inode_group(&self, ino: u32) -> usize {
+        ((ino - 1) / self.sb.inodes_per_group()) as usize
+    }
+
+    fn inode_pos(&self, ino: u32) -> VfsResult<u64> {
+        if ino == 0 || ino > self.sb.inodes_count() {
+            return ax_err!(InvalidData, "invalid inode number");
+        }
+        let group = self.inode_group(ino);
+        let index = (ino - 1) % self.sb.inodes_per_group();
+        let table = self.groups[group].inode_table();
+        Ok(self.block_pos(table) + index as u64 * self.sb.inode_size() as u64)
+    }
+
+    fn read_inode(&mut self, ino: u32) -> VfsResult<Inode> {
+        let pos = self.inode_pos(ino)?;
+        let mut inode = Inode {
+            raw: vec![0; self.sb.inode_size()],
+        };
+        self.read_bytes(pos, &mut inode.raw)?;
+        Ok(inode)
+    }
+
+    fn write_inode(&mut self, ino: u32, inode: &Inode) -> VfsResult {
+        let pos = self.inode_pos(ino)?;
+        self.write_bytes(pos, &inode.raw)
+    }
+
+    /// The `i_dtime` of deleted inodes.
+    ///
+    /// There is no clock here, so the last write time of the filesystem is
+    /// used. It must not be less than `s_inodes_count`, otherwise `e2fsck`
+    /// takes it as a link of the orphan inode list.
+    fn deletion_time(&self) -> u32 {
+        self.sb.wtime().max(self.sb.inodes_count())
+    }
+
+    /// Returns `true` if the symbolic link is stored in `i_block`.
+    fn is_fast_symlink(&self, inode: &Inode) -> bool {
+        let acl_blocks = if inode.file_acl() != 0 {
+            self.block_size as u32 / 512
+        } else {
+            0
+        };
+        inode.is_symlink() && inode.blocks() == acl_blocks
+    }
+
+    // allocation
+
+    fn blocks_in_group(&self, group: usize) -> u32 {
+        let start = self.sb.first_data_block() + group as u32 * self.sb.blocks_per_group();
+        (self.sb.blocks_count() - start).min(self.sb.blocks_per_group())
+    }
+
+    /// Finds a zero bit in the first `count` bits of the bitmap, sets it and
+    /// returns its index.
+    fn bitmap_alloc(bitmap: &mut [u8], count: usize) -> Option<usize> {
+        let (idx, byte) = bitmap[..count.div_ceil(8)]
+            .iter_mut()
+            .enumerate()
+            .find(|(_, b)| **b != 0xff)?;
+        let bit = idx * 8 + byte.trailing_ones() as usize;
+        if bit >= count {
+            return None;
+        }
+        *byte |= 1 << (bit % 8);
+        Some(bit)
+    }
+
+    /// Allocates a zeroed block, preferably in the group `goal`.
+    fn alloc_block(&mut self, goal: usize) -> VfsResult<u32> {
+        self.check_writable()?;
+        let mut bitmap = vec![0; self.block_size];
+        for i in 0..self.groups.len() {
+            let group = (goal + i) % self.groups.len();
+            if self.groups[group].free_blocks_count() == 0 {
+                continue;
+            }
+            let bitmap_blk = self.groups[group].block_bitmap();
+            self.read_block(bitmap_blk, &mut bitmap)?;
+            let Some(bit) = Self::bitmap_alloc(&mut bitmap, self.blocks_in_group(group) as usize)
+            else {
+                warn!("ext2: free blocks count of group {} is wrong", group);
+                continue;
+            };
+            self.write_block(bitmap_blk, &bitmap)?;
+
+            let desc = &mut self.groups[group];
+            desc.set_free_blocks_count(desc.free_blocks_count() - 1);
+            self.write_group(group)?;
+            self.sb
+                .set_free_blocks_count(self.sb.free_blocks_count().saturating_sub(1));
+            self.write_super()?;
+
+            let blk =
+                self.sb.first_data_block() + group as u32 * self.sb.blocks_per_group() + bit as u32;
+            bitmap.fill(0);
+            self.write_block(blk, &bitmap)?;
+            return Ok(blk);
+        }
+        ax_err!(StorageFull)
+    }
+
+    fn free_block(&mut self, blk: u32) -> VfsResult {
+        if blk < self.sb.first_data_block() || blk >= self.sb.blocks_count() {
+            return ax_err!(InvalidData, "invalid block number");
+        }
+        let idx = blk - self.sb.first_data_block();
+        let group = (idx / self.sb.blocks_per_group()) as usize;
+        let bit = (idx % self.sb.blocks_per_group()) as usize;
+        if group >= self.groups.len() {
+            return ax_err!(InvalidData, "invalid block number");
+        }
+        let bitmap_blk = self.groups[group].block_bitmap();
+        let mut bitmap = vec![0; self.block_size];
+        self.read_block(bitmap_blk, &mut bitmap)?;
+        if bitmap[bit / 8] & (1 << (bit % 8)) == 0 {
+            warn!("ext2: freeing free block {}", blk);
+            return Ok(());
+        }
+        bitmap[bit / 8] &= !(1 << (bit % 8));
+        self.write_block(bitmap_blk, &bitmap)?;
+
+        let desc = &mut self.groups[group];
+        desc.set_free_blocks_count(desc.free_blocks_count() + 1);
+        self.write_group(group)?;
+        self.sb
+            .set_free_blocks_count(self.sb.free_blocks_count() + 1);
+        self.write_super()
+    }
+
+    /// Allocates an inode, preferably in the group `goal`.
+    fn alloc_inode(&mut self, goal: usize, is_dir: bool) -> VfsResult<u32> {
+        self.check_writable()?;
+        let ipg = self.sb.inodes_per_group();
+        let mut bitmap = vec![0; self.block_size];
+        for i in 0..self.groups.len() {
+            let group = (goal + i) % self.groups.len();
+            if self.groups[group].free_inodes_count() == 0 {
+                continue;
+            }
+            let bitmap_blk = self.groups[group].inode_bitmap();
+            self.read_block(bitmap_blk, &mut bitmap)?;
+            let Some(bit) = Self::bitmap_alloc(&mut bitmap, ipg as usize) else {
+                warn!("ext2: free inodes count of group {} is wrong", group);
+                continue;
+            };
+            let ino = group as u32 * ipg + bit as u32 + 1;
+            if ino < self.sb.first_ino() {
+                warn!("ext2: reserved inode {} is not marked as used", ino);
+                continue;
+            }
+            self.write_block(bitmap_blk, &bitmap)?;
+
+            let desc = &mut self.groups[group];
+            desc.set_free_inodes_count(desc.free_inodes_count() - 1);
+            if is_dir {
+                desc.set_used_dirs_count(desc.used_dirs_count() + 1);
+            }
+            self.write_group(group)?;
+            self.sb
+                .set_free_inodes_count(self.sb.free_inodes_count().saturating_sub(1));
+            self.write_super()?;
+            return Ok(ino);
+        }
+        ax_err!(StorageFull)
+    }
+
+    fn free_inode(&mut self, ino: u32, is_dir: bool) -> VfsResult {
+        if ino == 0 || ino > self.sb.inodes_count() {
+            return ax_err!(InvalidData, "invalid inode number");
+        }
+        let group = self.inode_group(ino);
+        let bit = ((ino - 1) % self.sb.inodes_per_group()) as usize;
+        let bitmap_blk = self.groups[group].inode_bitmap();
+        let mut bitmap = vec![0; self.block_size];
+        self.read_block(bitmap_blk, &mut bitmap)?;
+        bitmap[bit / 8] &= !(1 << (bit % 8));
+        self.write_block(bitmap_blk, &bitmap)?;
+
+        let desc = &mut self.groups[group];
+        desc.set_free_inodes_count(desc.free_inodes_count() + 1);
+        if is_dir {
+            desc.set_used_dirs_count(desc.used_dirs_count().saturating_sub(1));
+        }
+        self.write_group(group)?;
+        self.sb
+            .set_free_inodes_count(self.sb.free_inodes_count() + 1);
+        self.write_super()
+    }
+
+    // block mapping
+
+    fn ptrs_per_block(&self) -> u64 {
+        self.block_size as u64 / 4
+    }
+
+    fn add_inode_blocks(&self, inode: &mut Inode, count: i64) {
+        let sectors = count * (self.block_size / 512) as i64;
+        inode.set_blocks((inode.blocks() as i64 + sectors) as u32);
+    }
+
+    /// Maps the file block `fblk` of the inode to a disk block.
+    ///
+    /// Returns `0` if the block is a hole and `create` is `false`. Otherwise
+    /// the missing blocks are allocated, and the caller should write the inode
+    /// back.
+    fn bmap(&mut self, ino: u32, inode: &mut Inode, fblk: u64, create: bool) -> VfsResult<u32> {
+        let ppb = self.ptrs_per_block();
+        let (slot, level, mut idx) = if fblk < NDIR_BLOCKS as u64 {
+            (fblk as usize, 0, 0)
+        } else if fblk - (NDIR_BLOCKS as u64) < ppb {
+            (IND_BLOCK, 1, fblk - NDIR_BLOCKS as u64)
+        } else if fblk - (NDIR_BLOCKS as u64) - ppb < ppb * ppb {
+            (DIND_BLOCK, 2, fblk - NDIR_BLOCKS as u64 - ppb)
+        } else if fblk - (NDIR_BLOCKS as u64) - ppb - ppb * ppb < ppb * ppb * ppb {
+            (TIND_BLOCK, 3, fblk - NDIR_BLOCKS as u64 - ppb - ppb * ppb)
+        } else {
+            return ax_err!(InvalidInput, "file too large");
+        };
+
+        let goal = self.inode_group(ino);
+        let mut blk = inode.block(slot);
+        if blk == 0 {
+            if !create {
+                return Ok(0);
+            }
+            blk = self.alloc_block(goal)?;
+            inode.set_block(slot, blk);
+            self.add_inode_blocks(inode, 1);
+        }
+        for l in (0..level).rev() {
+            let span = ppb.pow(l);
+            let ptr_pos = self.block_pos(blk) + (idx / span) * 4;
+            idx %= span;
+            let mut ptr = [0; 4];
+            self.read_bytes(ptr_pos, &mut ptr)?;
+            blk = u32::from_le_bytes(ptr);
+            if blk == 0 {
+                if !create {
+                    return Ok(0);
+                }
+                blk = self.alloc_block(goal)?;
+                self.write_bytes(ptr_pos, &blk.to_le_bytes())?;
+                self.add_inode_blocks(inode, 1);
+            }
+        }
+        Ok(blk)
+    }
+
+    /// Frees the (indirect) block `blk` at the given `level` which maps file
+    /// blocks from `base`, if they are not less than `keep`.
+    ///
+    /// Returns `true` if `blk` itself is freed.
+    fn truncate_tree(
+        &mut self,
+        blk: u32,
+        level: u32,
+        base: u64,
+        keep: u64,
+        freed: &mut i64,
+    ) -> VfsResult<bool> {
+        let ppb = self.ptrs_per_block();
+        if base + ppb.pow(level) <= keep {
+            return Ok(false); // all kept
+        }
+        if level > 0 {
+            let span = ppb.pow(level - 1);
+            let mut ptrs = vec![0; self.block_size];
+            self.read_block(blk, &mut ptrs)?;
+            let mut dirty = false;
+            for i in 0..ppb as usize {
+                let child = read_le32(&ptrs, i * 4);
+                if child != 0
+                    && self.truncate_tree(child, level - 1, base + i as u64 * span, keep, freed)?
+                {
+                    write_le32(&mut ptrs, i * 4, 0);
+                    dirty = true;
+                }
+            }
+            if base < keep {
+                if dirty {
+                    self.write_block(blk, &ptrs)?;
+                }
+                return Ok(false);
+            }
+        }
+        self.free_block(blk)?;
+        *freed += 1;
+        Ok(true)
+    }
+
+    /// Frees all blocks of the inode which map file blocks not less than
+    /// `keep`. The caller should write the inode back.
+    fn free_blocks_from(&mut self, inode: &mut Inode, keep: u64) -> VfsResult {
+        let ppb = self.ptrs_per_block();
+        let mut freed = 0;
+        for slot in 0..NDIR_BLOCKS {
+            let blk = inode.block(slot);
+            if blk != 0 && slot as u64 >= keep {
+                self.free_block(blk)?;
+                inode.set_block(slot, 0);
+                freed += 1;
+            }
+        }
+        let mut base = NDIR_BLOCKS as u64;
+        for (level, slot) in [(1, IND_BLOCK), (2, DIND_BLOCK), (3, TIND_BLOCK)] {
+            let blk = inode.block(slot);
+            if blk != 0 && self.truncate_tree(blk, level, base, keep, &mut freed)? {
+                inode.set_block(slot, 0);
+            }
+            base += ppb.pow(level);
+        }
+        self.add_inode_blocks(inode, -freed);
+        Ok(())
+    }
+
+    // file data
+
+    fn read_data(
+        &mut self,
+        ino: u32,
+        inode: &mut Inode,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> VfsResult<usize> {
+        let size = inode.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let end = size.min(offset + buf.len() as u64);
+        let bs = self.block_size as u64;
+        let mut pos = offset;
+        while pos < end {
+            let boff = pos % bs;
+            let len = (bs - boff).min(end - pos) as usize;
+            let dst = &mut buf[(pos - offset) as usize..][..len];
+            match self.bmap(ino, inode, pos / bs, false)? {
+                0 => dst.fill(0), // hole
+                blk => self.read_bytes(self.block_pos(blk) + boff, dst)?,
+            }
+            pos += len as u64;
+        }
+        Ok((end - offset) as usize)
+    }
+
+    fn write_data(
+        &mut self,
+        ino: u32,
+        inode: &mut Inode,
+        offset: u64,
+        buf: &[u8],
+    ) -> VfsResult<usize> {
+        self.check_writable()?;
+        let end = offset + buf.len() as u64;
+        let bs = self.block_size as u64;
+        let mut pos = offset;
+        let mut result = Ok(());
+        while pos < end {
+            let boff = pos % bs;
+            let len = (bs - boff).min(end - pos) as usize;
+            let blk = match self.bmap(ino, inode, pos / bs, true) {
+                Ok(blk) => blk,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            };
+            let data = &buf[(pos - offset) as usize..][..len];
+            if let Err(e) = self.write_bytes(self.block_pos(blk) + boff, data) {
+                result = Err(e);
+                break;
+            }
+            pos += len as u64;
+        }
+        if pos > inode.size() {
+            inode.set_size(pos);
+        }
+        // write the inode back even on errors, as blocks may have been
+        // allocated for it
+        self.write_inode(ino, inode)?;
+        if pos > i32::MAX as u64 && self.sb.feature_ro_compat() & FEATURE_RO_COMPAT_LARGE_FILE == 0
+        {
+            self.sb
+                .set_feature_ro_compat(self.sb.feature_ro_compat() | FEATURE_RO_COMPAT_LARGE_FILE);
+            self.write_super()?;
+        }
+        match result {
+            Err(e) if pos == offset => Err(e),
+            _ => Ok((pos - offset) as usize),
+        }
+    }
+
+    fn truncate(&mut self, ino: u32, size: u64) -> VfsResult {
+        self.check_writable()?;
+        let mut inode = self.read_inode(ino)?;
+        let bs = self.block_size as u64;
+        if size < inode.size() {
+            self.free_blocks_from(&mut inode, size.div_ceil(bs))?;
+            // zero the tail of the last block, which may be read after extending
+            if size % bs != 0 {
+                let blk = self.bmap(ino, &mut inode, size / bs, false)?;
+                if blk != 0 {
+                    let zeros = vec![0; (bs - size % bs) as usize];
+                    self.write_bytes(self.block_pos(blk) + size % bs, &zeros)?;
+                }
+            }
+        }
+        inode.set_size(size);
+        self.write_inode(ino, &inode)
+    }
+
+    // directories
+
+    /// Calls `f` with each entry in the directory, with the block buffer, the
+    /// offset of the entry, and the file block index.
+    ///
+    /// Stops if `f` returns `Some`, and returns the value.
+    fn for_each_entry<T>(
+        &mut self,
+        ino: u32,
+        dir: &mut Inode,
+        mut f: impl FnMut(&mut Self, &mut [u8], usize, &DirEntryHeader, u32) -> VfsResult<Option<T>>,
+    ) -> VfsResult<Option<T>> {
+        let has_file_type = self.has_file_type();
+        let nblocks = dir.size() / self.block_size as u64;
+        let mut buf = vec![0; self.block_size];
+        for fblk in 0..nblocks {
+            let blk = self.bmap(ino, dir, fblk, false)?;
+            if blk == 0 {
+                continue;
+            }
+            self.read_block(blk, &mut buf)?;
+            let mut off = 0;
+            while off < buf.len() {
+                let entry = DirEntryHeader::parse(&buf, off, has_file_type)
+                    .ok_or_else(|| ax_err_type!(InvalidData, "corrupted ext2 directory"))?;
+                if let Some(ret) = f(self, &mut buf, off, &entry, blk)? {
+                    return Ok(Some(ret));
+                }
+                off += entry.rec_len;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Finds the entry `name` in the directory, returns its inode number.
+    fn find_entry(&mut self, dir_ino: u32, name: &str) -> VfsResult<Option<u32>> {
+        let mut dir = self.read_inode(dir_ino)?;
+        if !dir.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        self.for_each_entry(dir_ino, &mut dir, |_, buf, off, entry, _| {
+            Ok((entry.ino != 0 && entry.name(buf, off) == name.as_bytes()).then_some(entry.ino))
+        })
+    }
+
+    /// Returns all entries in the directory.
+    fn list_entries(&mut self, dir_ino: u32) -> VfsResult<Vec<(String, u32)>> {
+        let mut dir = self.read_inode(dir_ino)?;
+        let mut entries = Vec::new();
+        self.for_each_entry::<()>(dir_ino, &mut dir, |_, buf, off, entry, _| {
+            if entry.ino != 0 {
+                let name = String::from_utf8_lossy(entry.name(buf, off)).into_owned();
+                entries.push((name, entry.ino));
+            }
+            Ok(None)
+        })?;
+        Ok(entries)
+    }
+
+    fn is_dir_empty(&mut self, dir_ino: u32) -> VfsResult<bool> {
+        let mut dir = self.read_inode(dir_ino)?;
+        let non_empty = self.for_each_entry(dir_ino, &mut dir, |_, buf, off, entry, _| {
+            let name = entry.name(buf, off);
+            Ok((entry.ino != 0 && name != b"." && name != b"..").then_some(()))
+        })?;
+        Ok(non_empty.is_none())
+    }
+
+    /// Adds an entry to the directory.
+    fn add_entry(&mut self, dir_ino: u32, name: &str, ino: u32, ty: VfsNodeType) -> VfsResult {
+        if name.len() > 255 {
+            return ax_err!(InvalidInput, "file name too long");
+        }
+        let ty = if self.has_file_type() {
+            dir_entry_type(ty)
+        } else {
+            0
+        };
+        let need = DirEntryHeader::min_rec_len(name.len());
+        let mut dir = self.read_inode(dir_ino)?;
+        let added = self.for_each_entry(dir_ino, &mut dir, |fs, buf, off, entry, blk| {
+            let used = if entry.ino == 0 {
+                0
+            } else {
+                DirEntryHeader::min_rec_len(entry.name_len)
+            };
+            if entry.rec_len - used < need {
+                return Ok(None);
+            }
+            if used == 0 {
+                DirEntryHeader::write(buf, off, ino, entry.rec_len, name.as_bytes(), ty);
+            } else {
+                DirEntryHeader::set_rec_len(buf, off, used);
+                DirEntryHeader::write(
+                    buf,
+                    off + used,
+                    ino,
+                    entry.rec_len - used,
+                    name.as_bytes(),
+                    ty,
+                );
+            }
+            fs.write_block(blk, buf)?;
+            Ok(Some(()))
+        })?;
+        if added.is_none() {
+            // no space in existing blocks, append a new one
+            let nblocks = dir.size() / self.block_size as u64;
+            let blk = self.bmap(dir_ino, &mut dir, nblocks, true)?;
+            let mut buf = vec![0; self.block_size];
+            DirEntryHeader::write(&mut buf, 0, ino, self.block_size, name.as_bytes(), ty);
+            self.write_block(blk, &buf)?;
+            dir.set_size((nblocks + 1) * self.block_size as u64);
+        }
+        // the hash index is not updated, fall back to linear directory
+        dir.set_flags(dir.flags() & !INDEX_FL);
+        self.write_inode(dir_ino, &dir)
+    }
+
+    /// Removes the entry `name` from the directory.
+    fn remove_entry(&mut self, dir_ino: u32, name: &str) -> VfsResult {
+        let mut dir = self.read_inode(dir_ino)?;
+        let mut prev = None;
+        let removed = self.for_each_entry(dir_ino, &mut dir, |fs, buf, off, entry, blk| {
+            if off == 0 {
+                prev = None;
+            }
+            if entry.ino == 0 || entry.name(buf, off) != name.as_bytes() {
+                prev = Some(off);
+                return Ok(None);
+            }
+            match prev {
+                Some(prev_off) => {
+                    // merge into the previous entry
+                    let prev_len = off - prev_off;
+                    DirEntryHeader::set_rec_len(buf, prev_off, prev_len + entry.rec_len);
+                }
+                None => DirEntryHeader::set_ino(buf, off, 0),
+            }
+            fs.write_block(blk, buf)?;
+            Ok(Some(()))
+        })?;
+        if removed.is_none() {
+            return ax_err!(NotFound);
+        }
+        if dir.flags() & INDEX_FL != 0 {
+            dir.set_flags(dir.flags() & !INDEX_FL);
+            self.write_inode(dir_ino, &dir)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the `..` entry of the directory to `parent`.
+    fn set_dotdot(&mut self, dir_ino: u32, parent: u32) -> VfsResult {
+        let mut dir = self.read_inode(dir_ino)?;
+        self.for_each_entry(dir_ino, &mut dir, |fs, buf, off, entry, blk| {
+            if entry.name(buf, off) != b".." {
+                return Ok(None);
+            }
+            DirEntryHeader::set_ino(buf, off, parent);
+            fs.write_block(blk, buf)?;
+            Ok(Some(()))
+        })?
+        .ok_or_else(|| ax_err_type!(InvalidData, "no `..` in ext2 directory"))
+    }
+
+    fn update_links(&mut self, ino: u32, delta: i16) -> VfsResult {
+        let mut inode = self.read_inode(ino)?;
+        inode.set_links_count(inode.links_count().wrapping_add_signed(delta));
+        self.write_inode(ino, &inode)
+    }
+
+    // path resolution
+
+    /// Looks up `path` relative to the directory `start`, returns the inode
+    /// number. Symbolic links are not followed.
+    ///
+    /// Like other filesystems, the root directory has no parent here, as it's
+    /// the mount point's business.
+    fn lookup_path(&mut self, start: u32, path: &str) -> VfsResult<u32> {
+        let mut ino = start;
+        for name in path.split('/') {
+            match name {
+                "" | "." => {}
+                ".." if ino == ROOT_INO => return ax_err!(NotFound),
+                _ => ino = self.find_entry(ino, name)?.ok_or(VfsError::NotFound)?,
+            }
+        }
+        Ok(ino)
+    }
+
+    /// Splits `path` relative to the directory `start` to the parent
+    /// directory and the last component.
+    fn lookup_parent<'a>(&mut self, start: u32, path: &'a str) -> VfsResult<(u32, &'a str)> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (self.lookup_path(start, parent)?, name),
+            None => (start, path),
+        };
+        if !self.read_inode(parent)?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        Ok((parent, name))
+    }
+
+    // node operations
+
+    /// Creates a new node `name` in the directory `parent`.
+    fn create_node(
+        &mut self,
+        parent: u32,
+        name: &str,
+        ty: VfsNodeType,
+        perm: u16,
+    ) -> VfsResult<u32> {
+        let mode = match ty {
+            VfsNodeType::File => S_IFREG,
+            VfsNodeType::Dir => S_IFDIR,
+            VfsNodeType::SymLink => S_IFLNK,
+            _ => return ax_err!(Unsupported),
+        };
+        let is_dir = ty == VfsNodeType::Dir;
+        let group = self.inode_group(parent);
+        let ino = self.alloc_inode(group, is_dir)?;
+        let mut inode = Inode::new(self.sb.inode_size(), mode | perm);
+        inode.set_links_count(if is_dir { 2 } else { 1 });
+
+        let mut init = || -> VfsResult {
+            if is_dir {
+                let blk = self.bmap(ino, &mut inode, 0, true)?;
+                let mut buf = vec![0; self.block_size];
+                let dir_ty = if self.has_file_type() { FT_DIR } else { 0 };
+                let dot_len = DirEntryHeader::min_rec_len(1);
+                DirEntryHeader::write(&mut buf, 0, ino, dot_len, b".", dir_ty);
+                DirEntryHeader::write(
+                    &mut buf,
+                    dot_len,
+                    parent,
+                    self.block_size - dot_len,
+                    b"..",
+                    dir_ty,
+                );
+                self.write_block(blk, &buf)?;
+                inode.set_size(self.block_size as u64);
+            }
+            self.write_inode(ino, &inode)?;
+            self.add_entry(parent, name, ino, ty)
+        };
+        if let Err(e) = init() {
+            // roll back
+            self.free_blocks_from(&mut inode, 0).ok();
+            inode.set_links_count(0);
+            inode.set_dtime(self.deletion_time());
+            self.write_inode(ino, &inode).ok();
+            self.free_inode(ino, is_dir).ok();
+            return Err(e);
+        }
+        if is_dir {
+            self.update_links(parent, 1)?;
+        }
+        Ok(ino)
+    }
+
+    fn create_symlink(&mut self, parent: u32, name: &str, target: &str) -> VfsResult {
+        let ino = self.create_node(parent, name, VfsNodeType::SymLink, 0o777)?;
+        let mut inode = self.read_inode(ino)?;
+        if target.len() < INODE_BLOCK_BYTES {
+            inode.block_bytes_mut()[..target.len()].copy_from_slice(target.as_bytes());
+            inode.set_size(target.len() as u64);
+            self.write_inode(ino, &inode)
+        } else {
+            self.write_data(ino, &mut inode, 0, target.as_bytes())
+                .map(|_| ())
+        }
+    }
+
+    fn read_link(&mut self, ino: u32, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut inode = self.read_inode(ino)?;
+        if !inode.is_symlink() {
+            return ax_err!(InvalidInput);
+        }
+        if self.is_fast_symlink(&inode) {
+            let len = (inode.size() as usize)
+                .min(INODE_BLOCK_BYTES)
+                .min(buf.len());
+            buf[..len].copy_from_slice(&inode.block_bytes()[..len]);
+            Ok(len)
+        } else {
+            self.read_data(ino, &mut inode, 0, buf)
+        }
+    }
+
+    /// Removes the entry `name` from the directory `parent`, and frees the
+    /// node if no links to it remain.
+    fn unlink(&mut self, parent: u32, name: &str) -> VfsResult {
+        let ino = self.find_entry(parent, name)?.ok_or(VfsError::NotFound)?;
+        let mut inode = self.read_inode(ino)?;
+        let is_dir = inode.is_dir();
+        if is_dir && !self.is_dir_empty(ino)? {
+            return ax_err!(DirectoryNotEmpty);
+        }
+        self.remove_entry(parent, name)?;
+        if is_dir {
+            self.update_links(parent, -1)?;
+            inode.set_links_count(0);
+        } else {
+            inode.set_links_count(inode.links_count().saturating_sub(1));
+        }
+        if inode.links_count() == 0 {
+            if !self.is_fast_symlink(&inode) {
+                self.free_blocks_from(&mut inode, 0)?;
+            }
+            inode.set_size(0);
+            inode.set_dtime(self.deletion_time());
+            self.write_inode(ino, &inode)?;
+            self.free_inode(ino, is_dir)
+        } else {
+            self.write_inode(ino, &inode)
+        }
+    }
+
+    fn rename(&mut self, start: u32, src_path: &str, dst_path: &str) -> VfsResult {
+        let (src_dir, src_name) = self.lookup_parent(start, src_path)?;
+        let (dst_dir, dst_name) = self.lookup_parent(start, dst_path)?;
+        for name in [src_name, dst_name] {
+            if name.is_empty() || name == "." || name == ".." {
+                return ax_err!(InvalidInput);
+            }
+        }
+        let ino = self
+            .find_entry(src_dir, src_name)?
+            .ok_or(VfsError::NotFound)?;
+        let inode = self.read_inode(ino)?;
+        let is_dir = inode.is_dir();
+
+        if is_dir {
+            // cannot move a directory into itself
+            let mut cur = dst_dir;
+            while cur != ROOT_INO {
+                if cur == ino {
+                    return ax_err!(InvalidInput);
+                }
+                match self.find_entry(cur, "..")? {
+                    Some(parent) if parent != cur => cur = parent,
+                    _ => break,
+                }
+            }
+        }
+        if let Some(old) = self.find_entry(dst_dir, dst_name)? {
+            if old == ino {
+                return Ok(());
+            }
+            match (is_dir, self.read_inode(old)?.is_dir()) {
+                (true, false) => return ax_err!(NotADirectory),
+                (false, true) => return ax_err!(IsADirectory),
+                _ => self.unlink(dst_dir, dst_name)?,
+            }
+        }
+
+        self.add_entry(dst_dir, dst_name, ino, inode.node_type())?;
+        self.remove_entry(src_dir, src_name)?;
+        if is_dir && src_dir != dst_dir {
+            self.set_dotdot(ino, dst_dir)?;
+            self.update_links(src_dir, -1)?;
+            self.update_links(dst_dir, 1)?;
+        }
+        Ok(())
+    }
+
+    // formatting
+
+    /// Creates a new ext2 filesystem on the whole disk.
+    ///
+    /// Uses 1 KiB blocks and 128-byte inodes, and stores backups of the
+    /// superblock in all block groups.
+    fn format(&mut self) -> VfsResult {
+        const BLOCK_SIZE: u32 = 1024;
+        const INODE_SIZE: u32 = 128;
+        const BLOCKS_PER_GROUP: u32 = BLOCK_SIZE * 8;
+        const INODES_PER_BLOCK: u32 = BLOCK_SIZE / INODE_SIZE;
+        const FIRST_DATA_BLOCK: u32 = 1;
+
+        let mut blocks_count = (self.disk.size() / BLOCK_SIZE as u64).min(u32::MAX as u64) as u32;
+        let mut group_count = (blocks_count - FIRST_DATA_BLOCK).div_ceil(BLOCKS_PER_GROUP);
+        let gdt_blocks = (group_count * GROUP_DESC_SIZE as u32).div_ceil(BLOCK_SIZE);
+        // one inode per 4 blocks
+        let inodes_per_group = (BLOCKS_PER_GROUP / 4)
+            .min((blocks_count - FIRST_DATA_BLOCK) / 4)
+            .next_multiple_of(INODES_PER_BLOCK)
+            .max(16);
+        let itable_blocks = inodes_per_group / INODES_PER_BLOCK;
+        let overhead = 1 + gdt_blocks + 2 + itable_blocks;
+
+        // drop the last group if it is too small
+        let last_size = blocks_count - FIRST_DATA_BLOCK - (group_count - 1) * BLOCKS_PER_GROUP;
+        if last_size < overhead + 16 {
+            group_count -= 1;
+            blocks_count = FIRST_DATA_BLOCK + group_count * BLOCKS_PER_GROUP;
+        }
+        if group_count == 0 {
+            return ax_err!(InvalidInput, "disk too small for ext2");
+        }
+
+        let mut sb = Superblock::new();
+        sb.set_inodes_count(inodes_per_group * group_count);
+        sb.set_blocks_count(blocks_count);
+        sb.set_first_data_block(FIRST_DATA_BLOCK);
+        sb.set_log_block_size(0);
+        sb.set_blocks_per_group(BLOCKS_PER_GROUP);
+        sb.set_frags_per_group(BLOCKS_PER_GROUP);
+        sb.set_inodes_per_group(inodes_per_group);
+        sb.set_max_mnt_count(u16::MAX);
+        sb.set_magic(EXT2_MAGIC);
+        sb.set_state(1); // cleanly unmounted
+        sb.set_errors(1); // continue on errors
+        sb.set_rev_level(1);
+        sb.set_first_ino(GOOD_OLD_FIRST_INO);
+        sb.set_inode_size(INODE_SIZE as u16);
+        sb.set_feature_incompat(FEATURE_INCOMPAT_FILETYPE);
+
+        self.sb = sb;
+        self.groups = Vec::new();
+        self.block_size = BLOCK_SIZE as usize;
+        self.read_only = false;
+        self.format_groups(group_count, gdt_blocks, overhead)
+    }
+
+    fn format_groups(&mut self, group_count: u32, gdt_blocks: u32, overhead: u32) -> VfsResult {
+        let bpg = self.sb.blocks_per_group();
+        let ipg = self.sb.inodes_per_group();
+        let mut free_blocks = 0;
+        for group in 0..group_count as usize {
+            let start = self.sb.first_data_block() + group as u32 * bpg;
+            let mut desc = GroupDesc::new();
+            desc.set_block_bitmap(start + 1 + gdt_blocks);
+            desc.set_inode_bitmap(start + 2 + gdt_blocks);
+            desc.set_inode_table(start + 3 + gdt_blocks);
+            self.groups.push(desc);
+            let size = self.blocks_in_group(group);
+
+            // block bitmap, with metadata and padding bits set
+            let mut bitmap = vec![0u8; self.block_size];
+            for bit in (0..overhead as usize).chain(size as usize..self.block_size * 8) {
+                bitmap[bit / 8] |= 1 << (bit % 8);
+            }
+            self.write_block(start + 1 + gdt_blocks, &bitmap)?;
+            // inode bitmap, with reserved inodes and padding bits set
+            let reserved = if group == 0 {
+                self.sb.first_ino() - 1
+            } else {
+                0
+            };
+            bitmap.fill(0);
+            for bit in (0..reserved as usize).chain(ipg as usize..self.block_size * 8) {
+                bitmap[bit / 8] |= 1 << (bit % 8);
+            }
+            self.write_block(start + 2 + gdt_blocks, &bitmap)?;
+            // inode table
+            bitmap.fill(0);
+            for blk in 0..overhead - 3 - gdt_blocks {
+                self.write_block(start + 3 + gdt_blocks + blk, &bitmap)?;
+            }
+
+            let desc = &mut self.groups[group];
+            desc.set_free_blocks_count((size - overhead) as u16);
+            desc.set_free_inodes_count((ipg - reserved) as u16);
+            free_blocks += size - overhead;
+        }
+        self.sb.set_free_blocks_count(free_blocks);
+        self.sb
+            .set_free_inodes_count(self.sb.inodes_count() - self.sb.first_ino() + 1);
+
+        // the root directory, whose inode is reserved
+        let mut root = Inode::new(self.sb.inode_size(), S_IFDIR | 0o755);
+        root.set_links_count(2);
+        let blk = self.bmap(ROOT_INO, &mut root, 0, true)?;
+        let mut buf = vec![0; self.block_size];
+        let dot_len = DirEntryHeader::min_rec_len(1);
+        DirEntryHeader::write(&mut buf, 0, ROOT_INO, dot_len, b".", FT_DIR);
+        DirEntryHeader::write(
+            &mut buf,
+            dot_len,
+            ROOT_INO,
+            self.block_size - dot_len,
+            b"..",
+            FT_DIR,
+        );
+        self.write_block(blk, &buf)?;
+        root.set_size(self.block_size as u64);
+        self.write_inode(ROOT_INO, &root)?;
+        self.groups[0].set_used_dirs_count(1);
+        self.create_node(ROOT_INO, "lost+found", VfsNodeType::Dir, 0o700)?;
+
+        // write the superblock and group descriptors, with backups in all groups
+        for group in 0..group_count {
+            let start = self.sb.first_data_block() + group * bpg;
+            self.sb.set_block_group_nr(group as u16);
+            let sb_pos = if group == 0 {
+                SUPERBLOCK_OFFSET
+            } else {
+                self.block_pos(start)
+            };
+            let raw = self.sb.raw;
+            self.write_bytes(sb_pos, &raw)?;
+            for (i, desc) in self
+                .groups
+                .iter()
+                .map(|d| d.raw)
+                .enumerate()
+                .collect::<Vec<_>>()
+            {
+                self.write_bytes(
+                    self.block_pos(start + 1) + (i * GROUP_DESC_SIZE) as u64,
+                    &desc,
+                )?;
+            }
+        }
+        self.sb.set_block_group_nr(0);
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::sync::Arc;
+use axerrno::ax_err;
+use axfs_vfs::VfsResult;
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axsync::{Mutex, MutexGuard};
+
+use super::layout::{Inode, ROOT_INO};
+use super::Ext2;
+
+const FILE_PERM: u16 = 0o644;
+const DIR_PERM: u16 = 0o755;
+
+/// A file, directory or symbolic link in the ext2 filesystem.
+///
+/// Only the inode number is kept, the inode is read from the disk on each
+/// operation.
+pub struct Ext2Node {
+    fs: Arc<Mutex<Ext2>>,
+    ino: u32,
+}
+
+impl Ext2Node {
+    pub(super) const fn new(fs: Arc<Mutex<Ext2>>, ino: u32) -> Self {
+        Self { fs, ino }
+    }
+
+    fn new_ref(&self, ino: u32) -> VfsNodeRef {
+        Arc::new(Self::new(self.fs.clone(), ino))
+    }
+
+    fn inode(&self, fs: &mut Ext2) -> VfsResult<Inode> {
+        fs.read_inode(self.ino)
+    }
+
+    /// Locks the filesystem, checks that this node is a directory.
+    fn lock_dir(&self) -> VfsResult<MutexGuard<'_, Ext2>> {
+        let mut fs = self.fs.lock();
+        if !self.inode(&mut fs)?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        Ok(fs)
+    }
+
+    /// Locks the filesystem, checks that this node is not a directory.
+    fn lock_file(&self) -> VfsResult<(MutexGuard<'_, Ext2>, Inode)> {
+        let mut fs = self.fs.lock();
+        let inode = self.inode(&mut fs)?;
+        if inode.is_dir() {
+            return ax_err!(IsADirectory);
+        }
+        Ok((fs, inode))
+    }
+}
+
+impl VfsNodeOps for Ext2Node {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let inode = self.inode(&mut self.fs.lock())?;
+        let perm = VfsNodePerm::from_bits_truncate(inode.mode() & 0o777);
+        Ok(VfsNodeAttr::new(
+            perm,
+            inode.node_type(),
+            inode.size(),
+            inode.blocks() as u64,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let (mut fs, mut inode) = self.lock_file()?;
+        if inode.is_symlink() {
+            return ax_err!(InvalidInput);
+        }
+        fs.read_data(self.ino, &mut inode, offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let (mut fs, mut inode) = self.lock_file()?;
+        if inode.is_symlink() {
+            return ax_err!(InvalidInput);
+        }
+        fs.write_data(self.ino, &mut inode, offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        let (mut fs, _) = self.lock_file()?;
+        fs.sync()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let (mut fs, inode) = self.lock_file()?;
+        if inode.is_symlink() {
+            return ax_err!(InvalidInput);
+        }
+        fs.truncate(self.ino, size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.ino == ROOT_INO {
+            return None;
+        }
+        let parent = self.lock_dir().ok()?.find_entry(self.ino, "..").ok()??;
+        Some(self.new_ref(parent))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at ext2: {}", path);
+        let ino = self.lock_dir()?.lookup_path(self.ino, path)?;
+        Ok(self.new_ref(ino))
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ext2: {}", ty, path);
+        let mut fs = self.lock_dir()?;
+        fs.check_writable()?;
+        let (parent, name) = fs.lookup_parent(self.ino, path)?;
+        if name.is_empty() || name == "." || name == ".." {
+            return ax_err!(InvalidInput);
+        }
+        if fs.find_entry(parent, name)?.is_some() {
+            return Ok(());
+        }
+        let perm = match ty {
+            VfsNodeType::Dir => DIR_PERM,
+            _ => FILE_PERM,
+        };
+        fs.create_node(parent, name, ty, perm).map(|_| ())
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ext2: {}", path);
+        let mut fs = self.lock_dir()?;
+        fs.check_writable()?;
+        let (parent, name) = fs.lookup_parent(self.ino, path)?;
+        if name.is_empty() || name == "." || name == ".." {
+            return ax_err!(InvalidInput);
+        }
+        fs.unlink(parent, name)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let mut fs = self.lock_dir()?;
+        let entries = fs.list_entries(self.ino)?;
+        let mut count = 0;
+        for ((name, ino), ent) in entries.iter().skip(start_idx).zip(dirents.iter_mut()) {
+            let ty = fs.read_inode(*ino)?.node_type();
+            *ent = VfsDirEntry::new(name, ty);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!("rename at ext2: {} -> {}", src_path, dst_path);
+        let mut fs = self.lock_dir()?;
+        fs.check_writable()?;
+        fs.rename(self.ino, src_path, dst_path)
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        debug!("symlink at ext2: {} -> {}", path, target);
+        let mut fs = self.lock_dir()?;
+        fs.check_writable()?;
+        let (parent, name) = fs.lookup_parent(self.ino, path)?;
+        if name.is_empty() || name == "." || name == ".." {
+            return ax_err!(InvalidInput);
+        }
+        if fs.find_entry(parent, name)?.is_some() {
+            return ax_err!(AlreadyExists);
+        }
+        fs.create_symlink(parent, name, target)
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        self.fs.lock().read_link(self.ino, buf)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
@@ -6,6 +6,7 @@ use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
 use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
 
+use crate::cache::BlockCache;
 use crate::dev::Disk;
 
 const BLOCK_SIZE: usize = 512;
@@ -13,6 +14,7 @@ const BLOCK_SIZE: usize = 512;
 pub struct FatFileSystem {
     inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
+    cache: Arc<Mutex<BlockCache>>,
 }
 
 pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
@@ -30,21 +32,25 @@ impl FatFileSystem {
     pub fn new(mut disk: Disk) -> Self {
         let opts = fatfs::FormatVolumeOptions::new();
         fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
+        let cache = disk.cache();
         let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
             .expect("failed to initialize FAT filesystem");
         Self {
             inner,
             root_dir: UnsafeCell::new(None),
+            cache,
         }
     }
 
     #[cfg(not(feature = "use-ramdisk"))]
     pub fn new(disk: Disk) -> Self {
+        let cache = disk.cache();
         let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
             .expect("failed to initialize FAT filesystem");
         Self {
             inner,
             root_dir: UnsafeCell::new(None),
+            cache,
         }
     }
 
@@ -85,6 +91,11 @@ impl VfsNodeOps for FileWrapper<'static> {
         file.write(buf).map_err(as_vfs_err)
     }
 
+    fn fsync(&self) -> VfsResult {
+        // also flushes the disk
+        self.0.lock().flush().map_err(as_vfs_err)
+    }
+
     fn truncate(&self, size: u64) -> VfsResult {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
@@ -192,6 +203,19 @@ impl VfsNodeOps for DirWrapper<'static> {
             src_path, dst_path
         );
 
+        let src_path = src_path.trim_matches('/');
+        let dst_path = dst_path.trim_matches('/');
+        if src_path.eq_ignore_ascii_case(dst_path) {
+            return Ok(());
+        }
+        // replace the destination file, as `rename` in POSIX
+        if self.0.open_file(dst_path).is_ok() {
+            if self.0.open_dir(src_path).is_ok() {
+                return Err(VfsError::NotADirectory);
+            }
+            self.0.open_file(src_path).map_err(as_vfs_err)?;
+            self.0.remove(dst_path).map_err(as_vfs_err)?;
+        }
         self.0
             .rename(src_path, &self.0, dst_path)
             .map_err(as_vfs_err)
@@ -199,6 +223,18 @@ impl VfsNodeOps for DirWrapper<'static> {
 }
 
 impl VfsOps for FatFileSystem {
+    fn umount(&self) -> VfsResult {
+        self.sync()
+    }
+
+    fn sync(&self) -> VfsResult {
+        self.cache.lock().flush().map_err(|_| VfsError::Io)
+    }
+
+    fn fs_type(&self) -> &str {
+        "vfat"
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         let root_dir = unsafe { (*self.root_dir.get()).as_ref().unwrap() };
         root_dir.clone()
@@ -243,7 +279,7 @@ impl Write for Disk {
         Ok(write_len)
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        Disk::flush(self).map_err(|_| ())
     }
 }
 
@@ -6,10 +6,16 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!    is **enabled** by default.
+//! - `ext2`: Use [ext2] as the main filesystem and mount it on `/`. It
+//!    overrides `fatfs` if both are enabled.
 //! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
 //!    **enabled** by default.
 //! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
 //!    **enabled** by default.
+//! - `procfs`: Mount [`axfs_procfs::ProcFileSystem`] on `/proc`. This feature
+//!    is **enabled** by default.
+//! - `sysfs`: Mount a [`axfs_ramfs::RamFileSystem`] with a few system files on
+//!    `/sys`. This feature is **enabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
@@ -17,6 +23,7 @@
 //!    both are enabled.
 //!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
+//! [ext2]: https://en.wikipedia.org/wiki/Ext2
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -26,6 +33,7 @@
 extern crate log;
 extern crate alloc;
 
+mod cache;
 mod dev;
 mod fs;
 mod mounts;
@@ -35,6 +43,12 @@ pub mod api;
 pub mod fops;
 
 use axdriver::{prelude::*, AxDeviceContainer};
+use axerrno::{AxError, AxResult};
+
+pub use self::cache::BlockCacheStats;
+
+#[cfg(feature = "procfs")]
+pub use self::fs::procfs;
 
 /// Initializes filesystems by block devices.
 pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
@@ -44,3 +58,41 @@ pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     info!("  use block device 0: {:?}", dev.device_name());
     self::root::init_rootfs(self::dev::Disk::new(dev));
 }
+
+/// Writes all cached data of the mounted filesystems to their block devices.
+///
+/// Disk blocks are cached and written back lazily, this should be called
+/// before the system shuts down. All filesystems are synced even if some of
+/// them fail, and the first error is returned.
+pub fn sync_filesystems() -> AxResult {
+    let mut result = Ok(());
+    for (path, fs) in self::root::mount_points() {
+        if let Err(e) = fs.sync() {
+            warn!("failed to sync the filesystem at {}: {:?}", path, e);
+            result = result.and(Err(e));
+        }
+    }
+    // the root filesystem may not implement `VfsOps::sync`
+    if let Some(cache) = self::root::block_cache() {
+        if cache.lock().flush().is_err() {
+            result = result.and(Err(AxError::Io));
+        }
+    }
+    result
+}
+
+/// Returns the root directory of the procfs mounted on `/proc`, other modules
+/// can add their entries to it.
+///
+/// Returns `None` if the filesystems are not initialized.
+#[cfg(feature = "procfs")]
+pub fn procfs_root() -> Option<alloc::sync::Arc<procfs::ProcDir>> {
+    self::root::procfs().map(|fs| fs.root_dir_node())
+}
+
+/// Returns the statistics of the block cache of the root filesystem.
+///
+/// Returns `None` if the filesystems are not initialized.
+pub fn block_cache_stats() -> Option<BlockCacheStats> {
+    self::root::block_cache().map(|cache| cache.lock().stats())
+}
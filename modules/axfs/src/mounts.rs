@@ -1,5 +1,7 @@
 use alloc::sync::Arc;
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+#[cfg(feature = "procfs")]
+use {alloc::string::String, core::fmt::Write};
 
 use crate::fs;
 
@@ -21,30 +23,33 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
     Arc::new(fs::ramfs::RamFileSystem::new())
 }
 
+/// Creates the procfs with the entries known by the filesystem module.
+///
+/// Entries about other kernel states (tasks, memory, etc.) are added by their
+/// owners through [`crate::procfs_root`].
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let procfs = fs::ramfs::RamFileSystem::new();
-    let proc_root = procfs.root_dir();
-
-    // Create /proc/sys/net/core/somaxconn
-    proc_root.create("sys", VfsNodeType::Dir)?;
-    proc_root.create("sys/net", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core/somaxconn", VfsNodeType::File)?;
-    let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
-    file_somaxconn.write_at(0, b"4096\n")?;
+pub(crate) fn procfs() -> Arc<fs::procfs::ProcFileSystem> {
+    let procfs = fs::procfs::ProcFileSystem::new();
 
-    // Create /proc/sys/vm/overcommit_memory
-    proc_root.create("sys/vm", VfsNodeType::Dir)?;
-    proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
+    // Create /proc/sys/net/core/somaxconn and /proc/sys/vm/overcommit_memory
+    let sys = procfs.mkdir("sys");
+    sys.mkdir("net")
+        .mkdir("core")
+        .add_file("somaxconn", || "4096\n".into());
+    sys.mkdir("vm")
+        .add_file("overcommit_memory", || "0\n".into());
 
-    // Create /proc/self/stat
-    proc_root.create("self", VfsNodeType::Dir)?;
-    proc_root.create("self/stat", VfsNodeType::File)?;
+    // Create /proc/mounts
+    procfs.add_file("mounts", || {
+        let mut mounts = String::new();
+        for (path, fs) in crate::root::mount_points() {
+            let fs_type = fs.fs_type();
+            let _ = writeln!(mounts, "{} {} {} rw 0 0", fs_type, path, fs_type);
+        }
+        mounts
+    });
 
-    Ok(Arc::new(procfs))
+    Arc::new(procfs)
 }
 
 #[cfg(feature = "sysfs")]
@@ -1,6 +1,4 @@
 //! Root directory of the filesystem
-//!
-//! TODO: it doesn't work very well if the mount points have containment relationships.
 
 use alloc::{string::String, sync::Arc, vec::Vec};
 use axerrno::{ax_err, AxError, AxResult};
@@ -8,25 +6,28 @@ use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResu
 use axsync::Mutex;
 use lazy_init::LazyInit;
 
-use crate::{api::FileType, fs, mounts};
+use crate::{api::FileType, cache::BlockCache, fs, mounts};
 
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
 static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
 
 struct MountPoint {
-    path: &'static str,
+    path: String,
     fs: Arc<dyn VfsOps>,
 }
 
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    mounts: Mutex<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
+static BLOCK_CACHE: LazyInit<Arc<Mutex<BlockCache>>> = LazyInit::new();
+#[cfg(feature = "procfs")]
+static PROC_FS: LazyInit<Arc<fs::procfs::ProcFileSystem>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
+    pub fn new(path: String, fs: Arc<dyn VfsOps>) -> Self {
         Self { path, fs }
     }
 }
@@ -37,37 +38,92 @@ impl Drop for MountPoint {
     }
 }
 
+/// Returns the length of the matched prefix if `path` is the same as the
+/// mount point `mp_path` or is inside it, where both paths have no leading
+/// and trailing slashes.
+fn match_mount_point(mp_path: &str, path: &str) -> Option<usize> {
+    let rest = path.strip_prefix(mp_path)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        Some(mp_path.len())
+    } else {
+        None
+    }
+}
+
 impl RootDirectory {
     pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    /// Mounts `fs` at the absolute and canonical path `path`.
+    ///
+    /// The mount point can be inside another mounted filesystem, it is
+    /// created if it does not exist.
+    pub fn mount(&self, path: &str, fs: Arc<dyn VfsOps>) -> AxResult {
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
         if !path.starts_with('/') {
             return ax_err!(InvalidInput, "mount path must start with '/'");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        let path = path.trim_end_matches('/');
+        if self.contains(path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        // create the mount point in the filesystem it belongs to if it does
+        // not exist
+        let mount_point = self.lookup_mounted_fs(path, |parent_fs, rest_path| {
+            let parent_root = parent_fs.root_dir();
+            match parent_root.clone().lookup(rest_path) {
+                Ok(node) if !node.get_attr()?.is_dir() => ax_err!(NotADirectory),
+                Ok(node) => Ok(node),
+                Err(AxError::NotFound) => {
+                    parent_root.create(rest_path, FileType::Dir)?;
+                    parent_root.lookup(rest_path)
+                }
+                Err(e) => Err(e),
+            }
+        })?;
+
+        let mut mounts = self.mounts.lock();
+        if mounts.iter().any(|mp| mp.path == path) {
+            // someone else mounted here at the same time
+            return ax_err!(InvalidInput, "mount point already exists");
+        }
+        fs.mount(path, mount_point)?;
+        mounts.push(MountPoint::new(path.into(), fs));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    /// Unmounts the filesystem mounted at the absolute and canonical path
+    /// `path`.
+    pub fn umount(&self, path: &str) -> AxResult {
+        let path = path.trim_end_matches('/');
+        if path.is_empty() {
+            return ax_err!(InvalidInput, "cannot unmount root filesystem");
+        }
+        let mut mounts = self.mounts.lock();
+        let Some(idx) = mounts.iter().position(|mp| mp.path == path) else {
+            return ax_err!(InvalidInput, "not a mount point");
+        };
+        let is_busy = mounts
+            .iter()
+            .any(|mp| mp.path != path && match_mount_point(path, &mp.path).is_some());
+        if is_busy {
+            return ax_err!(ResourceBusy, "other filesystems are mounted inside");
+        }
+        let mp = mounts.remove(idx);
+        drop(mounts);
+        drop(mp); // calls `VfsOps::umount` without holding the lock
+        Ok(())
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        let path = path.trim_end_matches('/');
+        self.mounts.lock().iter().any(|mp| mp.path == path)
     }
 
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
@@ -80,23 +136,21 @@ impl RootDirectory {
             return self.lookup_mounted_fs(rest, f);
         }
 
-        let mut idx = 0;
-        let mut max_len = 0;
-
         // Find the filesystem that has the longest mounted path match
         // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
-                max_len = mp.path.len() - 1;
-                idx = i;
-            }
-        }
-
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
-        } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
+        let matched = self
+            .mounts
+            .lock()
+            .iter()
+            .filter_map(|mp| {
+                // skip the first '/'
+                match_mount_point(&mp.path[1..], path).map(|len| (len, mp.fs.clone()))
+            })
+            .max_by_key(|(len, _)| *len);
+
+        match matched {
+            Some((len, fs)) => f(fs, &path[len..]), // matched the mount point
+            None => f(self.main_fs.clone(), path),  // not matched any mount point
         }
     }
 }
@@ -133,20 +187,37 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
+        self.lookup_mounted_fs(src_path, |src_fs, src_rest| {
+            self.lookup_mounted_fs(dst_path, |dst_fs, dst_rest| {
+                if src_rest.is_empty() || dst_rest.is_empty() {
+                    ax_err!(PermissionDenied) // cannot rename mount points
+                } else if !Arc::ptr_eq(&src_fs, &dst_fs) {
+                    ax_err!(CrossesDevices)
+                } else {
+                    src_fs.root_dir().rename(src_rest, dst_rest)
+                }
+            })
+        })
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        self.lookup_mounted_fs(path, |fs, rest_path| {
             if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot rename mount points
+                ax_err!(AlreadyExists) // mount points already exist
             } else {
-                fs.root_dir().rename(rest_path, dst_path)
+                fs.root_dir().symlink(rest_path, target)
             }
         })
     }
 }
 
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
+    BLOCK_CACHE.init_by(disk.cache());
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
             let main_fs = fs::myfs::new_myfs(disk);
+        } else if #[cfg(feature = "ext2")] {
+            let main_fs = Arc::new(fs::ext2::Ext2FileSystem::new(disk));
         } else if #[cfg(feature = "fatfs")] {
             static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
             // init_by 初始化一个数据结构，确保它只被初始化一次
@@ -157,7 +228,7 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
         }
     }
 
-    let mut root_dir = RootDirectory::new(main_fs);
+    let root_dir = RootDirectory::new(main_fs);
 
     // 挂载：指的就是将设备文件中的顶级目录连接到Linux根目录下的某一目录，访问此目录就等同于访问设备文件。
     #[cfg(feature = "devfs")]
@@ -170,11 +241,13 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
         .mount("/tmp", mounts::ramfs())
         .expect("failed to mount ramfs at /tmp");
 
-    // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
-    root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
-        .expect("fail to mount procfs at /proc");
+    {
+        PROC_FS.init_by(mounts::procfs());
+        root_dir // should not fail
+            .mount("/proc", PROC_FS.clone())
+            .expect("fail to mount procfs at /proc");
+    }
 
     // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
@@ -187,6 +260,10 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     *CURRENT_DIR_PATH.lock() = "/".into();
 }
 
+/// The maximum number of symbolic links that can be followed while resolving
+/// a single path, the same as `MAXSYMLINKS` in Linux.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
 fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     if path.starts_with('/') {
         ROOT_DIR.clone()
@@ -195,6 +272,139 @@ fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     }
 }
 
+/// Reads the target path of the symbolic link `node`.
+fn read_link_node(node: &VfsNodeRef) -> AxResult<String> {
+    let size = node.get_attr()?.size() as usize;
+    let mut buf = alloc::vec![0; size];
+    let len = node.readlink(&mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Resolves all symbolic links in `path`.
+///
+/// Returns the directory node where the lookup should start from, and the
+/// resolved path relative to it, which contains no symbolic links (except the
+/// last component if `follow_last` is `false`).
+///
+/// The last component is allowed to be nonexistent, so that the result can be
+/// used to create new nodes. Returns [`AxError::FilesystemLoop`] if more than
+/// [`MAX_SYMLINK_DEPTH`] links are encountered.
+fn resolve_path(
+    dir: Option<&VfsNodeRef>,
+    path: &str,
+    follow_last: bool,
+) -> AxResult<(VfsNodeRef, String)> {
+    let mut base = parent_node_of(dir, path);
+    let mut resolved: Vec<String> = Vec::new();
+    let mut is_absolute = path.starts_with('/');
+    // a trailing slash requires the last component to be a directory, so the
+    // link must be followed.
+    let follow_last = follow_last || path.ends_with('/');
+
+    // components that remain to be resolved, in reverse order
+    let mut pending: Vec<String> = path.rsplit('/').map(String::from).collect();
+    let mut links = 0;
+
+    let join = |is_absolute: bool, resolved: &[String]| {
+        let mut p = if is_absolute {
+            String::from("/")
+        } else {
+            String::new()
+        };
+        p += &resolved.join("/");
+        p
+    };
+
+    while let Some(comp) = pending.pop() {
+        match comp.as_str() {
+            "" | "." => continue,
+            ".." => {
+                if resolved.last().map_or(true, |c| c == "..") {
+                    // `/..` is the same as `/`
+                    if !is_absolute {
+                        resolved.push(comp);
+                    }
+                } else {
+                    resolved.pop();
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        resolved.push(comp);
+        let is_last = pending.iter().all(|c| c.is_empty() || c == ".");
+        if is_last && !follow_last {
+            break;
+        }
+
+        let node = match base.clone().lookup(&join(is_absolute, &resolved)) {
+            Ok(node) => node,
+            Err(AxError::NotFound) if is_last => break,
+            Err(e) => return Err(e),
+        };
+        if !node.get_attr()?.is_symlink() {
+            continue;
+        }
+
+        links += 1;
+        if links > MAX_SYMLINK_DEPTH {
+            return ax_err!(FilesystemLoop);
+        }
+        let target = read_link_node(&node)?;
+        debug!(
+            "follow symlink {:?} -> {:?}",
+            resolved.last().unwrap(),
+            target
+        );
+        resolved.pop();
+        if target.starts_with('/') {
+            base = ROOT_DIR.clone();
+            resolved.clear();
+            is_absolute = true;
+        }
+        pending.extend(target.rsplit('/').map(String::from));
+    }
+    Ok((base, join(is_absolute, &resolved)))
+}
+
+pub(crate) fn block_cache() -> Option<&'static Arc<Mutex<BlockCache>>> {
+    BLOCK_CACHE.try_get()
+}
+
+#[cfg(feature = "procfs")]
+pub(crate) fn procfs() -> Option<&'static Arc<fs::procfs::ProcFileSystem>> {
+    PROC_FS.try_get()
+}
+
+/// Returns the paths and filesystems of all mount points, starting with the
+/// root filesystem.
+pub(crate) fn mount_points() -> Vec<(String, Arc<dyn VfsOps>)> {
+    let Some(root_dir) = ROOT_DIR.try_get() else {
+        return Vec::new();
+    };
+    let mut mount_points = alloc::vec![(String::from("/"), root_dir.main_fs.clone())];
+    for mp in root_dir.mounts.lock().iter() {
+        mount_points.push((mp.path.clone(), mp.fs.clone()));
+    }
+    mount_points
+}
+
+pub(crate) fn mount(path: &str, fs: Arc<dyn VfsOps>) -> AxResult {
+    ROOT_DIR.mount(&absolute_path(path)?, fs)
+}
+
+pub(crate) fn umount(path: &str) -> AxResult {
+    let path = absolute_path(path)?;
+    let path = path.trim_end_matches('/');
+    let cwd = CURRENT_DIR_PATH.lock().clone();
+    if !path.is_empty() && match_mount_point(path, cwd.trim_end_matches('/')).is_some() {
+        return ax_err!(ResourceBusy, "current directory is inside the mount point");
+    }
+    ROOT_DIR.umount(path)
+}
+
 pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     if path.starts_with('/') {
         Ok(axfs_vfs::path::canonicalize(path))
@@ -208,7 +418,23 @@ pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRe
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    let (base, resolved) = resolve_path(dir, path, true)?;
+    let node = base.lookup(&resolved)?;
+    if path.ends_with('/') && !node.get_attr()?.is_dir() {
+        ax_err!(NotADirectory)
+    } else {
+        Ok(node)
+    }
+}
+
+/// Like [`lookup`], but does not follow the symbolic link if the last
+/// component of `path` is a link.
+pub(crate) fn lookup_no_follow(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let (base, resolved) = resolve_path(dir, path, false)?;
+    let node = base.lookup(&resolved)?;
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -222,28 +448,57 @@ pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsN
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
-    let parent = parent_node_of(dir, path);
-    parent.create(path, VfsNodeType::File)?;
-    parent.lookup(path)
+    // create the target if `path` is a dangling symbolic link
+    let (parent, resolved) = resolve_path(dir, path, true)?;
+    parent.create(&resolved, VfsNodeType::File)?;
+    parent.lookup(&resolved)
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
+    match lookup_no_follow(dir, path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            let (parent, resolved) = resolve_path(dir, path, false)?;
+            parent.create(&resolved, VfsNodeType::Dir)
+        }
         Err(e) => Err(e),
     }
 }
 
+pub(crate) fn create_symlink(dir: Option<&VfsNodeRef>, target: &str, path: &str) -> AxResult {
+    if target.is_empty() || path.is_empty() {
+        return ax_err!(NotFound);
+    } else if path.ends_with('/') {
+        return ax_err!(NotADirectory);
+    }
+    match lookup_no_follow(dir, path) {
+        Ok(_) => ax_err!(AlreadyExists),
+        Err(AxError::NotFound) => {
+            let (parent, resolved) = resolve_path(dir, path, false)?;
+            parent.symlink(&resolved, target)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn read_link(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<String> {
+    let node = lookup_no_follow(dir, path)?;
+    if !node.get_attr()?.is_symlink() {
+        return ax_err!(InvalidInput);
+    }
+    read_link_node(&node)
+}
+
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
+    let node = lookup_no_follow(dir, path)?;
     let attr = node.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        let (parent, resolved) = resolve_path(dir, path, false)?;
+        parent.remove(&resolved)
     }
 }
 
@@ -265,14 +520,15 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
         return ax_err!(PermissionDenied);
     }
 
-    let node = lookup(dir, path)?;
+    let node = lookup_no_follow(dir, path.trim_end_matches('/'))?;
     let attr = node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        let (parent, resolved) = resolve_path(dir, path.trim_end_matches('/'), false)?;
+        parent.remove(&resolved)
     }
 }
 
@@ -304,10 +560,21 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
     }
 }
 
-pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
-        warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+/// Resolves the symbolic links in `path` except the last component, and
+/// returns the absolute path.
+fn resolve_absolute_path(path: &str) -> AxResult<String> {
+    if path.is_empty() {
+        return ax_err!(NotFound);
     }
-    parent_node_of(None, old).rename(old, new)
+    let (_, resolved) = resolve_path(None, path, false)?;
+    absolute_path(&resolved)
+}
+
+pub(crate) fn rename(old: &str, new: &str) -> AxResult {
+    // both paths are resolved against the root, so they are renamed in the
+    // same filesystem, or fail with `CrossesDevices`. The destination is
+    // replaced by the filesystem.
+    let old = resolve_absolute_path(old)?;
+    let new = resolve_absolute_path(new)?;
+    ROOT_DIR.rename(&old, &new)
 }
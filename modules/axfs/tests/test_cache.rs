@@ -0,0 +1,75 @@
+#![cfg(feature = "myfs")] // `Disk` is public only with `myfs`
+
+use axfs::fops::Disk;
+use driver_block::ramdisk::RamDisk;
+
+const BLOCK_SIZE: usize = 512;
+
+fn block(byte: u8) -> [u8; BLOCK_SIZE] {
+    [byte; BLOCK_SIZE]
+}
+
+#[test]
+fn test_block_cache() {
+    println!("Testing block cache ...");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+
+    let mut disk = Disk::with_cache_capacity(RamDisk::new(16 * BLOCK_SIZE), 4);
+    let mut buf = [0; BLOCK_SIZE];
+
+    // write blocks 0..4, all are cached and dirty
+    for i in 0..4 {
+        disk.set_position(i * BLOCK_SIZE as u64);
+        assert_eq!(disk.write_one(&block(i as u8 + 1)).unwrap(), BLOCK_SIZE);
+    }
+    let stats = disk.cache_stats();
+    assert_eq!((stats.cached, stats.dirty, stats.writebacks), (4, 4, 0));
+
+    // read them back from the cache
+    for i in 0..4 {
+        disk.set_position(i * BLOCK_SIZE as u64);
+        assert_eq!(disk.read_one(&mut buf).unwrap(), BLOCK_SIZE);
+        assert_eq!(buf, block(i as u8 + 1));
+    }
+    let stats = disk.cache_stats();
+    assert_eq!((stats.hits, stats.misses), (4, 4));
+
+    // touch block 0, then block 1 is the least recently used
+    disk.set_position(0);
+    disk.read_one(&mut buf).unwrap();
+    disk.set_position(4 * BLOCK_SIZE as u64);
+    disk.read_one(&mut buf).unwrap();
+    let stats = disk.cache_stats();
+    assert_eq!((stats.cached, stats.dirty, stats.writebacks), (4, 3, 1));
+
+    // the evicted block is written back and reloaded
+    disk.set_position(BLOCK_SIZE as u64);
+    disk.read_one(&mut buf).unwrap();
+    assert_eq!(buf, block(2));
+    assert_eq!(disk.cache_stats().misses, 6);
+
+    // partial writes in a block
+    disk.set_position(8 * BLOCK_SIZE as u64 + 100);
+    assert_eq!(disk.write_one(&[0xaa; 1000]).unwrap(), BLOCK_SIZE - 100);
+    disk.set_position(8 * BLOCK_SIZE as u64);
+    disk.read_one(&mut buf).unwrap();
+    assert_eq!(buf[..100], [0; 100]);
+    assert_eq!(buf[100..], [0xaa; BLOCK_SIZE - 100]);
+
+    // flush all dirty blocks
+    assert!(disk.flush().is_ok());
+    let stats = disk.cache_stats();
+    assert_eq!(stats.dirty, 0);
+    assert!(stats.cached <= stats.capacity);
+    for i in 0..4 {
+        disk.set_position(i * BLOCK_SIZE as u64);
+        disk.read_one(&mut buf).unwrap();
+        assert_eq!(buf, block(i as u8 + 1));
+    }
+
+    // out of range
+    disk.set_position(16 * BLOCK_SIZE as u64);
+    assert!(disk.read_one(&mut buf).is_err());
+
+    println!("test_block_cache() OK!");
+}
@@ -1,4 +1,9 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use axfs::api as fs;
+use axfs_ramfs::RamFileSystem;
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
 use axio as io;
 
 use fs::{File, FileType, OpenOptions};
@@ -252,6 +257,181 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+fn test_symlink() -> Result<()> {
+    println!("test symlink in /tmp:");
+
+    fs::create_dir("/tmp/dir")?;
+    fs::write("/tmp/dir/test.txt", "symlink test\n")?;
+    assert_eq!(fs::symlink("dir/test.txt", "/tmp/l1"), Ok(()));
+    assert_eq!(fs::symlink("/tmp/dir", "/tmp/l2"), Ok(()));
+    assert_eq!(fs::symlink("l2/../l1", "/tmp/l3"), Ok(()));
+    assert_eq!(fs::symlink("not-exist.txt", "/tmp/l4"), Ok(()));
+    assert_eq!(fs::symlink("l6", "/tmp/l5"), Ok(()));
+    assert_eq!(fs::symlink("l5", "/tmp/l6"), Ok(()));
+    assert_err!(fs::symlink("dir", "/tmp/l1"), AlreadyExists);
+
+    // follow links
+    assert_eq!(fs::read_to_string("/tmp/l1")?, "symlink test\n");
+    assert_eq!(fs::read_to_string("tmp/l2/test.txt")?, "symlink test\n");
+    assert_eq!(
+        fs::read_to_string("/tmp/l2/../dir/test.txt")?,
+        "symlink test\n"
+    );
+    assert_eq!(fs::read_to_string("/tmp/l3")?, "symlink test\n");
+    assert!(fs::metadata("/tmp/l2/")?.is_dir());
+    assert_eq!(fs::read_dir("/tmp/l2")?.count(), 1);
+    assert_err!(fs::metadata("/tmp/l4"), NotFound);
+    assert_err!(fs::metadata("/tmp/l5"), FilesystemLoop);
+    assert_err!(fs::metadata("/tmp/l1/"), NotADirectory);
+
+    // do not follow links
+    assert_eq!(fs::read_link("/tmp/l1")?, "dir/test.txt");
+    assert_eq!(fs::read_link("tmp/l3")?, "l2/../l1");
+    assert_err!(fs::read_link("/tmp/dir"), InvalidInput);
+    let md = fs::symlink_metadata("/tmp/l2")?;
+    assert_eq!(md.file_type(), FileType::SymLink);
+    assert!(md.is_symlink());
+    assert!(!md.is_dir());
+    assert!(fs::symlink_metadata("/tmp/l5")?.is_symlink());
+
+    // create through a dangling link
+    fs::write("/tmp/l4", "created\n")?;
+    assert_eq!(fs::read_to_string("/tmp/not-exist.txt")?, "created\n");
+
+    // remove the links themselves
+    for name in ["l1", "l2", "l3", "l4", "l5", "l6"] {
+        assert_eq!(fs::remove_file(&format!("/tmp/{}", name)), Ok(()));
+    }
+    assert_eq!(fs::read_to_string("/tmp/dir/test.txt")?, "symlink test\n");
+    assert_eq!(fs::remove_file("/tmp/dir/test.txt"), Ok(()));
+    assert_eq!(fs::remove_file("/tmp/not-exist.txt"), Ok(()));
+    assert_eq!(fs::remove_dir("/tmp/dir"), Ok(()));
+    assert_eq!(fs::read_dir("tmp").unwrap().count(), 0);
+
+    println!("test_symlink() OK!");
+    Ok(())
+}
+
+/// A RAM filesystem that counts how many times it is synced.
+#[derive(Default)]
+struct SyncCounter(RamFileSystem, AtomicUsize);
+
+impl VfsOps for SyncCounter {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.0.mount(path, mount_point)
+    }
+
+    fn sync(&self) -> VfsResult {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.0.root_dir()
+    }
+}
+
+fn test_mount() -> Result<()> {
+    println!("test mount and umount:");
+
+    // mount inside a mounted filesystem
+    fs::mount("/tmp/mnt", Arc::new(RamFileSystem::new()))?;
+    fs::write("/tmp/mnt/f1", "ramfs 1\n")?;
+    assert_err!(
+        fs::mount("/tmp/mnt/", Arc::new(RamFileSystem::new())),
+        InvalidInput
+    );
+    assert_err!(
+        fs::mount("/tmp/mnt/f1", Arc::new(RamFileSystem::new())),
+        NotADirectory
+    );
+
+    // nested mount points, and paths sharing a prefix with a mount point
+    fs::mount("/tmp/mnt/data", Arc::new(RamFileSystem::new()))?;
+    fs::create_dir("/tmp/mnt2")?;
+    fs::write("/tmp/mnt2/f2", "tmpfs\n")?;
+    fs::write("/tmp/mnt/data/f3", "ramfs 2\n")?;
+    assert_eq!(fs::read_to_string("/tmp/mnt/f1")?, "ramfs 1\n");
+    assert_eq!(fs::read_to_string("/tmp/mnt2/f2")?, "tmpfs\n");
+    assert_eq!(
+        fs::read_to_string("/tmp/mnt/data/../data/./f3")?,
+        "ramfs 2\n"
+    );
+    assert_err!(fs::metadata("/tmp/mnt/f3"), NotFound);
+    assert_err!(fs::remove_dir("/tmp/mnt/data"), PermissionDenied);
+
+    // umount
+    assert_err!(fs::umount("/tmp/mnt2"), InvalidInput);
+    assert_err!(fs::umount("/tmp/mnt"), ResourceBusy);
+    fs::set_current_dir("/tmp/mnt/data")?;
+    assert_err!(fs::umount("/tmp/mnt/data"), ResourceBusy);
+    fs::set_current_dir("/")?;
+    fs::umount("/tmp/mnt/data/")?;
+    assert_err!(fs::metadata("/tmp/mnt/data/f3"), NotFound);
+    assert!(fs::metadata("/tmp/mnt/data")?.is_dir());
+    fs::umount("/tmp/mnt")?;
+    assert_err!(fs::metadata("/tmp/mnt/f1"), NotFound);
+    assert_err!(fs::umount("/"), InvalidInput);
+
+    // mount again at the same place, all mounted filesystems are synced
+    let counter = Arc::new(SyncCounter::default());
+    fs::mount("/tmp/mnt", counter.clone())?;
+    assert_eq!(fs::read_dir("/tmp/mnt")?.count(), 0);
+    assert_eq!(axfs::sync_filesystems(), Ok(()));
+    assert_eq!(counter.1.load(Ordering::Relaxed), 1);
+    fs::umount("/tmp/mnt")?;
+    assert_eq!(axfs::sync_filesystems(), Ok(()));
+    assert_eq!(counter.1.load(Ordering::Relaxed), 1);
+
+    fs::remove_dir("/tmp/mnt")?;
+    fs::remove_file("/tmp/mnt2/f2")?;
+    fs::remove_dir("/tmp/mnt2")?;
+
+    println!("test_mount() OK!");
+    Ok(())
+}
+
+#[cfg(feature = "procfs")]
+fn test_procfs() -> Result<()> {
+    println!("test procfs:");
+
+    assert_eq!(
+        fs::read_to_string("/proc/sys/net/core/somaxconn")?,
+        "4096\n"
+    );
+    assert_eq!(fs::read_to_string("/proc/sys/vm/overcommit_memory")?, "0\n");
+    assert_err!(
+        fs::write("/proc/sys/vm/overcommit_memory", "1"),
+        PermissionDenied
+    );
+    assert_err!(fs::create_dir("/proc/foo"), PermissionDenied);
+    assert_eq!(fs::metadata("/proc/mounts")?.len(), 0);
+
+    // the content is generated on each read
+    let mounts = fs::read_to_string("/proc/mounts")?;
+    assert!(mounts.contains("ramfs /tmp ramfs rw 0 0\n"));
+    assert!(mounts.contains(" /proc proc "));
+    assert!(!mounts.contains("/tmp/mnt"));
+    fs::mount("/tmp/mnt", Arc::new(RamFileSystem::new()))?;
+    assert!(fs::read_to_string("/proc/mounts")?.contains("ramfs /tmp/mnt ramfs"));
+    fs::umount("/tmp/mnt")?;
+    fs::remove_dir("/tmp/mnt")?;
+    assert_eq!(fs::read_to_string("/proc/mounts")?, mounts);
+
+    // entries added by other modules
+    let root = axfs::procfs_root().unwrap();
+    let counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let c = counter.clone();
+    root.add_file("counter", move || c.load(Ordering::Relaxed).to_string());
+    assert_eq!(fs::read_to_string("/proc/counter")?, "0");
+    counter.store(42, Ordering::Relaxed);
+    assert_eq!(fs::read_to_string("/proc/./counter")?, "42");
+    assert!(fs::read_dir("/proc")?.any(|e| e.unwrap().file_name() == "counter"));
+
+    println!("test_procfs() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
@@ -259,4 +439,8 @@ pub fn test_all() {
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_symlink().expect("test_symlink() failed");
+    test_mount().expect("test_mount() failed");
+    #[cfg(feature = "procfs")]
+    test_procfs().expect("test_procfs() failed");
 }
@@ -0,0 +1,113 @@
+#![cfg(all(feature = "ext2", not(feature = "myfs")))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axfs::api::{self as fs, File, FileType};
+use axio::{Error, Read, Result, Seek, SeekFrom, Write};
+use driver_block::ramdisk::RamDisk;
+
+const IMG_PATH: &str = "resources/ext2.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+fn test_large_file() -> Result<()> {
+    println!("test large file:");
+    let fname = "/large.bin";
+    let data: Vec<u8> = (0..300 * 1024).map(|i| (i % 251) as u8).collect();
+    fs::write(fname, &data)?; // uses double indirect blocks
+    assert_eq!(fs::read(fname)?, data);
+
+    // sparse write far beyond the end
+    let mut file = File::options().read(true).write(true).open(fname)?;
+    file.seek(SeekFrom::Start(1024 * 1024))?;
+    file.write_all(b"end")?;
+    assert_eq!(fs::metadata(fname)?.len(), 1024 * 1024 + 3);
+    let mut buf = [0xff; 16];
+    file.seek(SeekFrom::Start(512 * 1024))?;
+    file.read_exact(&mut buf)?;
+    assert_eq!(buf, [0; 16]);
+
+    // shrink, then extend with zeros
+    file.set_len(1000)?;
+    file.set_len(4000)?;
+    let contents = fs::read(fname)?;
+    assert_eq!(contents[..1000], data[..1000]);
+    assert!(contents[1000..].iter().all(|&b| b == 0));
+    drop(file);
+
+    fs::remove_file(fname)?;
+    println!("test_large_file() OK!");
+    Ok(())
+}
+
+fn test_symlink_rename() -> Result<()> {
+    println!("test symlink and rename:");
+    let long_target = "Program/Linux/../Python/../JavaScript/../Linux/./././linux.txt";
+    assert_eq!(fs::symlink("short.txt", "/s1"), Ok(()));
+    assert_eq!(fs::symlink(long_target, "/s2"), Ok(())); // stored in a data block
+    assert_eq!(fs::read_link("/s1")?, "short.txt");
+    assert_eq!(fs::read_link("/s2")?, long_target);
+    assert_eq!(fs::read_to_string("/s1")?, "Rust is cool!\n");
+    assert_eq!(fs::read_to_string("/s2")?, "Linux is very cool!\n");
+    assert_eq!(fs::symlink_metadata("/s2")?.file_type(), FileType::SymLink);
+
+    // move a directory to another parent
+    fs::create_dir("/Program/Rust/src")?;
+    fs::write("/Program/Rust/src/main.rs", "fn main() {}\n")?;
+    fs::rename("/Program/Rust/src", "/very/src")?;
+    assert_eq!(
+        fs::read_to_string("/very/src/../src/main.rs")?,
+        "fn main() {}\n"
+    );
+    assert_eq!(
+        fs::metadata("/Program/Rust/src").err(),
+        Some(Error::NotFound)
+    );
+    assert!(fs::rename("/very", "/very/src/very").is_err());
+
+    // replace an existing file, and rename across filesystems
+    fs::write("/old.txt", "old\n")?;
+    fs::write("/new.txt", "new\n")?;
+    fs::rename("/old.txt", "/new.txt")?;
+    assert_eq!(fs::read_to_string("/new.txt")?, "old\n");
+    assert_eq!(fs::metadata("/old.txt").err(), Some(Error::NotFound));
+    assert_eq!(
+        fs::rename("/new.txt", "/tmp/new.txt").err(),
+        Some(Error::CrossesDevices)
+    );
+    fs::remove_file("/new.txt")?;
+
+    fs::remove_file("/very/src/main.rs")?;
+    fs::remove_dir("/very/src")?;
+    fs::remove_file("/s1")?;
+    fs::remove_file("/s2")?;
+    println!("test_symlink_rename() OK!");
+    Ok(())
+}
+
+#[test]
+fn test_ext2() {
+    println!("Testing ext2 with ramdisk ...");
+
+    let disk = make_disk()
+        .expect("failed to load disk image, run `resources/create_test_img.sh ext2` first");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_common::test_all();
+    test_large_file().expect("test_large_file() failed");
+    test_symlink_rename().expect("test_symlink_rename() failed");
+
+    let stats = axfs::block_cache_stats().unwrap();
+    println!("block cache: {:?}", stats);
+    assert!(stats.hits > 0 && stats.cached <= stats.capacity);
+    assert_eq!(axfs::sync_filesystems(), Ok(()));
+    assert_eq!(axfs::block_cache_stats().unwrap().dirty, 0);
+}
@@ -1,4 +1,4 @@
-#![cfg(not(feature = "myfs"))]
+#![cfg(not(any(feature = "myfs", feature = "ext2")))]
 
 mod test_common;
 
@@ -24,4 +24,10 @@ fn test_fatfs() {
     axfs::init_filesystems(AxDeviceContainer::from_one(disk));
 
     test_common::test_all();
+
+    let stats = axfs::block_cache_stats().unwrap();
+    println!("block cache: {:?}", stats);
+    assert!(stats.hits > 0 && stats.cached <= stats.capacity);
+    assert_eq!(axfs::sync_filesystems(), Ok(()));
+    assert_eq!(axfs::block_cache_stats().unwrap().dirty, 0);
 }
@@ -1,5 +1,5 @@
 use core::arch::asm;
-use memory_addr::VirtAddr;
+use memory_addr::{PhysAddr, VirtAddr};
 
 /// Saved registers when a trap (exception) occurs.
 #[repr(C)]
@@ -15,6 +15,193 @@ pub struct TrapFrame {
     pub spsr: u64,
 }
 
+impl TrapFrame {
+    /// Gets the 0th syscall argument.
+    pub const fn arg0(&self) -> usize {
+        self.r[0] as _
+    }
+
+    /// Gets the 1st syscall argument.
+    pub const fn arg1(&self) -> usize {
+        self.r[1] as _
+    }
+
+    /// Gets the 2nd syscall argument.
+    pub const fn arg2(&self) -> usize {
+        self.r[2] as _
+    }
+
+    /// Gets the 3rd syscall argument.
+    pub const fn arg3(&self) -> usize {
+        self.r[3] as _
+    }
+
+    /// Gets the 4th syscall argument.
+    pub const fn arg4(&self) -> usize {
+        self.r[4] as _
+    }
+
+    /// Gets the 5th syscall argument.
+    pub const fn arg5(&self) -> usize {
+        self.r[5] as _
+    }
+
+    /// Whether the trap is from user space (EL0).
+    pub const fn is_user(&self) -> bool {
+        self.spsr & 0b1111 == 0 // M[3:0] = EL0t
+    }
+}
+
+/// Context to enter user space.
+#[cfg(feature = "uspace")]
+#[derive(Debug, Clone, Copy)]
+pub struct UspaceContext(TrapFrame);
+
+#[cfg(feature = "uspace")]
+impl UspaceContext {
+    /// Creates an empty context with all registers set to zero.
+    pub const fn empty() -> Self {
+        unsafe { core::mem::MaybeUninit::zeroed().assume_init() }
+    }
+
+    /// Creates a new context with the given entry point, user stack pointer,
+    /// and the argument.
+    pub fn new(entry: usize, ustack_top: VirtAddr, arg0: usize) -> Self {
+        use aarch64_cpu::registers::SPSR_EL1;
+        let mut regs = [0; 31];
+        regs[0] = arg0 as _;
+        Self(TrapFrame {
+            r: regs,
+            usp: ustack_top.as_usize() as _,
+            elr: entry as _,
+            spsr: (SPSR_EL1::M::EL0t
+                + SPSR_EL1::D::Masked
+                + SPSR_EL1::A::Masked
+                + SPSR_EL1::F::Masked)
+                .value,
+        })
+    }
+
+    /// Gets the instruction pointer.
+    pub const fn get_ip(&self) -> usize {
+        self.0.elr as _
+    }
+
+    /// Gets the stack pointer.
+    pub const fn get_sp(&self) -> usize {
+        self.0.usp as _
+    }
+
+    /// Sets the instruction pointer.
+    pub fn set_ip(&mut self, pc: usize) {
+        self.0.elr = pc as _;
+    }
+
+    /// Sets the stack pointer.
+    pub fn set_sp(&mut self, sp: usize) {
+        self.0.usp = sp as _;
+    }
+
+    /// Gets the return value register.
+    pub const fn get_retval(&self) -> usize {
+        self.0.r[0] as _
+    }
+
+    /// Sets the return value register.
+    pub fn set_retval(&mut self, r0: usize) {
+        self.0.r[0] = r0 as _;
+    }
+
+    /// Sets the first three arguments of the function to enter.
+    pub fn set_args(&mut self, arg0: usize, arg1: usize, arg2: usize) {
+        self.0.r[0] = arg0 as _;
+        self.0.r[1] = arg1 as _;
+        self.0.r[2] = arg2 as _;
+    }
+
+    /// Sets the return address register (the link register, `x30`).
+    pub fn set_ra(&mut self, ra: usize) {
+        self.0.r[30] = ra as _;
+    }
+
+    /// Restores the registers that user space can change from `saved` (e.g.,
+    /// a context saved in user memory), while keeping the privileged state
+    /// (all but the condition flags in `spsr`).
+    pub fn restore_user_regs(&mut self, saved: &Self) {
+        const NZCV: u64 = 0xf << 28;
+        let spsr = (self.0.spsr & !NZCV) | (saved.0.spsr & NZCV);
+        self.0 = saved.0;
+        self.0.spsr = spsr;
+    }
+
+    /// Enters user space.
+    ///
+    /// It restores the user registers and jumps to the user entry point
+    /// (saved in `ELR_EL1`). When an exception or syscall occurs, the kernel
+    /// stack pointer will be switched to `kstack_top`.
+    ///
+    /// The user page table must be activated (by [`write_page_table_root0`])
+    /// before calling this function.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it changes processor mode and the stack.
+    ///
+    /// [`write_page_table_root0`]: super::write_page_table_root0
+    #[inline(never)]
+    pub unsafe fn enter_uspace(&self, kstack_top: VirtAddr) -> ! {
+        super::disable_irqs();
+        // Exceptions from EL0 are handled with `SP_EL1`, which is not changed
+        // while running in user space, so it is just set to `kstack_top`.
+        asm!("
+            mov     sp, x1
+            ldp     x30, x9, [x0, 30 * 8]
+            ldp     x10, x11, [x0, 32 * 8]
+            msr     sp_el0, x9
+            msr     elr_el1, x10
+            msr     spsr_el1, x11
+
+            ldp     x28, x29, [x0, 28 * 8]
+            ldp     x26, x27, [x0, 26 * 8]
+            ldp     x24, x25, [x0, 24 * 8]
+            ldp     x22, x23, [x0, 22 * 8]
+            ldp     x20, x21, [x0, 20 * 8]
+            ldp     x18, x19, [x0, 18 * 8]
+            ldp     x16, x17, [x0, 16 * 8]
+            ldp     x14, x15, [x0, 14 * 8]
+            ldp     x12, x13, [x0, 12 * 8]
+            ldp     x10, x11, [x0, 10 * 8]
+            ldp     x8, x9, [x0, 8 * 8]
+            ldp     x6, x7, [x0, 6 * 8]
+            ldp     x4, x5, [x0, 4 * 8]
+            ldp     x2, x3, [x0, 2 * 8]
+            ldp     x0, x1, [x0]
+            eret",
+            in("x0") &self.0,
+            in("x1") kstack_top.as_usize(),
+            options(noreturn),
+        )
+    }
+}
+
+#[cfg(feature = "uspace")]
+impl From<&TrapFrame> for UspaceContext {
+    /// Creates a new context from the trap frame, e.g., the one saved on
+    /// syscalls, to return to user space at the same place.
+    fn from(tf: &TrapFrame) -> Self {
+        Self(*tf)
+    }
+}
+
+#[cfg(feature = "uspace")]
+impl From<UspaceContext> for TrapFrame {
+    /// Converts the context into a trap frame, e.g., to replace the one
+    /// saved on syscalls, so that the syscall returns to the new context.
+    fn from(ctx: UspaceContext) -> Self {
+        ctx.0
+    }
+}
+
 /// FP & SIMD registers.
 #[repr(C, align(16))]
 #[derive(Debug, Default)]
@@ -65,6 +252,9 @@ pub struct TaskContext {
     pub lr: u64, // r30
     #[cfg(feature = "fp_simd")]
     pub fp_state: FpState,
+    /// The page table root (`TTBR0_EL1`) of the task's user address space.
+    #[cfg(feature = "uspace")]
+    pub ttbr0_el1: PhysAddr,
 }
 
 impl TaskContext {
@@ -81,6 +271,16 @@ impl TaskContext {
         self.tpidr_el0 = tls_area.as_usize() as u64;
     }
 
+    /// Changes the page table root (`TTBR0_EL1`) of the task.
+    ///
+    /// The kernel space is always mapped by `TTBR1_EL1`, so only the user
+    /// address space is switched. The new page table is switched to when the
+    /// task is switched in.
+    #[cfg(feature = "uspace")]
+    pub fn set_page_table_root(&mut self, ttbr0_el1: PhysAddr) {
+        self.ttbr0_el1 = ttbr0_el1;
+    }
+
     /// Switches to another task.
     ///
     /// It first saves the current task's context from CPU to this place, and then
@@ -88,6 +288,10 @@ impl TaskContext {
     pub fn switch_to(&mut self, next_ctx: &Self) {
         #[cfg(feature = "fp_simd")]
         self.fp_state.switch_to(&next_ctx.fp_state);
+        #[cfg(feature = "uspace")]
+        if self.ttbr0_el1 != next_ctx.ttbr0_el1 {
+            unsafe { super::write_page_table_root0(next_ctx.ttbr0_el1) };
+        }
         unsafe { context_switch(self, next_ctx) }
     }
 }
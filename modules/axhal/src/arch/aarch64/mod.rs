@@ -9,6 +9,9 @@ use tock_registers::interfaces::{Readable, Writeable};
 
 pub use self::context::{FpState, TaskContext, TrapFrame};
 
+#[cfg(feature = "uspace")]
+pub use self::context::UspaceContext;
+
 /// Allows the current CPU to respond to interrupts.
 #[inline]
 pub fn enable_irqs() {
@@ -1,6 +1,8 @@
 use core::arch::global_asm;
 
 use aarch64_cpu::registers::{ESR_EL1, FAR_EL1};
+use memory_addr::VirtAddr;
+use page_table_entry::MappingFlags;
 use tock_registers::interfaces::Readable;
 
 use super::TrapFrame;
@@ -35,6 +37,41 @@ fn invalid_exception(tf: &TrapFrame, kind: TrapKind, source: TrapSource) {
     );
 }
 
+/// Returns the access type of a data abort, according to the `WnR` bit
+/// (bit 6) of the ISS field in `ESR_EL1`.
+fn data_abort_access_flags(esr: u64) -> MappingFlags {
+    if esr & (1 << 6) != 0 {
+        MappingFlags::WRITE
+    } else {
+        MappingFlags::READ
+    }
+}
+
+fn handle_page_fault(tf: &TrapFrame, mut access_flags: MappingFlags, is_user: bool) {
+    let vaddr = VirtAddr::from(FAR_EL1.get() as usize);
+    if is_user {
+        access_flags |= MappingFlags::USER;
+    }
+    #[cfg(feature = "paging")]
+    if crate::trap::handle_page_fault(vaddr, access_flags, is_user) {
+        return;
+    }
+    let iss = ESR_EL1.read(ESR_EL1::ISS);
+    // an unresolved user fault has terminated the faulting task in the
+    // handler, it only gets here if no handler can do that
+    if is_user {
+        panic!(
+            "Unhandled EL0 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x} ({:?}):\n{:#x?}",
+            tf.elr, vaddr, iss, access_flags, tf,
+        );
+    } else {
+        panic!(
+            "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x} ({:?}):\n{:#x?}",
+            tf.elr, vaddr, iss, access_flags, tf,
+        );
+    }
+}
+
 #[no_mangle]
 fn handle_sync_exception(tf: &mut TrapFrame) {
     let esr = ESR_EL1.extract();
@@ -44,29 +81,25 @@ fn handle_sync_exception(tf: &mut TrapFrame) {
             debug!("BRK #{:#x} @ {:#x} ", iss, tf.elr);
             tf.elr += 4;
         }
+        #[cfg(feature = "uspace")]
+        Some(ESR_EL1::EC::Value::SVC64) => {
+            tf.r[0] = crate::trap::handle_syscall(tf, tf.r[8] as usize) as u64;
+        }
+        #[cfg(not(feature = "uspace"))]
         Some(ESR_EL1::EC::Value::SVC64) => {
             warn!("No supervisor call is supported currently!");
         }
-        Some(ESR_EL1::EC::Value::DataAbortLowerEL)
-        | Some(ESR_EL1::EC::Value::InstrAbortLowerEL) => {
-            let iss = esr.read(ESR_EL1::ISS);
-            warn!(
-                "EL0 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}",
-                tf.elr,
-                FAR_EL1.get(),
-                iss
-            );
+        Some(ESR_EL1::EC::Value::InstrAbortLowerEL) => {
+            handle_page_fault(tf, MappingFlags::EXECUTE, true)
         }
-        Some(ESR_EL1::EC::Value::DataAbortCurrentEL)
-        | Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => {
-            let iss = esr.read(ESR_EL1::ISS);
-            panic!(
-                "EL1 Page Fault @ {:#x}, FAR={:#x}, ISS={:#x}:\n{:#x?}",
-                tf.elr,
-                FAR_EL1.get(),
-                iss,
-                tf,
-            );
+        Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => {
+            handle_page_fault(tf, MappingFlags::EXECUTE, false)
+        }
+        Some(ESR_EL1::EC::Value::DataAbortLowerEL) => {
+            handle_page_fault(tf, data_abort_access_flags(esr.get()), true)
+        }
+        Some(ESR_EL1::EC::Value::DataAbortCurrentEL) => {
+            handle_page_fault(tf, data_abort_access_flags(esr.get()), false)
         }
         _ => {
             panic!(
@@ -78,9 +111,17 @@ fn handle_sync_exception(tf: &mut TrapFrame) {
             );
         }
     }
+    #[cfg(feature = "uspace")]
+    if tf.is_user() {
+        crate::trap::handle_user_return(tf);
+    }
 }
 
 #[no_mangle]
-fn handle_irq_exception(_tf: &TrapFrame) {
-    crate::trap::handle_irq_extern(0)
+fn handle_irq_exception(tf: &mut TrapFrame) {
+    crate::trap::handle_irq_extern(0);
+    #[cfg(feature = "uspace")]
+    if tf.is_user() {
+        crate::trap::handle_user_return(tf);
+    }
 }
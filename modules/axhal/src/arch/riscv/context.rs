@@ -1,5 +1,5 @@
 use core::arch::asm;
-use memory_addr::VirtAddr;
+use memory_addr::{PhysAddr, VirtAddr};
 
 include_asm_marcos!();
 
@@ -53,6 +53,178 @@ pub struct TrapFrame {
     pub sstatus: usize,
 }
 
+impl TrapFrame {
+    /// Gets the 0th syscall argument.
+    pub const fn arg0(&self) -> usize {
+        self.regs.a0
+    }
+
+    /// Gets the 1st syscall argument.
+    pub const fn arg1(&self) -> usize {
+        self.regs.a1
+    }
+
+    /// Gets the 2nd syscall argument.
+    pub const fn arg2(&self) -> usize {
+        self.regs.a2
+    }
+
+    /// Gets the 3rd syscall argument.
+    pub const fn arg3(&self) -> usize {
+        self.regs.a3
+    }
+
+    /// Gets the 4th syscall argument.
+    pub const fn arg4(&self) -> usize {
+        self.regs.a4
+    }
+
+    /// Gets the 5th syscall argument.
+    pub const fn arg5(&self) -> usize {
+        self.regs.a5
+    }
+}
+
+/// Context to enter user space.
+#[cfg(feature = "uspace")]
+#[derive(Debug, Clone)]
+pub struct UspaceContext(TrapFrame);
+
+#[cfg(feature = "uspace")]
+impl UspaceContext {
+    /// Creates an empty context with all registers set to zero.
+    pub const fn empty() -> Self {
+        unsafe { core::mem::MaybeUninit::zeroed().assume_init() }
+    }
+
+    /// Creates a new context with the given entry point, user stack pointer,
+    /// and the argument.
+    pub fn new(entry: usize, ustack_top: VirtAddr, arg0: usize) -> Self {
+        const SPIE: usize = 1 << 5; // enable interrupts after `sret`
+        const SUM: usize = 1 << 18; // allow the kernel to access user memory
+        Self(TrapFrame {
+            regs: GeneralRegisters {
+                a0: arg0,
+                sp: ustack_top.as_usize(),
+                ..Default::default()
+            },
+            sepc: entry,
+            sstatus: SPIE | SUM, // SPP = 0: return to U-mode
+        })
+    }
+
+    /// Gets the instruction pointer.
+    pub const fn get_ip(&self) -> usize {
+        self.0.sepc
+    }
+
+    /// Gets the stack pointer.
+    pub const fn get_sp(&self) -> usize {
+        self.0.regs.sp
+    }
+
+    /// Sets the instruction pointer.
+    pub fn set_ip(&mut self, pc: usize) {
+        self.0.sepc = pc;
+    }
+
+    /// Sets the stack pointer.
+    pub fn set_sp(&mut self, sp: usize) {
+        self.0.regs.sp = sp;
+    }
+
+    /// Gets the return value register.
+    pub const fn get_retval(&self) -> usize {
+        self.0.regs.a0
+    }
+
+    /// Sets the return value register.
+    pub fn set_retval(&mut self, a0: usize) {
+        self.0.regs.a0 = a0;
+    }
+
+    /// Sets the first three arguments of the function to enter.
+    pub fn set_args(&mut self, arg0: usize, arg1: usize, arg2: usize) {
+        self.0.regs.a0 = arg0;
+        self.0.regs.a1 = arg1;
+        self.0.regs.a2 = arg2;
+    }
+
+    /// Sets the return address register.
+    pub fn set_ra(&mut self, ra: usize) {
+        self.0.regs.ra = ra;
+    }
+
+    /// Restores the registers that user space can change from `saved` (e.g.,
+    /// a context saved in user memory), while keeping the privileged state
+    /// (`sstatus`).
+    pub fn restore_user_regs(&mut self, saved: &Self) {
+        let sstatus = self.0.sstatus;
+        self.0 = saved.0.clone();
+        self.0.sstatus = sstatus;
+    }
+
+    /// Enters user space.
+    ///
+    /// It restores the user registers and jumps to the user entry point
+    /// (saved in `sepc`). When an exception or syscall occurs, the kernel
+    /// stack pointer will be switched to `kstack_top`.
+    ///
+    /// The user page table must be activated (by [`write_page_table_root`])
+    /// before calling this function.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it changes processor mode and the stack.
+    ///
+    /// [`write_page_table_root`]: super::write_page_table_root
+    #[inline(never)]
+    pub unsafe fn enter_uspace(&self, kstack_top: VirtAddr) -> ! {
+        use riscv::register::{sepc, sscratch};
+
+        super::disable_irqs();
+        sscratch::write(kstack_top.as_usize());
+        sepc::write(self.0.sepc);
+        // The trap frame will be saved right below `kstack_top` on traps from
+        // user space, where the supervisor `tp` is loaded from.
+        let kernel_trap_addr = kstack_top.as_usize() - core::mem::size_of::<TrapFrame>();
+        asm!("
+            mv      sp, {tf}
+
+            STR     tp, {kernel_trap_addr}, 3
+            LDR     gp, sp, 2                   // load user gp and tp
+            LDR     tp, sp, 3
+
+            LDR     t0, sp, 32
+            csrw    sstatus, t0
+            POP_GENERAL_REGS
+            LDR     sp, sp, 1
+            sret",
+            tf = in(reg) &(self.0),
+            kernel_trap_addr = in(reg) kernel_trap_addr,
+            options(noreturn),
+        )
+    }
+}
+
+#[cfg(feature = "uspace")]
+impl From<&TrapFrame> for UspaceContext {
+    /// Creates a new context from the trap frame, e.g., the one saved on
+    /// syscalls, to return to user space at the same place.
+    fn from(tf: &TrapFrame) -> Self {
+        Self(tf.clone())
+    }
+}
+
+#[cfg(feature = "uspace")]
+impl From<UspaceContext> for TrapFrame {
+    /// Converts the context into a trap frame, e.g., to replace the one
+    /// saved on syscalls, so that the syscall returns to the new context.
+    fn from(ctx: UspaceContext) -> Self {
+        ctx.0
+    }
+}
+
 /// Saved hardware states of a task.
 ///
 /// The context usually includes:
@@ -86,6 +258,9 @@ pub struct TaskContext {
     pub s11: usize,
 
     pub tp: usize,
+    /// The page table root (`satp`) of the task's address space.
+    #[cfg(feature = "uspace")]
+    pub satp: PhysAddr,
     // TODO: FP states
 }
 
@@ -103,6 +278,14 @@ impl TaskContext {
         self.tp = tls_area.as_usize();
     }
 
+    /// Changes the page table root (`satp`) of the task.
+    ///
+    /// The new page table is switched to when the task is switched in.
+    #[cfg(feature = "uspace")]
+    pub fn set_page_table_root(&mut self, satp: PhysAddr) {
+        self.satp = satp;
+    }
+
     /// Switches to another task.
     ///
     /// It first saves the current task's context from CPU to this place, and then
@@ -113,6 +296,10 @@ impl TaskContext {
             self.tp = super::read_thread_pointer();
             unsafe { super::write_thread_pointer(next_ctx.tp) };
         }
+        #[cfg(feature = "uspace")]
+        if self.satp != next_ctx.satp {
+            unsafe { super::write_page_table_root(next_ctx.satp) };
+        }
         unsafe {
             // TODO: switch FP states
             context_switch(self, next_ctx)
@@ -10,6 +10,9 @@ use riscv::register::{satp, sstatus, stvec};
 
 pub use self::context::{GeneralRegisters, TaskContext, TrapFrame};
 
+#[cfg(feature = "uspace")]
+pub use self::context::UspaceContext;
+
 /// Allows the current CPU to respond to interrupts.
 #[inline]
 pub fn enable_irqs() {
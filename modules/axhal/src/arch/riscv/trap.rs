@@ -1,4 +1,8 @@
 use riscv::register::scause::{self, Exception as E, Trap};
+use riscv::register::stval;
+
+use memory_addr::VirtAddr;
+use page_table_entry::MappingFlags;
 
 use super::TrapFrame;
 
@@ -14,11 +18,45 @@ fn handle_breakpoint(sepc: &mut usize) {
     *sepc += 2
 }
 
+fn handle_page_fault(tf: &TrapFrame, mut access_flags: MappingFlags, is_user: bool) {
+    let vaddr = VirtAddr::from(stval::read());
+    if is_user {
+        access_flags |= MappingFlags::USER;
+    }
+    #[cfg(feature = "paging")]
+    if crate::trap::handle_page_fault(vaddr, access_flags, is_user) {
+        return;
+    }
+    // an unresolved user fault has terminated the faulting task in the
+    // handler, it only gets here if no handler can do that
+    if is_user {
+        panic!(
+            "Unhandled user page fault @ {:#x}, fault_vaddr={:#x} ({:?}):\n{:#x?}",
+            tf.sepc, vaddr, access_flags, tf,
+        );
+    } else {
+        panic!(
+            "Kernel page fault @ {:#x}, fault_vaddr={:#x} ({:?}):\n{:#x?}",
+            tf.sepc, vaddr, access_flags, tf,
+        );
+    }
+}
+
 #[no_mangle]
-fn riscv_trap_handler(tf: &mut TrapFrame, _from_user: bool) {
+fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     let scause = scause::read();
     match scause.cause() {
         Trap::Exception(E::Breakpoint) => handle_breakpoint(&mut tf.sepc),
+        #[cfg(feature = "uspace")]
+        Trap::Exception(E::UserEnvCall) => {
+            tf.sepc += 4;
+            tf.regs.a0 = crate::trap::handle_syscall(tf, tf.regs.a7) as usize;
+        }
+        Trap::Exception(E::LoadPageFault) => handle_page_fault(tf, MappingFlags::READ, from_user),
+        Trap::Exception(E::StorePageFault) => handle_page_fault(tf, MappingFlags::WRITE, from_user),
+        Trap::Exception(E::InstructionPageFault) => {
+            handle_page_fault(tf, MappingFlags::EXECUTE, from_user)
+        }
         Trap::Interrupt(_) => crate::trap::handle_irq_extern(scause.bits()),
         _ => {
             panic!(
@@ -29,4 +67,8 @@ fn riscv_trap_handler(tf: &mut TrapFrame, _from_user: bool) {
             );
         }
     }
+    #[cfg(feature = "uspace")]
+    if from_user {
+        crate::trap::handle_user_return(tf);
+    }
 }
@@ -1,5 +1,5 @@
 use core::{arch::asm, fmt};
-use memory_addr::VirtAddr;
+use memory_addr::{PhysAddr, VirtAddr};
 
 /// Saved registers when a trap (interrupt or exception) occurs.
 #[allow(missing_docs)]
@@ -35,12 +35,193 @@ pub struct TrapFrame {
 }
 
 impl TrapFrame {
+    /// Gets the 0th syscall argument.
+    pub const fn arg0(&self) -> usize {
+        self.rdi as _
+    }
+
+    /// Gets the 1st syscall argument.
+    pub const fn arg1(&self) -> usize {
+        self.rsi as _
+    }
+
+    /// Gets the 2nd syscall argument.
+    pub const fn arg2(&self) -> usize {
+        self.rdx as _
+    }
+
+    /// Gets the 3rd syscall argument.
+    pub const fn arg3(&self) -> usize {
+        self.r10 as _
+    }
+
+    /// Gets the 4th syscall argument.
+    pub const fn arg4(&self) -> usize {
+        self.r8 as _
+    }
+
+    /// Gets the 5th syscall argument.
+    pub const fn arg5(&self) -> usize {
+        self.r9 as _
+    }
+
     /// Whether the trap is from userspace.
     pub const fn is_user(&self) -> bool {
         self.cs & 0b11 == 3
     }
 }
 
+/// Context to enter user space.
+#[cfg(feature = "uspace")]
+#[derive(Debug, Clone)]
+pub struct UspaceContext(TrapFrame);
+
+#[cfg(feature = "uspace")]
+impl UspaceContext {
+    /// Creates an empty context with all registers set to zero.
+    pub const fn empty() -> Self {
+        unsafe { core::mem::MaybeUninit::zeroed().assume_init() }
+    }
+
+    /// Creates a new context with the given entry point, user stack pointer,
+    /// and the argument.
+    pub fn new(entry: usize, ustack_top: VirtAddr, arg0: usize) -> Self {
+        use super::GdtStruct;
+        use x86_64::registers::rflags::RFlags;
+        Self(TrapFrame {
+            rdi: arg0 as _,
+            rip: entry as _,
+            cs: GdtStruct::UCODE64_SELECTOR.0 as _,
+            rflags: RFlags::INTERRUPT_FLAG.bits(), // IOPL = 0, IF = 1
+            rsp: ustack_top.as_usize() as _,
+            ss: GdtStruct::UDATA_SELECTOR.0 as _,
+            ..Default::default()
+        })
+    }
+
+    /// Gets the instruction pointer.
+    pub const fn get_ip(&self) -> usize {
+        self.0.rip as _
+    }
+
+    /// Gets the stack pointer.
+    pub const fn get_sp(&self) -> usize {
+        self.0.rsp as _
+    }
+
+    /// Sets the instruction pointer.
+    pub fn set_ip(&mut self, rip: usize) {
+        self.0.rip = rip as _;
+    }
+
+    /// Sets the stack pointer.
+    pub fn set_sp(&mut self, rsp: usize) {
+        self.0.rsp = rsp as _;
+    }
+
+    /// Gets the return value register.
+    pub const fn get_retval(&self) -> usize {
+        self.0.rax as _
+    }
+
+    /// Sets the return value register.
+    pub fn set_retval(&mut self, rax: usize) {
+        self.0.rax = rax as _;
+    }
+
+    /// Sets the first three arguments of the function to enter.
+    pub fn set_args(&mut self, arg0: usize, arg1: usize, arg2: usize) {
+        self.0.rdi = arg0 as _;
+        self.0.rsi = arg1 as _;
+        self.0.rdx = arg2 as _;
+    }
+
+    /// Restores the registers that user space can change from `saved` (e.g.,
+    /// a context saved in user memory), while keeping the privileged state
+    /// (the segment selectors and the system flags in `rflags`).
+    pub fn restore_user_regs(&mut self, saved: &Self) {
+        use x86_64::registers::rflags::RFlags;
+        let user_flags = (RFlags::CARRY_FLAG
+            | RFlags::PARITY_FLAG
+            | RFlags::AUXILIARY_CARRY_FLAG
+            | RFlags::ZERO_FLAG
+            | RFlags::SIGN_FLAG
+            | RFlags::TRAP_FLAG
+            | RFlags::DIRECTION_FLAG
+            | RFlags::OVERFLOW_FLAG
+            | RFlags::ALIGNMENT_CHECK)
+            .bits();
+        let rflags = (self.0.rflags & !user_flags) | (saved.0.rflags & user_flags);
+        let (cs, ss) = (self.0.cs, self.0.ss);
+        self.0 = saved.0.clone();
+        self.0.rflags = rflags;
+        self.0.cs = cs;
+        self.0.ss = ss;
+    }
+
+    /// Enters user space.
+    ///
+    /// It restores the user registers and jumps to the user entry point
+    /// (saved in `rip`). When an exception or syscall occurs, the kernel stack
+    /// pointer will be switched to `kstack_top`.
+    ///
+    /// The user page table must be activated (by [`write_page_table_root`])
+    /// before calling this function.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it changes processor mode and the stack.
+    ///
+    /// [`write_page_table_root`]: super::write_page_table_root
+    #[inline(never)]
+    pub unsafe fn enter_uspace(&self, kstack_top: VirtAddr) -> ! {
+        super::disable_irqs();
+        #[cfg(target_os = "none")]
+        super::set_kernel_stack(kstack_top);
+        asm!("
+            mov     rsp, {tf}
+            pop     rax
+            pop     rcx
+            pop     rdx
+            pop     rbx
+            pop     rbp
+            pop     rsi
+            pop     rdi
+            pop     r8
+            pop     r9
+            pop     r10
+            pop     r11
+            pop     r12
+            pop     r13
+            pop     r14
+            pop     r15
+            add     rsp, 16     # skip vector, error_code
+            swapgs
+            iretq",
+            tf = in(reg) &self.0,
+            options(noreturn),
+        )
+    }
+}
+
+#[cfg(feature = "uspace")]
+impl From<&TrapFrame> for UspaceContext {
+    /// Creates a new context from the trap frame, e.g., the one saved on
+    /// syscalls, to return to user space at the same place.
+    fn from(tf: &TrapFrame) -> Self {
+        Self(tf.clone())
+    }
+}
+
+#[cfg(feature = "uspace")]
+impl From<UspaceContext> for TrapFrame {
+    /// Converts the context into a trap frame, e.g., to replace the one
+    /// saved on syscalls, so that the syscall returns to the new context.
+    fn from(ctx: UspaceContext) -> Self {
+        ctx.0
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Default)]
 struct ContextSwitchFrame {
@@ -141,6 +322,9 @@ pub struct TaskContext {
     /// Extended states, i.e., FP/SIMD states.
     #[cfg(feature = "fp_simd")]
     pub ext_state: ExtendedState,
+    /// The page table root (`CR3`) of the task's address space.
+    #[cfg(feature = "uspace")]
+    pub cr3: PhysAddr,
 }
 
 impl TaskContext {
@@ -152,6 +336,8 @@ impl TaskContext {
             fs_base: 0,
             #[cfg(feature = "fp_simd")]
             ext_state: ExtendedState::default(),
+            #[cfg(feature = "uspace")]
+            cr3: PhysAddr::from(0),
         }
     }
 
@@ -177,6 +363,14 @@ impl TaskContext {
         self.fs_base = tls_area.as_usize();
     }
 
+    /// Changes the page table root (`CR3`) of the task.
+    ///
+    /// The new page table is switched to when the task is switched in.
+    #[cfg(feature = "uspace")]
+    pub fn set_page_table_root(&mut self, cr3: PhysAddr) {
+        self.cr3 = cr3;
+    }
+
     /// Switches to another task.
     ///
     /// It first saves the current task's context from CPU to this place, and then
@@ -187,11 +381,17 @@ impl TaskContext {
             self.ext_state.save();
             next_ctx.ext_state.restore();
         }
-        #[cfg(feature = "tls")]
+        #[cfg(any(feature = "tls", feature = "uspace"))]
         {
             self.fs_base = super::read_thread_pointer();
             unsafe { super::write_thread_pointer(next_ctx.fs_base) };
         }
+        #[cfg(feature = "uspace")]
+        if self.cr3 != next_ctx.cr3 {
+            unsafe { super::write_page_table_root(next_ctx.cr3) };
+        }
+        #[cfg(all(feature = "uspace", target_os = "none"))]
+        super::set_kernel_stack(next_ctx.kstack_top);
         unsafe { context_switch(&mut self.rsp, &next_ctx.rsp) }
     }
 }
@@ -6,6 +6,11 @@ use x86_64::structures::gdt::{Descriptor, DescriptorFlags};
 use x86_64::structures::{tss::TaskStateSegment, DescriptorTablePointer};
 use x86_64::{addr::VirtAddr, PrivilegeLevel};
 
+/// The Task State Segment (TSS) of each CPU.
+#[cfg(target_os = "none")]
+#[percpu::def_percpu]
+pub(crate) static TSS: TaskStateSegment = TaskStateSegment::new();
+
 /// A wrapper of the Global Descriptor Table (GDT) with maximum 16 entries.
 #[repr(align(16))]
 pub struct GdtStruct {
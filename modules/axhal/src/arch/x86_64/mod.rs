@@ -5,6 +5,9 @@ mod idt;
 #[cfg(target_os = "none")]
 mod trap;
 
+#[cfg(all(feature = "uspace", target_os = "none"))]
+mod syscall;
+
 use core::arch::asm;
 
 use memory_addr::{PhysAddr, VirtAddr};
@@ -16,6 +19,15 @@ pub use self::gdt::GdtStruct;
 pub use self::idt::IdtStruct;
 pub use x86_64::structures::tss::TaskStateSegment;
 
+#[cfg(target_os = "none")]
+pub(crate) use self::gdt::TSS;
+
+#[cfg(feature = "uspace")]
+pub use self::context::UspaceContext;
+
+#[cfg(all(feature = "uspace", target_os = "none"))]
+pub(crate) use self::syscall::{init_syscall, set_kernel_stack};
+
 /// Allows the current CPU to respond to interrupts.
 #[inline]
 pub fn enable_irqs() {
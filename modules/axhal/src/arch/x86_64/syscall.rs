@@ -0,0 +1,65 @@
+use memory_addr::VirtAddr;
+use x86_64::registers::model_specific::{Efer, EferFlags, KernelGsBase, LStar, SFMask, Star};
+use x86_64::registers::rflags::RFlags;
+
+use super::{GdtStruct, TrapFrame, TSS};
+
+/// The user stack pointer saved on the `syscall` entry.
+#[percpu::def_percpu]
+static USER_RSP: usize = 0;
+
+/// The kernel stack pointer loaded on the `syscall` entry.
+#[percpu::def_percpu]
+static KERNEL_RSP: usize = 0;
+
+#[cfg(target_os = "none")]
+core::arch::global_asm!(
+    include_str!("syscall.S"),
+    user_rsp = sym __PERCPU_USER_RSP,
+    kernel_rsp = sym __PERCPU_KERNEL_RSP,
+    ucode64 = const GdtStruct::UCODE64_SELECTOR.0,
+    udata = const GdtStruct::UDATA_SELECTOR.0,
+);
+
+#[no_mangle]
+fn x86_syscall_handler(tf: &mut TrapFrame) {
+    tf.rax = crate::trap::handle_syscall(tf, tf.rax as usize) as u64;
+    crate::trap::handle_user_return(tf);
+}
+
+/// Sets the kernel stack pointer loaded on traps and syscalls from user
+/// space, i.e., `RSP0` in the TSS.
+pub(crate) fn set_kernel_stack(kstack_top: VirtAddr) {
+    unsafe {
+        KERNEL_RSP.write_current_raw(kstack_top.as_usize());
+        TSS.current_ref_mut_raw().privilege_stack_table[0] =
+            x86_64::VirtAddr::new(kstack_top.as_usize() as u64);
+    }
+}
+
+/// Initializes the MSRs of the `syscall` instruction on the current CPU.
+pub(crate) fn init_syscall() {
+    extern "C" {
+        fn syscall_entry();
+    }
+    LStar::write(x86_64::VirtAddr::new(syscall_entry as usize as _));
+    Star::write(
+        GdtStruct::UCODE64_SELECTOR,
+        GdtStruct::UDATA_SELECTOR,
+        GdtStruct::KCODE64_SELECTOR,
+        GdtStruct::KDATA_SELECTOR,
+    )
+    .unwrap();
+    // Disable interrupts on the `syscall` entry until the stack is switched.
+    SFMask::write(
+        RFlags::TRAP_FLAG
+            | RFlags::INTERRUPT_FLAG
+            | RFlags::DIRECTION_FLAG
+            | RFlags::IOPL_LOW
+            | RFlags::IOPL_HIGH
+            | RFlags::NESTED_TASK
+            | RFlags::ALIGNMENT_CHECK,
+    );
+    unsafe { Efer::update(|efer| efer.insert(EferFlags::SYSTEM_CALL_EXTENSIONS)) };
+    KernelGsBase::write(x86_64::VirtAddr::new(0));
+}
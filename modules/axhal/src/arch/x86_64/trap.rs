@@ -7,27 +7,45 @@ core::arch::global_asm!(include_str!("trap.S"));
 const IRQ_VECTOR_START: u8 = 0x20;
 const IRQ_VECTOR_END: u8 = 0xff;
 
+fn handle_page_fault(tf: &TrapFrame) {
+    let vaddr = unsafe { cr2() };
+    #[cfg(feature = "paging")]
+    {
+        use crate::paging::MappingFlags;
+        let err = PageFaultError::from_bits_truncate(tf.error_code as u32);
+        let mut access_flags = if err.contains(PageFaultError::ID) {
+            MappingFlags::EXECUTE
+        } else if err.contains(PageFaultError::WR) {
+            MappingFlags::WRITE
+        } else {
+            MappingFlags::READ
+        };
+        if err.contains(PageFaultError::US) {
+            access_flags |= MappingFlags::USER;
+        }
+        if crate::trap::handle_page_fault(vaddr.into(), access_flags, tf.is_user()) {
+            return;
+        }
+    }
+    // an unresolved user fault has terminated the faulting task in the
+    // handler, it only gets here if no handler can do that
+    if tf.is_user() {
+        panic!(
+            "Unhandled user #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
+            tf.rip, vaddr, tf.error_code, tf,
+        );
+    } else {
+        panic!(
+            "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
+            tf.rip, vaddr, tf.error_code, tf,
+        );
+    }
+}
+
 #[no_mangle]
-fn x86_trap_handler(tf: &TrapFrame) {
+fn x86_trap_handler(tf: &mut TrapFrame) {
     match tf.vector as u8 {
-        PAGE_FAULT_VECTOR => {
-            if tf.is_user() {
-                warn!(
-                    "User #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}",
-                    tf.rip,
-                    unsafe { cr2() },
-                    tf.error_code,
-                );
-            } else {
-                panic!(
-                    "Kernel #PF @ {:#x}, fault_vaddr={:#x}, error_code={:#x}:\n{:#x?}",
-                    tf.rip,
-                    unsafe { cr2() },
-                    tf.error_code,
-                    tf,
-                );
-            }
-        }
+        PAGE_FAULT_VECTOR => handle_page_fault(tf),
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
         GENERAL_PROTECTION_FAULT_VECTOR => {
             panic!(
@@ -43,4 +61,8 @@ fn x86_trap_handler(tf: &TrapFrame) {
             );
         }
     }
+    #[cfg(feature = "uspace")]
+    if tf.is_user() {
+        crate::trap::handle_user_return(tf);
+    }
 }
@@ -1,20 +1,56 @@
 //! Interrupt management.
 
+use core::sync::atomic::{AtomicU64, Ordering};
 use handler_table::HandlerTable;
 
-use crate::platform::irq::MAX_IRQ_COUNT;
+use crate::platform::irq::{MAX_IRQ_COUNT, TIMER_IRQ_NUM};
 
-pub use crate::platform::irq::{dispatch_irq, register_handler, set_enable};
+pub use crate::platform::irq::{dispatch_irq, register_handler, send_ipi, set_enable, IPI_IRQ_NUM};
 
 /// The type if an IRQ handler.
 pub type IrqHandler = handler_table::Handler;
 
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
+/// Number of times each IRQ has been raised.
+static IRQ_COUNTS: [AtomicU64; MAX_IRQ_COUNT] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicU64 = AtomicU64::new(0);
+    [ZERO; MAX_IRQ_COUNT]
+};
+
+/// Number of timer IRQs, if [`TIMER_IRQ_NUM`] is out of the handler table
+/// (e.g., `scause` on RISC-V).
+static TIMER_IRQ_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Increases the counter of the IRQ.
+pub(crate) fn count_irq(irq_num: usize) {
+    let counter = if irq_num < MAX_IRQ_COUNT {
+        &IRQ_COUNTS[irq_num]
+    } else if irq_num == TIMER_IRQ_NUM {
+        &TIMER_IRQ_COUNT
+    } else {
+        return;
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the IRQ numbers that have been raised at least once, and the
+/// number of times they have been raised.
+pub fn irq_counts() -> impl Iterator<Item = (usize, u64)> {
+    IRQ_COUNTS
+        .iter()
+        .enumerate()
+        .chain(core::iter::once((TIMER_IRQ_NUM, &TIMER_IRQ_COUNT)))
+        .map(|(irq_num, count)| (irq_num, count.load(Ordering::Relaxed)))
+        .filter(|&(_, count)| count > 0)
+}
+
 /// Platform-independent IRQ dispatching.
 #[allow(dead_code)]
 pub(crate) fn dispatch_irq_common(irq_num: usize) {
     trace!("IRQ {}", irq_num);
+    count_irq(irq_num);
     if !IRQ_HANDLER_TABLE.handle(irq_num) {
         warn!("Unhandled IRQ {}", irq_num);
     }
@@ -20,6 +20,7 @@
 //! - `fp_simd`: Enable floating-point and SIMD support.
 //! - `paging`: Enable page table manipulation.
 //! - `irq`: Enable interrupt handling support.
+//! - `uspace`: Enable user-space execution and the syscall entry.
 //!
 //! [ArceOS]: https://github.com/rcore-os/arceos
 //! [cargo test]: https://doc.rust-lang.org/cargo/guide/tests.html
@@ -1,5 +1,7 @@
 //! Page table manipulation.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use axalloc::global_allocator;
 use page_table::PagingIf;
 
@@ -52,6 +54,26 @@ impl PagingIf for PagingIfImpl {
     }
 }
 
+static KERNEL_PAGE_TABLE_ROOT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the physical address of the kernel page table root.
+///
+/// Returns `0` if the kernel page table has not been set by
+/// [`set_kernel_page_table_root`].
+pub fn kernel_page_table_root() -> PhysAddr {
+    PhysAddr::from(KERNEL_PAGE_TABLE_ROOT.load(Ordering::Acquire))
+}
+
+/// Sets the kernel page table root, and switches to it on the current CPU.
+///
+/// # Safety
+///
+/// This function is unsafe as it changes the virtual memory address space.
+pub unsafe fn set_kernel_page_table_root(root_paddr: PhysAddr) {
+    KERNEL_PAGE_TABLE_ROOT.store(root_paddr.as_usize(), Ordering::Release);
+    crate::arch::write_page_table_root(root_paddr);
+}
+
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "x86_64")] {
         /// The architecture-specific page table.
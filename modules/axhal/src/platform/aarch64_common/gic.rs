@@ -10,6 +10,9 @@ pub const MAX_IRQ_COUNT: usize = 1024;
 /// The timer IRQ number.
 pub const TIMER_IRQ_NUM: usize = translate_irq(14, InterruptType::PPI).unwrap();
 
+/// The IRQ number of inter-processor interrupts (SGI 1).
+pub const IPI_IRQ_NUM: usize = translate_irq(1, InterruptType::SGI).unwrap();
+
 /// The UART IRQ number.
 pub const UART_IRQ_NUM: usize = translate_irq(axconfig::UART_IRQ, InterruptType::SPI).unwrap();
 
@@ -46,6 +49,11 @@ pub fn dispatch_irq(_unused: usize) {
     GICC.handle_irq(|irq_num| crate::irq::dispatch_irq_common(irq_num as _));
 }
 
+/// Sends an inter-processor interrupt ([`IPI_IRQ_NUM`]) to the given CPU.
+pub fn send_ipi(cpu_id: usize) {
+    GICD.lock().send_sgi(cpu_id, IPI_IRQ_NUM);
+}
+
 /// Initializes GICD, GICC on the primary CPU.
 pub(crate) fn init_primary() {
     info!("Initialize GICv2...");
@@ -57,4 +65,6 @@ pub(crate) fn init_primary() {
 #[cfg(feature = "smp")]
 pub(crate) fn init_secondary() {
     GICC.init();
+    // SGIs are banked per CPU, enable the IPI on this CPU too.
+    GICD.lock().set_enable(IPI_IRQ_NUM, true);
 }
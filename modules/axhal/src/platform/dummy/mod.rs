@@ -63,6 +63,12 @@ pub mod irq {
     /// The timer IRQ number.
     pub const TIMER_IRQ_NUM: usize = 0;
 
+    /// The IRQ number of inter-processor interrupts.
+    pub const IPI_IRQ_NUM: usize = 1;
+
+    /// Sends an inter-processor interrupt ([`IPI_IRQ_NUM`]) to the given CPU.
+    pub fn send_ipi(cpu_id: usize) {}
+
     /// Enables or disables the given IRQ.
     pub fn set_enable(irq_num: usize, enabled: bool) {}
 
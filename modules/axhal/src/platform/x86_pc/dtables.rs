@@ -1,26 +1,23 @@
 //! Description tables (per-CPU GDT, per-CPU ISS, IDT)
 
-use crate::arch::{GdtStruct, IdtStruct, TaskStateSegment};
+use crate::arch::{GdtStruct, IdtStruct, TSS};
 use lazy_init::LazyInit;
 
 static IDT: LazyInit<IdtStruct> = LazyInit::new();
 
-#[percpu::def_percpu]
-static TSS: LazyInit<TaskStateSegment> = LazyInit::new();
-
 #[percpu::def_percpu]
 static GDT: LazyInit<GdtStruct> = LazyInit::new();
 
 fn init_percpu() {
     unsafe {
         IDT.load();
-        let tss = TSS.current_ref_mut_raw();
         let gdt = GDT.current_ref_mut_raw();
-        tss.init_by(TaskStateSegment::new());
-        gdt.init_by(GdtStruct::new(tss));
+        gdt.init_by(GdtStruct::new(TSS.current_ref_raw()));
         gdt.load();
         gdt.load_tss();
     }
+    #[cfg(feature = "uspace")]
+    crate::arch::init_syscall();
 }
 
 /// Initializes IDT, GDT on the primary CPU.
@@ -2,6 +2,11 @@
 
 use crate_interface::{call_interface, def_interface};
 
+#[cfg(feature = "uspace")]
+use crate::arch::TrapFrame;
+#[cfg(feature = "paging")]
+use crate::{mem::VirtAddr, paging::MappingFlags};
+
 /// Trap handler interface.
 ///
 /// This trait is defined with the [`#[def_interface]`][1] attribute. Users
@@ -13,7 +18,6 @@ use crate_interface::{call_interface, def_interface};
 pub trait TrapHandler {
     /// Handles interrupt requests for the given IRQ number.
     fn handle_irq(irq_num: usize);
-    // more e.g.: handle_page_fault();
 }
 
 /// Call the external IRQ handler.
@@ -21,3 +25,97 @@ pub trait TrapHandler {
 pub(crate) fn handle_irq_extern(irq_num: usize) {
     call_interface!(TrapHandler::handle_irq, irq_num);
 }
+
+/// Page fault handler interface.
+///
+/// Like [`TrapHandler`], it should be implemented with
+/// [`#[impl_interface]`][1] in any other crate. It's used to implement demand
+/// paging in upper layers (e.g., `mmap`).
+///
+/// [1]: crate_interface::impl_interface
+#[cfg(feature = "paging")]
+#[def_interface]
+pub trait PageFaultHandler {
+    /// Handles the page fault at `vaddr`.
+    ///
+    /// `access_flags` is the access type (one of `READ`, `WRITE` or
+    /// `EXECUTE`, plus `USER` if the fault comes from user space). Returns
+    /// `true` if the fault is resolved and the faulting instruction can be
+    /// retried.
+    ///
+    /// If a fault from user space (`is_user` is `true`) cannot be resolved,
+    /// the faulting task should be terminated, otherwise it is retried
+    /// forever.
+    fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool) -> bool;
+}
+
+/// Call the external page fault handler.
+///
+/// Returns `false` if the fault is not resolved, the caller should then treat
+/// it as a fatal fault.
+#[cfg(feature = "paging")]
+pub(crate) fn handle_page_fault(
+    vaddr: VirtAddr,
+    access_flags: MappingFlags,
+    is_user: bool,
+) -> bool {
+    // The handler may read file contents, which may block on devices.
+    #[cfg(feature = "irq")]
+    if is_user {
+        crate::arch::enable_irqs();
+    }
+    let ret = call_interface!(
+        PageFaultHandler::handle_page_fault,
+        vaddr,
+        access_flags,
+        is_user
+    );
+    #[cfg(feature = "irq")]
+    if is_user {
+        crate::arch::disable_irqs();
+    }
+    ret
+}
+
+/// Syscall handler interface.
+///
+/// Like [`TrapHandler`], it should be implemented with
+/// [`#[impl_interface]`][1] in any other crate.
+///
+/// [1]: crate_interface::impl_interface
+#[cfg(feature = "uspace")]
+#[def_interface]
+pub trait SyscallHandler {
+    /// Handles the system call `syscall_num` from user space.
+    ///
+    /// The arguments can be read from `tf`, and the return value is written
+    /// back to the user's return value register.
+    fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize;
+
+    /// Called before returning to user space from syscalls, interrupts and
+    /// exceptions, e.g., to deliver pending signals by modifying `tf`.
+    fn handle_user_return(tf: &mut TrapFrame);
+}
+
+/// Call the external syscall handler.
+#[cfg(feature = "uspace")]
+pub(crate) fn handle_syscall(tf: &mut TrapFrame, syscall_num: usize) -> isize {
+    // IRQs are disabled on trap entry, but syscalls may block for a long time.
+    #[cfg(feature = "irq")]
+    crate::arch::enable_irqs();
+    let ret = call_interface!(SyscallHandler::handle_syscall, tf, syscall_num);
+    #[cfg(feature = "irq")]
+    crate::arch::disable_irqs();
+    ret
+}
+
+/// Call the external handler before returning to user space.
+#[cfg(feature = "uspace")]
+pub(crate) fn handle_user_return(tf: &mut TrapFrame) {
+    // The handler may block (e.g., stopped by signals) or exit the task.
+    #[cfg(feature = "irq")]
+    crate::arch::enable_irqs();
+    call_interface!(SyscallHandler::handle_user_return, tf);
+    #[cfg(feature = "irq")]
+    crate::arch::disable_irqs();
+}
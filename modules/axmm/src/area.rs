@@ -0,0 +1,92 @@
+use core::fmt;
+
+use axhal::mem::VirtAddr;
+use axhal::paging::MappingFlags;
+
+use crate::Backend;
+
+/// A continuous virtual memory area in an address space, with the same
+/// mapping flags and backend.
+pub struct MemoryArea {
+    start: VirtAddr,
+    size: usize,
+    flags: MappingFlags,
+    backend: Backend,
+}
+
+impl MemoryArea {
+    pub(crate) const fn new(
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        backend: Backend,
+    ) -> Self {
+        Self {
+            start,
+            size,
+            flags,
+            backend,
+        }
+    }
+
+    /// Returns the start address of the memory area.
+    pub const fn start(&self) -> VirtAddr {
+        self.start
+    }
+
+    /// Returns the end address (exclusive) of the memory area.
+    pub fn end(&self) -> VirtAddr {
+        self.start + self.size
+    }
+
+    /// Returns the size of the memory area.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the mapping flags of the memory area.
+    pub const fn flags(&self) -> MappingFlags {
+        self.flags
+    }
+
+    /// Returns the backend of the memory area.
+    pub const fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Whether the memory area contains the given address.
+    pub fn contains(&self, vaddr: VirtAddr) -> bool {
+        self.start <= vaddr && vaddr < self.end()
+    }
+
+    pub(crate) fn set_flags(&mut self, flags: MappingFlags) {
+        self.flags = flags;
+    }
+
+    /// Splits the memory area at `pos`, returns the part after `pos`, and
+    /// shrinks `self` to the part before it.
+    ///
+    /// `pos` must be in the middle of the memory area, and aligned to 4K.
+    pub(crate) fn split(&mut self, pos: VirtAddr) -> Self {
+        debug_assert!(self.start < pos && pos < self.end());
+        let left_size = pos.as_usize() - self.start.as_usize();
+        let right = Self::new(
+            pos,
+            self.size - left_size,
+            self.flags,
+            self.backend.split_at(left_size),
+        );
+        self.size = left_size;
+        right
+    }
+}
+
+impl fmt::Debug for MemoryArea {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryArea")
+            .field("va_range", &(self.start..self.end()))
+            .field("flags", &self.flags)
+            .field("backend", &self.backend)
+            .finish()
+    }
+}
@@ -0,0 +1,593 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::fmt;
+
+use axerrno::{ax_err, AxResult};
+use axhal::mem::{PhysAddr, VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::{MappingFlags, PageTable};
+use spinlock::SpinNoIrq;
+
+use crate::backend::{dealloc_frame, frame_as_mut_slice, map_frame, read_file_page};
+use crate::{paging_err_to_ax_err, Backend, FileWriteback, MemoryArea, MmapFile};
+
+/// The virtual memory address space.
+///
+/// It owns a page table, and tracks all memory areas mapped in it. The
+/// frames owned by the memory areas are deallocated when the address space
+/// is dropped.
+pub struct AddrSpace {
+    base: VirtAddr,
+    size: usize,
+    areas: BTreeMap<VirtAddr, MemoryArea>,
+    pt: PageTable,
+}
+
+impl AddrSpace {
+    /// Returns the base address of the address space.
+    pub const fn base(&self) -> VirtAddr {
+        self.base
+    }
+
+    /// Returns the end address (exclusive) of the address space.
+    pub fn end(&self) -> VirtAddr {
+        self.base + self.size
+    }
+
+    /// Returns the size of the address space.
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the reference to the inner page table.
+    pub const fn page_table(&self) -> &PageTable {
+        &self.pt
+    }
+
+    /// Returns the root physical address of the inner page table.
+    pub const fn page_table_root(&self) -> PhysAddr {
+        self.pt.root_paddr()
+    }
+
+    /// Returns an iterator over all memory areas, ordered by the start
+    /// address.
+    pub fn areas(&self) -> impl Iterator<Item = &MemoryArea> {
+        self.areas.values()
+    }
+
+    /// Returns the memory area that contains the given address.
+    pub fn find_area(&self, vaddr: VirtAddr) -> Option<&MemoryArea> {
+        self.areas
+            .range(..=vaddr)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.contains(vaddr))
+    }
+
+    /// Checks if the address space contains the given address range.
+    pub fn contains_range(&self, start: VirtAddr, size: usize) -> bool {
+        start >= self.base
+            && start
+                .as_usize()
+                .checked_add(size)
+                .is_some_and(|end| end <= self.end().as_usize())
+    }
+
+    /// Creates a new empty address space.
+    pub(crate) fn new_empty(base: VirtAddr, size: usize) -> AxResult<Self> {
+        Ok(Self {
+            base,
+            size,
+            areas: BTreeMap::new(),
+            pt: PageTable::try_new().map_err(paging_err_to_ax_err)?,
+        })
+    }
+
+    /// Copies the page table mappings of another address space, so that they
+    /// are shared by the two address spaces.
+    ///
+    /// It's usually used to copy the kernel part of the kernel address space
+    /// into user address spaces. The two address spaces must not overlap.
+    pub fn copy_mappings_from(&mut self, other: &AddrSpace) -> AxResult {
+        if self.base < other.end() && other.base < self.end() {
+            return ax_err!(InvalidInput, "address space overlaps");
+        }
+        self.pt.copy_from(&other.pt, other.base(), other.size());
+        Ok(())
+    }
+
+    /// Creates a copy of the address space for `fork`.
+    ///
+    /// The kernel part is shared with the kernel address space as in
+    /// [`new_user_aspace`](crate::new_user_aspace). The populated private
+    /// pages are shared by the two address spaces, and copied on write.
+    ///
+    /// On failure, the pages already shared with the new address space are
+    /// released.
+    pub fn fork(&mut self) -> AxResult<AddrSpace> {
+        let mut new_aspace = crate::new_user_aspace(self.base, self.size)?;
+        for area in self.areas.values() {
+            // insert the area first, so that its pages are released by
+            // `clear` if the fork fails halfway
+            new_aspace.areas.insert(
+                area.start(),
+                MemoryArea::new(
+                    area.start(),
+                    area.size(),
+                    area.flags(),
+                    area.backend().clone(),
+                ),
+            );
+            let res = area.backend().fork(
+                area.start(),
+                area.size(),
+                area.flags(),
+                &mut self.pt,
+                &mut new_aspace.pt,
+            );
+            if let Err(e) = res {
+                // the shared file pages are written back by this address
+                // space, drop them without writing
+                drop(new_aspace.clear());
+                return Err(e);
+            }
+        }
+        Ok(new_aspace)
+    }
+
+    /// Finds a free area that can accommodate the given size.
+    ///
+    /// The search starts from the given hint address, and the area should be
+    /// within the address space. Returns the start address of the free area,
+    /// or `None` if no such area is found.
+    pub fn find_free_area(&self, hint: VirtAddr, size: usize) -> Option<VirtAddr> {
+        let mut last_end = hint.max(self.base).align_up_4k().as_usize();
+        for area in self.areas.values() {
+            let (area_start, area_end) = (area.start().as_usize(), area.end().as_usize());
+            if area_end <= last_end {
+                continue;
+            }
+            if last_end.checked_add(size)? <= area_start {
+                return Some(last_end.into());
+            }
+            last_end = area_end;
+        }
+        if last_end.checked_add(size)? <= self.end().as_usize() {
+            Some(last_end.into())
+        } else {
+            None
+        }
+    }
+
+    /// Adds a new linear mapping.
+    ///
+    /// The virtual memory area starts from `start_vaddr` and is mapped to the
+    /// physical memory starting from `start_paddr`.
+    ///
+    /// The `flags` parameter indicates the mapping permissions and attributes.
+    pub fn map_linear(
+        &mut self,
+        start_vaddr: VirtAddr,
+        start_paddr: PhysAddr,
+        size: usize,
+        flags: MappingFlags,
+    ) -> AxResult {
+        if !start_paddr.is_aligned_4k() {
+            return ax_err!(InvalidInput, "address not aligned");
+        }
+        let pa_va_offset = start_vaddr.as_usize().wrapping_sub(start_paddr.as_usize());
+        self.map_area(start_vaddr, size, flags, Backend::new_linear(pa_va_offset))
+    }
+
+    /// Adds a new allocation mapping, which is backed by frames allocated
+    /// from the global allocator.
+    ///
+    /// If `populate` is `true`, all frames are allocated immediately.
+    /// Otherwise, they are allocated on page faults.
+    pub fn map_alloc(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        populate: bool,
+    ) -> AxResult {
+        self.map_area(start, size, flags, Backend::new_alloc(populate))
+    }
+
+    /// Adds a new file mapping, which is backed by the contents of `file`
+    /// starting from `offset`.
+    ///
+    /// The pages are read from the file on page faults. If `shared` is
+    /// `true`, the modifications are written back to the file on
+    /// [`sync`](Self::sync), [`unmap`](Self::unmap) and
+    /// [`clear`](Self::clear).
+    pub fn map_file(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        file: Arc<dyn MmapFile>,
+        offset: u64,
+        shared: bool,
+    ) -> AxResult {
+        if !memory_addr::is_aligned_4k(offset as usize) {
+            return ax_err!(InvalidInput, "file offset not aligned");
+        }
+        self.map_area(start, size, flags, Backend::new_file(file, offset, shared))
+    }
+
+    fn map_area(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        backend: Backend,
+    ) -> AxResult {
+        self.validate_region(start, size)?;
+        if size == 0 {
+            return ax_err!(InvalidInput, "empty memory area");
+        }
+        let overlapped = self
+            .areas
+            .range(..start + size)
+            .next_back()
+            .is_some_and(|(_, area)| area.end() > start);
+        if overlapped {
+            return ax_err!(AlreadyExists, "memory area overlaps");
+        }
+        let area = MemoryArea::new(start, size, flags, backend);
+        if let Err(e) = area.backend().map(start, size, flags, &mut self.pt) {
+            // roll back the partially mapped pages
+            area.backend().unmap(start, size, &mut self.pt)?;
+            return Err(e);
+        }
+        self.areas.insert(start, area);
+        Ok(())
+    }
+
+    /// Removes mappings within the specified virtual address range.
+    ///
+    /// Memory areas partially in the range are split. The pages of shared file
+    /// mappings are returned, to be written back to the files.
+    pub fn unmap(&mut self, start: VirtAddr, size: usize) -> AxResult<FileWriteback> {
+        self.validate_region(start, size)?;
+        let mut writeback = FileWriteback::default();
+        for area in self.take_areas(start, size) {
+            area.backend().sync(
+                area.start(),
+                area.start(),
+                area.size(),
+                &self.pt,
+                &mut writeback,
+            );
+            area.backend()
+                .unmap(area.start(), area.size(), &mut self.pt)?;
+        }
+        Ok(writeback)
+    }
+
+    /// Updates the mapping flags within the specified virtual address range.
+    ///
+    /// Memory areas partially in the range are split.
+    pub fn protect(&mut self, start: VirtAddr, size: usize, flags: MappingFlags) -> AxResult {
+        self.validate_region(start, size)?;
+        for mut area in self.take_areas(start, size) {
+            let end = area.end();
+            let mut vaddr = area.start();
+            while vaddr < end {
+                // skip the pages not populated yet
+                match self.pt.query(vaddr) {
+                    Ok((frame, _, page_size)) => {
+                        let page_flags = area.backend().page_flags(frame, flags);
+                        self.pt
+                            .update(vaddr, None, Some(page_flags))
+                            .map_err(paging_err_to_ax_err)?;
+                        axhal::arch::flush_tlb(Some(vaddr));
+                        vaddr += page_size as usize;
+                    }
+                    Err(_) => vaddr += PAGE_SIZE_4K,
+                }
+            }
+            area.set_flags(flags);
+            self.areas.insert(area.start(), area);
+        }
+        Ok(())
+    }
+
+    /// Returns the pages of shared file mappings within the specified virtual
+    /// address range, to be written back to the files.
+    pub fn sync(&self, start: VirtAddr, size: usize) -> AxResult<FileWriteback> {
+        self.validate_region(start, size)?;
+        let mut writeback = FileWriteback::default();
+        let end = start + size;
+        for area in self.areas.range(..end).map(|(_, area)| area) {
+            if area.end() <= start {
+                continue;
+            }
+            let sync_start = start.max(area.start());
+            let sync_end = end.min(area.end());
+            let sync_size = sync_end.as_usize() - sync_start.as_usize();
+            area.backend().sync(
+                area.start(),
+                sync_start,
+                sync_size,
+                &self.pt,
+                &mut writeback,
+            );
+        }
+        Ok(writeback)
+    }
+
+    /// Removes all mappings in the address space.
+    ///
+    /// The pages of shared file mappings are returned, to be written back to
+    /// the files.
+    pub fn clear(&mut self) -> FileWriteback {
+        let mut writeback = FileWriteback::default();
+        for (_, area) in core::mem::take(&mut self.areas) {
+            area.backend().sync(
+                area.start(),
+                area.start(),
+                area.size(),
+                &self.pt,
+                &mut writeback,
+            );
+            area.backend()
+                .unmap(area.start(), area.size(), &mut self.pt)
+                .ok();
+        }
+        writeback
+    }
+
+    /// Handles a page fault at the given address.
+    ///
+    /// `access_flags` indicates the access type that caused the page fault.
+    ///
+    /// Pages of file mappings are not populated here, as reading the file may
+    /// block. [`PageFault::ReadFile`] is returned instead, see
+    /// [`handle_page_fault`].
+    fn try_handle_page_fault(&mut self, vaddr: VirtAddr, access_flags: MappingFlags) -> PageFault {
+        let Some(area) = self
+            .areas
+            .range(..=vaddr)
+            .next_back()
+            .map(|(_, area)| area)
+            .filter(|area| area.contains(vaddr))
+        else {
+            return PageFault::Done(false);
+        };
+        if !area.flags().contains(access_flags) {
+            return PageFault::Done(false);
+        }
+        if let Ok((frame, flags, _)) = self.pt.query(vaddr) {
+            if access_flags.contains(MappingFlags::WRITE) && !flags.contains(MappingFlags::WRITE) {
+                // a copy-on-write page
+                return PageFault::Done(area.backend().handle_cow_fault(
+                    vaddr,
+                    frame,
+                    area.flags(),
+                    &mut self.pt,
+                ));
+            }
+            // already mapped by others, or a stale TLB entry
+            axhal::arch::flush_tlb(Some(vaddr));
+            return PageFault::Done(flags.contains(access_flags));
+        }
+        if let Some((file, pos)) = area.backend().file_page(area.start(), vaddr) {
+            return PageFault::ReadFile(file, pos);
+        }
+        PageFault::Done(
+            area.backend()
+                .handle_page_fault(vaddr, area.flags(), &mut self.pt),
+        )
+    }
+
+    /// Reads data from the address space into `buf`.
+    ///
+    /// The pages not populated yet are populated first, except for file
+    /// mappings, which must be populated by [`handle_page_fault`] in advance.
+    pub fn read(&mut self, start: VirtAddr, buf: &mut [u8]) -> AxResult {
+        self.process_area_data(start, buf.len(), false, |src, offset| {
+            buf[offset..offset + src.len()].copy_from_slice(src);
+        })
+    }
+
+    /// Writes data from `buf` into the address space.
+    ///
+    /// The pages not populated yet are populated first (except for file
+    /// mappings, as in [`read`](Self::read)), and the copy-on-write pages are
+    /// copied. The mapping flags are not checked, so it can be used
+    /// to initialize read-only memory.
+    pub fn write(&mut self, start: VirtAddr, buf: &[u8]) -> AxResult {
+        self.process_area_data(start, buf.len(), true, |dst, offset| {
+            dst.copy_from_slice(&buf[offset..offset + dst.len()]);
+        })
+    }
+
+    fn process_area_data<F>(
+        &mut self,
+        start: VirtAddr,
+        size: usize,
+        write: bool,
+        mut f: F,
+    ) -> AxResult
+    where
+        F: FnMut(&mut [u8], usize),
+    {
+        if !self.contains_range(start, size) {
+            return ax_err!(InvalidInput, "address out of range");
+        }
+        let mut offset = 0;
+        while offset < size {
+            let vaddr = start + offset;
+            let area_flags = self.find_area(vaddr).map(|area| area.flags());
+            let fault_flags = match self.pt.query(vaddr) {
+                Ok((_, flags, _))
+                    if write
+                        && !flags.contains(MappingFlags::WRITE)
+                        && area_flags.is_some_and(|f| f.contains(MappingFlags::WRITE)) =>
+                {
+                    // copy the copy-on-write page before writing
+                    Some(MappingFlags::WRITE)
+                }
+                Ok(_) => None,
+                Err(_) => area_flags,
+            };
+            if let Some(fault_flags) = fault_flags {
+                if !matches!(
+                    self.try_handle_page_fault(vaddr, fault_flags),
+                    PageFault::Done(true)
+                ) {
+                    return ax_err!(BadAddress);
+                }
+            }
+            let Ok((frame, _, _)) = self.pt.query(vaddr) else {
+                return ax_err!(BadAddress);
+            };
+            let page_offset = vaddr.align_offset_4k();
+            let len = (PAGE_SIZE_4K - page_offset).min(size - offset);
+            let data = unsafe { frame_as_mut_slice(frame) };
+            f(&mut data[page_offset..page_offset + len], offset);
+            offset += len;
+        }
+        Ok(())
+    }
+
+    fn validate_region(&self, start: VirtAddr, size: usize) -> AxResult {
+        if !self.contains_range(start, size) {
+            return ax_err!(InvalidInput, "address out of range");
+        }
+        if !start.is_aligned_4k() || !memory_addr::is_aligned_4k(size) {
+            return ax_err!(InvalidInput, "address not aligned");
+        }
+        Ok(())
+    }
+
+    /// Removes the memory areas overlapping `[start, start + size)`, and
+    /// returns the overlapped parts of them.
+    ///
+    /// The parts outside the range are split and kept in the address space.
+    fn take_areas(&mut self, start: VirtAddr, size: usize) -> Vec<MemoryArea> {
+        let end = start + size;
+        let keys: Vec<VirtAddr> = self
+            .areas
+            .range(..end)
+            .rev()
+            .take_while(|(_, area)| area.end() > start)
+            .map(|(&key, _)| key)
+            .collect();
+        let mut taken = Vec::with_capacity(keys.len());
+        for key in keys.into_iter().rev() {
+            let mut area = self.areas.remove(&key).unwrap();
+            if area.start() < start {
+                let right = area.split(start);
+                self.areas.insert(area.start(), area);
+                area = right;
+            }
+            if area.end() > end {
+                let right = area.split(end);
+                self.areas.insert(right.start(), right);
+            }
+            taken.push(area);
+        }
+        taken
+    }
+}
+
+impl fmt::Debug for AddrSpace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AddrSpace")
+            .field("va_range", &(self.base..self.end()))
+            .field("page_table_root", &self.pt.root_paddr())
+            .field("areas", &self.areas.values())
+            .finish()
+    }
+}
+
+impl Drop for AddrSpace {
+    fn drop(&mut self) {
+        self.clear().write().ok();
+    }
+}
+
+/// The result of [`AddrSpace::try_handle_page_fault`].
+enum PageFault {
+    /// Whether the page fault is handled.
+    Done(bool),
+    /// The page is in a file mapping, and should be filled with the file
+    /// contents at the offset.
+    ReadFile(Arc<dyn MmapFile>, u64),
+}
+
+/// Handles a page fault at the given address of the address space `aspace`.
+///
+/// `access_flags` indicates the access type that caused the page fault.
+///
+/// Reading the file for file mappings may block, so it's done with `aspace`
+/// unlocked, and the page is mapped after checking the mapping is unchanged.
+///
+/// Returns `true` if the page fault is handled successfully (not a real
+/// fault), and the faulting instruction can be retried.
+pub fn handle_page_fault(
+    aspace: &SpinNoIrq<AddrSpace>,
+    vaddr: VirtAddr,
+    access_flags: MappingFlags,
+) -> bool {
+    // the page read from the file with `aspace` unlocked
+    let mut page: Option<(Arc<dyn MmapFile>, u64, PhysAddr)> = None;
+    loop {
+        let mut guard = aspace.lock();
+        let res = guard.try_handle_page_fault(vaddr, access_flags);
+        if let (PageFault::ReadFile(file, pos), Some((read_file, read_pos, frame))) = (&res, &page)
+        {
+            if Arc::ptr_eq(read_file, file) && read_pos == pos {
+                let flags = guard.find_area(vaddr).unwrap().flags();
+                return map_frame(vaddr, *frame, flags, &mut guard.pt);
+            }
+        }
+        drop(guard);
+        // populated by others, or the mapping is changed
+        if let Some((_, _, frame)) = page.take() {
+            dealloc_frame(frame);
+        }
+        let PageFault::ReadFile(file, pos) = res else {
+            return matches!(res, PageFault::Done(true));
+        };
+        match read_file_page(file.as_ref(), pos) {
+            Ok(frame) => page = Some((file, pos, frame)),
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Checks the address range `[start, start + size)` of the address space
+/// `aspace` can be accessed with `access_flags`, and populates the pages not
+/// populated yet (or copies the copy-on-write pages for write accesses).
+///
+/// It's used to check the user memory before accessing it in the kernel, so
+/// `access_flags` usually contains [`MappingFlags::USER`]. Returns
+/// [`BadAddress`](axerrno::AxError::BadAddress) if any page cannot be
+/// accessed.
+pub fn populate(
+    aspace: &SpinNoIrq<AddrSpace>,
+    start: VirtAddr,
+    size: usize,
+    access_flags: MappingFlags,
+) -> AxResult {
+    if size == 0 {
+        return Ok(());
+    }
+    let Some(end) = start.as_usize().checked_add(size) else {
+        return ax_err!(BadAddress);
+    };
+    for vaddr in (start.align_down_4k().as_usize()..end).step_by(PAGE_SIZE_4K) {
+        let vaddr = VirtAddr::from(vaddr);
+        let accessible = aspace
+            .lock()
+            .pt
+            .query(vaddr)
+            .is_ok_and(|(_, flags, _)| flags.contains(access_flags));
+        if !accessible && !handle_page_fault(aspace, vaddr, access_flags) {
+            return ax_err!(BadAddress);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,476 @@
+//! Backends of memory areas, which decide how the pages are backed by
+//! physical frames.
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::fmt;
+
+use axalloc::global_allocator;
+use axerrno::{AxError, AxResult};
+use axhal::mem::{phys_to_virt, virt_to_phys, PhysAddr, VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::{MappingFlags, PageSize, PageTable, PagingError};
+use spinlock::SpinNoIrq;
+
+use crate::paging_err_to_ax_err;
+
+/// Reference counts of the frames shared by multiple address spaces (e.g.,
+/// after `fork`). Frames not in the map are owned exclusively.
+static SHARED_FRAMES: SpinNoIrq<BTreeMap<PhysAddr, usize>> = SpinNoIrq::new(BTreeMap::new());
+
+/// A file-like object that can be mapped into an address space.
+pub trait MmapFile: Send + Sync {
+    /// Reads data from the file at the given offset, returns the number of
+    /// bytes read.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> AxResult<usize>;
+
+    /// Writes data to the file at the given offset, returns the number of
+    /// bytes written.
+    fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize>;
+}
+
+/// The backend of a memory area.
+#[derive(Clone)]
+pub enum Backend {
+    /// Linear mapping, the target physical address is `vaddr - pa_va_offset`.
+    ///
+    /// The physical memory is not owned by the memory area, it's never
+    /// deallocated on unmapping.
+    Linear {
+        /// `vaddr - paddr`.
+        pa_va_offset: usize,
+    },
+    /// The pages are backed by frames allocated from the global allocator.
+    ///
+    /// The frames are deallocated on unmapping.
+    Alloc {
+        /// Whether to allocate all frames when mapping. Otherwise, the frames
+        /// are allocated on page faults.
+        populate: bool,
+    },
+    /// The pages are backed by a file.
+    ///
+    /// The frames are allocated on page faults, and filled with the file
+    /// contents.
+    File {
+        /// The mapped file.
+        file: Arc<dyn MmapFile>,
+        /// The offset in the file where the memory area starts.
+        offset: u64,
+        /// Whether the modifications are written back to the file.
+        shared: bool,
+    },
+}
+
+impl Backend {
+    /// Creates a new linear mapping backend.
+    pub const fn new_linear(pa_va_offset: usize) -> Self {
+        Self::Linear { pa_va_offset }
+    }
+
+    /// Creates a new allocation mapping backend.
+    pub const fn new_alloc(populate: bool) -> Self {
+        Self::Alloc { populate }
+    }
+
+    /// Creates a new file mapping backend.
+    pub fn new_file(file: Arc<dyn MmapFile>, offset: u64, shared: bool) -> Self {
+        Self::File {
+            file,
+            offset,
+            shared,
+        }
+    }
+
+    /// Returns the backend of the part of the area that starts at `pos`
+    /// bytes from the area start.
+    pub(crate) fn split_at(&self, pos: usize) -> Self {
+        match self {
+            Self::File {
+                file,
+                offset,
+                shared,
+            } => Self::new_file(file.clone(), offset + pos as u64, *shared),
+            _ => self.clone(),
+        }
+    }
+
+    /// Maps the memory region `[start, start + size)` in the page table.
+    pub(crate) fn map(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        pt: &mut PageTable,
+    ) -> AxResult {
+        match *self {
+            Self::Linear { pa_va_offset } => {
+                let paddr = PhysAddr::from(start.as_usize().wrapping_sub(pa_va_offset));
+                pt.map_region(start, paddr, size, flags, true)
+                    .map_err(paging_err_to_ax_err)
+            }
+            Self::Alloc { populate: true } => {
+                for offset in (0..size).step_by(PAGE_SIZE_4K) {
+                    let frame = alloc_frame()?;
+                    pt.map(start + offset, frame, PageSize::Size4K, flags)
+                        .map_err(|e| {
+                            dealloc_frame(frame);
+                            paging_err_to_ax_err(e)
+                        })?;
+                }
+                Ok(())
+            }
+            // allocated on page faults
+            Self::Alloc { populate: false } | Self::File { .. } => Ok(()),
+        }
+    }
+
+    /// Unmaps the memory region `[start, start + size)` from the page table,
+    /// and releases the frames owned by the memory area.
+    pub(crate) fn unmap(&self, start: VirtAddr, size: usize, pt: &mut PageTable) -> AxResult {
+        let owned = !matches!(self, Self::Linear { .. });
+        let end = start + size;
+        let mut vaddr = start;
+        while vaddr < end {
+            match pt.unmap(vaddr) {
+                Ok((frame, page_size)) => {
+                    if owned {
+                        release_frame(frame);
+                    }
+                    axhal::arch::flush_tlb(Some(vaddr));
+                    vaddr += page_size as usize;
+                }
+                // not populated yet
+                Err(PagingError::NotMapped) => vaddr += PAGE_SIZE_4K,
+                Err(e) => return Err(paging_err_to_ax_err(e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles the page fault at `vaddr`, by allocating a frame for the page
+    /// and mapping it.
+    ///
+    /// Pages of file mappings are not handled here, as reading the file may
+    /// block (see [`file_page`](Self::file_page)). Returns `true` if the page
+    /// is successfully mapped.
+    pub(crate) fn handle_page_fault(
+        &self,
+        vaddr: VirtAddr,
+        flags: MappingFlags,
+        pt: &mut PageTable,
+    ) -> bool {
+        if !matches!(self, Self::Alloc { populate: false }) {
+            return false;
+        }
+        let Ok(frame) = alloc_frame() else {
+            return false;
+        };
+        map_frame(vaddr, frame, flags, pt)
+    }
+
+    /// Returns the file and the offset in it of the page at `vaddr`, if it's
+    /// in a file mapping.
+    ///
+    /// `area_start` is the start address of the memory area.
+    pub(crate) fn file_page(
+        &self,
+        area_start: VirtAddr,
+        vaddr: VirtAddr,
+    ) -> Option<(Arc<dyn MmapFile>, u64)> {
+        match self {
+            Self::File { file, offset, .. } => {
+                let vaddr = vaddr.align_down_4k();
+                let pos = offset + (vaddr.as_usize() - area_start.as_usize()) as u64;
+                Some((file.clone(), pos))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the pages are private to the address space, i.e., they are
+    /// shared copy-on-write after `fork`.
+    fn is_private(&self) -> bool {
+        match self {
+            Self::Linear { .. } => false,
+            Self::Alloc { .. } => true,
+            Self::File { shared, .. } => !shared,
+        }
+    }
+
+    /// Returns the page table flags of the populated page `frame`, which is
+    /// in a memory area with `flags`.
+    ///
+    /// Private pages shared by multiple address spaces are mapped read-only,
+    /// so that they are copied on the first write.
+    pub(crate) fn page_flags(&self, frame: PhysAddr, flags: MappingFlags) -> MappingFlags {
+        if self.is_private() && is_frame_shared(frame) {
+            flags - MappingFlags::WRITE
+        } else {
+            flags
+        }
+    }
+
+    /// Maps the memory region `[start, start + size)` of `src_pt` into
+    /// `dst_pt`, for the address space created by `fork`.
+    ///
+    /// The populated frames are shared by the two page tables. Private pages
+    /// become read-only in both of them, and are copied on write.
+    pub(crate) fn fork(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        flags: MappingFlags,
+        src_pt: &mut PageTable,
+        dst_pt: &mut PageTable,
+    ) -> AxResult {
+        if let Self::Linear { .. } = self {
+            return self.map(start, size, flags, dst_pt);
+        }
+        for vaddr in (start.as_usize()..start.as_usize() + size).step_by(PAGE_SIZE_4K) {
+            let vaddr = VirtAddr::from(vaddr);
+            // not populated yet
+            let Ok((frame, _, _)) = src_pt.query(vaddr) else {
+                continue;
+            };
+            share_frame(frame);
+            let page_flags = self.page_flags(frame, flags);
+            if page_flags.bits() != flags.bits() {
+                src_pt.update(vaddr, None, Some(page_flags)).map_err(|e| {
+                    release_frame(frame);
+                    paging_err_to_ax_err(e)
+                })?;
+                axhal::arch::flush_tlb(Some(vaddr));
+            }
+            dst_pt
+                .map(vaddr, frame, PageSize::Size4K, page_flags)
+                .map_err(|e| {
+                    release_frame(frame);
+                    paging_err_to_ax_err(e)
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Handles the write fault at `vaddr` on a copy-on-write page, which is
+    /// mapped to `frame`.
+    ///
+    /// If the frame is still shared, the page is copied to a new frame.
+    /// Otherwise, it's just made writable again. Returns `true` if the fault
+    /// is handled.
+    pub(crate) fn handle_cow_fault(
+        &self,
+        vaddr: VirtAddr,
+        frame: PhysAddr,
+        flags: MappingFlags,
+        pt: &mut PageTable,
+    ) -> bool {
+        if !self.is_private() {
+            return false;
+        }
+        let vaddr = vaddr.align_down_4k();
+        let new_frame = if is_frame_shared(frame) {
+            let Ok(new_frame) = alloc_frame() else {
+                return false;
+            };
+            unsafe { frame_as_mut_slice(new_frame).copy_from_slice(frame_as_mut_slice(frame)) };
+            Some(new_frame)
+        } else {
+            None
+        };
+        match pt.update(vaddr, new_frame, Some(flags)) {
+            Ok(_) => {
+                if new_frame.is_some() {
+                    release_frame(frame);
+                }
+                axhal::arch::flush_tlb(Some(vaddr));
+                true
+            }
+            Err(e) => {
+                warn!("failed to remap page {:#x} on COW fault: {:?}", vaddr, e);
+                if let Some(new_frame) = new_frame {
+                    dealloc_frame(new_frame);
+                }
+                false
+            }
+        }
+    }
+
+    /// Adds the populated pages in `[start, start + size)` to `writeback`, if
+    /// it's a shared file mapping.
+    ///
+    /// `area_start` is the start address of the memory area.
+    pub(crate) fn sync(
+        &self,
+        area_start: VirtAddr,
+        start: VirtAddr,
+        size: usize,
+        pt: &PageTable,
+        writeback: &mut FileWriteback,
+    ) {
+        let Self::File {
+            file,
+            offset,
+            shared: true,
+        } = self
+        else {
+            return;
+        };
+        for vaddr in (start.as_usize()..start.as_usize() + size).step_by(PAGE_SIZE_4K) {
+            if let Ok((frame, _, _)) = pt.query(vaddr.into()) {
+                let pos = offset + (vaddr - area_start.as_usize()) as u64;
+                // keep the frame alive until it's written back
+                share_frame(frame);
+                writeback.0.push((file.clone(), pos, frame));
+            }
+        }
+    }
+}
+
+/// Pages of shared file mappings to be written back to the files.
+///
+/// Writing files may block, so it's not done while the address space is
+/// locked. [`AddrSpace::unmap`], [`AddrSpace::sync`] and [`AddrSpace::clear`]
+/// return the pages instead, which are written by [`write`](Self::write)
+/// after the lock is released. The frames are kept alive until then, and are
+/// released without writing if it's dropped.
+///
+/// [`AddrSpace::unmap`]: crate::AddrSpace::unmap
+/// [`AddrSpace::sync`]: crate::AddrSpace::sync
+/// [`AddrSpace::clear`]: crate::AddrSpace::clear
+#[must_use]
+#[derive(Default)]
+pub struct FileWriteback(Vec<(Arc<dyn MmapFile>, u64, PhysAddr)>);
+
+impl FileWriteback {
+    /// Writes the pages back to the files.
+    ///
+    /// The remaining pages are not written after the first error.
+    pub fn write(mut self) -> AxResult {
+        let mut res = Ok(());
+        for (file, pos, frame) in self.0.drain(..) {
+            if res.is_ok() {
+                let buf = unsafe { frame_as_mut_slice(frame) };
+                res = file.write_at(pos, buf).map(|_| ());
+            }
+            release_frame(frame);
+        }
+        res
+    }
+}
+
+impl Drop for FileWriteback {
+    fn drop(&mut self) {
+        for (_, _, frame) in self.0.drain(..) {
+            release_frame(frame);
+        }
+    }
+}
+
+impl fmt::Debug for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Linear { pa_va_offset } => f
+                .debug_struct("Linear")
+                .field("pa_va_offset", pa_va_offset)
+                .finish(),
+            Self::Alloc { populate } => {
+                f.debug_struct("Alloc").field("populate", populate).finish()
+            }
+            Self::File { offset, shared, .. } => f
+                .debug_struct("File")
+                .field("offset", offset)
+                .field("shared", shared)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Allocates a frame filled with the file contents at `pos`.
+///
+/// The part beyond the end of the file is filled with zeros.
+pub(crate) fn read_file_page(file: &dyn MmapFile, pos: u64) -> AxResult<PhysAddr> {
+    let frame = alloc_frame()?;
+    let buf = unsafe { frame_as_mut_slice(frame) };
+    read_full(file, pos, buf)
+        .inspect_err(|_| dealloc_frame(frame))
+        .map(|_| frame)
+}
+
+/// Maps the page at `vaddr` to the newly allocated `frame`, which is
+/// deallocated if failed.
+pub(crate) fn map_frame(
+    vaddr: VirtAddr,
+    frame: PhysAddr,
+    flags: MappingFlags,
+    pt: &mut PageTable,
+) -> bool {
+    let vaddr = vaddr.align_down_4k();
+    match pt.map(vaddr, frame, PageSize::Size4K, flags) {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("failed to map page {:#x} on page fault: {:?}", vaddr, e);
+            dealloc_frame(frame);
+            false
+        }
+    }
+}
+
+fn read_full(file: &dyn MmapFile, pos: u64, buf: &mut [u8]) -> AxResult {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read_at(pos + read as u64, &mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(())
+}
+
+/// Allocates a zeroed 4K frame.
+fn alloc_frame() -> AxResult<PhysAddr> {
+    let vaddr = global_allocator()
+        .alloc_pages(1, PAGE_SIZE_4K)
+        .map_err(|_| AxError::NoMemory)?;
+    unsafe { core::ptr::write_bytes(vaddr as *mut u8, 0, PAGE_SIZE_4K) };
+    Ok(virt_to_phys(vaddr.into()))
+}
+
+pub(crate) fn dealloc_frame(frame: PhysAddr) {
+    global_allocator().dealloc_pages(phys_to_virt(frame).as_usize(), 1);
+}
+
+/// Adds a reference to the frame, when it's shared by another address space.
+fn share_frame(frame: PhysAddr) {
+    *SHARED_FRAMES.lock().entry(frame).or_insert(1) += 1;
+}
+
+fn is_frame_shared(frame: PhysAddr) -> bool {
+    SHARED_FRAMES.lock().contains_key(&frame)
+}
+
+/// Removes a reference to the frame, and deallocates it if there are no
+/// other references.
+fn release_frame(frame: PhysAddr) {
+    let mut shared = SHARED_FRAMES.lock();
+    match shared.get_mut(&frame) {
+        Some(count) => {
+            *count -= 1;
+            if *count == 1 {
+                shared.remove(&frame);
+            }
+        }
+        None => dealloc_frame(frame),
+    }
+}
+
+/// Returns the contents of the 4K frame through the linear mapping.
+///
+/// # Safety
+///
+/// The caller must ensure the frame is not accessed through other mutable
+/// references at the same time.
+pub(crate) unsafe fn frame_as_mut_slice<'a>(frame: PhysAddr) -> &'a mut [u8] {
+    core::slice::from_raw_parts_mut(
+        phys_to_virt(frame.align_down_4k()).as_mut_ptr(),
+        PAGE_SIZE_4K,
+    )
+}
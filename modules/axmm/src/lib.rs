@@ -0,0 +1,97 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) virtual memory management
+//! module.
+//!
+//! It provides [`AddrSpace`], which owns a page table and tracks the memory
+//! areas mapped in it. The kernel address space is created by
+//! [`init_memory_management`], and other address spaces (e.g., of user
+//! processes) share the kernel part with it. User address spaces can be
+//! [forked](AddrSpace::fork) with copy-on-write pages.
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod area;
+mod aspace;
+mod backend;
+
+use axerrno::{AxError, AxResult};
+use axhal::mem::{memory_regions, phys_to_virt, PhysAddr, VirtAddr};
+use axhal::paging::PagingError;
+use lazy_init::LazyInit;
+use spinlock::SpinNoIrq;
+
+pub use self::area::MemoryArea;
+pub use self::aspace::{handle_page_fault, populate, AddrSpace};
+pub use self::backend::{Backend, FileWriteback, MmapFile};
+
+static KERNEL_ASPACE: LazyInit<SpinNoIrq<AddrSpace>> = LazyInit::new();
+
+fn paging_err_to_ax_err(err: PagingError) -> AxError {
+    warn!("Paging error: {:?}", err);
+    match err {
+        PagingError::NoMemory => AxError::NoMemory,
+        PagingError::NotAligned => AxError::InvalidInput,
+        PagingError::NotMapped => AxError::NotFound,
+        PagingError::AlreadyMapped => AxError::AlreadyExists,
+        PagingError::MappedToHugePage => AxError::InvalidInput,
+    }
+}
+
+/// Creates a new address space for kernel itself.
+///
+/// All memory regions of the platform are linearly mapped.
+pub fn new_kernel_aspace() -> AxResult<AddrSpace> {
+    let mut aspace = AddrSpace::new_empty(
+        VirtAddr::from(axconfig::KERNEL_ASPACE_BASE),
+        axconfig::KERNEL_ASPACE_SIZE,
+    )?;
+    for r in memory_regions() {
+        aspace.map_linear(phys_to_virt(r.paddr), r.paddr, r.size, r.flags.into())?;
+    }
+    Ok(aspace)
+}
+
+/// Creates a new address space for user processes or sandboxed tasks.
+///
+/// The kernel part is shared with the kernel address space, so the kernel
+/// code and data are still accessible after switching to it.
+pub fn new_user_aspace(base: VirtAddr, size: usize) -> AxResult<AddrSpace> {
+    let mut aspace = AddrSpace::new_empty(base, size)?;
+    if !cfg!(target_arch = "aarch64") {
+        // On AArch64, the kernel space is mapped by the other page table
+        // root (`TTBR1_EL1`), there is no need to copy it.
+        aspace.copy_mappings_from(&kernel_aspace().lock())?;
+    }
+    Ok(aspace)
+}
+
+/// Returns the globally unique kernel address space.
+pub fn kernel_aspace() -> &'static SpinNoIrq<AddrSpace> {
+    &KERNEL_ASPACE
+}
+
+/// Returns the root physical address of the kernel page table.
+pub fn kernel_page_table_root() -> PhysAddr {
+    KERNEL_ASPACE.lock().page_table_root()
+}
+
+/// Initializes virtual memory management on the primary CPU.
+///
+/// It mainly creates the kernel address space and switches to it.
+pub fn init_memory_management() {
+    info!("Initialize virtual memory management...");
+    let kernel_aspace = new_kernel_aspace().expect("failed to initialize kernel address space");
+    debug!("kernel address space init OK: {:#x?}", kernel_aspace);
+    KERNEL_ASPACE.init_by(SpinNoIrq::new(kernel_aspace));
+    unsafe { axhal::paging::set_kernel_page_table_root(kernel_page_table_root()) };
+}
+
+/// Initializes virtual memory management on secondary CPUs.
+///
+/// It switches to the kernel address space created by the primary CPU.
+pub fn init_memory_management_secondary() {
+    unsafe { axhal::paging::set_kernel_page_table_root(kernel_page_table_root()) };
+}
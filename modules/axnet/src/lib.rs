@@ -8,12 +8,26 @@
 //!
 //! - [`TcpSocket`]: A TCP socket that provides POSIX-like APIs.
 //! - [`UdpSocket`]: A UDP socket that provides POSIX-like APIs.
+//! - [`UnixStreamSocket`] and [`UnixDatagramSocket`]: Unix domain sockets,
+//!   bound to filesystem paths or abstract names.
 //! - [`dns_query`]: Function for DNS query.
+//! - [`interfaces`], [`routes`], [`add_route`], [`del_route`]: Functions to
+//!   list the network interfaces, and to manage the routing table.
 //!
 //! # Cargo Features
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `irq` and `multitask`: If both are enabled and the NIC supports
+//!   interrupts, blocked sockets sleep until they become ready, and the
+//!   interfaces are polled by a dedicated task on NIC interrupts.
+//! - `busy-poll`: Always poll the interfaces in a loop when sockets are
+//!   blocked, even if NIC interrupts are available. It has lower latency but
+//!   burns CPU, useful for benchmarks.
+//! - `dhcp`: Configure the IPv4 address, gateway, and DNS servers by DHCP, the
+//!   static configuration is used only if no DHCP server answers.
+//! - `fs`: Create a file when a Unix domain socket is bound to a path, so the
+//!   path is visible in the filesystem.
 //!
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
@@ -32,18 +46,59 @@ cfg_if::cfg_if! {
     }
 }
 
+mod unix;
+
 pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
+pub use self::net_impl::{add_route, del_route, interfaces, routes};
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
+pub use self::unix::{UnixDatagramSocket, UnixSocketAddr, UnixStreamSocket};
+
+use alloc::{string::String, vec::Vec};
+use core::net::IpAddr;
 
 use axdriver::{prelude::*, AxDeviceContainer};
 
+/// Information of a network interface.
+#[derive(Debug, Clone)]
+pub struct InterfaceInfo {
+    /// The interface name, `lo` for the loopback interface, and `eth0`,
+    /// `eth1`, ... for the NICs in the probing order.
+    pub name: String,
+    /// The MAC address.
+    pub mac: [u8; 6],
+    /// The assigned IP addresses and their prefix lengths.
+    pub addrs: Vec<(IpAddr, u8)>,
+}
+
+/// An entry of the routing table.
+#[derive(Debug, Clone)]
+pub struct RouteInfo {
+    /// The destination network address.
+    pub dest: IpAddr,
+    /// The prefix length of the destination network.
+    pub prefix_len: u8,
+    /// The next hop, or `None` if the network is directly connected.
+    pub gateway: Option<IpAddr>,
+    /// The name of the egress interface.
+    pub iface: String,
+}
+
 /// Initializes the network subsystem by NIC devices.
+///
+/// Each NIC gets its own interface. The loopback interface is always
+/// available, even if there are no NICs.
 pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>) {
     info!("Initialize network subsystem...");
 
-    let dev = net_devs.take_one().expect("No NIC device found!");
-    info!("  use NIC 0: {:?}", dev.device_name());
-    net_impl::init(dev);
+    let mut devs = Vec::new();
+    while let Some(dev) = net_devs.take_one() {
+        info!("  use NIC {}: {:?}", devs.len(), dev.device_name());
+        devs.push(dev);
+    }
+    if devs.is_empty() {
+        warn!("  no NIC device found, only the loopback interface is available");
+    }
+    net_impl::init(devs);
 }
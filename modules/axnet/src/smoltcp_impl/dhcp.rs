@@ -0,0 +1,131 @@
+//! DHCPv4 client ([RFC 2131]).
+//!
+//! A smoltcp DHCP socket is added to the first interface at initialization. It
+//! sends the requests and renews the lease in the background whenever the
+//! interfaces are polled, and the acquired configuration is applied to the
+//! interface by [`update_iface`].
+//!
+//! Only the first NIC is configured by DHCP.
+//!
+//! If no server answers during initialization, or the lease is lost later,
+//! the static configuration is used.
+//!
+//! [RFC 2131]: https://datatracker.ietf.org/doc/html/rfc2131
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use smoltcp::socket::dhcpv4;
+use smoltcp::wire::{IpAddress, IpCidr, IpVersion, Ipv4Address, Ipv4Cidr};
+
+use super::{dns, route, InterfaceWrapper, SocketHandle, IFACES, SOCKET_SET};
+
+/// How long to wait for a DHCP server during initialization.
+const DHCP_TIMEOUT: Duration = Duration::from_secs(5);
+
+static DHCP_HANDLE: spin::Once<SocketHandle> = spin::Once::new();
+/// The static configuration to fall back to.
+static FALLBACK: spin::Once<(Ipv4Cidr, Option<Ipv4Address>)> = spin::Once::new();
+/// Whether the interface is configured by DHCP.
+static CONFIGURED: AtomicBool = AtomicBool::new(false);
+
+/// Replaces the IPv4 address of the first NIC and the default IPv4 route.
+fn set_ipv4_config(iface: &InterfaceWrapper, cidr: Ipv4Cidr, router: Option<Ipv4Address>) {
+    let old_cidr = route::ipv4_addr(0);
+    iface.iface.lock().update_ip_addrs(|ip_addrs| {
+        let others: Vec<_> = ip_addrs
+            .iter()
+            .filter(|&&addr| Some(addr) != old_cidr.map(IpCidr::Ipv4))
+            .copied()
+            .collect();
+        // the new address goes first, as the default source address
+        ip_addrs.clear();
+        if ip_addrs.push(IpCidr::Ipv4(cidr)).is_err() {
+            warn!("DHCP: too many addresses, ignore {}", cidr);
+        }
+        for addr in others {
+            ip_addrs.push(addr).ok();
+        }
+    });
+    route::set_connected(0, cidr);
+    route::set_default(IpVersion::Ipv4, router.map(IpAddress::Ipv4));
+}
+
+fn apply_fallback(iface: &InterfaceWrapper) {
+    if let Some(&(cidr, gateway)) = FALLBACK.get() {
+        set_ipv4_config(iface, cidr, gateway);
+    }
+}
+
+/// Handles the events of the DHCP socket, and applies the acquired or lost
+/// configuration to the interface of the first NIC.
+///
+/// The interface and its socket set must not be locked by the caller.
+pub fn update_iface(iface: &InterfaceWrapper) {
+    let Some(&handle) = DHCP_HANDLE.get() else {
+        return;
+    };
+    let event = SOCKET_SET.with_socket_mut::<dhcpv4::Socket, _, _>(handle, |socket| {
+        // drop the received packet borrowed from the socket
+        socket.poll().map(|event| match event {
+            dhcpv4::Event::Configured(config) => dhcpv4::Event::Configured(dhcpv4::Config {
+                packet: None,
+                ..config
+            }),
+            dhcpv4::Event::Deconfigured => dhcpv4::Event::Deconfigured,
+        })
+    });
+    match event {
+        None => {}
+        Some(dhcpv4::Event::Configured(config)) => {
+            info!(
+                "DHCP: got address {} from {}",
+                config.address, config.server.address
+            );
+            set_ipv4_config(iface, config.address, config.router);
+            if let Some(router) = config.router {
+                info!("DHCP: default router {}", router);
+            }
+            if !config.dns_servers.is_empty() {
+                let servers: Vec<_> = config
+                    .dns_servers
+                    .iter()
+                    .map(|&addr| IpAddress::Ipv4(addr))
+                    .collect();
+                info!("DHCP: DNS servers {:?}", servers);
+                dns::set_dns_servers(&servers);
+            }
+            CONFIGURED.store(true, Ordering::Release);
+        }
+        Some(dhcpv4::Event::Deconfigured) if CONFIGURED.swap(false, Ordering::AcqRel) => {
+            warn!("DHCP: lease lost, use the static configuration");
+            apply_fallback(iface);
+            dns::set_dns_servers(&[]);
+        }
+        Some(dhcpv4::Event::Deconfigured) => {}
+    }
+}
+
+/// Starts the DHCP client, and waits until the interface is configured, or
+/// falls back to the static configuration (`ip` and `gateway`) on timeout.
+pub fn init(ip: Ipv4Cidr, gateway: Option<Ipv4Address>) {
+    FALLBACK.call_once(|| (ip, gateway));
+    let handle = SOCKET_SET.add(0, dhcpv4::Socket::new());
+    DHCP_HANDLE.call_once(|| handle);
+
+    let deadline = axhal::time::current_time() + DHCP_TIMEOUT;
+    while axhal::time::current_time() < deadline {
+        SOCKET_SET.poll_interfaces();
+        if CONFIGURED.load(Ordering::Acquire) {
+            return;
+        }
+        core::hint::spin_loop();
+    }
+    warn!("DHCP: no server answered, use the static configuration");
+    apply_fallback(&IFACES[0]);
+    info!("  ip:       {}", ip);
+    if let Some(gateway) = gateway {
+        info!("  gateway:  {}", gateway);
+    }
+}
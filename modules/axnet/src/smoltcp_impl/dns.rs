@@ -1,13 +1,34 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 use axerrno::{ax_err_type, AxError, AxResult};
 use core::net::IpAddr;
 
-use smoltcp::iface::SocketHandle;
-use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
-use smoltcp::wire::DnsQueryType;
+use smoltcp::socket::dns::{self, GetQueryResultError, QueryHandle, StartQueryError};
+use smoltcp::wire::{DnsQueryType, IpAddress};
 
 use super::addr::into_core_ipaddr;
-use super::{SocketSetWrapper, ETH0, SOCKET_SET};
+use super::wait::SocketWaiter;
+use super::{loopback, route, SocketHandle, SocketSetWrapper, DNS_SEVER, IFACES, SOCKET_SET};
+
+/// DNS servers configured at runtime (e.g., by DHCP), which override the
+/// default one.
+static DNS_SERVERS: spin::Mutex<Vec<IpAddress>> = spin::Mutex::new(Vec::new());
+
+/// Sets the DNS servers used by the new queries. An empty list restores the
+/// default server.
+#[allow(dead_code)]
+pub fn set_dns_servers(servers: &[IpAddress]) {
+    *DNS_SERVERS.lock() = servers.to_vec();
+}
+
+/// Returns the DNS servers to use.
+pub fn dns_servers() -> Vec<IpAddress> {
+    let servers = DNS_SERVERS.lock();
+    if servers.is_empty() {
+        vec![DNS_SEVER.parse().expect("invalid DNS server address")]
+    } else {
+        servers.clone()
+    }
+}
 
 /// A DNS socket.
 struct DnsSocket {
@@ -16,10 +37,15 @@ struct DnsSocket {
 
 impl DnsSocket {
     #[allow(clippy::new_without_default)]
-    /// Creates a new DNS socket.
+    /// Creates a new DNS socket, on the interface that routes packets to the
+    /// first DNS server.
     pub fn new() -> Self {
         let socket = SocketSetWrapper::new_dns_socket();
-        let handle = Some(SOCKET_SET.add(socket));
+        let iface = match dns_servers().first() {
+            Some(server) if !loopback::is_loopback(server) => route::egress_dev(server),
+            _ => 0,
+        };
+        let handle = Some(SOCKET_SET.add(iface, socket));
         Self { handle }
     }
 
@@ -31,12 +57,12 @@ impl DnsSocket {
         });
     }
 
-    /// Query a address with given DNS query type.
-    pub fn query(&self, name: &str, query_type: DnsQueryType) -> AxResult<Vec<IpAddr>> {
-        // let local_addr = self.local_addr.unwrap_or_else(f);
+    /// Starts a query of the address with given DNS query type, without
+    /// waiting for the result.
+    pub fn start_query(&self, name: &str, query_type: DnsQueryType) -> AxResult<QueryHandle> {
         let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
-        let iface = &ETH0.iface;
-        let query_handle = SOCKET_SET
+        let iface = &IFACES[handle.iface].iface;
+        SOCKET_SET
             .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
                 socket.start_query(iface.lock().context(), name, query_type)
             })
@@ -50,28 +76,30 @@ impl DnsSocket {
                 StartQueryError::NameTooLong => {
                     ax_err_type!(InvalidInput, "socket query() failed: too long name")
                 }
-            })?;
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.get_query_result(query_handle).map_err(|e| match e {
+            })
+    }
+
+    /// Waits for the result of a query started by [`start_query`].
+    ///
+    /// [`start_query`]: Self::start_query
+    pub fn wait_query(&self, query_handle: QueryHandle) -> AxResult<Vec<IpAddr>> {
+        let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
+        let register_waker = |waker: &_| {
+            SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                socket.register_query_waker(query_handle, waker)
+            })
+        };
+        SocketWaiter::new().block_on(register_waker, None, || {
+            SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                let addrs = socket.get_query_result(query_handle).map_err(|e| match e {
                     GetQueryResultError::Pending => AxError::WouldBlock,
                     GetQueryResultError::Failed => {
                         ax_err_type!(ConnectionRefused, "socket query() failed")
                     }
-                })
-            }) {
-                Ok(n) => {
-                    let mut res = Vec::with_capacity(n.capacity());
-                    for ip in n {
-                        res.push(into_core_ipaddr(ip))
-                    }
-                    return Ok(res);
-                }
-                Err(AxError::WouldBlock) => axtask::yield_now(),
-                Err(e) => return Err(e),
-            }
-        }
+                })?;
+                Ok(addrs.into_iter().map(into_core_ipaddr).collect())
+            })
+        })
     }
 }
 
@@ -84,7 +112,23 @@ impl Drop for DnsSocket {
 }
 
 /// Public function for DNS query.
+///
+/// It queries both IPv4 (A) and IPv6 (AAAA) addresses, and returns IPv4
+/// addresses first. It fails only if both queries fail.
 pub fn dns_query(name: &str) -> AxResult<alloc::vec::Vec<IpAddr>> {
     let socket = DnsSocket::new();
-    socket.query(name, DnsQueryType::A)
+    // send both queries before waiting, so that they are resolved in parallel
+    let v4 = socket.start_query(name, DnsQueryType::A);
+    let v6 = socket.start_query(name, DnsQueryType::Aaaa);
+    match (
+        v4.and_then(|q| socket.wait_query(q)),
+        v6.and_then(|q| socket.wait_query(q)),
+    ) {
+        (Err(e), Err(_)) => Err(e),
+        (v4, v6) => {
+            let mut addrs = v4.unwrap_or_default();
+            addrs.extend(v6.unwrap_or_default());
+            Ok(addrs)
+        }
+    }
 }
@@ -1,26 +1,33 @@
-use alloc::{boxed::Box, collections::VecDeque};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::ops::{Deref, DerefMut};
+use core::task::Waker;
 
 use axerrno::{ax_err, AxError, AxResult};
 use axsync::Mutex;
-use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::iface::SocketSet;
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{SocketSetWrapper, LISTEN_QUEUE_SIZE, SOCKET_SET};
+use super::{SocketHandle, SocketSetWrapper, LISTEN_QUEUE_SIZE, SOCKET_SET};
 
 const PORT_NUM: usize = 65536;
 
 struct ListenTableEntry {
     listen_endpoint: IpListenEndpoint,
+    /// Buffer sizes of the sockets for incoming connections.
+    buf_lens: (usize, usize),
     syn_queue: VecDeque<SocketHandle>,
+    /// Woken up when a socket in the SYN queue is connected.
+    waker: Option<Waker>,
 }
 
 impl ListenTableEntry {
-    pub fn new(listen_endpoint: IpListenEndpoint) -> Self {
+    pub fn new(listen_endpoint: IpListenEndpoint, buf_lens: (usize, usize)) -> Self {
         Self {
             listen_endpoint,
+            buf_lens,
             syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            waker: None,
         }
     }
 
@@ -61,12 +68,14 @@ impl ListenTable {
         self.tcp[port as usize].lock().is_none()
     }
 
-    pub fn listen(&self, listen_endpoint: IpListenEndpoint) -> AxResult {
+    /// Starts listening on the endpoint. The sockets for incoming connections
+    /// are created with the given receive and send buffer sizes.
+    pub fn listen(&self, listen_endpoint: IpListenEndpoint, buf_lens: (usize, usize)) -> AxResult {
         let port = listen_endpoint.port;
         assert_ne!(port, 0);
         let mut entry = self.tcp[port as usize].lock();
         if entry.is_none() {
-            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint)));
+            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint, buf_lens)));
             Ok(())
         } else {
             ax_err!(AddrInUse, "socket listen() failed")
@@ -110,10 +119,30 @@ impl ListenTable {
         }
     }
 
+    /// Registers the waker to be woken up when a new connection on the port is
+    /// established.
+    pub fn register_waker(&self, port: u16, waker: &Waker) {
+        let handles: Vec<_> = match self.tcp[port as usize].lock().deref_mut() {
+            Some(entry) => {
+                entry.waker = Some(waker.clone());
+                entry.syn_queue.iter().copied().collect()
+            }
+            None => return,
+        };
+        for handle in handles {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+            });
+        }
+    }
+
+    /// Creates a socket in the socket set of the `iface`-th interface for
+    /// the first incoming TCP packet, which the later accept() returns.
     pub fn incoming_tcp_packet(
         &self,
         src: IpEndpoint,
         dst: IpEndpoint,
+        iface: usize,
         sockets: &mut SocketSet<'_>,
     ) {
         if let Some(entry) = self.tcp[dst.port as usize].lock().deref_mut() {
@@ -126,9 +155,16 @@ impl ListenTable {
                 warn!("SYN queue overflow!");
                 return;
             }
-            let mut socket = SocketSetWrapper::new_tcp_socket();
+            let (rx_buf_len, tx_buf_len) = entry.buf_lens;
+            let mut socket = SocketSetWrapper::new_tcp_socket(rx_buf_len, tx_buf_len);
             if socket.listen(entry.listen_endpoint).is_ok() {
-                let handle = sockets.add(socket);
+                if let Some(waker) = &entry.waker {
+                    socket.register_recv_waker(waker);
+                }
+                let handle = SocketHandle {
+                    iface,
+                    inner: sockets.add(socket),
+                };
                 debug!(
                     "TCP socket {}: prepare for connection {} -> {}",
                     handle, src, entry.listen_endpoint
@@ -0,0 +1,107 @@
+//! Local traffic through the loopback device.
+//!
+//! Each interface has a loopback device besides its NIC, and the loopback
+//! addresses (`127.0.0.1/8` and `::1`) are assigned to the interface of the
+//! first NIC. The device of each interface sends each outgoing frame to the
+//! loopback device, the NIC, or both:
+//!
+//! - Frames to our own MAC address (i.e., to our own IP addresses, which are
+//!   resolved by the looped-back ARP or NDP), or with loopback IP addresses,
+//!   only go to the loopback device.
+//! - Broadcast and multicast frames go to both, so that the ARP requests and
+//!   neighbor solicitations for our own addresses are answered by ourselves.
+//! - Other frames only go to the NIC.
+
+use smoltcp::iface::Interface;
+use smoltcp::wire::{
+    ArpPacket, EthernetAddress, EthernetFrame, EthernetProtocol, Icmpv6Packet, IpAddress, IpCidr,
+    IpProtocol, Ipv4Address, Ipv4Cidr, Ipv4Packet, Ipv6Address, Ipv6Cidr, Ipv6Packet, NdiscRepr,
+};
+
+/// The IPv4 loopback address.
+pub const LOOPBACK_IPV4: Ipv4Cidr = Ipv4Cidr::new(Ipv4Address::new(127, 0, 0, 1), 8);
+/// The IPv6 loopback address.
+pub const LOOPBACK_IPV6: Ipv6Cidr = Ipv6Cidr::new(Ipv6Address::LOOPBACK, 128);
+
+/// Where to send an outgoing frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Egress {
+    Loopback,
+    Nic,
+    Both,
+}
+
+/// Adds the loopback addresses to the interface.
+///
+/// It should be called after the IPv4 address of the NIC is added, as smoltcp
+/// uses the first IPv4 address as the default source address.
+pub fn setup_iface(iface: &mut Interface) {
+    iface.update_ip_addrs(|ip_addrs| {
+        ip_addrs.push(IpCidr::Ipv4(LOOPBACK_IPV4)).unwrap();
+        ip_addrs.push(IpCidr::Ipv6(LOOPBACK_IPV6)).unwrap();
+    });
+}
+
+/// Whether the address is a loopback address.
+pub fn is_loopback(addr: &IpAddress) -> bool {
+    match addr {
+        IpAddress::Ipv4(addr) => addr.is_loopback(),
+        IpAddress::Ipv6(addr) => addr.is_loopback(),
+    }
+}
+
+/// Returns the loopback address to be used as the source address when
+/// connecting to `remote_addr`, or `None` if it's not a loopback address.
+pub fn source_addr(remote_addr: &IpAddress) -> Option<IpAddress> {
+    is_loopback(remote_addr).then(|| match remote_addr {
+        IpAddress::Ipv4(_) => IpAddress::Ipv4(LOOPBACK_IPV4.address()),
+        IpAddress::Ipv6(_) => IpAddress::Ipv6(LOOPBACK_IPV6.address()),
+    })
+}
+
+/// Decides where to send the outgoing frame, if the NIC is present.
+pub fn egress(frame: &[u8], ether_addr: EthernetAddress) -> Egress {
+    let Ok(ether_frame) = EthernetFrame::new_checked(frame) else {
+        return Egress::Nic;
+    };
+    let dst_addr = ether_frame.dst_addr();
+    if dst_addr == ether_addr || has_loopback_addr(&ether_frame).unwrap_or(false) {
+        Egress::Loopback
+    } else if dst_addr.is_broadcast() || dst_addr.is_multicast() {
+        Egress::Both
+    } else {
+        Egress::Nic
+    }
+}
+
+/// Whether the frame contains loopback IP addresses, which must not appear
+/// on the wire.
+fn has_loopback_addr(ether_frame: &EthernetFrame<&[u8]>) -> smoltcp::wire::Result<bool> {
+    Ok(match ether_frame.ethertype() {
+        EthernetProtocol::Arp => {
+            let arp_packet = ArpPacket::new_checked(ether_frame.payload())?;
+            Ipv4Address::from_bytes(arp_packet.source_protocol_addr()).is_loopback()
+                || Ipv4Address::from_bytes(arp_packet.target_protocol_addr()).is_loopback()
+        }
+        EthernetProtocol::Ipv4 => {
+            let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
+            ipv4_packet.src_addr().is_loopback() || ipv4_packet.dst_addr().is_loopback()
+        }
+        EthernetProtocol::Ipv6 => {
+            let ipv6_packet = Ipv6Packet::new_checked(ether_frame.payload())?;
+            if ipv6_packet.src_addr().is_loopback() || ipv6_packet.dst_addr().is_loopback() {
+                true
+            } else if ipv6_packet.next_header() == IpProtocol::Icmpv6 {
+                // neighbor solicitations for `::1` are sent from other addresses
+                let icmp_packet = Icmpv6Packet::new_checked(ipv6_packet.payload())?;
+                matches!(
+                    NdiscRepr::parse(&icmp_packet),
+                    Ok(NdiscRepr::NeighborSolicit { target_addr, .. }) if target_addr.is_loopback()
+                )
+            } else {
+                false
+            }
+        }
+        _ => false,
+    })
+}
@@ -1,26 +1,42 @@
 mod addr;
 mod bench;
+#[cfg(feature = "dhcp")]
+mod dhcp;
 mod dns;
 mod listen_table;
+mod loopback;
+mod route;
+mod slaac;
 mod tcp;
 mod udp;
+mod wait;
 
-use alloc::vec;
+use alloc::{format, string::String, vec, vec::Vec};
 use core::cell::RefCell;
+use core::fmt;
+use core::net::IpAddr;
 use core::ops::DerefMut;
 
 use axdriver::prelude::*;
+use axerrno::{ax_err, AxResult};
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS};
 use axsync::Mutex;
+use driver_net::loopback::LoopbackDevice;
 use driver_net::{DevError, NetBufPtr};
 use lazy_init::LazyInit;
-use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::iface::{Config, Interface, SocketSet};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+use smoltcp::wire::{
+    EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpVersion, Ipv4Address, Ipv4Cidr,
+    Ipv6Address, Ipv6Cidr,
+};
 
+use self::addr::{from_core_ipaddr, into_core_ipaddr};
 use self::listen_table::ListenTable;
+use self::loopback::{Egress, LOOPBACK_IPV4, LOOPBACK_IPV6};
+use crate::{InterfaceInfo, RouteInfo};
 
 pub use self::dns::dns_query;
 pub use self::tcp::TcpSocket;
@@ -37,8 +53,11 @@ macro_rules! env_or_default {
 
 const IP: &str = env_or_default!("AX_IP");
 const GATEWAY: &str = env_or_default!("AX_GW");
+const IP6: &str = env_or_default!("AX_IP6");
+const GATEWAY6: &str = env_or_default!("AX_GW6");
 const DNS_SEVER: &str = "8.8.8.8";
 const IP_PREFIX: u8 = 24;
+const IP6_PREFIX: u8 = 64;
 
 const STANDARD_MTU: usize = 1500;
 
@@ -46,35 +65,72 @@ const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
 
 const TCP_RX_BUF_LEN: usize = 64 * 1024;
 const TCP_TX_BUF_LEN: usize = 64 * 1024;
+const TCP_MIN_BUF_LEN: usize = 4 * 1024;
+const TCP_MAX_BUF_LEN: usize = 4 * 1024 * 1024;
 const UDP_RX_BUF_LEN: usize = 64 * 1024;
 const UDP_TX_BUF_LEN: usize = 64 * 1024;
 const LISTEN_QUEUE_SIZE: usize = 512;
 
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
 static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
-static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
+/// The interfaces of the NICs, in the same order. Without NICs, there is a
+/// single interface with only the loopback device.
+static IFACES: LazyInit<Vec<InterfaceWrapper>> = LazyInit::new();
 
-struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
+/// The socket sets of the interfaces, in the same order as [`IFACES`].
+///
+/// A socket is only polled by the interface of its socket set, so it must be
+/// added to the interface that routes its packets.
+struct SocketSetWrapper<'a>(Vec<Mutex<SocketSet<'a>>>);
+
+/// A handle of a socket in [`SOCKET_SET`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SocketHandle {
+    /// The index of the interface.
+    iface: usize,
+    inner: smoltcp::iface::SocketHandle,
+}
 
+impl fmt::Display for SocketHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.inner, self.iface)
+    }
+}
+
+/// A NIC and a loopback device, see [`loopback`] for how the outgoing frames
+/// are dispatched between them.
+///
+/// Each NIC has its own smoltcp interface, and [`route`] picks the one to
+/// send packets through.
 struct DeviceWrapper {
-    inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    /// The index of the interface.
+    index: usize,
+    // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    nic: Option<RefCell<AxNetDevice>>,
+    lo: RefCell<LoopbackDevice>,
+    ether_addr: EthernetAddress,
 }
 
 struct InterfaceWrapper {
-    name: &'static str,
+    index: usize,
     ether_addr: EthernetAddress,
     dev: Mutex<DeviceWrapper>,
     iface: Mutex<Interface>,
+    slaac: slaac::Slaac,
 }
 
 impl<'a> SocketSetWrapper<'a> {
-    fn new() -> Self {
-        Self(Mutex::new(SocketSet::new(vec![])))
+    fn new(iface_count: usize) -> Self {
+        Self(
+            (0..iface_count)
+                .map(|_| Mutex::new(SocketSet::new(vec![])))
+                .collect(),
+        )
     }
 
-    pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
-        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
+    pub fn new_tcp_socket(rx_buf_len: usize, tx_buf_len: usize) -> socket::tcp::Socket<'a> {
+        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; rx_buf_len]);
+        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; tx_buf_len]);
         socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
     }
 
@@ -91,12 +147,20 @@ impl<'a> SocketSetWrapper<'a> {
     }
 
     pub fn new_dns_socket() -> socket::dns::Socket<'a> {
-        let server_addr = DNS_SEVER.parse().expect("invalid DNS server address");
-        socket::dns::Socket::new(&[server_addr], vec![])
+        socket::dns::Socket::new(&dns::dns_servers(), vec![])
     }
 
-    pub fn add<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
-        let handle = self.0.lock().add(socket);
+    /// Returns the number of interfaces.
+    pub fn iface_count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Adds the socket to the socket set of the `iface`-th interface.
+    pub fn add<T: AnySocket<'a>>(&self, iface: usize, socket: T) -> SocketHandle {
+        let handle = SocketHandle {
+            iface,
+            inner: self.0[iface].lock().add(socket),
+        };
         debug!("socket {}: created", handle);
         handle
     }
@@ -105,8 +169,8 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&T) -> R,
     {
-        let set = self.0.lock();
-        let socket = set.get(handle);
+        let set = self.0[handle.iface].lock();
+        let socket = set.get(handle.inner);
         f(socket)
     }
 
@@ -114,33 +178,52 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&mut T) -> R,
     {
-        let mut set = self.0.lock();
-        let socket = set.get_mut(handle);
+        let mut set = self.0[handle.iface].lock();
+        let socket = set.get_mut(handle.inner);
         f(socket)
     }
 
     pub fn poll_interfaces(&self) {
-        ETH0.poll(&self.0);
+        for (iface, sockets) in IFACES.iter().zip(&self.0) {
+            iface.poll(sockets);
+        }
+    }
+
+    /// Returns how long to wait before the interfaces should be polled again,
+    /// or `None` if there are no pending timers.
+    #[cfg(all(feature = "irq", feature = "multitask", not(feature = "busy-poll")))]
+    pub fn poll_delay(&self) -> Option<smoltcp::time::Duration> {
+        IFACES
+            .iter()
+            .zip(&self.0)
+            .filter_map(|(iface, sockets)| iface.poll_delay(sockets))
+            .min()
     }
 
     pub fn remove(&self, handle: SocketHandle) {
-        self.0.lock().remove(handle);
+        self.0[handle.iface].lock().remove(handle.inner);
         debug!("socket {}: destroyed", handle);
     }
 }
 
 impl InterfaceWrapper {
-    fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
+    fn new(index: usize, nic: Option<AxNetDevice>) -> Self {
+        let lo = LoopbackDevice::new();
+        let mac = nic
+            .as_ref()
+            .map_or_else(|| lo.mac_address(), |nic| nic.mac_address());
+        let ether_addr = EthernetAddress(mac.0);
         let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
         config.random_seed = RANDOM_SEED;
 
-        let mut dev = DeviceWrapper::new(dev);
+        let mut dev = DeviceWrapper::new(index, nic, lo, ether_addr);
         let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
         Self {
-            name,
+            index,
             ether_addr,
             dev: Mutex::new(dev),
             iface,
+            slaac: slaac::Slaac::new(),
         }
     }
 
@@ -148,14 +231,16 @@ impl InterfaceWrapper {
         Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64)
     }
 
-    pub fn name(&self) -> &str {
-        self.name
-    }
-
     pub fn ethernet_address(&self) -> EthernetAddress {
         self.ether_addr
     }
 
+    /// Whether the interface has a NIC, otherwise it only has the loopback
+    /// device.
+    pub fn has_nic(&self) -> bool {
+        self.dev.lock().nic.is_some()
+    }
+
     pub fn setup_ip_addr(&self, ip: IpAddress, prefix_len: u8) {
         let mut iface = self.iface.lock();
         iface.update_ip_addrs(|ip_addrs| {
@@ -163,64 +248,121 @@ impl InterfaceWrapper {
         });
     }
 
-    pub fn setup_gateway(&self, gateway: IpAddress) {
-        let mut iface = self.iface.lock();
-        match gateway {
-            IpAddress::Ipv4(v4) => iface.routes_mut().add_default_ipv4_route(v4).unwrap(),
-        };
+    pub fn poll(&self, sockets: &Mutex<SocketSet>) {
+        {
+            let mut dev = self.dev.lock();
+            let mut iface = self.iface.lock();
+            let mut sockets = sockets.lock();
+            let timestamp = Self::current_time();
+            iface.poll(timestamp, dev.deref_mut(), &mut sockets);
+        }
+        // the first NIC is also configured by SLAAC and DHCP, which lock the
+        // routing table before the interface
+        if self.index == 0 && self.has_nic() {
+            slaac::update_iface(self);
+            #[cfg(feature = "dhcp")]
+            dhcp::update_iface(self);
+        }
     }
 
-    pub fn poll(&self, sockets: &Mutex<SocketSet>) {
-        let mut dev = self.dev.lock();
+    #[cfg(all(feature = "irq", feature = "multitask", not(feature = "busy-poll")))]
+    pub fn poll_delay(&self, sockets: &Mutex<SocketSet>) -> Option<smoltcp::time::Duration> {
         let mut iface = self.iface.lock();
-        let mut sockets = sockets.lock();
-        let timestamp = Self::current_time();
-        iface.poll(timestamp, dev.deref_mut(), &mut sockets);
+        let sockets = sockets.lock();
+        iface.poll_delay(Self::current_time(), &sockets)
+    }
+
+    /// The IRQ number of the NIC, or `None` if it does not support
+    /// interrupts.
+    #[cfg(all(feature = "irq", feature = "multitask", not(feature = "busy-poll")))]
+    pub fn irq_num(&self) -> Option<usize> {
+        self.dev.lock().nic.as_ref()?.borrow().irq_num()
+    }
+
+    #[cfg(all(feature = "irq", feature = "multitask", not(feature = "busy-poll")))]
+    pub fn set_irq_enabled(&self, enabled: bool) {
+        if let Some(nic) = &self.dev.lock().nic {
+            nic.borrow_mut().set_irq_enabled(enabled);
+        }
+    }
+
+    #[cfg(all(feature = "irq", feature = "multitask", not(feature = "busy-poll")))]
+    pub fn ack_irq(&self) -> bool {
+        match &self.dev.lock().nic {
+            Some(nic) => nic.borrow_mut().ack_irq(),
+            None => false,
+        }
     }
 }
 
 impl DeviceWrapper {
-    fn new(inner: AxNetDevice) -> Self {
+    fn new(
+        index: usize,
+        nic: Option<AxNetDevice>,
+        lo: LoopbackDevice,
+        ether_addr: EthernetAddress,
+    ) -> Self {
         Self {
-            inner: RefCell::new(inner),
+            index,
+            nic: nic.map(RefCell::new),
+            lo: RefCell::new(lo),
+            ether_addr,
         }
     }
-}
 
-impl Device for DeviceWrapper {
-    type RxToken<'a> = AxNetRxToken<'a> where Self: 'a;
-    type TxToken<'a> = AxNetTxToken<'a> where Self: 'a;
-
-    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        let mut dev = self.inner.borrow_mut();
-        if let Err(e) = dev.recycle_tx_buffers() {
+    /// Gives back the transmitted buffers of the NIC, and returns whether a
+    /// packet can be transmitted.
+    fn can_transmit(&self) -> bool {
+        let Some(nic) = &self.nic else {
+            return self.lo.borrow().can_transmit();
+        };
+        let mut nic = nic.borrow_mut();
+        if let Err(e) = nic.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
-            return None;
+            return false;
         }
+        nic.can_transmit()
+    }
 
-        if !dev.can_transmit() {
-            return None;
-        }
-        let rx_buf = match dev.receive() {
-            Ok(buf) => buf,
+    fn receive_from_nic(&self) -> Option<NetBufPtr> {
+        let mut nic = self.nic.as_ref()?.borrow_mut();
+        match nic.receive() {
+            Ok(buf) => Some(buf),
             Err(err) => {
                 if !matches!(err, DevError::Again) {
                     warn!("receive failed: {:?}", err);
                 }
-                return None;
+                None
             }
-        };
-        Some((AxNetRxToken(&self.inner, rx_buf), AxNetTxToken(&self.inner)))
+        }
     }
+}
 
-    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        let mut dev = self.inner.borrow_mut();
-        if let Err(e) = dev.recycle_tx_buffers() {
-            warn!("recycle_tx_buffers failed: {:?}", e);
+impl Device for DeviceWrapper {
+    type RxToken<'a>
+        = AxNetRxToken<'a>
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = AxNetTxToken<'a>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        if !self.can_transmit() {
             return None;
         }
-        if dev.can_transmit() {
-            Some(AxNetTxToken(&self.inner))
+        // local traffic first
+        let (rx_buf, from_nic) = match self.lo.borrow_mut().receive() {
+            Ok(buf) => (buf, false),
+            Err(_) => (self.receive_from_nic()?, true),
+        };
+        Some((AxNetRxToken(self, rx_buf, from_nic), AxNetTxToken(self)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        if self.can_transmit() {
+            Some(AxNetTxToken(self))
         } else {
             None
         }
@@ -235,12 +377,13 @@ impl Device for DeviceWrapper {
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+/// A received packet, and whether it's from the NIC or the loopback device.
+struct AxNetRxToken<'a>(&'a DeviceWrapper, NetBufPtr, bool);
+struct AxNetTxToken<'a>(&'a DeviceWrapper);
 
 impl<'a> RxToken for AxNetRxToken<'a> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_packet(self.1.packet(), sockets).ok();
+        snoop_packet(self.1.packet(), self.0.index, sockets).ok();
     }
 
     fn consume<R, F>(self, f: F) -> R
@@ -254,7 +397,10 @@ impl<'a> RxToken for AxNetRxToken<'a> {
             rx_buf.packet()
         );
         let result = f(rx_buf.packet_mut());
-        self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
+        match (self.2, &self.0.nic) {
+            (true, Some(nic)) => nic.borrow_mut().recycle_rx_buffer(rx_buf).unwrap(),
+            _ => self.0.lo.borrow_mut().recycle_rx_buffer(rx_buf).unwrap(),
+        }
         result
     }
 }
@@ -264,30 +410,96 @@ impl<'a> TxToken for AxNetTxToken<'a> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut dev = self.0.borrow_mut();
-        let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
+        let dev = self.0;
+        let mut lo = dev.lo.borrow_mut();
+        // We don't know where to send it until the frame is built, so build
+        // it in a loopback buffer, and copy it to the NIC if needed.
+        let mut tx_buf = lo.alloc_tx_buffer(len).unwrap();
         let ret = f(tx_buf.packet_mut());
         trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
-        dev.transmit(tx_buf).unwrap();
+
+        let egress = match &dev.nic {
+            Some(_) => loopback::egress(tx_buf.packet(), dev.ether_addr),
+            None => Egress::Loopback,
+        };
+        if let (Egress::Nic | Egress::Both, Some(nic)) = (egress, &dev.nic) {
+            let mut nic = nic.borrow_mut();
+            match nic.alloc_tx_buffer(len) {
+                Ok(mut nic_buf) => {
+                    nic_buf.packet_mut().copy_from_slice(tx_buf.packet());
+                    if let Err(e) = nic.transmit(nic_buf) {
+                        debug!(
+                            "NIC {} cannot transmit: {:?}, drop the packet",
+                            dev.index, e
+                        );
+                    }
+                }
+                Err(e) => debug!(
+                    "NIC {} cannot transmit: {:?}, drop the packet",
+                    dev.index, e
+                ),
+            }
+        }
+        if egress == Egress::Nic {
+            lo.free_tx_buffer(tx_buf);
+        } else if lo.transmit(tx_buf).is_err() {
+            debug!("loopback queue is full, drop the packet");
+        }
         ret
     }
 }
 
-fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
-    use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
+fn snoop_packet(
+    buf: &[u8],
+    iface: usize,
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::{EthernetFrame, EthernetProtocol, IpProtocol, Ipv4Packet, Ipv6Packet};
 
     let ether_frame = EthernetFrame::new_checked(buf)?;
-    let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
-
-    if ipv4_packet.next_header() == IpProtocol::Tcp {
-        let tcp_packet = TcpPacket::new_checked(ipv4_packet.payload())?;
-        let src_addr = (ipv4_packet.src_addr(), tcp_packet.src_port()).into();
-        let dst_addr = (ipv4_packet.dst_addr(), tcp_packet.dst_port()).into();
-        let is_first = tcp_packet.syn() && !tcp_packet.ack();
-        if is_first {
-            // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, sockets);
+    match ether_frame.ethertype() {
+        EthernetProtocol::Ipv4 => {
+            let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
+            if ipv4_packet.next_header() == IpProtocol::Tcp {
+                let src_addr = ipv4_packet.src_addr().into();
+                let dst_addr = ipv4_packet.dst_addr().into();
+                snoop_tcp_packet(src_addr, dst_addr, ipv4_packet.payload(), iface, sockets)?;
+            }
+        }
+        EthernetProtocol::Ipv6 => {
+            let ipv6_packet = Ipv6Packet::new_checked(ether_frame.payload())?;
+            match ipv6_packet.next_header() {
+                IpProtocol::Tcp => {
+                    let src_addr = ipv6_packet.src_addr().into();
+                    let dst_addr = ipv6_packet.dst_addr().into();
+                    snoop_tcp_packet(src_addr, dst_addr, ipv6_packet.payload(), iface, sockets)?;
+                }
+                // IPv6 is only configured on the first NIC
+                IpProtocol::Icmpv6 if iface == 0 => {
+                    IFACES[iface].slaac.snoop_router_advert(&ipv6_packet)?
+                }
+                _ => {}
+            }
         }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn snoop_tcp_packet(
+    src_ip: IpAddress,
+    dst_ip: IpAddress,
+    buf: &[u8],
+    iface: usize,
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    let tcp_packet = smoltcp::wire::TcpPacket::new_checked(buf)?;
+    let src_addr = (src_ip, tcp_packet.src_port()).into();
+    let dst_addr = (dst_ip, tcp_packet.dst_port()).into();
+    let is_first = tcp_packet.syn() && !tcp_packet.ack();
+    if is_first {
+        // create a socket for the first incoming TCP packet, as the later accept() returns.
+        LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, iface, sockets);
     }
     Ok(())
 }
@@ -302,29 +514,198 @@ pub fn poll_interfaces() {
 
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
-    ETH0.dev.lock().bench_transmit_bandwidth();
+    IFACES[0].dev.lock().bench_transmit_bandwidth();
 }
 
 /// Benchmark raw socket receive bandwidth.
 pub fn bench_receive() {
-    ETH0.dev.lock().bench_receive_bandwidth();
+    IFACES[0].dev.lock().bench_receive_bandwidth();
 }
 
-pub(crate) fn init(net_dev: AxNetDevice) {
-    let ether_addr = EthernetAddress(net_dev.mac_address().0);
-    let eth0 = InterfaceWrapper::new("eth0", net_dev, ether_addr);
+/// Returns the name of the `index`-th NIC.
+fn nic_name(index: usize) -> String {
+    format!("eth{}", index)
+}
 
-    let ip = IP.parse().expect("invalid IP address");
-    let gateway = GATEWAY.parse().expect("invalid gateway IP address");
-    eth0.setup_ip_addr(ip, IP_PREFIX);
-    eth0.setup_gateway(gateway);
+/// Returns all network interfaces and their addresses.
+pub fn interfaces() -> Vec<InterfaceInfo> {
+    let mut infos = vec![InterfaceInfo {
+        name: String::from("lo"),
+        mac: [0; 6],
+        addrs: vec![
+            (
+                into_core_ipaddr(LOOPBACK_IPV4.address().into()),
+                LOOPBACK_IPV4.prefix_len(),
+            ),
+            (
+                into_core_ipaddr(LOOPBACK_IPV6.address().into()),
+                LOOPBACK_IPV6.prefix_len(),
+            ),
+        ],
+    }];
+    for iface in IFACES.iter().filter(|iface| iface.has_nic()) {
+        let addrs = iface
+            .iface
+            .lock()
+            .ip_addrs()
+            .iter()
+            .filter(|cidr| !loopback::is_loopback(&cidr.address()))
+            .map(|cidr| (into_core_ipaddr(cidr.address()), cidr.prefix_len()))
+            .collect();
+        infos.push(InterfaceInfo {
+            name: nic_name(iface.index),
+            mac: iface.ethernet_address().0,
+            addrs,
+        });
+    }
+    infos
+}
+
+/// Returns all entries of the routing table.
+pub fn routes() -> Vec<RouteInfo> {
+    route::routes()
+        .into_iter()
+        .map(|r| RouteInfo {
+            dest: into_core_ipaddr(r.cidr.address()),
+            prefix_len: r.cidr.prefix_len(),
+            gateway: r.gateway.map(into_core_ipaddr),
+            iface: nic_name(r.dev),
+        })
+        .collect()
+}
+
+/// Adds a route to `dest/prefix_len` via `gateway`.
+///
+/// The egress interface is the one directly connected to `gateway`.
+pub fn add_route(dest: IpAddr, prefix_len: u8, gateway: IpAddr) -> AxResult {
+    let cidr = route_cidr(dest, prefix_len)?;
+    route::add(cidr, from_core_ipaddr(gateway))
+}
 
-    ETH0.init_by(eth0);
-    SOCKET_SET.init_by(SocketSetWrapper::new());
+/// Removes the route to `dest/prefix_len`.
+pub fn del_route(dest: IpAddr, prefix_len: u8) -> AxResult {
+    let cidr = route_cidr(dest, prefix_len)?;
+    route::remove(cidr)
+}
+
+fn route_cidr(dest: IpAddr, prefix_len: u8) -> AxResult<IpCidr> {
+    let max_len = if dest.is_ipv4() { 32 } else { 128 };
+    if prefix_len > max_len {
+        return ax_err!(InvalidInput, "invalid prefix length");
+    }
+    // the host bits are ignored
+    Ok(match IpCidr::new(from_core_ipaddr(dest), prefix_len) {
+        IpCidr::Ipv4(cidr) => IpCidr::Ipv4(cidr.network()),
+        IpCidr::Ipv6(cidr) => IpCidr::Ipv6(ipv6_network(cidr)),
+    })
+}
+
+fn ipv6_network(cidr: Ipv6Cidr) -> Ipv6Cidr {
+    let mut addr = cidr.address().0;
+    let prefix_len = cidr.prefix_len() as usize;
+    for (i, byte) in addr.iter_mut().enumerate() {
+        let bits = prefix_len.saturating_sub(i * 8).min(8);
+        *byte &= !(0xffu8.checked_shr(bits as u32).unwrap_or(0));
+    }
+    Ipv6Cidr::new(Ipv6Address(addr), cidr.prefix_len())
+}
+
+/// Parses the comma-separated IPv4 addresses of the NICs, each with an
+/// optional prefix length (`IP_PREFIX` by default).
+fn parse_ipv4_addrs(addrs: &str) -> Vec<Ipv4Cidr> {
+    addrs
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(|addr| match addr.split_once('/') {
+            Some(_) => addr.parse().expect("invalid IP address"),
+            None => Ipv4Cidr::new(addr.parse().expect("invalid IP address"), IP_PREFIX),
+        })
+        .collect()
+}
+
+pub(crate) fn init(net_devs: Vec<AxNetDevice>) {
+    let nic_count = net_devs.len();
+    let ifaces: Vec<_> = if net_devs.is_empty() {
+        vec![InterfaceWrapper::new(0, None)]
+    } else {
+        net_devs
+            .into_iter()
+            .enumerate()
+            .map(|(index, nic)| InterfaceWrapper::new(index, Some(nic)))
+            .collect()
+    };
+    let ether_addr = ifaces[0].ethernet_address();
+
+    let ips = parse_ipv4_addrs(IP);
+    let gateway: Option<Ipv4Address> =
+        (!GATEWAY.is_empty()).then(|| GATEWAY.parse().expect("invalid gateway IP address"));
+    let link_local_ip6 = slaac::link_local_addr(ether_addr);
+    let ip6 = (!IP6.is_empty()).then(|| IP6.parse().expect("invalid IPv6 address"));
+    for (index, cidr) in ips.iter().enumerate().take(nic_count) {
+        // with DHCP, the static IPv4 configuration of the first NIC is only
+        // used as a fallback
+        if cfg!(feature = "dhcp") && index == 0 {
+            continue;
+        }
+        ifaces[index].setup_ip_addr(IpAddress::Ipv4(cidr.address()), cidr.prefix_len());
+        route::set_connected(index, *cidr);
+    }
+    if nic_count > 0 {
+        ifaces[0].setup_ip_addr(IpAddress::Ipv6(link_local_ip6), IP6_PREFIX);
+        if let Some(ip6) = ip6 {
+            ifaces[0].setup_ip_addr(ip6, IP6_PREFIX);
+        }
+    }
+    loopback::setup_iface(&mut ifaces[0].iface.lock());
+
+    let iface_count = ifaces.len();
+    IFACES.init_by(ifaces);
+    SOCKET_SET.init_by(SocketSetWrapper::new(iface_count));
     LISTEN_TABLE.init_by(ListenTable::new());
 
-    info!("created net interface {:?}:", ETH0.name());
-    info!("  ether:    {}", ETH0.ethernet_address());
-    info!("  ip:       {}/{}", ip, IP_PREFIX);
-    info!("  gateway:  {}", gateway);
+    #[cfg(not(feature = "dhcp"))]
+    if let Some(gateway) = gateway.filter(|_| nic_count > 0) {
+        route::set_default(IpVersion::Ipv4, Some(IpAddress::Ipv4(gateway)));
+    }
+    if nic_count > 0 && !GATEWAY6.is_empty() {
+        let gateway6 = GATEWAY6.parse().expect("invalid IPv6 gateway address");
+        route::set_default(IpVersion::Ipv6, Some(gateway6));
+    }
+
+    for (index, iface) in IFACES.iter().enumerate().take(nic_count) {
+        info!("created net interface {:?}:", nic_name(index));
+        info!("  ether:    {}", iface.ethernet_address());
+        match ips.get(index) {
+            #[cfg(feature = "dhcp")]
+            Some(&ip) if index == 0 => dhcp::init(ip, gateway),
+            #[cfg(feature = "dhcp")]
+            None if index == 0 => panic!("no static IPv4 address for the DHCP fallback"),
+            Some(ip) => info!("  ip:       {}", ip),
+            None => warn!("  no IPv4 address"),
+        }
+        if index == 0 {
+            info!("  ip6:      {}/{} (link-local)", link_local_ip6, IP6_PREFIX);
+            if let Some(ip6) = ip6 {
+                info!("  ip6:      {}/{}", ip6, IP6_PREFIX);
+            }
+        }
+    }
+    #[cfg(not(feature = "dhcp"))]
+    if let Some(gateway) = gateway.filter(|_| nic_count > 0) {
+        info!("  gateway:  {}", gateway);
+    }
+    info!("created net interface \"lo\":");
+    info!(
+        "  ip:       {}, {}",
+        loopback::LOOPBACK_IPV4,
+        loopback::LOOPBACK_IPV6
+    );
+
+    if nic_count > 0 {
+        // get a global IPv6 address by SLAAC
+        slaac::send_router_solicit(ether_addr);
+    }
+
+    wait::init_poller();
 }
@@ -0,0 +1,453 @@
+//! The routing table, which picks the interface to send each outgoing packet.
+//!
+//! Each NIC has its own smoltcp interface, which resolves the next hop of the
+//! packets sent through it by its own routes. This table holds the routes of
+//! all interfaces with their egress NICs, and a packet is sent through the
+//! interface of the longest matching route, or the first NIC if none matches.
+//! The routes with gateways are also added to the smoltcp interface of their
+//! egress NICs.
+//!
+//! Routes of the directly connected IPv4 networks are added when addresses
+//! are assigned to the NICs. Routes with gateways are added by the static
+//! configuration, DHCP, SLAAC, and [`add_route`]. Routes without gateways
+//! cannot be added otherwise, as smoltcp only sends packets directly to the
+//! networks of its addresses.
+//!
+//! [`add_route`]: super::add_route
+
+use alloc::vec::Vec;
+
+use axerrno::{ax_err, ax_err_type, AxResult};
+use smoltcp::iface::Route;
+use smoltcp::wire::{IpAddress, IpCidr, IpVersion, Ipv4Cidr};
+use spin::Mutex;
+
+use super::IFACES;
+
+/// An entry of the routing table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteEntry {
+    /// The destination network.
+    pub cidr: IpCidr,
+    /// The next hop, or `None` for the directly connected networks.
+    pub gateway: Option<IpAddress>,
+    /// The index of the egress NIC.
+    pub dev: usize,
+    /// Our address on the NIC, only for the directly connected networks.
+    pub src: Option<IpAddress>,
+}
+
+struct RouteTable(Vec<RouteEntry>);
+
+/// It's locked before the smoltcp interfaces when both are needed.
+static ROUTES: Mutex<RouteTable> = Mutex::new(RouteTable::new());
+
+/// Longest prefix match, the earliest one wins if there are ties.
+fn lookup_in<'a>(
+    routes: impl Iterator<Item = &'a RouteEntry>,
+    addr: &IpAddress,
+) -> Option<&'a RouteEntry> {
+    let mut best: Option<&RouteEntry> = None;
+    for route in routes.filter(|r| r.cidr.contains_addr(addr)) {
+        if best.is_none_or(|b| route.cidr.prefix_len() > b.cidr.prefix_len()) {
+            best = Some(route);
+        }
+    }
+    best
+}
+
+/// Looks up the directly connected network that `addr` is in.
+fn connected<'a>(routes: &'a [RouteEntry], addr: &IpAddress) -> Option<&'a RouteEntry> {
+    lookup_in(routes.iter().filter(|r| r.gateway.is_none()), addr)
+}
+
+fn default_cidr(version: IpVersion) -> IpCidr {
+    match version {
+        IpVersion::Ipv4 => IpCidr::new(IpAddress::v4(0, 0, 0, 0), 0),
+        IpVersion::Ipv6 => IpCidr::new(IpAddress::v6(0, 0, 0, 0, 0, 0, 0, 0), 0),
+    }
+}
+
+impl RouteTable {
+    const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns the index of the NIC to send packets to `addr`.
+    fn egress_dev(&self, addr: &IpAddress) -> usize {
+        lookup_in(self.0.iter(), addr).map_or(0, |r| r.dev)
+    }
+
+    /// Returns our address on the NIC to send packets to `addr`, or `None`
+    /// if the NIC has no address of the same version.
+    fn source_addr(&self, addr: &IpAddress) -> Option<IpAddress> {
+        let dev = self.egress_dev(addr);
+        self.0
+            .iter()
+            .find(|r| {
+                r.gateway.is_none() && r.dev == dev && r.cidr.address().version() == addr.version()
+            })
+            .and_then(|r| r.src)
+    }
+
+    /// Returns the IPv4 address assigned to the NIC.
+    fn ipv4_addr(&self, dev: usize) -> Option<Ipv4Cidr> {
+        self.0
+            .iter()
+            .find_map(|r| match (r.gateway, r.src, r.cidr) {
+                (None, Some(IpAddress::Ipv4(src)), IpCidr::Ipv4(cidr)) if r.dev == dev => {
+                    Some(Ipv4Cidr::new(src, cidr.prefix_len()))
+                }
+                _ => None,
+            })
+    }
+
+    /// Replaces the directly connected IPv4 network of the NIC, after `addr`
+    /// is assigned to it.
+    fn set_connected(&mut self, dev: usize, addr: Ipv4Cidr) {
+        self.0.retain(|r| {
+            !(r.gateway.is_none() && r.dev == dev && r.cidr.address().version() == IpVersion::Ipv4)
+        });
+        self.0.push(RouteEntry {
+            cidr: IpCidr::Ipv4(addr.network()),
+            gateway: None,
+            dev,
+            src: Some(IpAddress::Ipv4(addr.address())),
+        });
+    }
+
+    /// Replaces the default route of the IP version, or removes it if
+    /// `gateway` is `None`.
+    ///
+    /// Returns the removed route and the added one, which should be synced to
+    /// the smoltcp interfaces.
+    fn set_default(
+        &mut self,
+        version: IpVersion,
+        gateway: Option<IpAddress>,
+    ) -> (Option<RouteEntry>, Option<RouteEntry>) {
+        let cidr = default_cidr(version);
+        let old = self
+            .0
+            .iter()
+            .position(|r| r.cidr == cidr)
+            .map(|index| self.0.remove(index));
+        let new = gateway.map(|gateway| RouteEntry {
+            cidr,
+            gateway: Some(gateway),
+            dev: connected(&self.0, &gateway).map_or(0, |r| r.dev),
+            src: None,
+        });
+        self.0.extend(new);
+        (old, new)
+    }
+
+    /// Adds a route to `cidr` via `gateway`, and returns it.
+    ///
+    /// The egress NIC is the one directly connected to `gateway`. For IPv6,
+    /// it's always the first NIC.
+    fn add(&mut self, cidr: IpCidr, gateway: IpAddress) -> AxResult<RouteEntry> {
+        if cidr.address().version() != gateway.version() || !gateway.is_unicast() {
+            return ax_err!(InvalidInput, "invalid gateway");
+        }
+        if self.0.iter().any(|r| r.cidr == cidr) {
+            return ax_err!(AlreadyExists, "route exists");
+        }
+        let dev = match (connected(&self.0, &gateway), gateway) {
+            (Some(route), _) => route.dev,
+            (None, IpAddress::Ipv6(_)) => 0,
+            (None, IpAddress::Ipv4(_)) => return ax_err!(InvalidInput, "gateway unreachable"),
+        };
+        let route = RouteEntry {
+            cidr,
+            gateway: Some(gateway),
+            dev,
+            src: None,
+        };
+        self.0.push(route);
+        Ok(route)
+    }
+
+    /// Removes the route to `cidr` added with a gateway, and returns it.
+    fn remove(&mut self, cidr: IpCidr) -> AxResult<RouteEntry> {
+        let Some(index) = self.0.iter().position(|r| r.cidr == cidr) else {
+            return ax_err!(NotFound, "no such route");
+        };
+        if self.0[index].gateway.is_none() {
+            return ax_err!(InvalidInput, "cannot remove directly connected networks");
+        }
+        Ok(self.0.remove(index))
+    }
+
+    /// Returns all routes.
+    fn routes(&self) -> &[RouteEntry] {
+        &self.0
+    }
+}
+
+/// Adds the route with a gateway to the smoltcp interface of its egress NIC.
+fn install(route: &RouteEntry) -> AxResult {
+    let Some(gateway) = route.gateway else {
+        return Ok(());
+    };
+    let mut res = Ok(());
+    IFACES[route.dev]
+        .iface
+        .lock()
+        .routes_mut()
+        .update(|storage| {
+            storage.retain(|r| r.cidr != route.cidr);
+            res = storage.push(Route {
+                cidr: route.cidr,
+                via_router: gateway,
+                preferred_until: None,
+                expires_at: None,
+            });
+        });
+    res.map_err(|_| ax_err_type!(NoMemory, "too many routes"))
+}
+
+/// Removes the route from the smoltcp interface of its egress NIC.
+fn uninstall(route: &RouteEntry) {
+    IFACES[route.dev]
+        .iface
+        .lock()
+        .routes_mut()
+        .update(|storage| storage.retain(|r| r.cidr != route.cidr));
+}
+
+/// Returns the index of the NIC to send packets to `addr`.
+pub fn egress_dev(addr: &IpAddress) -> usize {
+    ROUTES.lock().egress_dev(addr)
+}
+
+/// Returns our address on the NIC to send packets to `addr`, or `None` if
+/// the NIC has no address of the same version.
+pub fn source_addr(addr: &IpAddress) -> Option<IpAddress> {
+    ROUTES.lock().source_addr(addr)
+}
+
+/// Returns the IPv4 address assigned to the NIC.
+#[allow(dead_code)]
+pub fn ipv4_addr(dev: usize) -> Option<Ipv4Cidr> {
+    ROUTES.lock().ipv4_addr(dev)
+}
+
+/// Replaces the directly connected IPv4 network of the NIC, after `addr` is
+/// assigned to it.
+pub fn set_connected(dev: usize, addr: Ipv4Cidr) {
+    ROUTES.lock().set_connected(dev, addr);
+}
+
+/// Replaces the default route of the IP version, or removes it if `gateway`
+/// is `None`.
+///
+/// The smoltcp interfaces must not be locked by the caller.
+pub fn set_default(version: IpVersion, gateway: Option<IpAddress>) {
+    let mut routes = ROUTES.lock();
+    let (old, new) = routes.set_default(version, gateway);
+    if let Some(old) = old {
+        uninstall(&old);
+    }
+    if let Some(new) = new {
+        install(&new).ok();
+    }
+}
+
+/// Adds a route to `cidr` via `gateway`.
+///
+/// The egress NIC is the one directly connected to `gateway`. For IPv6, it's
+/// always the first NIC.
+pub fn add(cidr: IpCidr, gateway: IpAddress) -> AxResult {
+    let mut routes = ROUTES.lock();
+    let route = routes.add(cidr, gateway)?;
+    install(&route).inspect_err(|_| {
+        routes.remove(cidr).ok();
+    })
+}
+
+/// Removes the route to `cidr` added with a gateway.
+pub fn remove(cidr: IpCidr) -> AxResult {
+    let route = ROUTES.lock().remove(cidr)?;
+    uninstall(&route);
+    Ok(())
+}
+
+/// Returns all routes.
+pub fn routes() -> Vec<RouteEntry> {
+    ROUTES.lock().routes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axerrno::AxError;
+    use smoltcp::wire::{IpVersion, Ipv4Address};
+
+    fn cidr(addr: [u8; 4], prefix_len: u8) -> IpCidr {
+        IpCidr::new(
+            IpAddress::v4(addr[0], addr[1], addr[2], addr[3]),
+            prefix_len,
+        )
+    }
+
+    fn ip(addr: [u8; 4]) -> IpAddress {
+        IpAddress::v4(addr[0], addr[1], addr[2], addr[3])
+    }
+
+    /// `eth0` on `10.0.0.2/24`, and `eth1` on `192.168.1.2/24`.
+    fn two_nics() -> RouteTable {
+        let mut table = RouteTable::new();
+        table.set_connected(0, Ipv4Cidr::new(Ipv4Address::new(10, 0, 0, 2), 24));
+        table.set_connected(1, Ipv4Cidr::new(Ipv4Address::new(192, 168, 1, 2), 24));
+        table
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut table = two_nics();
+        table
+            .add(cidr([172, 16, 0, 0], 12), ip([10, 0, 0, 1]))
+            .unwrap();
+        table
+            .add(cidr([172, 16, 1, 0], 24), ip([192, 168, 1, 1]))
+            .unwrap();
+
+        assert_eq!(table.egress_dev(&ip([10, 0, 0, 9])), 0);
+        assert_eq!(table.egress_dev(&ip([192, 168, 1, 9])), 1);
+        assert_eq!(table.egress_dev(&ip([172, 16, 2, 1])), 0);
+        assert_eq!(table.egress_dev(&ip([172, 16, 1, 1])), 1);
+        let route = lookup_in(table.routes().iter(), &ip([172, 16, 1, 1])).unwrap();
+        assert_eq!(route.gateway, Some(ip([192, 168, 1, 1])));
+
+        assert_eq!(
+            table.source_addr(&ip([172, 16, 1, 1])),
+            Some(ip([192, 168, 1, 2]))
+        );
+        assert_eq!(
+            table.source_addr(&ip([172, 16, 2, 1])),
+            Some(ip([10, 0, 0, 2]))
+        );
+    }
+
+    #[test]
+    fn test_prefix_tie() {
+        // two NICs on the same network, the earliest route wins
+        let mut table = two_nics();
+        table.set_connected(2, Ipv4Cidr::new(Ipv4Address::new(10, 0, 0, 3), 24));
+        assert_eq!(table.egress_dev(&ip([10, 0, 0, 9])), 0);
+        let route = connected(table.routes(), &ip([10, 0, 0, 9])).unwrap();
+        assert_eq!(route.src, Some(ip([10, 0, 0, 2])));
+
+        // the gateway is connected to both, and the first NIC is picked
+        let route = table
+            .add(cidr([172, 16, 0, 0], 12), ip([10, 0, 0, 1]))
+            .unwrap();
+        assert_eq!(route.dev, 0);
+
+        // the same for the gateway routes
+        let mut routes = table.routes().to_vec();
+        routes.push(RouteEntry {
+            cidr: cidr([172, 16, 0, 0], 12),
+            gateway: Some(ip([192, 168, 1, 1])),
+            dev: 1,
+            src: None,
+        });
+        assert_eq!(
+            lookup_in(routes.iter(), &ip([172, 16, 0, 1])).unwrap().dev,
+            0
+        );
+    }
+
+    #[test]
+    fn test_default_route() {
+        let mut table = two_nics();
+        // no match, the first NIC is used
+        assert_eq!(table.egress_dev(&ip([8, 8, 8, 8])), 0);
+
+        let (old, new) = table.set_default(IpVersion::Ipv4, Some(ip([192, 168, 1, 1])));
+        assert_eq!(old, None);
+        let new = new.unwrap();
+        assert_eq!(new.cidr, cidr([0, 0, 0, 0], 0));
+        assert_eq!(new.dev, 1);
+        assert_eq!(table.egress_dev(&ip([8, 8, 8, 8])), 1);
+        assert_eq!(
+            table.source_addr(&ip([8, 8, 8, 8])),
+            Some(ip([192, 168, 1, 2]))
+        );
+        // more specific routes are still preferred
+        assert_eq!(table.egress_dev(&ip([10, 0, 0, 9])), 0);
+
+        // replaced
+        let (old, new) = table.set_default(IpVersion::Ipv4, Some(ip([10, 0, 0, 1])));
+        assert_eq!(old.unwrap().dev, 1);
+        assert_eq!(new.unwrap().dev, 0);
+        assert_eq!(table.egress_dev(&ip([8, 8, 8, 8])), 0);
+        assert_eq!(
+            table.add(cidr([0, 0, 0, 0], 0), ip([10, 0, 0, 1])).err(),
+            Some(AxError::AlreadyExists)
+        );
+
+        // removed
+        let (old, new) = table.set_default(IpVersion::Ipv4, None);
+        assert_eq!(old.unwrap().gateway, Some(ip([10, 0, 0, 1])));
+        assert_eq!(new, None);
+        assert_eq!(table.routes().len(), 2);
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut table = two_nics();
+        // the gateway is not on a connected network
+        assert_eq!(
+            table
+                .add(cidr([172, 16, 0, 0], 12), ip([10, 0, 1, 1]))
+                .err(),
+            Some(AxError::InvalidInput)
+        );
+        // invalid gateways
+        assert_eq!(
+            table
+                .add(cidr([172, 16, 0, 0], 12), ip([224, 0, 0, 1]))
+                .err(),
+            Some(AxError::InvalidInput)
+        );
+        assert_eq!(
+            table
+                .add(
+                    cidr([172, 16, 0, 0], 12),
+                    IpAddress::v6(0xfe80, 0, 0, 0, 0, 0, 0, 1)
+                )
+                .err(),
+            Some(AxError::InvalidInput)
+        );
+        assert_eq!(table.routes().len(), 2);
+
+        let route = table
+            .add(cidr([172, 16, 0, 0], 12), ip([192, 168, 1, 1]))
+            .unwrap();
+        assert_eq!(route.dev, 1);
+        assert_eq!(
+            table
+                .add(cidr([172, 16, 0, 0], 12), ip([10, 0, 0, 1]))
+                .err(),
+            Some(AxError::AlreadyExists)
+        );
+        // IPv6 gateways are on the first NIC
+        let v6_cidr = IpCidr::new(IpAddress::v6(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0), 32);
+        let v6_gateway = IpAddress::v6(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        assert_eq!(table.add(v6_cidr, v6_gateway).unwrap().dev, 0);
+
+        assert_eq!(table.remove(cidr([172, 16, 0, 0], 12)), Ok(route));
+        assert_eq!(
+            table.remove(cidr([172, 16, 0, 0], 12)).err(),
+            Some(AxError::NotFound)
+        );
+        assert_eq!(
+            table.remove(cidr([10, 0, 0, 0], 24)).err(),
+            Some(AxError::InvalidInput)
+        );
+        assert_eq!(table.remove(v6_cidr).unwrap().gateway, Some(v6_gateway));
+        assert_eq!(table.routes().len(), 2);
+    }
+}
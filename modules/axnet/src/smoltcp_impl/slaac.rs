@@ -0,0 +1,317 @@
+//! IPv6 stateless address autoconfiguration (SLAAC, [RFC 4862]).
+//!
+//! Router advertisements are snooped from the incoming packets, and the
+//! advertised prefix is combined with the EUI-64 interface identifier to form
+//! a global address. Each interface keeps its own [`Slaac`] state, but only
+//! the first NIC is configured by SLAAC.
+//!
+//! [RFC 4862]: https://datatracker.ietf.org/doc/html/rfc4862
+
+use alloc::{vec, vec::Vec};
+
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::socket::raw;
+use smoltcp::wire::{
+    EthernetAddress, Icmpv6Packet, Icmpv6Repr, IpAddress, IpCidr, IpProtocol, IpVersion,
+    Ipv6Address, Ipv6Cidr, Ipv6Packet, Ipv6Repr, NdiscPrefixInfoFlags, NdiscRepr,
+};
+use spin::Mutex;
+
+use super::{route, InterfaceWrapper, SOCKET_SET};
+
+/// The prefix length of addresses generated by SLAAC.
+const SLAAC_PREFIX_LEN: u8 = 64;
+
+/// The SLAAC state of an interface.
+pub struct Slaac {
+    /// The configuration learned from the last router advertisement, which
+    /// has not been applied to the interface.
+    pending: Mutex<Option<RouterAdvert>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct RouterAdvert {
+    router: Ipv6Address,
+    router_alive: bool,
+    prefix: Option<Ipv6Address>,
+}
+
+/// Returns the address with the given 64-bit prefix and the EUI-64 interface
+/// identifier derived from the MAC address.
+pub fn eui64_addr(prefix: Ipv6Address, mac: EthernetAddress) -> Ipv6Address {
+    let mut addr = prefix.0;
+    addr[8..11].copy_from_slice(&mac.0[..3]);
+    addr[8] ^= 0x02; // flip the universal/local bit
+    addr[11] = 0xff;
+    addr[12] = 0xfe;
+    addr[13..].copy_from_slice(&mac.0[3..]);
+    Ipv6Address(addr)
+}
+
+/// Returns the link-local address of the interface.
+pub fn link_local_addr(mac: EthernetAddress) -> Ipv6Address {
+    eui64_addr(Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), mac)
+}
+
+impl Slaac {
+    pub const fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// Checks whether the packet is a router advertisement, and records it
+    /// to be applied by [`update_iface`].
+    pub fn snoop_router_advert(
+        &self,
+        ipv6_packet: &Ipv6Packet<&[u8]>,
+    ) -> Result<(), smoltcp::wire::Error> {
+        if let Some(advert) = parse_router_advert(ipv6_packet)? {
+            *self.pending.lock() = Some(advert);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the router advertisement in the packet, with the prefix that can
+/// be used by SLAAC if any.
+fn parse_router_advert(
+    ipv6_packet: &Ipv6Packet<&[u8]>,
+) -> Result<Option<RouterAdvert>, smoltcp::wire::Error> {
+    let src_addr = ipv6_packet.src_addr();
+    // RFC 4861, section 6.1.2: validate the router advertisement.
+    if ipv6_packet.hop_limit() != 255 || !src_addr.is_link_local() {
+        return Ok(None);
+    }
+    let icmp_packet = Icmpv6Packet::new_checked(ipv6_packet.payload())?;
+    let NdiscRepr::RouterAdvert {
+        router_lifetime,
+        prefix_info,
+        ..
+    } = NdiscRepr::parse(&icmp_packet)?
+    else {
+        return Ok(None);
+    };
+    let prefix = prefix_info
+        .filter(|info| {
+            info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+                && info.prefix_len == SLAAC_PREFIX_LEN
+                && info.valid_lifetime.total_millis() > 0
+                && !info.prefix.is_link_local()
+        })
+        .map(|info| info.prefix);
+    Ok(Some(RouterAdvert {
+        router: src_addr,
+        router_alive: router_lifetime.total_millis() > 0,
+        prefix,
+    }))
+}
+
+/// Applies the address and the default route from the last router
+/// advertisement received by the interface of the first NIC.
+///
+/// The interface must not be locked by the caller.
+pub fn update_iface(iface: &InterfaceWrapper) {
+    let Some(advert) = iface.slaac.pending.lock().take() else {
+        return;
+    };
+    if let Some(prefix) = advert.prefix {
+        let mac = iface.ethernet_address();
+        let cidr = IpCidr::Ipv6(Ipv6Cidr::new(eui64_addr(prefix, mac), SLAAC_PREFIX_LEN));
+        let mut iface = iface.iface.lock();
+        if !iface.ip_addrs().contains(&cidr) {
+            iface.update_ip_addrs(|ip_addrs| match ip_addrs.push(cidr) {
+                Ok(_) => info!("SLAAC: add address {}", cidr),
+                Err(_) => warn!("SLAAC: too many addresses, ignore {}", cidr),
+            });
+        }
+    }
+    // TODO: expire addresses and routes by their lifetimes
+    if advert.router_alive {
+        route::set_default(IpVersion::Ipv6, Some(advert.router.into()));
+        debug!("SLAAC: default router {}", advert.router);
+    } else {
+        route::set_default(IpVersion::Ipv6, None);
+    }
+}
+
+/// Sends a router solicitation to all routers on the link, so that we don't
+/// need to wait for the periodic router advertisements.
+pub fn send_router_solicit(mac: EthernetAddress) {
+    let buf = ndisc_packet(
+        link_local_addr(mac),
+        Ipv6Address::LINK_LOCAL_ALL_ROUTERS,
+        255,
+        NdiscRepr::RouterSolicit { lladdr: None },
+    );
+    let rx_buffer = raw::PacketBuffer::new(vec![], vec![]);
+    let tx_buffer = raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY], vec![0; buf.len()]);
+    let mut socket = raw::Socket::new(IpVersion::Ipv6, IpProtocol::Icmpv6, rx_buffer, tx_buffer);
+    if socket.send_slice(&buf).is_ok() {
+        let handle = SOCKET_SET.add(0, socket);
+        SOCKET_SET.poll_interfaces();
+        SOCKET_SET.remove(handle);
+    }
+}
+
+/// Builds an IPv6 packet carrying the neighbor discovery message.
+fn ndisc_packet(
+    src_addr: Ipv6Address,
+    dst_addr: Ipv6Address,
+    hop_limit: u8,
+    ndisc_repr: NdiscRepr,
+) -> Vec<u8> {
+    let icmp_repr = Icmpv6Repr::Ndisc(ndisc_repr);
+    let ip_repr = Ipv6Repr {
+        src_addr,
+        dst_addr,
+        next_header: IpProtocol::Icmpv6,
+        payload_len: icmp_repr.buffer_len(),
+        hop_limit,
+    };
+
+    let mut buf = vec![0; ip_repr.buffer_len() + icmp_repr.buffer_len()];
+    let mut ip_packet = Ipv6Packet::new_unchecked(&mut buf[..]);
+    ip_repr.emit(&mut ip_packet);
+    icmp_repr.emit(
+        &IpAddress::Ipv6(src_addr),
+        &IpAddress::Ipv6(dst_addr),
+        &mut Icmpv6Packet::new_unchecked(ip_packet.payload_mut()),
+        &ChecksumCapabilities::default(),
+    );
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smoltcp::time::Duration;
+    use smoltcp::wire::{NdiscPrefixInformation, NdiscRouterFlags};
+
+    const MAC: EthernetAddress = EthernetAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+    const ROUTER: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+    const PREFIX: Ipv6Address = Ipv6Address::new(0x2001, 0xdb8, 1, 0, 0, 0, 0, 0);
+
+    fn prefix_info(prefix: Ipv6Address, prefix_len: u8) -> NdiscPrefixInformation {
+        NdiscPrefixInformation {
+            prefix_len,
+            flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+            valid_lifetime: Duration::from_secs(86400),
+            preferred_lifetime: Duration::from_secs(14400),
+            prefix,
+        }
+    }
+
+    fn router_advert(
+        src_addr: Ipv6Address,
+        hop_limit: u8,
+        router_lifetime: Duration,
+        prefix_info: Option<NdiscPrefixInformation>,
+    ) -> Vec<u8> {
+        let ndisc_repr = NdiscRepr::RouterAdvert {
+            hop_limit: 64,
+            flags: NdiscRouterFlags::empty(),
+            router_lifetime,
+            reachable_time: Duration::ZERO,
+            retrans_time: Duration::ZERO,
+            lladdr: None,
+            mtu: None,
+            prefix_info,
+        };
+        let dst_addr = Ipv6Address::LINK_LOCAL_ALL_NODES;
+        ndisc_packet(src_addr, dst_addr, hop_limit, ndisc_repr)
+    }
+
+    fn parse(buf: &[u8]) -> Option<RouterAdvert> {
+        parse_router_advert(&Ipv6Packet::new_checked(buf).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_eui64_addr() {
+        assert_eq!(
+            eui64_addr(PREFIX, MAC),
+            Ipv6Address::new(0x2001, 0xdb8, 1, 0, 0x5054, 0x00ff, 0xfe12, 0x3456)
+        );
+        assert_eq!(
+            link_local_addr(MAC),
+            Ipv6Address::new(0xfe80, 0, 0, 0, 0x5054, 0x00ff, 0xfe12, 0x3456)
+        );
+        // the universal/local bit is flipped, not set
+        let mac = EthernetAddress([0x02, 0, 0, 0, 0, 1]);
+        assert_eq!(
+            link_local_addr(mac),
+            Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0x00ff, 0xfe00, 0x0001)
+        );
+    }
+
+    #[test]
+    fn test_router_advert_prefix() {
+        let lifetime = Duration::from_secs(1800);
+        let buf = router_advert(ROUTER, 255, lifetime, Some(prefix_info(PREFIX, 64)));
+        assert_eq!(
+            parse(&buf),
+            Some(RouterAdvert {
+                router: ROUTER,
+                router_alive: true,
+                prefix: Some(PREFIX),
+            })
+        );
+
+        // prefixes that cannot be used by SLAAC
+        let mut no_addrconf = prefix_info(PREFIX, 64);
+        no_addrconf.flags = NdiscPrefixInfoFlags::ON_LINK;
+        let mut expired = prefix_info(PREFIX, 64);
+        expired.valid_lifetime = Duration::ZERO;
+        let link_local = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0);
+        for info in [
+            no_addrconf,
+            expired,
+            prefix_info(PREFIX, 48),
+            prefix_info(link_local, 64),
+        ] {
+            let advert = parse(&router_advert(ROUTER, 255, lifetime, Some(info))).unwrap();
+            assert_eq!(advert.prefix, None);
+            assert!(advert.router_alive);
+        }
+
+        // the router is no longer a default router
+        let advert = parse(&router_advert(ROUTER, 255, Duration::ZERO, None)).unwrap();
+        assert_eq!(advert.prefix, None);
+        assert!(!advert.router_alive);
+    }
+
+    #[test]
+    fn test_invalid_router_advert() {
+        let lifetime = Duration::from_secs(1800);
+        let info = Some(prefix_info(PREFIX, 64));
+        // forwarded by a router
+        assert_eq!(parse(&router_advert(ROUTER, 64, lifetime, info)), None);
+        // not from a link-local address
+        let global = Ipv6Address::new(0x2001, 0xdb8, 1, 0, 0, 0, 0, 1);
+        assert_eq!(parse(&router_advert(global, 255, lifetime, info)), None);
+        // not a router advertisement
+        let buf = ndisc_packet(
+            link_local_addr(MAC),
+            Ipv6Address::LINK_LOCAL_ALL_ROUTERS,
+            255,
+            NdiscRepr::RouterSolicit { lladdr: None },
+        );
+        assert_eq!(parse(&buf), None);
+    }
+
+    #[test]
+    fn test_per_interface_state() {
+        let (eth0, eth1) = (Slaac::new(), Slaac::new());
+        let buf = router_advert(
+            ROUTER,
+            255,
+            Duration::from_secs(1800),
+            Some(prefix_info(PREFIX, 64)),
+        );
+        eth0.snoop_router_advert(&Ipv6Packet::new_checked(&buf[..]).unwrap())
+            .unwrap();
+        assert_eq!(eth0.pending.lock().take().unwrap().prefix, Some(PREFIX));
+        assert!(eth0.pending.lock().is_none());
+        assert!(eth1.pending.lock().is_none());
+    }
+}
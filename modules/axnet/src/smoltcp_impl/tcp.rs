@@ -1,17 +1,21 @@
 use core::cell::UnsafeCell;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axio::PollState;
 use axsync::Mutex;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::tcp::{self, ConnectError, State};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{from_core_sockaddr, into_core_sockaddr, is_unspecified, UNSPECIFIED_ENDPOINT};
-use super::{SocketSetWrapper, ETH0, LISTEN_TABLE, SOCKET_SET};
+use super::wait::{poller_running, SocketWaiter};
+use super::{loopback, route};
+use super::{SocketHandle, SocketSetWrapper, IFACES, LISTEN_TABLE, SOCKET_SET};
+use super::{TCP_MAX_BUF_LEN, TCP_MIN_BUF_LEN, TCP_RX_BUF_LEN, TCP_TX_BUF_LEN};
 
 // State transitions:
 // CLOSED -(connect)-> BUSY -> CONNECTING -> CONNECTED -(shutdown)-> BUSY -> CLOSED
@@ -25,6 +29,53 @@ const STATE_CONNECTING: u8 = 2;
 const STATE_CONNECTED: u8 = 3;
 const STATE_LISTENING: u8 = 4;
 
+/// How long a connection is idle before keep-alive packets are sent, if
+/// `SO_KEEPALIVE` is enabled.
+const KEEP_ALIVE_INTERVAL: smoltcp::time::Duration = smoltcp::time::Duration::from_secs(75);
+
+/// Socket options of a TCP socket.
+///
+/// The accepted sockets inherit the options of the listening socket.
+#[derive(Debug, Clone, Copy)]
+struct TcpOptions {
+    reuse_addr: bool,
+    recv_timeout: Option<Duration>,
+    send_timeout: Option<Duration>,
+    recv_buf_len: usize,
+    send_buf_len: usize,
+    keep_alive: bool,
+    nodelay: bool,
+    linger: Option<Duration>,
+}
+
+impl TcpOptions {
+    const fn new() -> Self {
+        Self {
+            reuse_addr: false,
+            recv_timeout: None,
+            send_timeout: None,
+            recv_buf_len: TCP_RX_BUF_LEN,
+            send_buf_len: TCP_TX_BUF_LEN,
+            keep_alive: false,
+            nodelay: false,
+            linger: None,
+        }
+    }
+
+    /// Creates a smoltcp socket with the options.
+    fn new_socket<'a>(&self) -> tcp::Socket<'a> {
+        let mut socket = SocketSetWrapper::new_tcp_socket(self.recv_buf_len, self.send_buf_len);
+        self.apply(&mut socket);
+        socket
+    }
+
+    /// Applies the options that can be changed on an existing smoltcp socket.
+    fn apply(&self, socket: &mut tcp::Socket) {
+        socket.set_nagle_enabled(!self.nodelay);
+        socket.set_keep_alive(self.keep_alive.then_some(KEEP_ALIVE_INTERVAL));
+    }
+}
+
 /// A TCP socket that provides POSIX-like APIs.
 ///
 /// - [`connect`] is for TCP clients.
@@ -41,6 +92,8 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    options: Mutex<TcpOptions>,
+    waiter: SocketWaiter,
 }
 
 unsafe impl Sync for TcpSocket {}
@@ -54,6 +107,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            options: Mutex::new(TcpOptions::new()),
+            waiter: SocketWaiter::new(),
         }
     }
 
@@ -62,6 +117,7 @@ impl TcpSocket {
         handle: SocketHandle,
         local_addr: IpEndpoint,
         peer_addr: IpEndpoint,
+        options: TcpOptions,
     ) -> Self {
         Self {
             state: AtomicU8::new(STATE_CONNECTED),
@@ -69,6 +125,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            options: Mutex::new(options),
+            waiter: SocketWaiter::new(),
         }
     }
 
@@ -120,14 +178,36 @@ impl TcpSocket {
     /// The local port is generated automatically.
     pub fn connect(&self, remote_addr: SocketAddr) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
-            // SAFETY: no other threads can read or write these fields.
-            let handle = unsafe { self.handle.get().read() }
-                .unwrap_or_else(|| SOCKET_SET.add(SocketSetWrapper::new_tcp_socket()));
-
             // TODO: check remote addr unreachable
             let remote_endpoint = from_core_sockaddr(remote_addr);
-            let bound_endpoint = self.bound_endpoint()?;
-            let iface = &ETH0.iface;
+            // loopback addresses are on the first interface
+            let iface = if loopback::is_loopback(&remote_endpoint.addr) {
+                0
+            } else {
+                route::egress_dev(&remote_endpoint.addr)
+            };
+            // SAFETY: no other threads can read or write these fields.
+            let handle = match unsafe { self.handle.get().read() } {
+                Some(handle) if handle.iface == iface => handle,
+                old => {
+                    // the socket is only polled by its interface, so move it
+                    if let Some(handle) = old {
+                        SOCKET_SET.remove(handle);
+                    }
+                    let handle = SOCKET_SET.add(iface, self.options.lock().new_socket());
+                    unsafe { self.handle.get().write(Some(handle)) };
+                    handle
+                }
+            };
+
+            let mut bound_endpoint = self.bound_endpoint()?;
+            if bound_endpoint.addr.is_none() {
+                // connect to loopback addresses from loopback addresses, and
+                // others from the address of the egress NIC
+                bound_endpoint.addr = loopback::source_addr(&remote_endpoint.addr)
+                    .or_else(|| route::source_addr(&remote_endpoint.addr));
+            }
+            let iface = &IFACES[iface].iface;
             let (local_endpoint, remote_endpoint) = SOCKET_SET
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                     socket
@@ -140,7 +220,7 @@ impl TcpSocket {
                                 ax_err!(ConnectionRefused, "socket connect() failed")
                             }
                         })?;
-                    Ok((
+                    Ok::<_, AxError>((
                         socket.local_endpoint().unwrap(),
                         socket.remote_endpoint().unwrap(),
                     ))
@@ -150,7 +230,6 @@ impl TcpSocket {
                 // have changed the state to `BUSY`.
                 self.local_addr.get().write(local_endpoint);
                 self.peer_addr.get().write(remote_endpoint);
-                self.handle.get().write(Some(handle));
             }
             Ok(())
         })
@@ -160,7 +239,7 @@ impl TcpSocket {
         if self.is_nonblocking() {
             Err(AxError::WouldBlock)
         } else {
-            self.block_on(|| {
+            self.block_on(None, || {
                 let PollState { writable, .. } = self.poll_connect()?;
                 if !writable {
                     Err(AxError::WouldBlock)
@@ -209,7 +288,8 @@ impl TcpSocket {
             unsafe {
                 (*self.local_addr.get()).port = bound_endpoint.port;
             }
-            LISTEN_TABLE.listen(bound_endpoint)?;
+            let options = self.options.lock();
+            LISTEN_TABLE.listen(bound_endpoint, (options.recv_buf_len, options.send_buf_len))?;
             debug!("TCP socket listening on {}", bound_endpoint);
             Ok(())
         })
@@ -229,29 +309,58 @@ impl TcpSocket {
 
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
-        self.block_on(|| {
+        let options = *self.options.lock();
+        self.block_on(options.recv_timeout, || {
             let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
             debug!("TCP socket accepted a new connection {}", peer_addr);
-            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                options.apply(socket);
+            });
+            Ok(TcpSocket::new_connected(
+                handle, local_addr, peer_addr, options,
+            ))
         })
     }
 
     /// Close the connection.
+    ///
+    /// If `SO_LINGER` is enabled, the connection is reset if the linger time
+    /// is zero, otherwise it blocks until the queued data is sent or the
+    /// linger time expires.
     pub fn shutdown(&self) -> AxResult {
         // stream
-        self.update_state(STATE_CONNECTED, STATE_CLOSED, || {
+        let linger = self.options.lock().linger;
+        if let Ok(res) = self.update_state(STATE_CONNECTED, STATE_CLOSED, || {
             // SAFETY: `self.handle` should be initialized in a connected socket, and
             // no other threads can read or write it.
             let handle = unsafe { self.handle.get().read().unwrap() };
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 debug!("TCP socket {}: shutting down", handle);
-                socket.close();
+                if linger == Some(Duration::ZERO) {
+                    socket.abort();
+                } else {
+                    socket.close();
+                }
             });
             unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
             SOCKET_SET.poll_interfaces();
-            Ok(())
-        })
-        .unwrap_or(Ok(()))?;
+            Ok(handle)
+        }) {
+            let handle = res?;
+            if let Some(linger) = linger.filter(|linger| !linger.is_zero()) {
+                // timeouts are not errors
+                self.block_on(Some(linger), || {
+                    SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
+                        if socket.send_queue() == 0 || !socket.is_active() {
+                            Ok(())
+                        } else {
+                            Err(AxError::WouldBlock)
+                        }
+                    })
+                })
+                .ok();
+            }
+        }
 
         // listener
         self.update_state(STATE_LISTENING, STATE_CLOSED, || {
@@ -279,7 +388,8 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        let timeout = self.options.lock().recv_timeout;
+        self.block_on(timeout, || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 if !socket.is_active() {
                     // not open
@@ -312,7 +422,8 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        let timeout = self.options.lock().send_timeout;
+        self.block_on(timeout, || {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
                 if !socket.is_active() || !socket.may_send() {
                     // closed by remote
@@ -332,6 +443,18 @@ impl TcpSocket {
         })
     }
 
+    /// Registers the waker to be woken up once the result of [`poll`] may
+    /// change, e.g., for `epoll`.
+    ///
+    /// Returns `false` if the waker will never be woken up, as the interfaces
+    /// are busy polled, or the socket is neither connected nor listening. The
+    /// caller should keep polling the socket instead.
+    ///
+    /// [`poll`]: Self::poll
+    pub fn register_poll_waker(&self, waker: &Waker) -> bool {
+        poller_running() && self.register_waker(waker)
+    }
+
     /// Whether the socket is readable or writable.
     pub fn poll(&self) -> AxResult<PollState> {
         match self.get_state() {
@@ -341,9 +464,117 @@ impl TcpSocket {
             _ => Ok(PollState {
                 readable: false,
                 writable: false,
+                hangup: false,
             }),
         }
     }
+
+    /// Returns the value of the `SO_REUSEADDR` option.
+    pub fn reuse_address(&self) -> bool {
+        self.options.lock().reuse_addr
+    }
+
+    /// Sets the value of the `SO_REUSEADDR` option.
+    ///
+    /// Ports are released as soon as the listening sockets are closed, so it
+    /// has no other effects.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.options.lock().reuse_addr = reuse;
+    }
+
+    /// Returns the timeout of receiving and accepting (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        self.options.lock().recv_timeout
+    }
+
+    /// Sets the timeout of receiving and accepting (`SO_RCVTIMEO`).
+    ///
+    /// If the operation times out, it fails with
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). `None` means blocking
+    /// indefinitely, and a zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().recv_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of sending (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        self.options.lock().send_timeout
+    }
+
+    /// Sets the timeout of sending (`SO_SNDTIMEO`).
+    ///
+    /// The same as [`set_recv_timeout`](Self::set_recv_timeout) otherwise.
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.options.lock().send_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> usize {
+        self.options.lock().recv_buf_len
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`), clamped to
+    /// 4 KiB..=4 MiB.
+    ///
+    /// The buffers of an existing connection can't be resized, so it takes
+    /// effect on the next [`connect`](Self::connect) or
+    /// [`listen`](Self::listen).
+    pub fn set_recv_buffer_size(&self, size: usize) {
+        self.options.lock().recv_buf_len = size.clamp(TCP_MIN_BUF_LEN, TCP_MAX_BUF_LEN);
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> usize {
+        self.options.lock().send_buf_len
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`).
+    ///
+    /// The same as [`set_recv_buffer_size`](Self::set_recv_buffer_size)
+    /// otherwise.
+    pub fn set_send_buffer_size(&self, size: usize) {
+        self.options.lock().send_buf_len = size.clamp(TCP_MIN_BUF_LEN, TCP_MAX_BUF_LEN);
+    }
+
+    /// Returns whether keep-alive packets are sent (`SO_KEEPALIVE`).
+    pub fn keep_alive(&self) -> bool {
+        self.options.lock().keep_alive
+    }
+
+    /// Sets whether to send keep-alive packets on idle connections
+    /// (`SO_KEEPALIVE`).
+    pub fn set_keep_alive(&self, keep_alive: bool) {
+        self.update_options(|options| options.keep_alive = keep_alive);
+    }
+
+    /// Returns whether the Nagle algorithm is disabled (`TCP_NODELAY`).
+    pub fn nodelay(&self) -> bool {
+        self.options.lock().nodelay
+    }
+
+    /// Sets whether to disable the Nagle algorithm (`TCP_NODELAY`), i.e.,
+    /// to send small segments as soon as possible.
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.update_options(|options| options.nodelay = nodelay);
+    }
+
+    /// Returns the linger time on [`shutdown`](Self::shutdown) (`SO_LINGER`).
+    pub fn linger(&self) -> Option<Duration> {
+        self.options.lock().linger
+    }
+
+    /// Sets the linger time on [`shutdown`](Self::shutdown) (`SO_LINGER`).
+    pub fn set_linger(&self, linger: Option<Duration>) {
+        self.options.lock().linger = linger;
+    }
 }
 
 /// Private methods
@@ -446,6 +677,7 @@ impl TcpSocket {
         Ok(PollState {
             readable: false,
             writable,
+            hangup: false,
         })
     }
 
@@ -456,6 +688,7 @@ impl TcpSocket {
             Ok(PollState {
                 readable: !socket.may_recv() || socket.can_recv(),
                 writable: !socket.may_send() || socket.can_send(),
+                hangup: !socket.may_recv() && !socket.may_send(),
             })
         })
     }
@@ -466,29 +699,61 @@ impl TcpSocket {
         Ok(PollState {
             readable: LISTEN_TABLE.can_accept(local_addr.port)?,
             writable: false,
+            hangup: false,
         })
     }
 
-    /// Block the current thread until the given function completes or fails.
+    /// Registers the waker to be woken up when the socket becomes ready.
+    ///
+    /// Returns `false` if there is nothing to register to.
+    fn register_waker(&self, waker: &Waker) -> bool {
+        if self.is_listening() {
+            // SAFETY: `self.local_addr` should be initialized in a listening socket.
+            let local_port = unsafe { self.local_addr.get().read().port };
+            LISTEN_TABLE.register_waker(local_port, waker);
+        } else if let Some(handle) = unsafe { self.handle.get().read() } {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            });
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Block the current thread until the given function completes or fails,
+    /// or the timeout expires.
     ///
     /// If the socket is non-blocking, it calls the function once and returns
     /// immediately. Otherwise, it may call the function multiple times if it
     /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
         if self.is_nonblocking() {
             f()
         } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
-            }
+            self.waiter.block_on(
+                |waker| {
+                    self.register_waker(waker);
+                },
+                timeout,
+                f,
+            )
+        }
+    }
+
+    /// Updates the options, and applies them to the smoltcp socket if it
+    /// exists.
+    fn update_options<F: FnOnce(&mut TcpOptions)>(&self, f: F) {
+        let mut options = self.options.lock();
+        f(&mut options);
+        if let Some(handle) = unsafe { self.handle.get().read() } {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                options.apply(socket);
+            });
         }
     }
 }
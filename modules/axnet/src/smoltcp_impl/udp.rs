@@ -1,37 +1,53 @@
+use alloc::vec::Vec;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axio::PollState;
 use axsync::Mutex;
 use spin::RwLock;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::udp::{self, BindError, SendError};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{from_core_sockaddr, into_core_sockaddr, is_unspecified, UNSPECIFIED_ENDPOINT};
-use super::{SocketSetWrapper, SOCKET_SET};
+use super::wait::{poller_running, SocketWaiter};
+use super::{loopback, route};
+use super::{SocketHandle, SocketSetWrapper, SOCKET_SET, UDP_RX_BUF_LEN, UDP_TX_BUF_LEN};
 
 /// A UDP socket that provides POSIX-like APIs.
 pub struct UdpSocket {
-    handle: SocketHandle,
+    /// One smoltcp socket on each interface, all bound to the same endpoint.
+    /// Datagrams are sent through the one routing them, and received from
+    /// any of them.
+    handles: Vec<SocketHandle>,
     local_addr: RwLock<Option<IpEndpoint>>,
     peer_addr: RwLock<Option<IpEndpoint>>,
     nonblock: AtomicBool,
+    reuse_addr: AtomicBool,
+    recv_timeout: RwLock<Option<Duration>>,
+    send_timeout: RwLock<Option<Duration>>,
+    waiter: SocketWaiter,
 }
 
 impl UdpSocket {
     /// Creates a new UDP socket.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_udp_socket();
-        let handle = SOCKET_SET.add(socket);
+        let handles = (0..SOCKET_SET.iface_count())
+            .map(|iface| SOCKET_SET.add(iface, SocketSetWrapper::new_udp_socket()))
+            .collect();
         Self {
-            handle,
+            handles,
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
             nonblock: AtomicBool::new(false),
+            reuse_addr: AtomicBool::new(false),
+            recv_timeout: RwLock::new(None),
+            send_timeout: RwLock::new(None),
+            waiter: SocketWaiter::new(),
         }
     }
 
@@ -88,15 +104,17 @@ impl UdpSocket {
             addr: (!is_unspecified(local_endpoint.addr)).then_some(local_endpoint.addr),
             port: local_endpoint.port,
         };
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.bind(endpoint).or_else(|e| match e {
-                BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
-                BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
-            })
-        })?;
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.bind(endpoint).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            })?;
+        }
 
         *self_local_addr = Some(local_endpoint);
-        debug!("UDP socket {}: bound on {}", self.handle, endpoint);
+        debug!("UDP socket {}: bound on {}", self.handles[0], endpoint);
         Ok(())
     }
 
@@ -142,7 +160,7 @@ impl UdpSocket {
         }
 
         *self_peer_addr = Some(from_core_sockaddr(addr));
-        debug!("UDP socket {}: connected to {}", self.handle, addr);
+        debug!("UDP socket {}: connected to {}", self.handles[0], addr);
         Ok(())
     }
 
@@ -172,28 +190,105 @@ impl UdpSocket {
 
     /// Close the socket.
     pub fn shutdown(&self) -> AxResult {
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            debug!("UDP socket {}: shutting down", self.handle);
-            socket.close();
-        });
+        debug!("UDP socket {}: shutting down", self.handles[0]);
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| socket.close());
+        }
         SOCKET_SET.poll_interfaces();
         Ok(())
     }
 
+    /// Registers the waker to be woken up once the result of [`poll`] may
+    /// change, e.g., for `epoll`.
+    ///
+    /// Returns `false` if the waker will never be woken up, as the interfaces
+    /// are busy polled, or the socket is not bound. The caller should keep
+    /// polling the socket instead.
+    ///
+    /// [`poll`]: Self::poll
+    pub fn register_poll_waker(&self, waker: &Waker) -> bool {
+        if !poller_running() || self.local_addr.read().is_none() {
+            return false;
+        }
+        self.register_waker(waker);
+        true
+    }
+
     /// Whether the socket is readable or writable.
     pub fn poll(&self) -> AxResult<PollState> {
         if self.local_addr.read().is_none() {
             return Ok(PollState {
                 readable: false,
                 writable: false,
+                hangup: false,
             });
         }
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            Ok(PollState {
-                readable: socket.can_recv(),
-                writable: socket.can_send(),
-            })
-        })
+        let mut state = PollState {
+            readable: false,
+            writable: false,
+            hangup: false,
+        };
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket::<udp::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable |= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+
+    /// Returns the value of the `SO_REUSEADDR` option.
+    pub fn reuse_address(&self) -> bool {
+        self.reuse_addr.load(Ordering::Acquire)
+    }
+
+    /// Sets the value of the `SO_REUSEADDR` option. It has no other effects.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.reuse_addr.store(reuse, Ordering::Release);
+    }
+
+    /// Returns the timeout of receiving (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        *self.recv_timeout.read()
+    }
+
+    /// Sets the timeout of receiving (`SO_RCVTIMEO`).
+    ///
+    /// If the operation times out, it fails with
+    /// [`Err(WouldBlock)`](AxError::WouldBlock). `None` means blocking
+    /// indefinitely, and a zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        *self.recv_timeout.write() = timeout;
+        Ok(())
+    }
+
+    /// Returns the timeout of sending (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        *self.send_timeout.read()
+    }
+
+    /// Sets the timeout of sending (`SO_SNDTIMEO`).
+    ///
+    /// The same as [`set_recv_timeout`](Self::set_recv_timeout) otherwise.
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        *self.send_timeout.write() = timeout;
+        Ok(())
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`), which is fixed.
+    pub fn recv_buffer_size(&self) -> usize {
+        UDP_RX_BUF_LEN
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`), which is fixed.
+    pub fn send_buffer_size(&self) -> usize {
+        UDP_TX_BUF_LEN
     }
 }
 
@@ -211,8 +306,15 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+        // loopback addresses are on the first interface
+        let iface = if loopback::is_loopback(&remote_endpoint.addr) {
+            0
+        } else {
+            route::egress_dev(&remote_endpoint.addr)
+        };
+        let timeout = *self.send_timeout.read();
+        self.block_on(timeout, || {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handles[iface], |socket| {
                 if socket.can_send() {
                     socket
                         .send_slice(buf, remote_endpoint)
@@ -239,34 +341,40 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_recv() {
+        let timeout = *self.recv_timeout.read();
+        self.block_on(timeout, || {
+            for &handle in &self.handles {
+                let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
                     // data available
-                    op(socket)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
+                    socket.can_recv().then(|| op(socket))
+                });
+                if let Some(res) = res {
+                    return res;
                 }
-            })
+            }
+            // no more data
+            Err(AxError::WouldBlock)
         })
     }
 
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    fn register_waker(&self, waker: &Waker) {
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
+                socket.register_send_waker(waker);
+            });
+        }
+    }
+
+    fn block_on<F, T>(&self, timeout: Option<Duration>, mut f: F) -> AxResult<T>
     where
         F: FnMut() -> AxResult<T>,
     {
         if self.is_nonblocking() {
             f()
         } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
-            }
+            self.waiter
+                .block_on(|waker| self.register_waker(waker), timeout, f)
         }
     }
 }
@@ -274,7 +382,9 @@ impl UdpSocket {
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
-        SOCKET_SET.remove(self.handle);
+        for &handle in &self.handles {
+            SOCKET_SET.remove(handle);
+        }
     }
 }
 
@@ -0,0 +1,268 @@
+//! Blocking operations on sockets.
+//!
+//! If all NICs support interrupts or there is no NIC (and the `busy-poll`
+//! feature is not enabled), a blocked task registers a waker to the smoltcp socket and sleeps
+//! on the per-socket wait queue. NIC interrupts wake up the `net-poll` task to
+//! poll the interfaces, and smoltcp invokes the wakers when the sockets become
+//! ready.
+//!
+//! Otherwise, the blocked task polls the interfaces and yields the CPU in a
+//! loop.
+//!
+//! In both cases, a blocking operation fails with
+//! [`WouldBlock`](AxError::WouldBlock) if it doesn't complete within the
+//! timeout, as the `SO_RCVTIMEO` and `SO_SNDTIMEO` socket options.
+
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult};
+
+use super::SOCKET_SET;
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "irq", feature = "multitask", not(feature = "busy-poll")))] {
+        use alloc::{string::ToString, sync::Arc, task::Wake};
+        use alloc::vec::Vec;
+        use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        use axtask::WaitQueue;
+
+        use super::IFACES;
+
+        /// Whether the `net-poll` task is running.
+        static POLLER_RUNNING: AtomicBool = AtomicBool::new(false);
+        /// Whether the interfaces need to be polled by the `net-poll` task.
+        static POLL_PENDING: AtomicBool = AtomicBool::new(false);
+        static POLL_WQ: WaitQueue = WaitQueue::new();
+        static NET_IRQ_NUMS: spin::Once<Vec<usize>> = spin::Once::new();
+
+        struct WaiterInner {
+            wq: WaitQueue,
+            /// Incremented on each wakeup. The blocked tasks compare it with
+            /// the value before they try, so no wakeup is lost even if other
+            /// tasks on the same socket are woken up in between.
+            generation: AtomicUsize,
+        }
+
+        impl Wake for WaiterInner {
+            fn wake(self: Arc<Self>) {
+                self.wake_by_ref();
+            }
+
+            fn wake_by_ref(self: &Arc<Self>) {
+                self.generation.fetch_add(1, Ordering::Release);
+                self.wq.notify_all(false);
+            }
+        }
+
+        /// The wait queue of a socket, on which blocked tasks sleep until the
+        /// socket becomes ready.
+        pub struct SocketWaiter(spin::Once<Arc<WaiterInner>>);
+
+        impl SocketWaiter {
+            pub const fn new() -> Self {
+                Self(spin::Once::new())
+            }
+
+            fn inner(&self) -> &Arc<WaiterInner> {
+                self.0.call_once(|| {
+                    Arc::new(WaiterInner {
+                        wq: WaitQueue::new(),
+                        generation: AtomicUsize::new(0),
+                    })
+                })
+            }
+
+            /// Blocks the current task until `f` completes or fails, or the
+            /// timeout expires.
+            ///
+            /// `register` is called before each try of `f`, which should
+            /// register the given waker to the smoltcp sockets that `f` is
+            /// waiting for.
+            pub fn block_on<R, F, T>(
+                &self,
+                register: R,
+                timeout: Option<Duration>,
+                f: F,
+            ) -> AxResult<T>
+            where
+                R: Fn(&Waker),
+                F: FnMut() -> AxResult<T>,
+            {
+                if !POLLER_RUNNING.load(Ordering::Acquire) {
+                    return busy_block_on(timeout, f);
+                }
+                let deadline = timeout.map(|timeout| axhal::time::current_time() + timeout);
+                let inner = self.inner();
+                let waker = Waker::from(inner.clone());
+                let mut f = f;
+                loop {
+                    let generation = inner.generation.load(Ordering::Acquire);
+                    register(&waker);
+                    SOCKET_SET.poll_interfaces();
+                    match f() {
+                        Ok(t) => {
+                            // let the `net-poll` task flush the queued packets
+                            kick_poller();
+                            return Ok(t);
+                        }
+                        Err(AxError::WouldBlock) => {
+                            let woken = || inner.generation.load(Ordering::Acquire) != generation;
+                            match deadline {
+                                Some(deadline) => {
+                                    let now = axhal::time::current_time();
+                                    if now >= deadline {
+                                        return Err(AxError::WouldBlock);
+                                    }
+                                    inner.wq.wait_timeout_until(deadline - now, woken);
+                                }
+                                None => inner.wq.wait_until(woken),
+                            }
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+
+        /// Whether the wakers registered to the sockets will be invoked, i.e.,
+        /// the interfaces are polled by the `net-poll` task.
+        pub fn poller_running() -> bool {
+            POLLER_RUNNING.load(Ordering::Acquire)
+        }
+
+        fn kick_poller() {
+            POLL_PENDING.store(true, Ordering::Release);
+            POLL_WQ.notify_one(false);
+        }
+
+        fn net_irq_handler() {
+            // Mask the IRQs until the `net-poll` task acknowledges the devices.
+            for &irq_num in NET_IRQ_NUMS.get().into_iter().flatten() {
+                axhal::irq::set_enable(irq_num, false);
+            }
+            kick_poller();
+        }
+
+        fn net_poll_task() {
+            loop {
+                let pending = || POLL_PENDING.swap(false, Ordering::AcqRel);
+                match SOCKET_SET.poll_delay() {
+                    Some(delay) => {
+                        let delay = Duration::from_micros(delay.total_micros());
+                        POLL_WQ.wait_timeout_until(delay, pending);
+                    }
+                    None => POLL_WQ.wait_until(pending),
+                }
+                if let Some(irq_nums) = NET_IRQ_NUMS.get() {
+                    for iface in IFACES.iter() {
+                        iface.ack_irq();
+                    }
+                    for &irq_num in irq_nums {
+                        axhal::irq::set_enable(irq_num, true);
+                    }
+                }
+                SOCKET_SET.poll_interfaces();
+            }
+        }
+
+        /// Registers the interrupt handler of the NICs and spawns the
+        /// `net-poll` task.
+        ///
+        /// If it fails for any NIC, blocked tasks fall back to busy polling.
+        /// Without a NIC, the task is spawned without interrupts, as the
+        /// looped-back packets are delivered when the interfaces are polled
+        /// after sending.
+        pub(super) fn init_poller() {
+            let irq_nums: Option<Vec<_>> = IFACES
+                .iter()
+                .filter(|iface| iface.has_nic())
+                .map(|iface| iface.irq_num())
+                .collect();
+            match irq_nums {
+                Some(irq_nums) if irq_nums.is_empty() => {}
+                Some(mut irq_nums) => {
+                    // NICs may share IRQs
+                    irq_nums.sort_unstable();
+                    irq_nums.dedup();
+                    for &irq_num in &irq_nums {
+                        if !axhal::irq::register_handler(irq_num, net_irq_handler) {
+                            warn!("  failed to register NIC IRQ {}, use busy polling", irq_num);
+                            return;
+                        }
+                    }
+                    info!("  use NIC IRQs {:?}", irq_nums);
+                    NET_IRQ_NUMS.call_once(|| irq_nums);
+                    for iface in IFACES.iter() {
+                        iface.set_irq_enabled(true);
+                    }
+                }
+                None => {
+                    info!("  some NIC has no IRQ, use busy polling");
+                    return;
+                }
+            }
+            axtask::spawn_raw(
+                net_poll_task,
+                "net-poll".to_string(),
+                axconfig::TASK_STACK_SIZE,
+            );
+            POLLER_RUNNING.store(true, Ordering::Release);
+        }
+    } else {
+        /// The wait queue of a socket. Tasks never sleep on it in the polling
+        /// mode.
+        pub struct SocketWaiter;
+
+        impl SocketWaiter {
+            pub const fn new() -> Self {
+                Self
+            }
+
+            /// Blocks the current task until `f` completes or fails, or the
+            /// timeout expires.
+            pub fn block_on<R, F, T>(
+                &self,
+                _register: R,
+                timeout: Option<Duration>,
+                f: F,
+            ) -> AxResult<T>
+            where
+                R: Fn(&Waker),
+                F: FnMut() -> AxResult<T>,
+            {
+                busy_block_on(timeout, f)
+            }
+        }
+
+        /// Whether the wakers registered to the sockets will be invoked.
+        pub fn poller_running() -> bool {
+            false
+        }
+
+        pub(super) fn init_poller() {}
+    }
+}
+
+/// Polls the interfaces and calls `f` in a loop, until `f` completes or fails,
+/// or the timeout expires.
+fn busy_block_on<F, T>(timeout: Option<Duration>, mut f: F) -> AxResult<T>
+where
+    F: FnMut() -> AxResult<T>,
+{
+    let deadline = timeout.map(|timeout| axhal::time::current_time() + timeout);
+    loop {
+        SOCKET_SET.poll_interfaces();
+        match f() {
+            Ok(t) => return Ok(t),
+            Err(AxError::WouldBlock)
+                if deadline.is_some_and(|deadline| axhal::time::current_time() >= deadline) =>
+            {
+                return Err(AxError::WouldBlock)
+            }
+            Err(AxError::WouldBlock) => axtask::yield_now(),
+            Err(e) => return Err(e),
+        }
+    }
+}
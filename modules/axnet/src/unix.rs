@@ -0,0 +1,915 @@
+//! Unix domain sockets.
+//!
+//! They are independent of the network stack: the connected sockets exchange
+//! data through in-memory buffers, and the names are kept in a global table.
+//! With the `fs` feature, binding to a path also creates a placeholder file at
+//! that path, so the name is visible in the filesystem and can't be bound
+//! again until the file is removed, as on Linux.
+//!
+//! With the `multitask` and `irq` features, blocked tasks register a waker to
+//! the buffers they are waiting for and sleep on the per-socket wait queue,
+//! until the peer wakes them up. Otherwise, they yield the CPU in a loop.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{ax_err, AxError, AxResult};
+use axio::PollState;
+use axsync::Mutex;
+use spin::RwLock;
+
+/// The buffer size of each direction of a stream connection.
+const UNIX_STREAM_BUF_LEN: usize = 64 * 1024;
+/// The maximum size of a datagram.
+const UNIX_DGRAM_MAX_LEN: usize = 64 * 1024;
+/// The maximum number of datagrams queued on a socket.
+const UNIX_DGRAM_QUEUE_LEN: usize = 64;
+/// The maximum number of pending connections of a listening socket.
+const UNIX_LISTEN_QUEUE_LEN: usize = 128;
+
+/// The address of a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixSocketAddr {
+    /// The socket is not bound to a name.
+    Unnamed,
+    /// A filesystem path.
+    Pathname(String),
+    /// A name in the abstract namespace, which is not visible in the
+    /// filesystem (`sun_path` starting with a null byte on Linux).
+    Abstract(Vec<u8>),
+}
+
+/// The socket that a name is bound to.
+enum Binding {
+    Stream(Weak<Mutex<Backlog>>),
+    Datagram(Weak<Mutex<DgramQueue>>),
+}
+
+/// The bound names, where the paths are absolute.
+static BINDINGS: Mutex<BTreeMap<UnixSocketAddr, Binding>> = Mutex::new(BTreeMap::new());
+
+/// Returns the key of `addr` in [`BINDINGS`].
+fn binding_key(addr: &UnixSocketAddr) -> AxResult<UnixSocketAddr> {
+    match addr {
+        UnixSocketAddr::Unnamed => ax_err!(InvalidInput, "unnamed unix socket address"),
+        #[cfg(feature = "fs")]
+        UnixSocketAddr::Pathname(path) => {
+            Ok(UnixSocketAddr::Pathname(axfs::api::canonicalize(path)?))
+        }
+        addr => Ok(addr.clone()),
+    }
+}
+
+/// Looks up the socket bound to `addr`.
+fn lookup(addr: &UnixSocketAddr) -> AxResult<Binding> {
+    let key = binding_key(addr)?;
+    #[cfg(feature = "fs")]
+    if let UnixSocketAddr::Pathname(path) = &key {
+        axfs::api::metadata(path)?;
+    }
+    match BINDINGS.lock().get(&key) {
+        Some(Binding::Stream(backlog)) => Ok(Binding::Stream(backlog.clone())),
+        Some(Binding::Datagram(queue)) => Ok(Binding::Datagram(queue.clone())),
+        None => ax_err!(ConnectionRefused, "no unix socket bound to the address"),
+    }
+}
+
+/// A name bound to a socket, which is released when dropped.
+struct BoundName(UnixSocketAddr);
+
+impl BoundName {
+    fn bind(addr: &UnixSocketAddr, binding: Binding) -> AxResult<Self> {
+        let key = binding_key(addr)?;
+        let mut bindings = BINDINGS.lock();
+        if bindings.contains_key(&key) {
+            return ax_err!(AddrInUse, "unix socket address in use");
+        }
+        #[cfg(feature = "fs")]
+        if let UnixSocketAddr::Pathname(path) = &key {
+            axfs::api::File::create_new(path).map_err(|e| match e {
+                AxError::AlreadyExists => AxError::AddrInUse,
+                e => e,
+            })?;
+        }
+        bindings.insert(key.clone(), binding);
+        Ok(Self(key))
+    }
+}
+
+impl Drop for BoundName {
+    fn drop(&mut self) {
+        // the placeholder file is kept, as on Linux
+        BINDINGS.lock().remove(&self.0);
+    }
+}
+
+#[derive(Default)]
+struct Wakers(Vec<Waker>);
+
+impl Wakers {
+    fn register(&mut self, waker: &Waker) {
+        if !self.0.iter().any(|w| w.will_wake(waker)) {
+            self.0.push(waker.clone());
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for waker in self.0.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "irq", feature = "multitask"))] {
+        use alloc::task::Wake;
+        use core::sync::atomic::AtomicUsize;
+
+        use axtask::WaitQueue;
+
+        struct WaiterInner {
+            wq: WaitQueue,
+            /// Incremented on each wakeup, so no wakeup is lost between a try
+            /// and going to sleep.
+            generation: AtomicUsize,
+        }
+
+        impl Wake for WaiterInner {
+            fn wake(self: Arc<Self>) {
+                self.wake_by_ref();
+            }
+
+            fn wake_by_ref(self: &Arc<Self>) {
+                self.generation.fetch_add(1, Ordering::Release);
+                self.wq.notify_all(false);
+            }
+        }
+
+        /// The wait queue of a socket, on which blocked tasks sleep until
+        /// they are woken up through [`Wakers`].
+        struct SocketWaiter(spin::Once<Arc<WaiterInner>>);
+
+        impl SocketWaiter {
+            const fn new() -> Self {
+                Self(spin::Once::new())
+            }
+
+            fn inner(&self) -> &Arc<WaiterInner> {
+                self.0.call_once(|| {
+                    Arc::new(WaiterInner {
+                        wq: WaitQueue::new(),
+                        generation: AtomicUsize::new(0),
+                    })
+                })
+            }
+
+            /// Calls `f` until it completes or fails, or the timeout expires,
+            /// and sleeps between the tries.
+            ///
+            /// `register` is called before each try of `f`, which should
+            /// register the given waker to the buffers that `f` is waiting
+            /// for.
+            fn block_on<R, F, T>(
+                &self,
+                register: R,
+                timeout: Option<Duration>,
+                mut f: F,
+            ) -> AxResult<T>
+            where
+                R: Fn(&Waker),
+                F: FnMut() -> AxResult<T>,
+            {
+                let deadline = timeout.map(|timeout| axhal::time::current_time() + timeout);
+                let inner = self.inner();
+                let waker = Waker::from(inner.clone());
+                loop {
+                    let generation = inner.generation.load(Ordering::Acquire);
+                    register(&waker);
+                    match f() {
+                        Err(AxError::WouldBlock) => {
+                            let woken = || inner.generation.load(Ordering::Acquire) != generation;
+                            match deadline {
+                                Some(deadline) => {
+                                    let now = axhal::time::current_time();
+                                    if now >= deadline {
+                                        return Err(AxError::WouldBlock);
+                                    }
+                                    inner.wq.wait_timeout_until(deadline - now, woken);
+                                }
+                                None => inner.wq.wait_until(woken),
+                            }
+                        }
+                        res => return res,
+                    }
+                }
+            }
+        }
+    } else {
+        /// The wait queue of a socket. Tasks never sleep on it without
+        /// interrupts or multitasking.
+        struct SocketWaiter;
+
+        impl SocketWaiter {
+            const fn new() -> Self {
+                Self
+            }
+
+            /// Calls `f` in a loop until it completes or fails, or the
+            /// timeout expires, and yields the CPU between the tries.
+            fn block_on<R, F, T>(
+                &self,
+                _register: R,
+                timeout: Option<Duration>,
+                mut f: F,
+            ) -> AxResult<T>
+            where
+                R: Fn(&Waker),
+                F: FnMut() -> AxResult<T>,
+            {
+                let deadline = timeout.map(|timeout| axhal::time::current_time() + timeout);
+                loop {
+                    match f() {
+                        Err(AxError::WouldBlock)
+                            if deadline.is_some_and(|deadline| {
+                                axhal::time::current_time() >= deadline
+                            }) =>
+                        {
+                            return Err(AxError::WouldBlock)
+                        }
+                        Err(AxError::WouldBlock) => axtask::yield_now(),
+                        res => return res,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Options shared by stream and datagram sockets.
+struct SocketOptions {
+    nonblock: AtomicBool,
+    recv_timeout: RwLock<Option<Duration>>,
+    send_timeout: RwLock<Option<Duration>>,
+    waiter: SocketWaiter,
+}
+
+impl SocketOptions {
+    const fn new() -> Self {
+        Self {
+            nonblock: AtomicBool::new(false),
+            recv_timeout: RwLock::new(None),
+            send_timeout: RwLock::new(None),
+            waiter: SocketWaiter::new(),
+        }
+    }
+
+    fn set_timeout(slot: &RwLock<Option<Duration>>, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        *slot.write() = timeout;
+        Ok(())
+    }
+
+    /// Blocks the current task until `f` completes or fails, or the timeout
+    /// expires. It's called only once in nonblocking mode.
+    ///
+    /// `register` should register the given waker to the [`Wakers`] of the
+    /// buffer that `f` is waiting for.
+    fn block_on<R, F, T>(&self, register: R, timeout: Option<Duration>, mut f: F) -> AxResult<T>
+    where
+        R: Fn(&Waker),
+        F: FnMut() -> AxResult<T>,
+    {
+        if self.nonblock.load(Ordering::Acquire) {
+            return f();
+        }
+        self.waiter.block_on(register, timeout, f)
+    }
+}
+
+/// One direction of a stream connection.
+#[derive(Default)]
+struct Channel {
+    buf: VecDeque<u8>,
+    /// The writing end is shut down or closed.
+    write_closed: bool,
+    /// The reading end is shut down or closed.
+    read_closed: bool,
+    wakers: Wakers,
+}
+
+/// One end of a stream connection.
+struct StreamEnd {
+    rx: Arc<Mutex<Channel>>,
+    tx: Arc<Mutex<Channel>>,
+    peer_addr: UnixSocketAddr,
+}
+
+impl StreamEnd {
+    fn pair(addr1: UnixSocketAddr, addr2: UnixSocketAddr) -> (Self, Self) {
+        let ch1 = Arc::new(Mutex::new(Channel::default()));
+        let ch2 = Arc::new(Mutex::new(Channel::default()));
+        let end1 = Self {
+            rx: ch1.clone(),
+            tx: ch2.clone(),
+            peer_addr: addr2,
+        };
+        let end2 = Self {
+            rx: ch2,
+            tx: ch1,
+            peer_addr: addr1,
+        };
+        (end1, end2)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut rx = self.rx.lock();
+        if rx.buf.is_empty() {
+            return if rx.write_closed || rx.read_closed {
+                Ok(0) // EOF
+            } else {
+                Err(AxError::WouldBlock)
+            };
+        }
+        let len = buf.len().min(rx.buf.len());
+        for (dst, src) in buf.iter_mut().zip(rx.buf.drain(..len)) {
+            *dst = src;
+        }
+        rx.wakers.wake_all();
+        Ok(len)
+    }
+
+    fn send(&self, buf: &[u8]) -> AxResult<usize> {
+        let mut tx = self.tx.lock();
+        if tx.write_closed {
+            return ax_err!(NotConnected, "unix socket is shut down");
+        } else if tx.read_closed {
+            return ax_err!(ConnectionReset, "unix socket peer is closed");
+        }
+        let len = buf.len().min(UNIX_STREAM_BUF_LEN - tx.buf.len());
+        if len == 0 && !buf.is_empty() {
+            return Err(AxError::WouldBlock);
+        }
+        tx.buf.extend(&buf[..len]);
+        tx.wakers.wake_all();
+        Ok(len)
+    }
+
+    fn poll(&self) -> PollState {
+        // don't lock both channels at once, as the peer locks them in the
+        // reverse order
+        let (readable, hangup) = {
+            let rx = self.rx.lock();
+            (
+                !rx.buf.is_empty() || rx.write_closed || rx.read_closed,
+                rx.write_closed,
+            )
+        };
+        let writable = {
+            let tx = self.tx.lock();
+            tx.buf.len() < UNIX_STREAM_BUF_LEN || tx.read_closed || tx.write_closed
+        };
+        PollState {
+            readable,
+            writable,
+            hangup,
+        }
+    }
+
+    fn register_waker(&self, waker: &Waker) {
+        self.rx.lock().wakers.register(waker);
+        self.tx.lock().wakers.register(waker);
+    }
+
+    fn shutdown(&self) {
+        let mut rx = self.rx.lock();
+        rx.read_closed = true;
+        rx.wakers.wake_all();
+        drop(rx);
+        let mut tx = self.tx.lock();
+        tx.write_closed = true;
+        tx.wakers.wake_all();
+    }
+}
+
+impl Drop for StreamEnd {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// The pending connections of a bound stream socket.
+struct Backlog {
+    addr: UnixSocketAddr,
+    listening: bool,
+    queue: VecDeque<StreamEnd>,
+    wakers: Wakers,
+}
+
+/// A Unix domain stream socket that provides POSIX-like APIs.
+///
+/// Like [`TcpSocket`](crate::TcpSocket), it is a client after
+/// [`connect`](Self::connect), or a server after [`bind`](Self::bind) and
+/// [`listen`](Self::listen).
+pub struct UnixStreamSocket {
+    local_addr: RwLock<UnixSocketAddr>,
+    name: RwLock<Option<BoundName>>,
+    backlog: RwLock<Option<Arc<Mutex<Backlog>>>>,
+    conn: RwLock<Option<Arc<StreamEnd>>>,
+    options: SocketOptions,
+}
+
+impl UnixStreamSocket {
+    /// Creates a new Unix domain stream socket.
+    pub const fn new() -> Self {
+        Self {
+            local_addr: RwLock::new(UnixSocketAddr::Unnamed),
+            name: RwLock::new(None),
+            backlog: RwLock::new(None),
+            conn: RwLock::new(None),
+            options: SocketOptions::new(),
+        }
+    }
+
+    fn new_connected(local_addr: UnixSocketAddr, conn: StreamEnd) -> Self {
+        let socket = Self::new();
+        *socket.local_addr.write() = local_addr;
+        *socket.conn.write() = Some(Arc::new(conn));
+        socket
+    }
+
+    /// Creates a pair of connected unnamed sockets, like `socketpair`.
+    pub fn pair() -> (Self, Self) {
+        let (end1, end2) = StreamEnd::pair(UnixSocketAddr::Unnamed, UnixSocketAddr::Unnamed);
+        (
+            Self::new_connected(UnixSocketAddr::Unnamed, end1),
+            Self::new_connected(UnixSocketAddr::Unnamed, end2),
+        )
+    }
+
+    fn connection(&self) -> AxResult<Arc<StreamEnd>> {
+        self.conn
+            .read()
+            .clone()
+            .ok_or_else(|| axerrno::ax_err_type!(NotConnected, "unix socket is not connected"))
+    }
+
+    /// Returns the local address, which is [`UnixSocketAddr::Unnamed`] if
+    /// not bound.
+    pub fn local_addr(&self) -> AxResult<UnixSocketAddr> {
+        Ok(self.local_addr.read().clone())
+    }
+
+    /// Returns the address of the peer, or
+    /// [`Err(NotConnected)`](AxError::NotConnected) if not connected.
+    pub fn peer_addr(&self) -> AxResult<UnixSocketAddr> {
+        Ok(self.connection()?.peer_addr.clone())
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.options.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.options.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Binds the socket to the given name.
+    pub fn bind(&self, addr: UnixSocketAddr) -> AxResult {
+        let mut name = self.name.write();
+        if name.is_some() {
+            return ax_err!(InvalidInput, "unix socket is already bound");
+        }
+        let backlog = Arc::new(Mutex::new(Backlog {
+            addr: addr.clone(),
+            listening: false,
+            queue: VecDeque::new(),
+            wakers: Wakers::default(),
+        }));
+        *name = Some(BoundName::bind(
+            &addr,
+            Binding::Stream(Arc::downgrade(&backlog)),
+        )?);
+        *self.backlog.write() = Some(backlog);
+        *self.local_addr.write() = addr;
+        Ok(())
+    }
+
+    /// Starts listening for connections on the bound name.
+    pub fn listen(&self) -> AxResult {
+        if self.conn.read().is_some() {
+            return ax_err!(InvalidInput, "unix socket is connected");
+        }
+        match self.backlog.read().as_ref() {
+            Some(backlog) => {
+                backlog.lock().listening = true;
+                Ok(())
+            }
+            None => ax_err!(InvalidInput, "unix socket is not bound"),
+        }
+    }
+
+    /// Accepts a new connection.
+    ///
+    /// It blocks until a client connects, unless in nonblocking mode.
+    pub fn accept(&self) -> AxResult<UnixStreamSocket> {
+        let backlog = match self.backlog.read().as_ref() {
+            Some(backlog) if backlog.lock().listening => backlog.clone(),
+            _ => return ax_err!(InvalidInput, "unix socket is not listening"),
+        };
+        let timeout = *self.options.recv_timeout.read();
+        let register = |waker: &Waker| backlog.lock().wakers.register(waker);
+        self.options.block_on(register, timeout, || {
+            let mut backlog = backlog.lock();
+            let conn = backlog.queue.pop_front().ok_or(AxError::WouldBlock)?;
+            // wake up the clients blocked on the full queue
+            backlog.wakers.wake_all();
+            Ok(UnixStreamSocket::new_connected(backlog.addr.clone(), conn))
+        })
+    }
+
+    /// Connects to the socket bound to the given name, which must be
+    /// listening.
+    pub fn connect(&self, addr: UnixSocketAddr) -> AxResult {
+        if self.conn.read().is_some() {
+            return ax_err!(AlreadyExists, "unix socket is already connected");
+        }
+        let backlog = match lookup(&addr)? {
+            Binding::Stream(backlog) => backlog.upgrade(),
+            Binding::Datagram(_) => None,
+        }
+        .ok_or_else(|| axerrno::ax_err_type!(ConnectionRefused, "no listening unix socket"))?;
+        let local_addr = self.local_addr.read().clone();
+        let timeout = *self.options.send_timeout.read();
+        let register = |waker: &Waker| backlog.lock().wakers.register(waker);
+        let conn = self.options.block_on(register, timeout, || {
+            let mut backlog = backlog.lock();
+            if !backlog.listening {
+                return ax_err!(ConnectionRefused, "unix socket is not listening");
+            } else if backlog.queue.len() >= UNIX_LISTEN_QUEUE_LEN {
+                return Err(AxError::WouldBlock);
+            }
+            let (client, server) = StreamEnd::pair(local_addr.clone(), backlog.addr.clone());
+            backlog.queue.push_back(server);
+            backlog.wakers.wake_all();
+            Ok(client)
+        })?;
+        *self.conn.write() = Some(Arc::new(conn));
+        Ok(())
+    }
+
+    /// Receives data from the peer, and returns the number of bytes read,
+    /// which is 0 at the end of the stream.
+    pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let conn = self.connection()?;
+        let timeout = *self.options.recv_timeout.read();
+        let register = |waker: &Waker| conn.rx.lock().wakers.register(waker);
+        self.options.block_on(register, timeout, || conn.recv(buf))
+    }
+
+    /// Sends data to the peer, and returns the number of bytes written.
+    pub fn send(&self, buf: &[u8]) -> AxResult<usize> {
+        let conn = self.connection()?;
+        let timeout = *self.options.send_timeout.read();
+        let register = |waker: &Waker| conn.tx.lock().wakers.register(waker);
+        self.options.block_on(register, timeout, || conn.send(buf))
+    }
+
+    /// Shuts down both directions of the connection.
+    pub fn shutdown(&self) -> AxResult {
+        if let Some(conn) = self.conn.read().as_ref() {
+            conn.shutdown();
+        }
+        if let Some(backlog) = self.backlog.read().as_ref() {
+            let mut backlog = backlog.lock();
+            backlog.listening = false;
+            backlog.queue.clear();
+            backlog.wakers.wake_all();
+        }
+        Ok(())
+    }
+
+    /// Registers a waker that is woken up when the result of [`poll`] may
+    /// change, e.g., for `epoll`.
+    ///
+    /// Returns `false` if the socket is neither connected nor bound. The
+    /// caller should keep polling the socket instead.
+    ///
+    /// [`poll`]: Self::poll
+    pub fn register_poll_waker(&self, waker: &Waker) -> bool {
+        if let Some(conn) = self.conn.read().as_ref() {
+            conn.register_waker(waker);
+        } else if let Some(backlog) = self.backlog.read().as_ref() {
+            backlog.lock().wakers.register(waker);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Whether the socket is readable or writable.
+    ///
+    /// A listening socket is readable if there are pending connections.
+    pub fn poll(&self) -> AxResult<PollState> {
+        if let Some(conn) = self.conn.read().as_ref() {
+            return Ok(conn.poll());
+        }
+        let readable = self
+            .backlog
+            .read()
+            .as_ref()
+            .is_some_and(|backlog| !backlog.lock().queue.is_empty());
+        Ok(PollState {
+            readable,
+            writable: false,
+            hangup: false,
+        })
+    }
+
+    /// Returns the timeout of receiving and accepting (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        *self.options.recv_timeout.read()
+    }
+
+    /// Sets the timeout of receiving and accepting (`SO_RCVTIMEO`). `None`
+    /// means blocking indefinitely, and a zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        SocketOptions::set_timeout(&self.options.recv_timeout, timeout)
+    }
+
+    /// Returns the timeout of sending and connecting (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        *self.options.send_timeout.read()
+    }
+
+    /// Sets the timeout of sending and connecting (`SO_SNDTIMEO`).
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        SocketOptions::set_timeout(&self.options.send_timeout, timeout)
+    }
+
+    /// Returns the buffer size of each direction (`SO_RCVBUF` and
+    /// `SO_SNDBUF`), which is fixed.
+    pub fn buffer_size(&self) -> usize {
+        UNIX_STREAM_BUF_LEN
+    }
+}
+
+impl Default for UnixStreamSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for UnixStreamSocket {
+    fn drop(&mut self) {
+        // wake up the clients blocked on the backlog
+        self.shutdown().ok();
+    }
+}
+
+/// The received datagrams of a socket, and their sources.
+#[derive(Default)]
+struct DgramQueue {
+    queue: VecDeque<(Vec<u8>, UnixSocketAddr)>,
+    closed: bool,
+    wakers: Wakers,
+}
+
+type DgramQueueRef = Arc<Mutex<DgramQueue>>;
+
+/// A Unix domain datagram socket that provides POSIX-like APIs.
+pub struct UnixDatagramSocket {
+    rx: DgramQueueRef,
+    local_addr: RwLock<UnixSocketAddr>,
+    name: RwLock<Option<BoundName>>,
+    peer: RwLock<Option<(UnixSocketAddr, Weak<Mutex<DgramQueue>>)>>,
+    options: SocketOptions,
+}
+
+impl UnixDatagramSocket {
+    /// Creates a new Unix domain datagram socket.
+    pub fn new() -> Self {
+        Self {
+            rx: Arc::new(Mutex::new(DgramQueue::default())),
+            local_addr: RwLock::new(UnixSocketAddr::Unnamed),
+            name: RwLock::new(None),
+            peer: RwLock::new(None),
+            options: SocketOptions::new(),
+        }
+    }
+
+    /// Creates a pair of connected unnamed sockets, like `socketpair`.
+    pub fn pair() -> (Self, Self) {
+        let socket1 = Self::new();
+        let socket2 = Self::new();
+        *socket1.peer.write() = Some((UnixSocketAddr::Unnamed, Arc::downgrade(&socket2.rx)));
+        *socket2.peer.write() = Some((UnixSocketAddr::Unnamed, Arc::downgrade(&socket1.rx)));
+        (socket1, socket2)
+    }
+
+    /// Returns the local address, which is [`UnixSocketAddr::Unnamed`] if
+    /// not bound.
+    pub fn local_addr(&self) -> AxResult<UnixSocketAddr> {
+        Ok(self.local_addr.read().clone())
+    }
+
+    /// Returns the address of the peer, or
+    /// [`Err(NotConnected)`](AxError::NotConnected) if not connected.
+    pub fn peer_addr(&self) -> AxResult<UnixSocketAddr> {
+        match self.peer.read().as_ref() {
+            Some((addr, _)) => Ok(addr.clone()),
+            None => ax_err!(NotConnected, "unix socket is not connected"),
+        }
+    }
+
+    /// Returns whether this socket is in nonblocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.options.nonblock.load(Ordering::Acquire)
+    }
+
+    /// Moves this socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.options.nonblock.store(nonblocking, Ordering::Release);
+    }
+
+    /// Binds the socket to the given name.
+    pub fn bind(&self, addr: UnixSocketAddr) -> AxResult {
+        let mut name = self.name.write();
+        if name.is_some() {
+            return ax_err!(InvalidInput, "unix socket is already bound");
+        }
+        *name = Some(BoundName::bind(
+            &addr,
+            Binding::Datagram(Arc::downgrade(&self.rx)),
+        )?);
+        *self.local_addr.write() = addr;
+        Ok(())
+    }
+
+    /// Sets the default destination of [`send`](Self::send).
+    pub fn connect(&self, addr: UnixSocketAddr) -> AxResult {
+        let queue = Self::lookup_queue(&addr)?;
+        *self.peer.write() = Some((addr, Arc::downgrade(&queue)));
+        Ok(())
+    }
+
+    fn lookup_queue(addr: &UnixSocketAddr) -> AxResult<DgramQueueRef> {
+        match lookup(addr)? {
+            Binding::Datagram(queue) => queue.upgrade(),
+            Binding::Stream(_) => None,
+        }
+        .ok_or_else(|| axerrno::ax_err_type!(ConnectionRefused, "no unix datagram socket"))
+    }
+
+    /// Sends a datagram to the given address.
+    pub fn send_to(&self, buf: &[u8], addr: UnixSocketAddr) -> AxResult<usize> {
+        let queue = Self::lookup_queue(&addr)?;
+        self.send_impl(buf, &queue)
+    }
+
+    /// Sends a datagram to the connected peer.
+    pub fn send(&self, buf: &[u8]) -> AxResult<usize> {
+        let queue = match self.peer.read().as_ref() {
+            Some((_, queue)) => queue.upgrade(),
+            None => return ax_err!(NotConnected, "unix socket is not connected"),
+        }
+        .ok_or_else(|| axerrno::ax_err_type!(ConnectionRefused, "unix socket peer is closed"))?;
+        self.send_impl(buf, &queue)
+    }
+
+    /// Receives a datagram, and returns the number of bytes read and the
+    /// source address. The rest of the datagram is discarded if `buf` is too
+    /// small.
+    pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, UnixSocketAddr)> {
+        let timeout = *self.options.recv_timeout.read();
+        let register = |waker: &Waker| self.rx.lock().wakers.register(waker);
+        self.options.block_on(register, timeout, || {
+            let mut rx = self.rx.lock();
+            let Some((data, addr)) = rx.queue.pop_front() else {
+                return if rx.closed {
+                    Ok((0, UnixSocketAddr::Unnamed))
+                } else {
+                    Err(AxError::WouldBlock)
+                };
+            };
+            let len = buf.len().min(data.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            rx.wakers.wake_all();
+            Ok((len, addr))
+        })
+    }
+
+    /// Receives a datagram, and returns the number of bytes read.
+    pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
+        self.recv_from(buf).map(|(len, _)| len)
+    }
+
+    /// Stops receiving datagrams. Further receiving returns 0 once the queued
+    /// datagrams are consumed.
+    pub fn shutdown(&self) -> AxResult {
+        let mut rx = self.rx.lock();
+        rx.closed = true;
+        rx.wakers.wake_all();
+        Ok(())
+    }
+
+    /// Registers a waker that is woken up when the result of [`poll`] may
+    /// change, e.g., for `epoll`.
+    ///
+    /// [`poll`]: Self::poll
+    pub fn register_poll_waker(&self, waker: &Waker) -> bool {
+        self.rx.lock().wakers.register(waker);
+        if let Some(queue) = self.peer.read().as_ref().and_then(|(_, q)| q.upgrade()) {
+            queue.lock().wakers.register(waker);
+        }
+        true
+    }
+
+    /// Whether the socket is readable or writable.
+    pub fn poll(&self) -> AxResult<PollState> {
+        let readable = {
+            let rx = self.rx.lock();
+            !rx.queue.is_empty() || rx.closed
+        };
+        let writable = match self.peer.read().as_ref() {
+            Some((_, queue)) => queue.upgrade().is_none_or(|queue| {
+                let queue = queue.lock();
+                queue.closed || queue.queue.len() < UNIX_DGRAM_QUEUE_LEN
+            }),
+            None => true,
+        };
+        Ok(PollState {
+            readable,
+            writable,
+            hangup: false,
+        })
+    }
+
+    /// Returns the timeout of receiving (`SO_RCVTIMEO`).
+    pub fn recv_timeout(&self) -> Option<Duration> {
+        *self.options.recv_timeout.read()
+    }
+
+    /// Sets the timeout of receiving (`SO_RCVTIMEO`). `None` means blocking
+    /// indefinitely, and a zero timeout is invalid.
+    pub fn set_recv_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        SocketOptions::set_timeout(&self.options.recv_timeout, timeout)
+    }
+
+    /// Returns the timeout of sending (`SO_SNDTIMEO`).
+    pub fn send_timeout(&self) -> Option<Duration> {
+        *self.options.send_timeout.read()
+    }
+
+    /// Sets the timeout of sending (`SO_SNDTIMEO`).
+    pub fn set_send_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        SocketOptions::set_timeout(&self.options.send_timeout, timeout)
+    }
+
+    /// Returns the maximum size of a datagram (`SO_SNDBUF`), which is fixed.
+    pub fn buffer_size(&self) -> usize {
+        UNIX_DGRAM_MAX_LEN
+    }
+}
+
+impl UnixDatagramSocket {
+    fn send_impl(&self, buf: &[u8], queue: &DgramQueueRef) -> AxResult<usize> {
+        if buf.len() > UNIX_DGRAM_MAX_LEN {
+            return ax_err!(InvalidInput, "datagram too large");
+        }
+        let local_addr = self.local_addr.read().clone();
+        let timeout = *self.options.send_timeout.read();
+        let register = |waker: &Waker| queue.lock().wakers.register(waker);
+        self.options.block_on(register, timeout, || {
+            let mut queue = queue.lock();
+            if queue.closed {
+                return ax_err!(ConnectionRefused, "unix socket peer is closed");
+            } else if queue.queue.len() >= UNIX_DGRAM_QUEUE_LEN {
+                return Err(AxError::WouldBlock);
+            }
+            queue.queue.push_back((buf.to_vec(), local_addr.clone()));
+            queue.wakers.wake_all();
+            Ok(buf.len())
+        })
+    }
+}
+
+impl Default for UnixDatagramSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for UnixDatagramSocket {
+    fn drop(&mut self) {
+        self.shutdown().ok();
+    }
+}
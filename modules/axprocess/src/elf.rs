@@ -0,0 +1,108 @@
+//! Minimal ELF64 definitions and parsing.
+
+use axerrno::{ax_err, AxResult};
+
+const ELF_MAGIC: [u8; 4] = *b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+
+/// Object file types.
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+
+/// Program header types.
+pub const PT_LOAD: u32 = 1;
+pub const PT_INTERP: u32 = 3;
+pub const PT_PHDR: u32 = 6;
+
+/// Segment permission flags.
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+#[cfg(target_arch = "x86_64")]
+const EM_CURRENT: u16 = 62; // EM_X86_64
+#[cfg(target_arch = "aarch64")]
+const EM_CURRENT: u16 = 183; // EM_AARCH64
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+const EM_CURRENT: u16 = 243; // EM_RISCV
+
+/// The ELF64 file header.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64Ehdr {
+    pub e_ident: [u8; 16],
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_version: u32,
+    pub e_entry: u64,
+    pub e_phoff: u64,
+    pub e_shoff: u64,
+    pub e_flags: u32,
+    pub e_ehsize: u16,
+    pub e_phentsize: u16,
+    pub e_phnum: u16,
+    pub e_shentsize: u16,
+    pub e_shnum: u16,
+    pub e_shstrndx: u16,
+}
+
+/// The ELF64 program header.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Elf64Phdr {
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_offset: u64,
+    pub p_vaddr: u64,
+    pub p_paddr: u64,
+    pub p_filesz: u64,
+    pub p_memsz: u64,
+    pub p_align: u64,
+}
+
+impl Elf64Ehdr {
+    /// Parses and validates the file header in `data`.
+    ///
+    /// Only little-endian ELF64 executables (`ET_EXEC`) or position
+    /// independent executables (`ET_DYN`) for the current architecture are
+    /// accepted.
+    pub fn parse(data: &[u8]) -> AxResult<Self> {
+        if data.len() < core::mem::size_of::<Self>() || data[..4] != ELF_MAGIC {
+            return ax_err!(InvalidData, "not an ELF file");
+        }
+        // SAFETY: the length is checked above, and any bit pattern is valid.
+        let hdr: Self = unsafe { core::ptr::read_unaligned(data.as_ptr() as *const Self) };
+        if hdr.e_ident[4] != ELFCLASS64
+            || hdr.e_ident[5] != ELFDATA2LSB
+            || hdr.e_ident[6] != EV_CURRENT
+        {
+            return ax_err!(Unsupported, "not a little-endian ELF64 file");
+        }
+        if hdr.e_machine != EM_CURRENT {
+            return ax_err!(Unsupported, "ELF machine mismatch");
+        }
+        if hdr.e_type != ET_EXEC && hdr.e_type != ET_DYN {
+            return ax_err!(Unsupported, "not an executable ELF file");
+        }
+        if hdr.e_phentsize as usize != core::mem::size_of::<Elf64Phdr>() {
+            return ax_err!(InvalidData, "invalid ELF program header size");
+        }
+        Ok(hdr)
+    }
+
+    /// Returns the size in bytes of the program header table.
+    pub fn ph_table_size(&self) -> usize {
+        self.e_phnum as usize * self.e_phentsize as usize
+    }
+}
+
+impl Elf64Phdr {
+    /// Parses the program header table in `data`.
+    pub fn parse_table(data: &[u8]) -> impl Iterator<Item = Self> + '_ {
+        data.chunks_exact(core::mem::size_of::<Self>())
+            // SAFETY: each chunk has the exact size, and any bit pattern is valid.
+            .map(|chunk| unsafe { core::ptr::read_unaligned(chunk.as_ptr() as *const Self) })
+    }
+}
@@ -0,0 +1,100 @@
+//! [ArceOS](https://github.com/rcore-os/arceos) user process management
+//! module.
+//!
+//! It loads statically linked ELF executables from the file system into
+//! separate user address spaces, and runs them as user processes. Processes
+//! can [`fork`](Process::fork) (with copy-on-write address spaces),
+//! [`exec`](Process::exec) new programs, and wait for their children. Signal
+//! handlers return through a [trampoline](sigreturn_trampoline) mapped in
+//! each address space.
+//!
+//! The system calls from user tasks are handled by the implementation of
+//! [`axhal::trap::SyscallHandler`].
+
+#![no_std]
+
+#[macro_use]
+extern crate log;
+extern crate alloc;
+
+mod elf;
+mod loader;
+mod process;
+mod stack;
+mod trampoline;
+
+use alloc::{string::String, sync::Arc};
+
+use axerrno::AxResult;
+use axhal::arch::UspaceContext;
+use axhal::mem::VirtAddr;
+use axmm::AddrSpace;
+
+pub use self::loader::{load_elf, ElfInfo};
+pub use self::process::{
+    current, current_aspace, exit_current, find, handle_page_fault, kill_current, Pid, Process,
+    ProcessExt,
+};
+pub use self::stack::init_user_stack;
+pub use self::trampoline::sigreturn_trampoline;
+
+/// Creates a new user address space, loads the ELF executable at `path` into
+/// it, and sets up the user stack with `args` and `envs`. The signal return
+/// trampoline is also mapped.
+///
+/// Returns the address space and the context to enter the user space.
+pub fn load_user_app(
+    path: &str,
+    args: &[String],
+    envs: &[String],
+) -> AxResult<(AddrSpace, UspaceContext)> {
+    let mut aspace = axmm::new_user_aspace(
+        VirtAddr::from(axconfig::USER_ASPACE_BASE),
+        axconfig::USER_ASPACE_SIZE,
+    )?;
+    let elf = load_elf(&mut aspace, path)?;
+
+    let ustack_top = aspace.end().align_down_4k();
+    let ustack_size = axconfig::USER_STACK_SIZE;
+    aspace.map_alloc(
+        ustack_top - ustack_size,
+        ustack_size,
+        axhal::paging::MappingFlags::READ
+            | axhal::paging::MappingFlags::WRITE
+            | axhal::paging::MappingFlags::USER,
+        true,
+    )?;
+    trampoline::map_sigreturn_trampoline(&mut aspace)?;
+    let sp = init_user_stack(&mut aspace, ustack_top, args, envs, &elf)?;
+    debug!(
+        "user app {:?} loaded: entry={:#x}, sp={:#x}",
+        path, elf.entry, sp
+    );
+    Ok((aspace, UspaceContext::new(elf.entry.as_usize(), sp, 0)))
+}
+
+/// Loads the ELF executable at `path`, and spawns a new process to run it.
+///
+/// `args` is passed to the program as `argv`, so it usually starts with the
+/// program name. The process has no parent, and it should be reaped by
+/// [`wait`].
+pub fn spawn(path: &str, args: &[String], envs: &[String]) -> AxResult<Arc<Process>> {
+    let (aspace, uctx) = load_user_app(path, args, envs)?;
+    let root = aspace.page_table_root();
+    let cwd = axfs::api::current_dir().unwrap_or_else(|_| "/".into());
+    let task = axtask::new_task(
+        move || process::enter_user(root, uctx),
+        path.into(),
+        axconfig::TASK_STACK_SIZE,
+    );
+    let process = process::spawn_with(task, aspace, cwd);
+    Ok(process)
+}
+
+/// Waits for the process spawned by [`spawn`] to exit, reaps it, and returns
+/// its exit code.
+pub fn wait(process: &Arc<Process>) -> Option<i32> {
+    let exit_code = process.task().join();
+    process::reap(process);
+    exit_code
+}
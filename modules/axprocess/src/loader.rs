@@ -0,0 +1,182 @@
+//! Loading ELF executables into user address spaces.
+
+use alloc::{vec, vec::Vec};
+
+use axerrno::{ax_err, ax_err_type, AxResult};
+use axfs::fops::{File, OpenOptions};
+use axhal::mem::{VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::MappingFlags;
+use axmm::AddrSpace;
+use memory_addr::{align_down_4k, align_up_4k};
+
+use crate::elf::*;
+
+/// The load address of position independent executables.
+const PIE_LOAD_BASE: usize = 0x1000_0000;
+
+/// The information of a loaded ELF executable, used to start it and fill
+/// the auxiliary vector.
+#[derive(Debug, Clone, Copy)]
+pub struct ElfInfo {
+    /// The entry point.
+    pub entry: VirtAddr,
+    /// The address of the program header table in the user memory, or zero
+    /// if it's not loaded.
+    pub phdr: VirtAddr,
+    /// The size of each entry in the program header table.
+    pub phent: usize,
+    /// The number of entries in the program header table.
+    pub phnum: usize,
+}
+
+fn segment_flags(p_flags: u32) -> MappingFlags {
+    let mut flags = MappingFlags::USER;
+    if p_flags & PF_R != 0 {
+        flags |= MappingFlags::READ;
+    }
+    if p_flags & PF_W != 0 {
+        flags |= MappingFlags::WRITE;
+    }
+    if p_flags & PF_X != 0 {
+        flags |= MappingFlags::EXECUTE;
+    }
+    flags
+}
+
+/// Adds `b` to the address `a` in the ELF file, which is invalid if it
+/// overflows.
+fn elf_addr_add(a: usize, b: usize) -> AxResult<usize> {
+    a.checked_add(b)
+        .ok_or_else(|| ax_err_type!(InvalidData, "ELF address overflow"))
+}
+
+/// Returns the end of the range of `size` bytes at `offset` in the ELF file,
+/// which is invalid if it overflows.
+fn elf_offset_end(offset: u64, size: u64) -> AxResult<u64> {
+    offset
+        .checked_add(size)
+        .ok_or_else(|| ax_err_type!(InvalidData, "ELF offset overflow"))
+}
+
+fn read_exact_at(file: &File, offset: u64, buf: &mut [u8]) -> AxResult {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read_at(offset + read as u64, &mut buf[read..])? {
+            0 => return ax_err!(UnexpectedEof, "ELF file truncated"),
+            n => read += n,
+        }
+    }
+    Ok(())
+}
+
+/// Copies `size` bytes at `offset` of the file to `vaddr` in the address
+/// space.
+fn copy_file_to_aspace(
+    aspace: &mut AddrSpace,
+    file: &File,
+    offset: u64,
+    vaddr: VirtAddr,
+    size: usize,
+) -> AxResult {
+    let mut buf = vec![0; PAGE_SIZE_4K * 16];
+    let mut copied = 0;
+    while copied < size {
+        let len = buf.len().min(size - copied);
+        read_exact_at(file, offset + copied as u64, &mut buf[..len])?;
+        aspace.write(vaddr + copied, &buf[..len])?;
+        copied += len;
+    }
+    Ok(())
+}
+
+/// Loads the ELF executable at `path` into the address space.
+///
+/// All `PT_LOAD` segments are mapped and populated, with the part beyond the
+/// file size (e.g., `.bss`) zeroed. Programs that require a dynamic linker
+/// (with a `PT_INTERP` segment) are not supported.
+pub fn load_elf(aspace: &mut AddrSpace, path: &str) -> AxResult<ElfInfo> {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    let file = File::open(path, &opts)?;
+
+    let mut ehdr_buf = [0; core::mem::size_of::<Elf64Ehdr>()];
+    read_exact_at(&file, 0, &mut ehdr_buf)?;
+    let ehdr = Elf64Ehdr::parse(&ehdr_buf)?;
+    let ph_table_end = elf_offset_end(ehdr.e_phoff, ehdr.ph_table_size() as u64)?;
+    let mut ph_buf = vec![0; ehdr.ph_table_size()];
+    read_exact_at(&file, ehdr.e_phoff, &mut ph_buf)?;
+    let phdrs: Vec<Elf64Phdr> = Elf64Phdr::parse_table(&ph_buf).collect();
+
+    if phdrs.iter().any(|ph| ph.p_type == PT_INTERP) {
+        return ax_err!(Unsupported, "dynamically linked ELF file");
+    }
+    let bias = if ehdr.e_type == ET_DYN {
+        PIE_LOAD_BASE
+    } else {
+        0
+    };
+
+    let mut phdr = VirtAddr::from(0);
+    // the end address and the flags of the last mapped segment
+    let mut last: Option<(usize, MappingFlags)> = None;
+    for ph in phdrs.iter() {
+        match ph.p_type {
+            PT_PHDR => {
+                phdr = VirtAddr::from(elf_addr_add(ph.p_vaddr as usize, bias)?);
+                continue;
+            }
+            PT_LOAD => {}
+            _ => continue,
+        }
+        if ph.p_filesz > ph.p_memsz {
+            return ax_err!(InvalidData, "invalid ELF segment size");
+        }
+        let vaddr = elf_addr_add(ph.p_vaddr as usize, bias)?;
+        let vaddr_end = elf_addr_add(vaddr, ph.p_memsz as usize)?;
+        let file_end = elf_offset_end(ph.p_offset, ph.p_filesz)?;
+        if !aspace.contains_range(vaddr.into(), ph.p_memsz as usize) {
+            return ax_err!(InvalidData, "ELF segment out of the user address space");
+        }
+        let flags = segment_flags(ph.p_flags);
+        let mut start = align_down_4k(vaddr);
+        let end = align_up_4k(vaddr_end);
+        debug!(
+            "load ELF segment [{:#x}, {:#x}) {:?}",
+            vaddr, vaddr_end, flags
+        );
+
+        if let Some((last_end, last_flags)) = last {
+            if start < last_end {
+                // the first page is shared with the last segment
+                let shared_page = VirtAddr::from(start);
+                aspace.protect(shared_page, PAGE_SIZE_4K, last_flags | flags)?;
+                start += PAGE_SIZE_4K;
+            }
+        }
+        if start < end {
+            aspace.map_alloc(start.into(), end - start, flags, true)?;
+        }
+        copy_file_to_aspace(
+            aspace,
+            &file,
+            ph.p_offset,
+            vaddr.into(),
+            ph.p_filesz as usize,
+        )?;
+        last = Some((end, flags));
+
+        if phdr.as_usize() == 0 && ph.p_offset <= ehdr.e_phoff && ph_table_end <= file_end {
+            phdr = VirtAddr::from(vaddr + (ehdr.e_phoff - ph.p_offset) as usize);
+        }
+    }
+    if last.is_none() {
+        return ax_err!(InvalidData, "no loadable ELF segment");
+    }
+
+    Ok(ElfInfo {
+        entry: VirtAddr::from(elf_addr_add(ehdr.e_entry as usize, bias)?),
+        phdr,
+        phent: ehdr.e_phentsize as usize,
+        phnum: ehdr.e_phnum as usize,
+    })
+}
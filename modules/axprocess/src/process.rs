@@ -0,0 +1,421 @@
+//! User processes and the process tree.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axerrno::{ax_err, AxResult};
+use axhal::arch::UspaceContext;
+use axhal::mem::{PhysAddr, VirtAddr};
+use axhal::paging::MappingFlags;
+use axmm::AddrSpace;
+use axtask::{AxTaskRef, WaitQueue};
+use spinlock::SpinNoIrq;
+
+/// The process ID, which is the ID of the task running the process.
+pub type Pid = u64;
+
+/// The signal number of `SIGSEGV`, used in the wait status of the processes
+/// killed by unresolved page faults.
+const SIGSEGV: i32 = 11;
+
+/// All processes that have not been reaped, indexed by the process ID.
+///
+/// The lock also protects the parent-child relationships.
+static PROCESSES: SpinNoIrq<BTreeMap<Pid, Arc<Process>>> = SpinNoIrq::new(BTreeMap::new());
+
+/// Per-process data of upper layers, e.g., the file descriptor table of the
+/// POSIX API layer.
+pub trait ProcessExt: Send + Sync {
+    /// Creates the data of the child process on [`Process::fork`].
+    fn fork(&self) -> Arc<dyn ProcessExt>;
+
+    /// Updates the data when the process replaces its program on
+    /// [`Process::exec`], e.g., to reset the signal handlers.
+    fn exec(&self) {}
+
+    /// Returns `self` as [`Any`], to downcast it to the concrete type.
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// A user process.
+///
+/// Each process is run by a single task (thread) for now.
+pub struct Process {
+    pid: Pid,
+    task: AxTaskRef,
+    /// The parent process, or `None` if it's spawned by the kernel, which
+    /// waits for it by [`wait`](crate::wait).
+    parent: Option<Weak<Process>>,
+    children: SpinNoIrq<Vec<Arc<Process>>>,
+    aspace: SpinNoIrq<Arc<SpinNoIrq<AddrSpace>>>,
+    cwd: SpinNoIrq<String>,
+    ext: SpinNoIrq<Option<Arc<dyn ProcessExt>>>,
+    /// The user address cleared on exit (set by `set_tid_address`), or `0`.
+    clear_child_tid: AtomicUsize,
+    /// The wait status (as in `waitpid`) after the process exits.
+    exit_status: SpinNoIrq<Option<i32>>,
+    /// Notified when a child process exits.
+    child_exit_wq: WaitQueue,
+}
+
+impl Process {
+    /// Returns the process ID.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Returns the ID of the parent process, or `0` if it's spawned by the
+    /// kernel.
+    pub fn parent_pid(&self) -> Pid {
+        self.parent
+            .as_ref()
+            .and_then(|p| p.upgrade())
+            .map_or(0, |p| p.pid)
+    }
+
+    /// Returns the task running the process.
+    pub fn task(&self) -> &AxTaskRef {
+        &self.task
+    }
+
+    /// Returns the address space of the process.
+    pub fn aspace(&self) -> Arc<SpinNoIrq<AddrSpace>> {
+        self.aspace.lock().clone()
+    }
+
+    /// Returns the current working directory.
+    pub fn cwd(&self) -> String {
+        self.cwd.lock().clone()
+    }
+
+    /// Sets the current working directory, which must be an absolute path.
+    pub fn set_cwd(&self, cwd: String) {
+        *self.cwd.lock() = cwd;
+    }
+
+    /// Returns the per-process data of upper layers, or initializes it by
+    /// `init` if it's not set.
+    pub fn ext_or_init(&self, init: impl FnOnce() -> Arc<dyn ProcessExt>) -> Arc<dyn ProcessExt> {
+        self.ext.lock().get_or_insert_with(init).clone()
+    }
+
+    /// Sets the user address to clear on exit, `0` to disable it.
+    ///
+    /// On exit, a 32-bit `0` is written to the address, and a waiter of the
+    /// futex there is woken up.
+    pub fn set_clear_child_tid(&self, addr: usize) {
+        self.clear_child_tid.store(addr, Ordering::Relaxed);
+    }
+
+    /// Whether the process has exited, and not been reaped yet.
+    pub fn is_zombie(&self) -> bool {
+        self.exit_status.lock().is_some()
+    }
+
+    fn new(
+        task: AxTaskRef,
+        parent: Option<Weak<Process>>,
+        aspace: AddrSpace,
+        cwd: String,
+        ext: Option<Arc<dyn ProcessExt>>,
+    ) -> Self {
+        Self {
+            pid: task.id().as_u64(),
+            task,
+            parent,
+            children: SpinNoIrq::new(Vec::new()),
+            aspace: SpinNoIrq::new(Arc::new(SpinNoIrq::new(aspace))),
+            cwd: SpinNoIrq::new(cwd),
+            ext: SpinNoIrq::new(ext),
+            clear_child_tid: AtomicUsize::new(0),
+            exit_status: SpinNoIrq::new(None),
+            child_exit_wq: WaitQueue::new(),
+        }
+    }
+
+    /// Creates a child process of the current process, which returns to user
+    /// space with `uctx` (usually built from the trap frame of the `fork`
+    /// syscall), and the return value set to `0`.
+    ///
+    /// The address space is copied on write, and the file descriptor table
+    /// (in [`ProcessExt`]), the working directory and the signal mask are
+    /// copied.
+    pub fn fork(self: &Arc<Self>, mut uctx: UspaceContext) -> AxResult<Pid> {
+        let aspace = self.aspace().lock().fork()?;
+        let root = aspace.page_table_root();
+        uctx.set_retval(0);
+        // the thread pointer is saved in the trap frame on RISC-V
+        #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+        let tls = axhal::arch::read_thread_pointer();
+        let cwd = self.cwd();
+        let ext = self.ext.lock().as_ref().map(|ext| ext.fork());
+        let sig_blocked = axtask::current().blocked_signals();
+
+        // the child is run after it's registered, so that it cannot make
+        // syscalls before that
+        let task = axtask::new_task(
+            move || {
+                #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+                unsafe {
+                    axhal::arch::write_thread_pointer(tls)
+                };
+                axtask::current().set_blocked_signals(sig_blocked);
+                enter_user(root, uctx)
+            },
+            self.task.name().into(),
+            axconfig::TASK_STACK_SIZE,
+        );
+        let child = Arc::new(Self::new(
+            task,
+            Some(Arc::downgrade(self)),
+            aspace,
+            cwd,
+            ext,
+        ));
+        let pid = child.pid;
+        debug!("process {} forked: child {}", self.pid, pid);
+        let task = child.task.clone();
+        {
+            let mut processes = PROCESSES.lock();
+            self.children.lock().push(child.clone());
+            processes.insert(pid, child);
+        }
+        axtask::spawn_task(task);
+        Ok(pid)
+    }
+
+    /// Replaces the program of the current process with the ELF executable
+    /// at `path`.
+    ///
+    /// A new address space is created and activated, the old one is
+    /// released. Returns the context to enter the new program, which should
+    /// replace the trap frame of the `execve` syscall.
+    pub fn exec(&self, path: &str, args: &[String], envs: &[String]) -> AxResult<UspaceContext> {
+        let (aspace, uctx) = crate::load_user_app(path, args, envs)?;
+        unsafe { axtask::set_current_page_table_root(aspace.page_table_root()) };
+        #[cfg(not(any(target_arch = "riscv32", target_arch = "riscv64")))]
+        unsafe {
+            axhal::arch::write_thread_pointer(0)
+        };
+        let old_aspace =
+            core::mem::replace(&mut *self.aspace.lock(), Arc::new(SpinNoIrq::new(aspace)));
+        drop(old_aspace);
+        // the address is in the old address space
+        self.set_clear_child_tid(0);
+        let ext = self.ext.lock().clone();
+        if let Some(ext) = ext {
+            ext.exec();
+        }
+        debug!("process {} exec: {:?}", self.pid, path);
+        Ok(uctx)
+    }
+
+    /// Waits for a child process to exit, and reaps it.
+    ///
+    /// If `pid` is `None`, waits for any child. Returns the process ID and
+    /// the wait status of the reaped child, or `None` if `nohang` is set and
+    /// no child has exited. Returns [`NotFound`] if there is no such child,
+    /// or [`Interrupted`] if the wait is interrupted by signals.
+    ///
+    /// [`NotFound`]: axerrno::AxError::NotFound
+    /// [`Interrupted`]: axerrno::AxError::Interrupted
+    pub fn wait_child(&self, pid: Option<Pid>, nohang: bool) -> AxResult<Option<(Pid, i32)>> {
+        let matches = |child: &Arc<Process>| pid.map_or(true, |pid| child.pid == pid);
+        loop {
+            {
+                let mut processes = PROCESSES.lock();
+                let mut children = self.children.lock();
+                if !children.iter().any(matches) {
+                    return ax_err!(NotFound, "no such child process");
+                }
+                if let Some(idx) = children.iter().position(|c| matches(c) && c.is_zombie()) {
+                    let child = children.remove(idx);
+                    processes.remove(&child.pid);
+                    let status = child.exit_status.lock().unwrap();
+                    debug!("process {} reaped: status {:#x}", child.pid, status);
+                    return Ok(Some((child.pid, status)));
+                }
+            }
+            if nohang {
+                return Ok(None);
+            }
+            let exited = self.child_exit_wq.wait_until_interruptible(|| {
+                let children = self.children.lock();
+                !children.iter().any(matches)
+                    || children.iter().any(|c| matches(c) && c.is_zombie())
+            });
+            if !exited {
+                return ax_err!(Interrupted);
+            }
+        }
+    }
+
+    /// Clears the address set by [`set_clear_child_tid`], and wakes up a
+    /// waiter of the futex there.
+    ///
+    /// [`set_clear_child_tid`]: Self::set_clear_child_tid
+    fn clear_child_tid(&self) {
+        let addr = self.clear_child_tid.swap(0, Ordering::Relaxed);
+        if addr == 0 {
+            return;
+        }
+        let aspace = self.aspace();
+        let size = core::mem::size_of::<u32>();
+        let flags = MappingFlags::WRITE | MappingFlags::USER;
+        if axmm::populate(&aspace, addr.into(), size, flags).is_err() {
+            return;
+        }
+        let mut aspace = aspace.lock();
+        if aspace.write(addr.into(), &0u32.to_ne_bytes()).is_ok() {
+            let key = axtask::FutexKey::new(aspace.page_table_root().as_usize(), addr);
+            drop(aspace);
+            axtask::futex_wake(key, 1);
+        }
+    }
+
+    /// Releases the resources of the process, and turns it into a zombie
+    /// with the wait status `status`.
+    fn exit(&self, status: i32) {
+        self.clear_child_tid();
+        // switch to the kernel page table before releasing the address space
+        unsafe { axtask::set_current_page_table_root(axhal::paging::kernel_page_table_root()) };
+        let aspace = self.aspace();
+        let (base, size) = {
+            let aspace = aspace.lock();
+            (aspace.base(), aspace.size())
+        };
+        // take the address space out and tear it down without the lock held,
+        // as writing back file mappings may block
+        let writeback = match axmm::new_user_aspace(base, size) {
+            Ok(empty) => {
+                let mut old = core::mem::replace(&mut *aspace.lock(), empty);
+                old.clear()
+            }
+            Err(_) => aspace.lock().clear(),
+        };
+        writeback.write().ok();
+        let ext = self.ext.lock().take();
+        drop(ext);
+
+        let mut processes = PROCESSES.lock();
+        *self.exit_status.lock() = Some(status);
+        // reap the exited children, the others will be reaped by themselves
+        for child in self.children.lock().drain(..) {
+            if child.is_zombie() {
+                processes.remove(&child.pid);
+            }
+        }
+        let Some(parent) = &self.parent else {
+            // spawned by the kernel, reaped in `wait`
+            return;
+        };
+        match parent.upgrade() {
+            Some(parent) if !parent.is_zombie() => {
+                drop(processes);
+                parent.child_exit_wq.notify_all(false);
+            }
+            _ => {
+                processes.remove(&self.pid);
+            }
+        }
+    }
+}
+
+/// Returns the current process, or `None` if the current task is not a user
+/// process.
+pub fn current() -> Option<Arc<Process>> {
+    find(axtask::current().id().as_u64())
+}
+
+/// Returns the process with ID `pid`, or `None` if there is no such process
+/// (or it has been reaped).
+pub fn find(pid: Pid) -> Option<Arc<Process>> {
+    PROCESSES.lock().get(&pid).cloned()
+}
+
+/// Returns the address space of the current process, or `None` if the
+/// current task is not a user process.
+pub fn current_aspace() -> Option<Arc<SpinNoIrq<AddrSpace>>> {
+    current().map(|p| p.aspace())
+}
+
+/// Terminates the current process with `exit_code`.
+///
+/// # Panics
+///
+/// Panics if the current task is not a user process.
+pub fn exit_current(exit_code: i32) -> ! {
+    let process = current().expect("not a user process");
+    debug!("process {} exit: {}", process.pid, exit_code);
+    process.exit((exit_code & 0xff) << 8);
+    drop(process);
+    axtask::exit(exit_code)
+}
+
+/// Terminates the current process as killed by signal `signo`, e.g., by the
+/// default action of `SIGTERM`. The exit code of its task is `-1`.
+///
+/// # Panics
+///
+/// Panics if the current task is not a user process.
+pub fn kill_current(signo: i32) -> ! {
+    let process = current().expect("not a user process");
+    debug!("process {} killed by signal {}", process.pid, signo);
+    process.exit(signo & 0x7f);
+    drop(process);
+    axtask::exit(-1)
+}
+
+/// Activates the page table `root` and enters user space with `uctx`, in a
+/// newly spawned task.
+pub(crate) fn enter_user(root: PhysAddr, uctx: UspaceContext) -> ! {
+    unsafe { axtask::set_current_page_table_root(root) };
+    let kstack_top = axtask::current()
+        .kernel_stack_top()
+        .expect("user task has no kernel stack");
+    unsafe { uctx.enter_uspace(kstack_top) }
+}
+
+/// Creates a process spawned by the kernel, which is run by `task` created
+/// by [`axtask::new_task`].
+///
+/// The task is spawned after the process is registered, so it cannot make
+/// syscalls before that.
+pub(crate) fn spawn_with(task: AxTaskRef, aspace: AddrSpace, cwd: String) -> Arc<Process> {
+    let process = Arc::new(Process::new(task.clone(), None, aspace, cwd, None));
+    PROCESSES.lock().insert(process.pid, process.clone());
+    axtask::spawn_task(task);
+    process
+}
+
+/// Removes the process spawned by the kernel from the process table, after
+/// it has exited.
+pub(crate) fn reap(process: &Process) {
+    PROCESSES.lock().remove(&process.pid);
+}
+
+/// Handles page faults of user processes, and of the kernel address space
+/// for other tasks.
+///
+/// The user process is killed if the fault cannot be resolved.
+pub fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool) -> bool {
+    let handled = match current_aspace() {
+        Some(aspace) => axmm::handle_page_fault(&aspace, vaddr, access_flags),
+        None => axmm::handle_page_fault(axmm::kernel_aspace(), vaddr, access_flags),
+    };
+    if !handled && is_user {
+        if let Some(process) = current() {
+            warn!(
+                "process {} segmentation fault @ {:#x} ({:?}), killed",
+                process.pid, vaddr, access_flags
+            );
+            drop(process);
+            kill_current(SIGSEGV);
+        }
+    }
+    handled
+}
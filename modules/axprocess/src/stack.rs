@@ -0,0 +1,151 @@
+//! Initial user stack layout.
+//!
+//! The layout follows the System V ABI, from the stack top downwards:
+//!
+//! ```text
+//! +-------------------------+ <- ustack_top
+//! | AT_RANDOM bytes         |
+//! | envp & argv strings     |
+//! +-------------------------+
+//! | (padding)               |
+//! | auxv (AT_NULL ended)    |
+//! | envp (NULL ended)       |
+//! | argv (NULL ended)       |
+//! | argc                    | <- sp (16-byte aligned)
+//! +-------------------------+
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use axerrno::AxResult;
+use axhal::mem::VirtAddr;
+use axmm::AddrSpace;
+
+use crate::loader::ElfInfo;
+
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_ENTRY: usize = 9;
+const AT_UID: usize = 11;
+const AT_EUID: usize = 12;
+const AT_GID: usize = 13;
+const AT_EGID: usize = 14;
+const AT_HWCAP: usize = 16;
+const AT_CLKTCK: usize = 17;
+const AT_SECURE: usize = 23;
+const AT_RANDOM: usize = 25;
+
+/// Builds the stack contents in a kernel buffer, which is then copied to the
+/// user stack as a whole.
+struct StackBuilder {
+    /// The user address of `data[0]`, i.e., the current stack pointer.
+    bottom: usize,
+    data: Vec<u8>,
+}
+
+impl StackBuilder {
+    fn new(top: usize) -> Self {
+        Self {
+            bottom: top,
+            data: Vec::new(),
+        }
+    }
+
+    /// Pushes the bytes onto the stack, returns their user address.
+    fn push_bytes(&mut self, bytes: &[u8]) -> usize {
+        self.data.splice(0..0, bytes.iter().copied());
+        self.bottom -= bytes.len();
+        self.bottom
+    }
+
+    /// Pushes a NUL-terminated string onto the stack, returns its user
+    /// address.
+    fn push_str(&mut self, s: &str) -> usize {
+        self.push_bytes(&[0]);
+        self.push_bytes(s.as_bytes())
+    }
+
+    /// Pushes the values onto the stack as a whole, the first value is at
+    /// the lowest address.
+    fn push_usizes(&mut self, vals: &[usize]) {
+        let bytes: Vec<u8> = vals.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        self.push_bytes(&bytes);
+    }
+
+    fn align_down(&mut self, align: usize) {
+        let pad = self.bottom % align;
+        self.push_bytes(&alloc::vec![0; pad]);
+    }
+}
+
+/// Generates the 16 random bytes pointed by `AT_RANDOM`.
+fn random_bytes() -> [u8; 16] {
+    let mut seed = axhal::time::current_time_nanos();
+    let mut bytes = [0; 16];
+    for b in bytes.iter_mut() {
+        // xorshift64
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        *b = seed as u8;
+    }
+    bytes
+}
+
+/// Writes the arguments, environment variables and auxiliary vector onto the
+/// user stack whose top is `ustack_top`, returns the initial stack pointer.
+pub fn init_user_stack(
+    aspace: &mut AddrSpace,
+    ustack_top: VirtAddr,
+    args: &[String],
+    envs: &[String],
+    elf: &ElfInfo,
+) -> AxResult<VirtAddr> {
+    let mut stack = StackBuilder::new(ustack_top.as_usize());
+
+    let random = stack.push_bytes(&random_bytes());
+    let envp: Vec<usize> = envs.iter().map(|s| stack.push_str(s)).collect();
+    let argv: Vec<usize> = args.iter().map(|s| stack.push_str(s)).collect();
+
+    let auxv = [
+        (AT_PHDR, elf.phdr.as_usize()),
+        (AT_PHENT, elf.phent),
+        (AT_PHNUM, elf.phnum),
+        (AT_PAGESZ, axhal::mem::PAGE_SIZE_4K),
+        (AT_BASE, 0),
+        (AT_ENTRY, elf.entry.as_usize()),
+        (AT_UID, 0),
+        (AT_EUID, 0),
+        (AT_GID, 0),
+        (AT_EGID, 0),
+        (AT_HWCAP, 0),
+        (AT_CLKTCK, axconfig::TICKS_PER_SEC),
+        (AT_SECURE, 0),
+        (AT_RANDOM, random),
+        (AT_NULL, 0),
+    ];
+
+    // the number of words below the strings, they must take a multiple of
+    // 16 bytes to keep `sp` aligned
+    let nwords = 1 + (argv.len() + 1) + (envp.len() + 1) + auxv.len() * 2;
+    stack.align_down(16);
+    if nwords & 1 != 0 {
+        stack.push_usizes(&[0]);
+    }
+
+    let auxv: Vec<usize> = auxv.iter().flat_map(|&(k, v)| [k, v]).collect();
+    stack.push_usizes(&auxv);
+    stack.push_usizes(&[0]);
+    stack.push_usizes(&envp);
+    stack.push_usizes(&[0]);
+    stack.push_usizes(&argv);
+    stack.push_usizes(&[argv.len()]);
+
+    let sp = VirtAddr::from(stack.bottom);
+    aspace.write(sp, &stack.data)?;
+    Ok(sp)
+}
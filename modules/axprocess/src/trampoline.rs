@@ -0,0 +1,41 @@
+//! The signal return trampoline mapped into each user address space.
+
+use axerrno::AxResult;
+use axhal::mem::VirtAddr;
+use axhal::paging::MappingFlags;
+use axmm::AddrSpace;
+
+/// `mov eax, 15 (rt_sigreturn); syscall`
+#[cfg(target_arch = "x86_64")]
+const SIGRETURN_CODE: &[u8] = &[0xb8, 0x0f, 0x00, 0x00, 0x00, 0x0f, 0x05];
+
+/// `li a7, 139 (rt_sigreturn); ecall`
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+const SIGRETURN_CODE: &[u8] = &[0x93, 0x08, 0xb0, 0x08, 0x73, 0x00, 0x00, 0x00];
+
+/// `mov x8, 139 (rt_sigreturn); svc #0`
+#[cfg(target_arch = "aarch64")]
+const SIGRETURN_CODE: &[u8] = &[0x68, 0x11, 0x80, 0xd2, 0x01, 0x00, 0x00, 0xd4];
+
+/// Returns the address of the signal return trampoline, which is the page
+/// below the guard page of the user stack.
+///
+/// Signal handlers return to it (unless `SA_RESTORER` is given), and it
+/// calls `rt_sigreturn`.
+pub fn sigreturn_trampoline() -> VirtAddr {
+    let ustack_top =
+        VirtAddr::from(axconfig::USER_ASPACE_BASE + axconfig::USER_ASPACE_SIZE).align_down_4k();
+    ustack_top - axconfig::USER_STACK_SIZE - 2 * axhal::mem::PAGE_SIZE_4K
+}
+
+/// Maps the signal return trampoline into the user address space.
+pub(crate) fn map_sigreturn_trampoline(aspace: &mut AddrSpace) -> AxResult {
+    let addr = sigreturn_trampoline();
+    aspace.map_alloc(
+        addr,
+        axhal::mem::PAGE_SIZE_4K,
+        MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+        true,
+    )?;
+    aspace.write(addr, SIGRETURN_CODE)
+}
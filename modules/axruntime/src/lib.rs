@@ -7,6 +7,7 @@
 //!
 //! - `alloc`: Enable global memory allocator.
 //! - `paging`: Enable page table manipulation support.
+//! - `uspace`: Enable user processes, whose page faults are handled here.
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
@@ -22,10 +23,16 @@
 #[macro_use]
 extern crate axlog;
 
+#[cfg(feature = "fs")]
+extern crate alloc;
+
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
 mod trap;
 
+#[cfg(feature = "fs")]
+mod procfs;
+
 #[cfg(feature = "smp")]
 mod mp;
 
@@ -141,10 +148,7 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     init_allocator();
 
     #[cfg(feature = "paging")]
-    {
-        info!("Initialize kernel page table...");
-        remap_kernel_memory().expect("remap kernel memoy failed");
-    }
+    axmm::init_memory_management();
 
     info!("Initialize platform devices...");
     axhal::platform_init();
@@ -158,7 +162,10 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         let all_devices = axdriver::init_drivers();
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        {
+            axfs::init_filesystems(all_devices.block);
+            procfs::init();
+        }
 
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
@@ -191,6 +198,11 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
     unsafe { main() };
 
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::sync_filesystems() {
+        warn!("failed to sync filesystems: {:?}", e);
+    }
+
     #[cfg(feature = "multitask")]
     axtask::exit(0);
     #[cfg(not(feature = "multitask"))]
@@ -229,32 +241,6 @@ fn init_allocator() {
     }
 }
 
-#[cfg(feature = "paging")]
-fn remap_kernel_memory() -> Result<(), axhal::paging::PagingError> {
-    use axhal::mem::{memory_regions, phys_to_virt};
-    use axhal::paging::PageTable;
-    use lazy_init::LazyInit;
-
-    static KERNEL_PAGE_TABLE: LazyInit<PageTable> = LazyInit::new();
-
-    if axhal::cpu::this_cpu_is_bsp() {
-        let mut kernel_page_table = PageTable::try_new()?;
-        for r in memory_regions() {
-            kernel_page_table.map_region(
-                phys_to_virt(r.paddr),
-                r.paddr,
-                r.size,
-                r.flags.into(),
-                true,
-            )?;
-        }
-        KERNEL_PAGE_TABLE.init_by(kernel_page_table);
-    }
-
-    unsafe { axhal::arch::write_page_table_root(KERNEL_PAGE_TABLE.root_paddr()) };
-    Ok(())
-}
-
 #[cfg(feature = "irq")]
 fn init_interrupt() {
     use axhal::time::TIMER_IRQ_NUM;
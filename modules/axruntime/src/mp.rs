@@ -35,7 +35,7 @@ pub extern "C" fn rust_main_secondary(cpu_id: usize) -> ! {
     info!("Secondary CPU {:x} started.", cpu_id);
 
     #[cfg(feature = "paging")]
-    super::remap_kernel_memory().unwrap();
+    axmm::init_memory_management_secondary();
 
     axhal::platform_init_secondary();
 
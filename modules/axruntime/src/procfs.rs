@@ -0,0 +1,225 @@
+//! Entries of `/proc` generated from the kernel states.
+
+use alloc::string::{String, ToString};
+use alloc::{sync::Arc, vec::Vec};
+use core::fmt::Write;
+use core::time::Duration;
+
+use axfs::fops::FileType;
+use axfs::procfs::{ProcDir, ProcSymlink};
+
+/// Information of a task shown in `/proc/<id>/`.
+struct TaskInfo {
+    id: u64,
+    name: String,
+    state: char,
+    priority: isize,
+    cpu_id: usize,
+    cpumask: usize,
+    cpu_time: Duration,
+}
+
+#[cfg(feature = "multitask")]
+fn task_info(task: &axtask::AxTaskRef) -> TaskInfo {
+    use axtask::TaskState;
+    TaskInfo {
+        id: task.id().as_u64(),
+        name: task.name().into(),
+        state: match task.state() {
+            TaskState::Running | TaskState::Ready => 'R',
+            TaskState::Blocked => 'S',
+            TaskState::Exited => 'Z',
+        },
+        priority: task.priority(),
+        cpu_id: task.cpu_id(),
+        cpumask: task.cpumask().bits(),
+        cpu_time: task.cpu_time(),
+    }
+}
+
+fn all_tasks() -> Vec<TaskInfo> {
+    #[cfg(feature = "multitask")]
+    {
+        axtask::all_tasks().iter().map(task_info).collect()
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        alloc::vec![TaskInfo {
+            id: current_task_id(),
+            name: "main".into(),
+            state: 'R',
+            priority: 0,
+            cpu_id: axhal::cpu::this_cpu_id(),
+            cpumask: 1 << axhal::cpu::this_cpu_id(),
+            cpu_time: axhal::time::current_time(),
+        }]
+    }
+}
+
+fn find_task(id: u64) -> Option<TaskInfo> {
+    all_tasks().into_iter().find(|t| t.id == id)
+}
+
+fn current_task_id() -> u64 {
+    #[cfg(feature = "multitask")]
+    {
+        axtask::current().id().as_u64()
+    }
+    #[cfg(not(feature = "multitask"))]
+    {
+        2 // the same as `main` task ID in multitask mode
+    }
+}
+
+/// Content of `/proc/<id>/status`.
+fn task_status(id: u64) -> String {
+    let Some(t) = find_task(id) else {
+        return String::new();
+    };
+    let state = match t.state {
+        'R' => "R (running)",
+        'S' => "S (sleeping)",
+        _ => "Z (zombie)",
+    };
+    let mut s = String::new();
+    let _ = writeln!(s, "Name:\t{}", t.name);
+    let _ = writeln!(s, "State:\t{}", state);
+    let _ = writeln!(s, "Pid:\t{}", t.id);
+    let _ = writeln!(s, "Priority:\t{}", t.priority);
+    let _ = writeln!(s, "Cpu:\t{}", t.cpu_id);
+    let _ = writeln!(s, "Cpus_allowed:\t{:x}", t.cpumask);
+    let _ = writeln!(
+        s,
+        "CpuTime:\t{}.{:06}",
+        t.cpu_time.as_secs(),
+        t.cpu_time.subsec_micros()
+    );
+    s
+}
+
+/// Content of `/proc/<id>/stat`, in the same format as Linux, unknown fields
+/// are filled with 0.
+fn task_stat(id: u64) -> String {
+    const USER_HZ: u128 = 100;
+    let Some(t) = find_task(id) else {
+        return String::new();
+    };
+    let utime = t.cpu_time.as_millis() * USER_HZ / 1000;
+    // pid (comm) state ppid pgrp session tty_nr tpgid flags minflt cminflt
+    // majflt cmajflt utime stime cutime cstime priority nice num_threads
+    // itrealvalue starttime vsize rss
+    alloc::format!(
+        "{id} ({}) {} 0 {id} {id} 0 -1 0 0 0 0 0 {} 0 0 0 {} {} 1 0 0 0 0\n",
+        t.name,
+        t.state,
+        utime,
+        t.priority + 20,
+        t.priority,
+    )
+}
+
+/// Builds `/proc/<id>/` if the task with `name` as its ID exists.
+fn task_dir(parent: &Arc<ProcDir>, name: &str) -> Option<Arc<ProcDir>> {
+    let id = find_task(name.parse().ok()?)?.id;
+    let dir = parent.new_child();
+    dir.add_file("status", move || task_status(id));
+    dir.add_file("stat", move || task_stat(id));
+    Some(dir)
+}
+
+/// Content of `/proc/meminfo`, sizes are in kB.
+#[cfg(feature = "alloc")]
+fn meminfo() -> String {
+    use axhal::mem::PAGE_SIZE_4K;
+    let allocator = axalloc::global_allocator();
+    let total_pages = allocator.used_pages() + allocator.available_pages();
+    let free = allocator.available_pages() * PAGE_SIZE_4K;
+    let heap_used = allocator.used_bytes();
+    let heap_free = allocator.available_bytes();
+
+    let mut s = String::new();
+    let _ = writeln!(s, "MemTotal:\t{:>8} kB", total_pages * PAGE_SIZE_4K / 1024);
+    let _ = writeln!(s, "MemFree:\t{:>8} kB", free / 1024);
+    let _ = writeln!(s, "MemAvailable:\t{:>8} kB", (free + heap_free) / 1024);
+    let _ = writeln!(s, "HeapTotal:\t{:>8} kB", (heap_used + heap_free) / 1024);
+    let _ = writeln!(s, "HeapUsed:\t{:>8} kB", heap_used / 1024);
+    s
+}
+
+/// Content of `/proc/uptime`: the uptime and the time spent in idle tasks,
+/// in seconds.
+fn uptime() -> String {
+    let uptime = axhal::time::current_time();
+    #[cfg(feature = "multitask")]
+    let idle: Duration = axtask::all_tasks()
+        .iter()
+        .filter(|t| t.is_idle())
+        .map(|t| t.cpu_time())
+        .sum();
+    #[cfg(not(feature = "multitask"))]
+    let idle = Duration::ZERO;
+    alloc::format!(
+        "{}.{:02} {}.{:02}\n",
+        uptime.as_secs(),
+        uptime.subsec_millis() / 10,
+        idle.as_secs(),
+        idle.subsec_millis() / 10,
+    )
+}
+
+/// Content of `/proc/cpuinfo`.
+fn cpuinfo() -> String {
+    let mut s = String::new();
+    for cpu_id in 0..axconfig::SMP {
+        let _ = writeln!(s, "processor\t: {}", cpu_id);
+        let _ = writeln!(s, "arch\t\t: {}", option_env!("AX_ARCH").unwrap_or(""));
+        let _ = writeln!(
+            s,
+            "platform\t: {}\n",
+            option_env!("AX_PLATFORM").unwrap_or("")
+        );
+    }
+    s
+}
+
+/// Content of `/proc/interrupts`: IRQ numbers and how many times they have
+/// been raised.
+#[cfg(feature = "irq")]
+fn interrupts() -> String {
+    let mut s = String::new();
+    for (irq_num, count) in axhal::irq::irq_counts() {
+        let name = if irq_num == axhal::time::TIMER_IRQ_NUM {
+            "timer"
+        } else {
+            ""
+        };
+        let _ = writeln!(s, "{:>4}: {:>10}  {}", irq_num, count, name);
+    }
+    s
+}
+
+/// Adds entries about tasks, memory, interrupts, etc. to `/proc`.
+pub(crate) fn init() {
+    let Some(root) = axfs::procfs_root() else {
+        return;
+    };
+    #[cfg(feature = "alloc")]
+    root.add_file("meminfo", meminfo);
+    #[cfg(feature = "irq")]
+    root.add_file("interrupts", interrupts);
+    root.add_file("uptime", uptime);
+    root.add_file("cpuinfo", cpuinfo);
+    root.add(
+        "self",
+        Arc::new(ProcSymlink::new(|| current_task_id().to_string())),
+    );
+    root.set_dynamic(
+        || {
+            all_tasks()
+                .into_iter()
+                .map(|t| (t.id.to_string(), FileType::Dir))
+                .collect()
+        },
+        |parent, name| task_dir(parent, name).map(|dir| dir as _),
+    );
+}
@@ -11,3 +11,34 @@ impl axhal::trap::TrapHandler for TrapHandlerImpl {
         }
     }
 }
+
+#[cfg(feature = "paging")]
+#[crate_interface::impl_interface]
+impl axhal::trap::PageFaultHandler for TrapHandlerImpl {
+    fn handle_page_fault(
+        vaddr: axhal::mem::VirtAddr,
+        access_flags: axhal::paging::MappingFlags,
+        _is_user: bool,
+    ) -> bool {
+        #[cfg(feature = "uspace")]
+        return axprocess::handle_page_fault(vaddr, access_flags, _is_user);
+        #[cfg(not(feature = "uspace"))]
+        {
+            if axmm::handle_page_fault(axmm::kernel_aspace(), vaddr, access_flags) {
+                return true;
+            }
+            // there are no processes, just terminate the faulting task
+            #[cfg(feature = "multitask")]
+            if _is_user {
+                warn!(
+                    "task {} page fault @ {:#x} ({:?}), killed",
+                    axtask::current().id_name(),
+                    vaddr,
+                    access_flags
+                );
+                axtask::exit(-1);
+            }
+            false
+        }
+    }
+}
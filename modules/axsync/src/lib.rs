@@ -3,26 +3,72 @@
 //! Currently supported primitives:
 //!
 //! - [`Mutex`]: A mutual exclusion primitive.
+//! - [`Condvar`]: A condition variable, used with [`Mutex`].
+//! - [`RwLock`]: A readers-writer lock.
+//! - [`Semaphore`]: A counting semaphore.
+//! - [`Barrier`]: A barrier to synchronize a number of tasks.
 //! - mod [`spin`](spinlock): spin-locks.
 //!
 //! # Cargo Features
 //!
 //! - `multitask`: For use in the multi-threaded environments. If the feature is
-//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`]. This
-//!   feature is enabled by default.
+//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`], and other
+//!   blocking primitives are not available. This feature is enabled by default.
+//! - `irq`: Use timers to implement the timed waits (e.g.,
+//!   [`Condvar::wait_timeout`]). If the feature is not enabled, the waiting
+//!   task keeps yielding until the deadline instead.
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
 
 pub use spinlock as spin;
 
+#[cfg(feature = "multitask")]
+mod barrier;
+#[cfg(feature = "multitask")]
+mod condvar;
 #[cfg(feature = "multitask")]
 mod mutex;
+#[cfg(feature = "multitask")]
+mod rwlock;
+#[cfg(feature = "multitask")]
+mod semaphore;
 
 #[cfg(feature = "multitask")]
 #[doc(cfg(feature = "multitask"))]
-pub use self::mutex::{Mutex, MutexGuard};
+pub use self::{
+    barrier::{Barrier, BarrierWaitResult},
+    condvar::{Condvar, WaitTimeoutResult},
+    mutex::{Mutex, MutexGuard},
+    rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    semaphore::Semaphore,
+};
 
 #[cfg(not(feature = "multitask"))]
 #[doc(cfg(not(feature = "multitask")))]
 pub use spinlock::{SpinNoIrq as Mutex, SpinNoIrqGuard as MutexGuard};
+
+/// Blocks the current task in the wait queue until the `condition` becomes
+/// true or the given duration has elapsed, returns whether it timed out.
+#[cfg(feature = "multitask")]
+fn wait_timeout_until<F>(wq: &axtask::WaitQueue, dur: core::time::Duration, condition: F) -> bool
+where
+    F: Fn() -> bool,
+{
+    #[cfg(feature = "irq")]
+    {
+        wq.wait_timeout_until(dur, condition)
+    }
+    #[cfg(not(feature = "irq"))]
+    {
+        let _ = wq; // no timers to wake us up, poll the condition instead
+        let deadline = axhal::time::current_time() + dur;
+        while !condition() {
+            if axhal::time::current_time() >= deadline {
+                return true;
+            }
+            axtask::yield_now();
+        }
+        false
+    }
+}
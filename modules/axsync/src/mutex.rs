@@ -23,7 +23,7 @@ pub struct Mutex<T: ?Sized> {
 ///
 /// When the guard falls out of scope it will release the lock.
 pub struct MutexGuard<'a, T: ?Sized + 'a> {
-    lock: &'a Mutex<T>,
+    pub(crate) lock: &'a Mutex<T>,
     data: *mut T,
 }
 
@@ -118,6 +118,20 @@ impl<T: ?Sized> Mutex<T> {
         }
     }
 
+    /// Creates a guard for the [`Mutex`] without locking it.
+    ///
+    /// # Safety
+    ///
+    /// The lock must be held by the current thread, e.g., it was locked
+    /// before and the guard was forgotten. This can be useful for FFI that
+    /// doesn’t know how to deal with RAII.
+    pub unsafe fn make_guard_unchecked(&self) -> MutexGuard<T> {
+        MutexGuard {
+            lock: self,
+            data: unsafe { &mut *self.data.get() },
+        }
+    }
+
     /// Force unlock the [`Mutex`].
     ///
     /// # Safety
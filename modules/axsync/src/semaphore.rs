@@ -0,0 +1,78 @@
+//! A counting semaphore.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axtask::WaitQueue;
+
+/// A counting semaphore.
+///
+/// It maintains a number of permits. [`Semaphore::acquire`] blocks the
+/// current task until a permit is available and then takes it,
+/// [`Semaphore::release`] adds a permit and wakes up a waiting task.
+pub struct Semaphore {
+    wq: WaitQueue,
+    count: AtomicUsize,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given number of permits.
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            count: AtomicUsize::new(permits),
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Tries to take a permit without blocking, returns `true` if successful.
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+        loop {
+            if count == 0 {
+                return false;
+            }
+            match self.count.compare_exchange_weak(
+                count,
+                count - 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(c) => count = c,
+            }
+        }
+    }
+
+    /// Takes a permit, blocks the current task until one is available.
+    pub fn acquire(&self) {
+        self.wq.wait_until(|| self.try_acquire());
+    }
+
+    /// Takes a permit, blocks the current task until one is available or the
+    /// given duration has elapsed.
+    ///
+    /// Returns `true` if a permit is taken, `false` if timed out.
+    pub fn acquire_timeout(&self, dur: Duration) -> bool {
+        self.try_acquire() || !crate::wait_timeout_until(&self.wq, dur, || self.try_acquire())
+    }
+
+    /// Adds a permit to the semaphore and wakes up one waiting task.
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}
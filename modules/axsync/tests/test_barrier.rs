@@ -0,0 +1,30 @@
+use axsync::{Barrier, Mutex, Semaphore};
+use axtask as thread;
+
+#[test]
+fn test_barrier() {
+    thread::init_scheduler();
+
+    const NUM_TASKS: usize = 10;
+    static BARRIER: Barrier = Barrier::new(NUM_TASKS);
+    static LEADERS: Mutex<usize> = Mutex::new(0);
+    static DONE: Semaphore = Semaphore::new(0);
+
+    for _ in 0..NUM_TASKS - 1 {
+        thread::spawn(|| {
+            if BARRIER.wait().is_leader() {
+                *LEADERS.lock() += 1;
+            }
+            DONE.release();
+        });
+    }
+
+    if BARRIER.wait().is_leader() {
+        *LEADERS.lock() += 1;
+    }
+    for _ in 0..NUM_TASKS - 1 {
+        DONE.acquire();
+    }
+    assert_eq!(*LEADERS.lock(), 1);
+    println!("Barrier test OK");
+}
@@ -0,0 +1,29 @@
+use axsync::{Condvar, Mutex};
+use axtask as thread;
+
+#[test]
+fn test_condvar() {
+    thread::init_scheduler();
+
+    const NUM_TASKS: u32 = 10;
+    static M: Mutex<(u32, bool)> = Mutex::new((0, false));
+    static CV: Condvar = Condvar::new();
+
+    for _ in 0..NUM_TASKS {
+        thread::spawn(|| {
+            let mut val = M.lock();
+            val.0 += 1;
+            CV.notify_all();
+            let mut val = CV.wait_while(val, |v| !v.1);
+            val.0 -= 1;
+            CV.notify_all();
+        });
+    }
+
+    let mut val = CV.wait_while(M.lock(), |v| v.0 < NUM_TASKS);
+    val.1 = true;
+    CV.notify_all();
+    let val = CV.wait_while(val, |v| v.0 > 0);
+    assert_eq!(val.0, 0);
+    println!("Condvar test OK");
+}
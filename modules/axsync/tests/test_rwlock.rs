@@ -0,0 +1,42 @@
+use axsync::{RwLock, Semaphore};
+use axtask as thread;
+
+fn may_interrupt() {
+    // simulate interrupts
+    if rand::random::<u32>() % 3 == 0 {
+        thread::yield_now();
+    }
+}
+
+#[test]
+fn test_rwlock() {
+    thread::init_scheduler();
+
+    const NUM_TASKS: u32 = 10;
+    const NUM_ITERS: u32 = 1000;
+    static RW: RwLock<(u32, u32)> = RwLock::new((0, 0));
+    static DONE: Semaphore = Semaphore::new(0);
+
+    for _ in 0..NUM_TASKS {
+        thread::spawn(|| {
+            for _ in 0..NUM_ITERS {
+                let mut val = RW.write();
+                val.0 += 1;
+                may_interrupt();
+                val.1 += 1;
+                drop(val);
+                let val = RW.read();
+                may_interrupt();
+                assert_eq!(val.0, val.1);
+            }
+            DONE.release();
+        });
+    }
+
+    for _ in 0..NUM_TASKS {
+        DONE.acquire();
+    }
+    assert_eq!(*RW.read(), (NUM_TASKS * NUM_ITERS, NUM_TASKS * NUM_ITERS));
+    assert_eq!(DONE.available_permits(), 0);
+    println!("RwLock test OK");
+}
@@ -2,10 +2,16 @@
 
 use alloc::{string::String, sync::Arc};
 
-pub(crate) use crate::run_queue::{AxRunQueue, RUN_QUEUE};
+pub(crate) use crate::run_queue::{current_run_queue, AxRunQueue};
 
 #[doc(cfg(feature = "multitask"))]
-pub use crate::task::{CurrentTask, TaskId, TaskInner};
+pub use crate::cpumask::AxCpuMask;
+#[doc(cfg(feature = "multitask"))]
+pub use crate::futex::{
+    futex_requeue, futex_wait, futex_wait_timeout, futex_wake, FutexKey, FutexWaitResult,
+};
+#[doc(cfg(feature = "multitask"))]
+pub use crate::task::{all_tasks, CurrentTask, TaskId, TaskInner, TaskState};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::wait_queue::WaitQueue;
 
@@ -84,7 +90,7 @@ pub fn init_scheduler_secondary() {
 #[doc(cfg(feature = "irq"))]
 pub fn on_timer_tick() {
     crate::timers::check_events();
-    RUN_QUEUE.lock().scheduler_timer_tick();
+    current_run_queue().scheduler_timer_tick();
 }
 
 /// Spawns a new task with the given parameters.
@@ -94,11 +100,50 @@ pub fn spawn_raw<F>(f: F, name: String, stack_size: usize) -> AxTaskRef
 where
     F: FnOnce() + Send + 'static,
 {
-    let task = TaskInner::new(f, name, stack_size);
-    RUN_QUEUE.lock().add_task(task.clone());
+    spawn_raw_with_affinity(f, name, stack_size, AxCpuMask::full())
+}
+
+/// Spawns a new task with the given parameters, it only runs on CPUs in
+/// `cpumask`.
+///
+/// Returns the task reference.
+///
+/// # Panics
+///
+/// Panics if `cpumask` is empty.
+pub fn spawn_raw_with_affinity<F>(
+    f: F,
+    name: String,
+    stack_size: usize,
+    cpumask: AxCpuMask,
+) -> AxTaskRef
+where
+    F: FnOnce() + Send + 'static,
+{
+    let task = new_task(f, name, stack_size);
+    assert!(task.set_cpumask(cpumask), "empty CPU affinity mask");
+    spawn_task(task.clone());
     task
 }
 
+/// Creates a new task with the given parameters, which doesn't run until it's
+/// passed to [`spawn_task`].
+///
+/// It allows the caller to set up the data indexed by the task ID before the
+/// task runs.
+pub fn new_task<F>(f: F, name: String, stack_size: usize) -> AxTaskRef
+where
+    F: FnOnce() + Send + 'static,
+{
+    TaskInner::new(f, name, stack_size)
+}
+
+/// Starts running the task created by [`new_task`]. It must be called only
+/// once for each task.
+pub fn spawn_task(task: AxTaskRef) {
+    current_run_queue().add_task(task);
+}
+
 /// Spawns a new task with the default parameters.
 ///
 /// The default task name is an empty string. The default task stack size is
@@ -122,13 +167,50 @@ where
 ///
 /// [CFS]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
 pub fn set_priority(prio: isize) -> bool {
-    RUN_QUEUE.lock().set_current_priority(prio)
+    current_run_queue().set_current_priority(prio)
+}
+
+/// Sets the CPU affinity mask of the current task.
+///
+/// If the current CPU is not in `cpumask`, the current task is moved to
+/// another CPU immediately.
+///
+/// Returns `false` if `cpumask` is empty.
+pub fn set_current_affinity(cpumask: AxCpuMask) -> bool {
+    if !current().set_cpumask(cpumask) {
+        return false;
+    }
+    let mut rq = current_run_queue();
+    if !cpumask.contains(axhal::cpu::this_cpu_id()) {
+        rq.yield_current();
+    }
+    true
+}
+
+/// Switches the current task to the address space with the given page table
+/// root.
+///
+/// The page table is also switched to whenever the task is switched in later.
+///
+/// # Safety
+///
+/// The page table must map the kernel space (except on AArch64, where the
+/// kernel space is mapped by `TTBR1_EL1`), and must not be freed while it is
+/// used by the task.
+#[cfg(feature = "uspace")]
+pub unsafe fn set_current_page_table_root(root_paddr: axhal::mem::PhysAddr) {
+    let _guard = kernel_guard::NoPreemptIrqSave::new();
+    (*current().ctx_mut_ptr()).set_page_table_root(root_paddr);
+    #[cfg(target_arch = "aarch64")]
+    axhal::arch::write_page_table_root0(root_paddr);
+    #[cfg(not(target_arch = "aarch64"))]
+    axhal::arch::write_page_table_root(root_paddr);
 }
 
 /// Current task gives up the CPU time voluntarily, and switches to another
 /// ready task.
 pub fn yield_now() {
-    RUN_QUEUE.lock().yield_current();
+    current_run_queue().yield_current();
 }
 
 /// Current task is going to sleep for the given duration.
@@ -143,14 +225,56 @@ pub fn sleep(dur: core::time::Duration) {
 /// If the feature `irq` is not enabled, it uses busy-wait instead.
 pub fn sleep_until(deadline: axhal::time::TimeValue) {
     #[cfg(feature = "irq")]
-    RUN_QUEUE.lock().sleep_until(deadline);
+    current_run_queue().sleep_until(deadline, false);
     #[cfg(not(feature = "irq"))]
     axhal::time::busy_wait_until(deadline);
 }
 
+/// Like [`sleep_until`], but returns early if the current task has pending
+/// signals that are not blocked.
+///
+/// Returns `false` if it's interrupted by signals.
+pub fn sleep_until_interruptible(deadline: axhal::time::TimeValue) -> bool {
+    #[cfg(feature = "irq")]
+    current_run_queue().sleep_until(deadline, true);
+    #[cfg(not(feature = "irq"))]
+    while axhal::time::current_time() < deadline && !current().has_pending_signal() {
+        crate::yield_now();
+    }
+    axhal::time::current_time() >= deadline || !current().has_pending_signal()
+}
+
+/// Sends signal `signo` to the task.
+///
+/// The signal is added to the pending set of the task. If it's not blocked,
+/// the task is woken up if it's blocked in an interruptible wait (e.g.,
+/// [`WaitQueue::wait_until_interruptible`]). It's up to the upper layers to
+/// deliver the signal, i.e., to run the signal handler or the default action.
+///
+/// # Panics
+///
+/// Panics if `signo` is not in `1..=64`.
+pub fn send_signal(task: &AxTaskRef, signo: usize) {
+    assert!((1..=64).contains(&signo), "invalid signal number {}", signo);
+    if task.add_pending_signal(signo) {
+        interrupt(task);
+    }
+}
+
+/// Wakes up the task if it's blocked in an interruptible wait.
+fn interrupt(task: &AxTaskRef) {
+    // The task may be about to block on another CPU after checking its
+    // pending signals. It checks them again after being marked blocked, so
+    // it's only woken up here if it has been marked.
+    core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+    if task.is_interruptible() && task.state() == TaskState::Blocked {
+        current_run_queue().unblock_task(task.clone(), true);
+    }
+}
+
 /// Exits the current task.
 pub fn exit(exit_code: i32) -> ! {
-    RUN_QUEUE.lock().exit_current(exit_code)
+    current_run_queue().exit_current(exit_code)
 }
 
 /// The idle task routine.
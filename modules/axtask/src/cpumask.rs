@@ -0,0 +1,91 @@
+use core::fmt;
+
+use axconfig::SMP;
+
+const _: () = assert!(SMP <= usize::BITS as usize, "too many CPUs for `AxCpuMask`");
+
+const VALID_BITS: usize = usize::MAX >> (usize::BITS as usize - SMP);
+
+/// A set of CPUs that a task is allowed to run on (the CPU affinity).
+///
+/// CPU `i` is in the set if bit `i` is set. Bits of CPUs that do not exist
+/// (i.e., not less than [`axconfig::SMP`]) are always cleared.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AxCpuMask(usize);
+
+impl AxCpuMask {
+    /// Creates an empty mask.
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Creates a mask with all CPUs.
+    pub const fn full() -> Self {
+        Self(VALID_BITS)
+    }
+
+    /// Creates a mask with only the given CPU.
+    pub const fn one_shot(cpu_id: usize) -> Self {
+        if cpu_id < SMP {
+            Self(1 << cpu_id)
+        } else {
+            Self(0)
+        }
+    }
+
+    /// Creates a mask from the raw bits, bits of CPUs that do not exist are
+    /// ignored.
+    pub const fn from_raw_bits(bits: usize) -> Self {
+        Self(bits & VALID_BITS)
+    }
+
+    /// Returns the raw bits of the mask.
+    pub const fn bits(&self) -> usize {
+        self.0
+    }
+
+    /// Whether the mask contains no CPU.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the given CPU is in the mask.
+    pub const fn contains(&self, cpu_id: usize) -> bool {
+        cpu_id < SMP && self.0 & (1 << cpu_id) != 0
+    }
+
+    /// Adds (`value` is `true`) or removes (`value` is `false`) the given CPU
+    /// to or from the mask.
+    pub fn set(&mut self, cpu_id: usize, value: bool) {
+        if cpu_id < SMP {
+            if value {
+                self.0 |= 1 << cpu_id;
+            } else {
+                self.0 &= !(1 << cpu_id);
+            }
+        }
+    }
+
+    /// Returns the CPU with the smallest ID in the mask.
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    /// Returns an iterator over the CPU IDs in the mask, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let bits = self.0;
+        (0..SMP).filter(move |&i| bits & (1 << i) != 0)
+    }
+}
+
+impl Default for AxCpuMask {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AxCpuMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AxCpuMask({:#x})", self.0)
+    }
+}
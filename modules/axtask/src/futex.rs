@@ -0,0 +1,282 @@
+//! Fast user-space mutexes (futexes).
+//!
+//! Tasks block on a futex word (a 32-bit integer) until it's woken up by
+//! others. The waiting tasks are kept in a fixed number of buckets hashed by
+//! the address of the futex word, so no memory is allocated for the futex
+//! itself, and the uncontended paths of the synchronization primitives built
+//! on it never enter the scheduler.
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use axhal::time::TimeValue;
+use spinlock::SpinRaw;
+
+use crate::{current_run_queue, AxTaskRef};
+
+const FUTEX_BUCKETS: usize = 64;
+
+/// The bucket index of a waiter that has been woken up.
+const WOKEN: usize = usize::MAX;
+
+/// Waiting tasks whose futex keys have the same hash.
+type Bucket = SpinRaw<VecDeque<(FutexKey, Arc<Waiter>)>>;
+
+/// Buckets of waiting tasks, indexed by the hash of their [`FutexKey`].
+///
+/// They are only locked with IRQs disabled (mostly when the run queue is
+/// locked), so a raw spinlock is enough.
+static BUCKETS: [Bucket; FUTEX_BUCKETS] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Bucket = SpinRaw::new(VecDeque::new());
+    [EMPTY; FUTEX_BUCKETS]
+};
+
+/// Identifies a futex by the address of the futex word, and the address
+/// space it's in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FutexKey {
+    space: usize,
+    addr: usize,
+}
+
+impl FutexKey {
+    /// Creates a key of the futex word at `addr` in the address space
+    /// `space`.
+    ///
+    /// `space` can be any value that identifies the address space, e.g., the
+    /// page table root of a user process, or 0 for the kernel space.
+    pub const fn new(space: usize, addr: usize) -> Self {
+        Self { space, addr }
+    }
+
+    /// Creates a key of the futex word in the kernel space.
+    pub fn kernel(word: &AtomicU32) -> Self {
+        Self::new(0, word as *const _ as usize)
+    }
+
+    fn bucket(&self) -> usize {
+        ((self.addr >> 2) ^ (self.space >> 12)) % FUTEX_BUCKETS
+    }
+}
+
+/// The result of [`futex_wait`] and [`futex_wait_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FutexWaitResult {
+    /// Woken up by [`futex_wake`] or [`futex_requeue`].
+    Woken,
+    /// The futex word did not contain the expected value, the task was not
+    /// blocked.
+    Mismatch,
+    /// The deadline has passed.
+    TimedOut,
+    /// Interrupted by signals that are not blocked.
+    Interrupted,
+}
+
+struct Waiter {
+    task: AxTaskRef,
+    /// The bucket that the waiter is in, it's changed by [`futex_requeue`],
+    /// and is [`WOKEN`] after woken up.
+    bucket: AtomicUsize,
+}
+
+fn wait_inner(
+    key: FutexKey,
+    word: &AtomicU32,
+    expected: u32,
+    deadline: Option<TimeValue>,
+    interruptible: bool,
+) -> FutexWaitResult {
+    let curr = crate::current();
+    let waiter = Arc::new(Waiter {
+        task: curr.clone(),
+        bucket: AtomicUsize::new(key.bucket()),
+    });
+    // Check the value before taking the locks, so that the page of the futex
+    // word is faulted in (if it's mapped lazily) without locks held.
+    if word.load(Ordering::Acquire) != expected {
+        return FutexWaitResult::Mismatch;
+    }
+    {
+        let mut rq = current_run_queue();
+        // Hold the bucket lock while checking the value, so a waker that
+        // changes the value first cannot miss us.
+        let mut bucket = BUCKETS[key.bucket()].lock();
+        if word.load(Ordering::Acquire) != expected {
+            return FutexWaitResult::Mismatch;
+        }
+        if interruptible {
+            curr.set_interruptible(true);
+            if curr.has_pending_signal() {
+                curr.set_interruptible(false);
+                return FutexWaitResult::Interrupted;
+            }
+        }
+        let queued = waiter.clone();
+        rq.block_current(move |_task| {
+            bucket.push_back((key, queued));
+            drop(bucket);
+            // Set the alarm after blocked, as the timer may be fired on
+            // another CPU immediately.
+            #[cfg(feature = "irq")]
+            if let Some(deadline) = deadline {
+                crate::timers::set_alarm_wakeup(deadline, _task);
+            }
+        });
+    }
+    curr.set_interruptible(false);
+    #[cfg(feature = "irq")]
+    if curr.in_timer_list() {
+        crate::timers::cancel_alarm(curr.as_task_ref());
+    }
+
+    // Not woken up by others, remove ourselves from the bucket. It may be
+    // requeued to another bucket at the same time, so retry on changes.
+    let _guard = kernel_guard::IrqSave::new();
+    loop {
+        let index = waiter.bucket.load(Ordering::Acquire);
+        if index == WOKEN {
+            return FutexWaitResult::Woken;
+        }
+        let mut bucket = BUCKETS[index].lock();
+        if waiter.bucket.load(Ordering::Acquire) == index {
+            bucket.retain(|(_, w)| !Arc::ptr_eq(w, &waiter));
+            break;
+        }
+    }
+    if deadline.is_some_and(|deadline| axhal::time::current_time() >= deadline) {
+        return FutexWaitResult::TimedOut;
+    }
+    FutexWaitResult::Interrupted
+}
+
+/// Blocks the current task on the futex `key` if the futex word `word`
+/// contains `expected`, until it's woken up by [`futex_wake`].
+///
+/// The value is checked atomically with blocking, i.e., a waker that changes
+/// the value and then calls [`futex_wake`] never misses this task.
+///
+/// If `interruptible` is true, it also returns when the current task has
+/// pending signals that are not blocked.
+///
+/// The futex word must be valid memory. It's read with IRQs disabled and the
+/// run queue locked, so page faults on it are only tolerated on the first
+/// read, which is done before taking the locks.
+pub fn futex_wait(
+    key: FutexKey,
+    word: &AtomicU32,
+    expected: u32,
+    interruptible: bool,
+) -> FutexWaitResult {
+    wait_inner(key, word, expected, None, interruptible)
+}
+
+/// Like [`futex_wait`], but also returns when the given deadline has passed.
+///
+/// If the `irq` feature is not enabled, it keeps yielding and polling the
+/// futex word until the deadline.
+pub fn futex_wait_timeout(
+    key: FutexKey,
+    word: &AtomicU32,
+    expected: u32,
+    deadline: TimeValue,
+    interruptible: bool,
+) -> FutexWaitResult {
+    #[cfg(feature = "irq")]
+    if axhal::time::current_time() < deadline {
+        return wait_inner(key, word, expected, Some(deadline), interruptible);
+    }
+    #[cfg(not(feature = "irq"))]
+    if word.load(Ordering::Acquire) == expected {
+        // no timers to wake us up, poll the futex word instead
+        let _ = key;
+        let curr = crate::current();
+        while word.load(Ordering::Acquire) == expected {
+            if interruptible && curr.has_pending_signal() {
+                return FutexWaitResult::Interrupted;
+            }
+            if axhal::time::current_time() >= deadline {
+                return FutexWaitResult::TimedOut;
+            }
+            crate::yield_now();
+        }
+        return FutexWaitResult::Woken;
+    }
+    if word.load(Ordering::Acquire) != expected {
+        FutexWaitResult::Mismatch
+    } else {
+        FutexWaitResult::TimedOut
+    }
+}
+
+/// Wakes up at most `count` tasks blocked on the futex `key`.
+///
+/// Returns the number of tasks woken up.
+pub fn futex_wake(key: FutexKey, count: usize) -> usize {
+    futex_requeue(key, count, key, 0, None).unwrap_or(0)
+}
+
+/// Wakes up at most `count` tasks blocked on the futex `key`, and moves at
+/// most `requeue_count` of the remaining ones to the futex `key2`.
+///
+/// If `cmp` is given as `(word, expected)`, it checks that the futex word
+/// contains `expected` first, and returns `None` if not.
+///
+/// Returns the number of tasks woken up or requeued.
+pub fn futex_requeue(
+    key: FutexKey,
+    count: usize,
+    key2: FutexKey,
+    requeue_count: usize,
+    cmp: Option<(&AtomicU32, u32)>,
+) -> Option<usize> {
+    let (index, index2) = (key.bucket(), key2.bucket());
+    // fault in the futex word before taking the locks, as in `wait_inner`
+    if let Some((word, expected)) = cmp {
+        if word.load(Ordering::Acquire) != expected {
+            return None;
+        }
+    }
+    let mut rq = current_run_queue();
+    // Lock the buckets in order to avoid deadlocks.
+    let mut bucket = BUCKETS[index.min(index2)].lock();
+    let mut bucket2 = if index != index2 {
+        Some(BUCKETS[index.max(index2)].lock())
+    } else {
+        None
+    };
+    let (bucket, mut bucket2) = match bucket2.as_mut() {
+        Some(other) if index > index2 => (&mut **other, Some(&mut *bucket)),
+        Some(other) => (&mut *bucket, Some(&mut **other)),
+        None => (&mut *bucket, None),
+    };
+    if cmp.is_some_and(|(word, expected)| word.load(Ordering::Acquire) != expected) {
+        return None;
+    }
+
+    let (mut woken, mut requeued) = (0, 0);
+    let mut i = 0;
+    while i < bucket.len() && (woken < count || requeued < requeue_count) {
+        if bucket[i].0 != key {
+            i += 1;
+        } else if woken < count {
+            let (_, waiter) = bucket.remove(i).unwrap();
+            waiter.bucket.store(WOKEN, Ordering::Release);
+            rq.unblock_task(waiter.task.clone(), true);
+            woken += 1;
+        } else if let Some(bucket2) = bucket2.as_mut() {
+            let (_, waiter) = bucket.remove(i).unwrap();
+            waiter.bucket.store(index2, Ordering::Release);
+            bucket2.push_back((key2, waiter));
+            requeued += 1;
+        } else {
+            // in the same bucket, just change the key
+            bucket[i].0 = key2;
+            i += 1;
+            requeued += 1;
+        }
+    }
+    Some(woken + requeued)
+}
@@ -13,6 +13,8 @@
 //!    APIs can be used, such as [`sleep`], [`sleep_until`], and
 //!    [`WaitQueue::wait_timeout`].
 //! - `preempt`: Enable preemptive scheduling.
+//! - `uspace`: Enable per-task address spaces. The page table of the task is
+//!   switched on context switch.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
@@ -35,6 +37,8 @@ cfg_if::cfg_if! {
         extern crate log;
         extern crate alloc;
 
+        mod cpumask;
+        mod futex;
         mod run_queue;
         mod task;
         mod api;
@@ -43,6 +47,9 @@ cfg_if::cfg_if! {
         #[cfg(feature = "irq")]
         mod timers;
 
+        #[cfg(test)]
+        mod tests;
+
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
         pub use self::api::{sleep, sleep_until, yield_now};
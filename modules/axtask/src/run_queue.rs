@@ -1,14 +1,57 @@
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+use axconfig::SMP;
+use axhal::cpu::this_cpu_id;
+use kernel_guard::NoPreemptIrqSave;
 use lazy_init::LazyInit;
 use scheduler::BaseScheduler;
-use spinlock::SpinNoIrq;
+use spinlock::{SpinNoIrq, SpinRaw, SpinRawGuard};
 
 use crate::task::{CurrentTask, TaskState};
-use crate::{AxTaskRef, Scheduler, TaskInner, WaitQueue};
+use crate::{AxCpuMask, AxTaskRef, Scheduler, TaskInner, WaitQueue};
 
-// TODO: per-CPU
-pub(crate) static RUN_QUEUE: LazyInit<SpinNoIrq<AxRunQueue>> = LazyInit::new();
+/// How often (in timer ticks) each CPU tries to pull a task from the busiest
+/// CPU.
+#[cfg(feature = "irq")]
+const LOAD_BALANCE_INTERVAL: usize = 10;
+
+/// Per-CPU run queues, indexed by the CPU ID.
+///
+/// IRQs and preemption are disabled by [`CurrentRunQueueGuard`] before
+/// locking, so a raw spinlock is enough.
+pub(crate) static RUN_QUEUES: [LazyInit<SpinRaw<AxRunQueue>>; SMP] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const RQ: LazyInit<SpinRaw<AxRunQueue>> = LazyInit::new();
+    [RQ; SMP]
+};
+
+/// Number of ready tasks of each CPU (including those in the wake list), it
+/// can be read without locking the run queue.
+pub(crate) static RQ_LOADS: [AtomicUsize; SMP] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: AtomicUsize = AtomicUsize::new(0);
+    [ZERO; SMP]
+};
+
+/// Tasks woken up (or spawned) by other CPUs, they will be moved to the run
+/// queue of the target CPU on its next reschedule or IPI.
+pub(crate) static WAKE_LISTS: [SpinNoIrq<VecDeque<AxTaskRef>>; SMP] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: SpinNoIrq<VecDeque<AxTaskRef>> = SpinNoIrq::new(VecDeque::new());
+    [EMPTY; SMP]
+};
+
+/// Tasks that have just been switched out on each CPU, but are no longer
+/// allowed to run on it. They are moved away once the context switch is done.
+static MIGRATING_TASKS: [SpinNoIrq<Option<AxTaskRef>>; SMP] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const NONE: SpinNoIrq<Option<AxTaskRef>> = SpinNoIrq::new(None);
+    [NONE; SMP]
+};
 
 // TODO: per-CPU
 static EXITED_TASKS: SpinNoIrq<VecDeque<AxTaskRef>> = SpinNoIrq::new(VecDeque::new());
@@ -19,21 +62,126 @@ static WAIT_FOR_EXIT: WaitQueue = WaitQueue::new();
 static IDLE_TASK: LazyInit<AxTaskRef> = LazyInit::new();
 
 pub(crate) struct AxRunQueue {
+    cpu_id: usize,
     scheduler: Scheduler,
+    #[cfg(feature = "irq")]
+    ticks: usize,
+}
+
+/// A guard of the locked run queue of the current CPU.
+///
+/// IRQs and preemption are disabled until the guard is dropped.
+pub(crate) struct CurrentRunQueueGuard {
+    rq: ManuallyDrop<SpinRawGuard<'static, AxRunQueue>>,
+    _irq_guard: NoPreemptIrqSave,
+}
+
+/// Locks the run queue of the current CPU.
+pub(crate) fn current_run_queue() -> CurrentRunQueueGuard {
+    let irq_guard = NoPreemptIrqSave::new();
+    let rq = RUN_QUEUES[this_cpu_id()].lock();
+    CurrentRunQueueGuard {
+        rq: ManuallyDrop::new(rq),
+        _irq_guard: irq_guard,
+    }
+}
+
+/// Unlocks the run queue of the current CPU.
+///
+/// # Safety
+///
+/// The run queue must be locked by the previous task on this CPU, which
+/// switched to the current task without releasing it.
+pub(crate) unsafe fn force_unlock_current() {
+    RUN_QUEUES[this_cpu_id()].force_unlock();
+}
+
+impl Deref for CurrentRunQueueGuard {
+    type Target = AxRunQueue;
+    fn deref(&self) -> &Self::Target {
+        &self.rq
+    }
+}
+
+impl DerefMut for CurrentRunQueueGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rq
+    }
+}
+
+impl Drop for CurrentRunQueueGuard {
+    fn drop(&mut self) {
+        // The lock is held across context switches, and the current task may
+        // be resumed on another CPU. Release the run queue of the CPU we are
+        // running on now, which was locked by the task switched from.
+        unsafe { force_unlock_current() };
+    }
+}
+
+pub(crate) fn load_of(cpu_id: usize) -> usize {
+    RQ_LOADS[cpu_id].load(Ordering::Relaxed)
+}
+
+/// Selects the least loaded CPU in `cpumask`, `preferred` is selected if
+/// there is a tie.
+///
+/// If none of CPUs in `cpumask` is started, returns the first one of them.
+fn select_cpu(cpumask: AxCpuMask, preferred: usize) -> usize {
+    let mut selected = cpumask
+        .contains(preferred)
+        .then(|| (preferred, load_of(preferred)));
+    for cpu_id in cpumask.iter().filter(|&i| RUN_QUEUES[i].is_init()) {
+        let load = load_of(cpu_id);
+        if !matches!(selected, Some((_, min_load)) if min_load <= load) {
+            selected = Some((cpu_id, load));
+        }
+    }
+    selected.map_or_else(|| cpumask.first().unwrap(), |(cpu_id, _)| cpu_id)
+}
+
+/// Pushes a ready task to the wake list of another CPU, and sends an IPI to
+/// notify it.
+///
+/// The task must have been switched out completely.
+fn queue_remote(cpu_id: usize, task: AxTaskRef) {
+    trace!("task {} is queued to CPU {}", task.id_name(), cpu_id);
+    task.set_cpu_id(cpu_id);
+    RQ_LOADS[cpu_id].fetch_add(1, Ordering::Relaxed);
+    WAKE_LISTS[cpu_id].lock().push_back(task);
+    #[cfg(feature = "irq")]
+    if RUN_QUEUES[cpu_id].is_init() {
+        axhal::irq::send_ipi(cpu_id);
+    }
+}
+
+/// Moves the task that was just switched out on this CPU to an allowed CPU,
+/// if its CPU affinity has changed.
+///
+/// It must be called by the task switched to, before releasing the run queue.
+pub(crate) fn finish_migration() {
+    let cpu_id = this_cpu_id();
+    if let Some(task) = MIGRATING_TASKS[cpu_id].lock().take() {
+        queue_remote(select_cpu(task.cpumask(), cpu_id), task);
+    }
 }
 
 impl AxRunQueue {
-    pub fn new() -> SpinNoIrq<Self> {
-        let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE);
-        let mut scheduler = Scheduler::new();
-        scheduler.add_task(gc_task);
-        SpinNoIrq::new(Self { scheduler })
+    pub(crate) fn new(cpu_id: usize) -> SpinRaw<Self> {
+        SpinRaw::new(Self {
+            cpu_id,
+            scheduler: Scheduler::new(),
+            #[cfg(feature = "irq")]
+            ticks: 0,
+        })
     }
 
+    /// Adds a newly spawned task to the least loaded CPU in its CPU affinity
+    /// mask.
     pub fn add_task(&mut self, task: AxTaskRef) {
         debug!("task spawn: {}", task.id_name());
         assert!(task.is_ready());
-        self.scheduler.add_task(task);
+        let cpu_id = select_cpu(task.cpumask(), self.cpu_id);
+        self.add_task_to(cpu_id, task, false);
     }
 
     #[cfg(feature = "irq")]
@@ -43,6 +191,11 @@ impl AxRunQueue {
             #[cfg(feature = "preempt")]
             curr.set_preempt_pending(true);
         }
+
+        self.ticks += 1;
+        if SMP > 1 && self.ticks % LOAD_BALANCE_INTERVAL == 0 {
+            self.load_balance();
+        }
     }
 
     pub fn yield_current(&mut self) {
@@ -53,8 +206,12 @@ impl AxRunQueue {
     }
 
     pub fn set_current_priority(&mut self, prio: isize) -> bool {
-        self.scheduler
-            .set_priority(crate::current().as_task_ref(), prio)
+        let curr = crate::current();
+        let ok = self.scheduler.set_priority(curr.as_task_ref(), prio);
+        if ok {
+            curr.set_priority(prio);
+        }
+        ok
     }
 
     #[cfg(feature = "preempt")]
@@ -63,10 +220,9 @@ impl AxRunQueue {
         assert!(curr.is_running());
 
         // When we get the mutable reference of the run queue, we must
-        // have held the `SpinNoIrq` lock with both IRQs and preemption
-        // disabled. So we need to set `current_disable_count` to 1 in
-        // `can_preempt()` to obtain the preemption permission before
-        //  locking the run queue.
+        // have locked it with both IRQs and preemption disabled. So we need
+        // to set `current_disable_count` to 1 in `can_preempt()` to obtain
+        // the preemption permission before locking the run queue.
         let can_preempt = curr.can_preempt(1);
 
         debug!(
@@ -114,23 +270,33 @@ impl AxRunQueue {
 
         curr.set_state(TaskState::Blocked);
         wait_queue_push(curr.clone());
+        if interrupted_after_block(&curr) {
+            return;
+        }
         self.resched(false);
     }
 
+    /// Wakes up a blocked task on the CPU it last ran on.
+    ///
+    /// The task may be still switching out on that CPU, so it is always queued
+    /// there even if its CPU affinity has changed. It will be moved when that
+    /// CPU picks it.
     pub fn unblock_task(&mut self, task: AxTaskRef, resched: bool) {
         debug!("task unblock: {}", task.id_name());
-        if task.is_blocked() {
-            task.set_state(TaskState::Ready);
-            self.scheduler.add_task(task); // TODO: priority
-            if resched {
-                #[cfg(feature = "preempt")]
-                crate::current().set_preempt_pending(true);
-            }
+        // Other CPUs may try to wake up the same task at the same time (e.g.,
+        // by a timer and a `notify()`), only one of them can succeed.
+        if task.transition_state(TaskState::Blocked, TaskState::Ready) {
+            let cpu_id = task.cpu_id();
+            self.add_task_to(cpu_id, task, resched); // TODO: priority
         }
     }
 
+    /// Blocks the current task until `deadline`.
+    ///
+    /// If `interruptible` is true, it returns early if the task has pending
+    /// signals (before or during the sleep).
     #[cfg(feature = "irq")]
-    pub fn sleep_until(&mut self, deadline: axhal::time::TimeValue) {
+    pub fn sleep_until(&mut self, deadline: axhal::time::TimeValue, interruptible: bool) {
         let curr = crate::current();
         debug!("task sleep: {}, deadline={:?}", curr.id_name(), deadline);
         assert!(curr.is_running());
@@ -138,28 +304,74 @@ impl AxRunQueue {
 
         let now = axhal::time::current_time();
         if now < deadline {
-            crate::timers::set_alarm_wakeup(deadline, curr.clone());
+            if interruptible {
+                curr.set_interruptible(true);
+                if curr.has_pending_signal() {
+                    curr.set_interruptible(false);
+                    return;
+                }
+            }
+            // Block before setting the alarm, as the timer may be fired on
+            // another CPU immediately.
             curr.set_state(TaskState::Blocked);
+            if interrupted_after_block(&curr) {
+                curr.set_interruptible(false);
+                return;
+            }
+            crate::timers::set_alarm_wakeup(deadline, curr.clone());
             self.resched(false);
+            if interruptible {
+                curr.set_interruptible(false);
+                if curr.in_timer_list() {
+                    // woken up by a signal
+                    crate::timers::cancel_alarm(curr.as_task_ref());
+                }
+            }
         }
     }
 }
 
+/// Checks the pending signals again after the current task is marked
+/// blocked in an interruptible wait, and makes it running again if there are
+/// any, so it doesn't miss the signals sent after the first check.
+///
+/// It pairs with [`interrupt`](crate::api::interrupt), which only wakes up
+/// the tasks that have been marked blocked. Returns `false` if the task
+/// should switch out, which is also the case if it has been woken up by
+/// others in the meantime.
+fn interrupted_after_block(curr: &CurrentTask) -> bool {
+    if !curr.is_interruptible() {
+        return false;
+    }
+    fence(Ordering::SeqCst);
+    curr.has_pending_signal() && curr.transition_state(TaskState::Blocked, TaskState::Running)
+}
+
 impl AxRunQueue {
     /// Common reschedule subroutine. If `preempt`, keep current task's time
     /// slice, otherwise reset it.
     fn resched(&mut self, preempt: bool) {
+        self.drain_wake_list();
         let prev = crate::current();
         if prev.is_running() {
             prev.set_state(TaskState::Ready);
-            if !prev.is_idle() {
+            if prev.is_idle() {
+                // idle tasks are not in the run queue
+            } else if prev.cpumask().contains(self.cpu_id) {
                 self.scheduler.put_prev_task(prev.clone(), preempt);
+                RQ_LOADS[self.cpu_id].fetch_add(1, Ordering::Relaxed);
+            } else {
+                // It's still running on this CPU, move it after switching out.
+                *MIGRATING_TASKS[self.cpu_id].lock() = Some(prev.clone());
             }
         }
-        let next = self.scheduler.pick_next_task().unwrap_or_else(|| unsafe {
-            // Safety: IRQs must be disabled at this time.
-            IDLE_TASK.current_ref_raw().get_unchecked().clone()
-        });
+        let next = self
+            .pick_next_task()
+            .or_else(|| self.steal_task(1))
+            .unwrap_or_else(|| unsafe {
+                // Safety: IRQs must be disabled at this time.
+                IDLE_TASK.current_ref_raw().get_unchecked().clone()
+            });
         self.switch_to(prev, next);
     }
 
@@ -172,10 +384,15 @@ impl AxRunQueue {
         #[cfg(feature = "preempt")]
         next_task.set_preempt_pending(false);
         next_task.set_state(TaskState::Running);
+        next_task.set_cpu_id(self.cpu_id);
         if prev_task.ptr_eq(&next_task) {
             return;
         }
 
+        let now_ns = axhal::time::current_time_nanos();
+        prev_task.account_cpu_time(now_ns, false);
+        next_task.account_cpu_time(now_ns, true);
+
         unsafe {
             let prev_ctx_ptr = prev_task.ctx_mut_ptr();
             let next_ctx_ptr = next_task.ctx_mut_ptr();
@@ -188,6 +405,115 @@ impl AxRunQueue {
             CurrentTask::set_current(prev_task, next_task);
             (*prev_ctx_ptr).switch_to(&*next_ctx_ptr);
         }
+        // Now we are the next task, which may be resumed on another CPU.
+        finish_migration();
+    }
+
+    /// Picks the next task allowed to run on this CPU, tasks not allowed
+    /// (their CPU affinity has changed after queued) are moved to other CPUs.
+    fn pick_next_task(&mut self) -> Option<AxTaskRef> {
+        while let Some(task) = self.scheduler.pick_next_task() {
+            RQ_LOADS[self.cpu_id].fetch_sub(1, Ordering::Relaxed);
+            let cpumask = task.cpumask();
+            if cpumask.contains(self.cpu_id) {
+                return Some(task);
+            }
+            queue_remote(select_cpu(cpumask, self.cpu_id), task);
+        }
+        None
+    }
+
+    /// Adds a ready task to the run queue of `cpu_id`.
+    ///
+    /// If it's another CPU, the task is pushed to the wake list of that CPU,
+    /// and an IPI is sent to notify it.
+    pub(crate) fn add_task_to(&mut self, cpu_id: usize, task: AxTaskRef, resched: bool) {
+        if cpu_id == self.cpu_id {
+            task.set_cpu_id(cpu_id);
+            RQ_LOADS[cpu_id].fetch_add(1, Ordering::Relaxed);
+            self.scheduler.add_task(task);
+            if resched {
+                #[cfg(feature = "preempt")]
+                crate::current().set_preempt_pending(true);
+            }
+        } else {
+            queue_remote(cpu_id, task);
+        }
+    }
+
+    /// Moves tasks in the wake list of this CPU to the run queue, returns
+    /// whether there were any.
+    pub(crate) fn drain_wake_list(&mut self) -> bool {
+        let tasks = core::mem::take(&mut *WAKE_LISTS[self.cpu_id].lock());
+        let woken = !tasks.is_empty();
+        for task in tasks {
+            // already counted in `RQ_LOADS` when pushed to the wake list
+            self.scheduler.add_task(task);
+        }
+        woken
+    }
+
+    /// Takes a ready task from the busiest CPU, if its load is at least
+    /// `min_load` and the task is allowed to run on this CPU.
+    ///
+    /// It never spins on the lock of other run queues, to avoid deadlocks
+    /// when two CPUs steal from each other.
+    pub(crate) fn steal_task(&mut self, min_load: usize) -> Option<AxTaskRef> {
+        let (busiest, load) = (0..SMP)
+            .filter(|&i| i != self.cpu_id && RUN_QUEUES[i].is_init())
+            .map(|i| (i, load_of(i)))
+            .max_by_key(|&(_, load)| load)?;
+        if load < min_load {
+            return None;
+        }
+        let mut busiest_rq = RUN_QUEUES[busiest].try_lock()?;
+        let task = busiest_rq.pick_next_task()?;
+        if !task.cpumask().contains(self.cpu_id) {
+            busiest_rq.scheduler.put_prev_task(task, true);
+            RQ_LOADS[busiest].fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        debug!(
+            "task {} is migrated from CPU {} to CPU {}",
+            task.id_name(),
+            busiest,
+            self.cpu_id
+        );
+        Some(task)
+    }
+
+    /// Pulls a task from the busiest CPU if it has at least 2 more ready tasks
+    /// than this CPU.
+    #[cfg(feature = "irq")]
+    pub(crate) fn load_balance(&mut self) {
+        let min_load = load_of(self.cpu_id) + 2;
+        if let Some(task) = self.steal_task(min_load) {
+            task.set_cpu_id(self.cpu_id);
+            self.scheduler.add_task(task);
+            RQ_LOADS[self.cpu_id].fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "preempt")]
+            {
+                let curr = crate::current();
+                if curr.is_idle() {
+                    curr.set_preempt_pending(true);
+                }
+            }
+        }
+    }
+}
+
+/// Handles the IPI sent by [`queue_remote`] from other CPUs.
+#[cfg(feature = "irq")]
+fn ipi_handler() {
+    let mut rq = current_run_queue();
+    if rq.drain_wake_list() {
+        #[cfg(feature = "preempt")]
+        {
+            let curr = crate::current();
+            if curr.is_idle() {
+                curr.set_preempt_pending(true);
+            }
+        }
     }
 }
 
@@ -221,7 +547,14 @@ pub(crate) fn init() {
     let main_task = TaskInner::new_init("main".into());
     main_task.set_state(TaskState::Running);
 
-    RUN_QUEUE.init_by(AxRunQueue::new());
+    let cpu_id = this_cpu_id();
+    RUN_QUEUES[cpu_id].init_by(AxRunQueue::new(cpu_id));
+    let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE);
+    RUN_QUEUES[cpu_id].lock().add_task_to(cpu_id, gc_task, false);
+
+    #[cfg(feature = "irq")]
+    axhal::irq::register_handler(axhal::irq::IPI_IRQ_NUM, ipi_handler);
+
     unsafe { CurrentTask::init_current(main_task) }
 }
 
@@ -229,5 +562,8 @@ pub(crate) fn init_secondary() {
     let idle_task = TaskInner::new_init("idle".into());
     idle_task.set_state(TaskState::Running);
     IDLE_TASK.with_current(|i| i.init_by(idle_task.clone()));
+
+    let cpu_id = this_cpu_id();
+    RUN_QUEUES[cpu_id].init_by(AxRunQueue::new(cpu_id));
     unsafe { CurrentTask::init_current(idle_task) }
 }
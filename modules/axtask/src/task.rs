@@ -1,18 +1,19 @@
-use alloc::{boxed::Box, string::String, sync::Arc};
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{boxed::Box, string::String, vec::Vec};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
-use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
-
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicIsize, AtomicU64, AtomicU8};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull, time::Duration};
 
 #[cfg(feature = "tls")]
 use axhal::tls::TlsArea;
 
 use axhal::arch::TaskContext;
 use memory_addr::{align_up_4k, VirtAddr};
+use spinlock::SpinNoIrq;
 
-use crate::{AxRunQueue, AxTask, AxTaskRef, WaitQueue};
+use crate::{AxCpuMask, AxRunQueue, AxTask, AxTaskRef, WaitQueue};
 
 /// A unique identifier for a thread.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -21,13 +22,20 @@ pub struct TaskId(u64);
 /// The possible states of a task.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub(crate) enum TaskState {
+pub enum TaskState {
+    /// The task is running on a CPU.
     Running = 1,
+    /// The task is ready to run, waiting in the run queue.
     Ready = 2,
+    /// The task is blocked, waiting for an event or a timer.
     Blocked = 3,
+    /// The task has exited, but has not been dropped.
     Exited = 4,
 }
 
+/// All living tasks, indexed by the task ID.
+static TASK_LIST: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
@@ -37,10 +45,29 @@ pub struct TaskInner {
 
     entry: Option<*mut dyn FnOnce()>,
     state: AtomicU8,
+    priority: AtomicIsize,
+    /// The CPU that the task is running on, or last ran on.
+    cpu_id: AtomicUsize,
+    /// Raw bits of the CPU affinity mask.
+    cpumask: AtomicUsize,
+
+    /// Total CPU time consumed (in nanoseconds), excluding the current run.
+    cpu_time_ns: AtomicU64,
+    /// The time (in nanoseconds) when the task was last switched in.
+    last_run_ns: AtomicU64,
 
     in_wait_queue: AtomicBool,
     #[cfg(feature = "irq")]
     in_timer_list: AtomicBool,
+    /// Whether the task is blocked in a wait that can be interrupted by
+    /// signals.
+    interruptible: AtomicBool,
+
+    /// Pending signals, bit `n - 1` is set if signal `n` is pending.
+    sig_pending: AtomicU64,
+    /// Blocked signals (the signal mask), in the same layout as
+    /// `sig_pending`.
+    sig_blocked: AtomicU64,
 
     #[cfg(feature = "preempt")]
     need_resched: AtomicBool,
@@ -96,11 +123,137 @@ impl TaskInner {
         self.name.as_str()
     }
 
+    /// Gets the state of the task.
+    #[inline]
+    pub fn state(&self) -> TaskState {
+        self.state.load(Ordering::Acquire).into()
+    }
+
+    /// Gets the priority of the task, which is last set by [`set_priority`].
+    ///
+    /// [`set_priority`]: crate::set_priority
+    pub fn priority(&self) -> isize {
+        self.priority.load(Ordering::Relaxed)
+    }
+
+    /// Gets the ID of the CPU that the task is running on, or last ran on if
+    /// it is not running.
+    ///
+    /// A ready task that has not run yet returns the CPU whose run queue it
+    /// is in.
+    pub fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Relaxed)
+    }
+
+    /// Gets the CPU affinity mask of the task.
+    pub fn cpumask(&self) -> AxCpuMask {
+        AxCpuMask::from_raw_bits(self.cpumask.load(Ordering::Relaxed))
+    }
+
+    /// Sets the CPU affinity mask of the task.
+    ///
+    /// Returns `false` and leaves the mask unchanged if `cpumask` is empty.
+    ///
+    /// If the task is running or ready on a CPU that is no longer in the
+    /// mask, it is moved to an allowed CPU the next time it is rescheduled or
+    /// picked. Use [`set_current_affinity`] to move the current task
+    /// immediately.
+    ///
+    /// [`set_current_affinity`]: crate::set_current_affinity
+    pub fn set_cpumask(&self, cpumask: AxCpuMask) -> bool {
+        if cpumask.is_empty() {
+            return false;
+        }
+        self.cpumask.store(cpumask.bits(), Ordering::Relaxed);
+        true
+    }
+
+    /// Gets the total CPU time consumed by the task.
+    pub fn cpu_time(&self) -> Duration {
+        let mut nanos = self.cpu_time_ns.load(Ordering::Relaxed);
+        if self.is_running() {
+            let now = axhal::time::current_time_nanos();
+            nanos += now.saturating_sub(self.last_run_ns.load(Ordering::Relaxed));
+        }
+        Duration::from_nanos(nanos)
+    }
+
+    /// Whether the task is an idle task.
+    #[inline]
+    pub const fn is_idle(&self) -> bool {
+        self.is_idle
+    }
+
+    /// Whether the task is an init task, i.e., the main task or the idle
+    /// task of a secondary CPU. The system is terminated when it exits.
+    #[inline]
+    pub const fn is_init(&self) -> bool {
+        self.is_init
+    }
+
+    /// Returns the top address of the kernel stack, or `None` if the task
+    /// has no kernel stack allocated by itself (e.g., an init task).
+    pub fn kernel_stack_top(&self) -> Option<VirtAddr> {
+        self.kstack.as_ref().map(|s| s.top())
+    }
+
     /// Get a combined string of the task ID and name.
     pub fn id_name(&self) -> alloc::string::String {
         alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name)
     }
 
+    /// Gets the set of pending signals, bit `n - 1` is set if signal `n` is
+    /// pending.
+    pub fn pending_signals(&self) -> u64 {
+        // pairs with `set_interruptible` and `add_pending_signal`
+        self.sig_pending.load(Ordering::SeqCst)
+    }
+
+    /// Gets the set of blocked signals (the signal mask), in the same layout
+    /// as [`pending_signals`](Self::pending_signals).
+    pub fn blocked_signals(&self) -> u64 {
+        self.sig_blocked.load(Ordering::Acquire)
+    }
+
+    /// Sets the set of blocked signals, returns the old one.
+    ///
+    /// Pending signals that become unblocked are not delivered by this
+    /// function, the caller should check them by
+    /// [`take_pending_signal`](Self::take_pending_signal).
+    pub fn set_blocked_signals(&self, blocked: u64) -> u64 {
+        self.sig_blocked.swap(blocked, Ordering::AcqRel)
+    }
+
+    /// Whether the task has pending signals that are not blocked.
+    pub fn has_pending_signal(&self) -> bool {
+        self.pending_signals() & !self.blocked_signals() != 0
+    }
+
+    /// Removes the lowest numbered pending signal that is not blocked from
+    /// the pending set, and returns its number.
+    pub fn take_pending_signal(&self) -> Option<usize> {
+        let blocked = self.blocked_signals();
+        let mut signo = None;
+        let _ = self
+            .sig_pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                let deliverable = pending & !blocked;
+                if deliverable == 0 {
+                    return None;
+                }
+                let bit = deliverable.trailing_zeros();
+                signo = Some(bit as usize + 1);
+                Some(pending & !(1 << bit))
+            });
+        signo
+    }
+
+    /// Removes the signals in `set` from the pending set, whether they are
+    /// blocked or not.
+    pub fn discard_signals(&self, set: u64) {
+        self.sig_pending.fetch_and(!set, Ordering::AcqRel);
+    }
+
     /// Wait for the task to exit, and return the exit code.
     ///
     /// It will return immediately if the task has already exited (but not dropped).
@@ -121,9 +274,17 @@ impl TaskInner {
             is_init: false,
             entry: None,
             state: AtomicU8::new(TaskState::Ready as u8),
+            priority: AtomicIsize::new(0),
+            cpu_id: AtomicUsize::new(axhal::cpu::this_cpu_id()),
+            cpumask: AtomicUsize::new(AxCpuMask::full().bits()),
+            cpu_time_ns: AtomicU64::new(0),
+            last_run_ns: AtomicU64::new(axhal::time::current_time_nanos()),
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
             in_timer_list: AtomicBool::new(false),
+            interruptible: AtomicBool::new(false),
+            sig_pending: AtomicU64::new(0),
+            sig_blocked: AtomicU64::new(0),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
@@ -153,11 +314,15 @@ impl TaskInner {
 
         t.entry = Some(Box::into_raw(Box::new(entry)));
         t.ctx.get_mut().init(task_entry as usize, kstack.top(), tls);
+        #[cfg(feature = "uspace")]
+        t.ctx
+            .get_mut()
+            .set_page_table_root(axhal::paging::kernel_page_table_root());
         t.kstack = Some(kstack);
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        Self::register(Arc::new(AxTask::new(t)))
     }
 
     /// Creates an "init task" using the current CPU states, to use as the
@@ -171,15 +336,20 @@ impl TaskInner {
     pub(crate) fn new_init(name: String) -> AxTaskRef {
         let mut t = Self::new_common(TaskId::new(), name);
         t.is_init = true;
+        #[cfg(feature = "uspace")]
+        t.ctx
+            .get_mut()
+            .set_page_table_root(axhal::paging::kernel_page_table_root());
         if t.name == "idle" {
             t.is_idle = true;
         }
-        Arc::new(AxTask::new(t))
+        Self::register(Arc::new(AxTask::new(t)))
     }
 
-    #[inline]
-    pub(crate) fn state(&self) -> TaskState {
-        self.state.load(Ordering::Acquire).into()
+    fn register(task: AxTaskRef) -> AxTaskRef {
+        let id = task.id().as_u64();
+        TASK_LIST.lock().insert(id, Arc::downgrade(&task));
+        task
     }
 
     #[inline]
@@ -187,6 +357,15 @@ impl TaskInner {
         self.state.store(state as u8, Ordering::Release)
     }
 
+    /// Changes the state from `from` to `to` atomically, returns `false` if
+    /// the current state is not `from`.
+    #[inline]
+    pub(crate) fn transition_state(&self, from: TaskState, to: TaskState) -> bool {
+        self.state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
     #[inline]
     pub(crate) fn is_running(&self) -> bool {
         matches!(self.state(), TaskState::Running)
@@ -198,28 +377,31 @@ impl TaskInner {
     }
 
     #[inline]
-    pub(crate) fn is_blocked(&self) -> bool {
-        matches!(self.state(), TaskState::Blocked)
+    pub(crate) fn in_wait_queue(&self) -> bool {
+        self.in_wait_queue.load(Ordering::Acquire)
     }
 
     #[inline]
-    pub(crate) const fn is_init(&self) -> bool {
-        self.is_init
+    pub(crate) fn set_in_wait_queue(&self, in_wait_queue: bool) {
+        self.in_wait_queue.store(in_wait_queue, Ordering::Release);
     }
 
     #[inline]
-    pub(crate) const fn is_idle(&self) -> bool {
-        self.is_idle
+    pub(crate) fn is_interruptible(&self) -> bool {
+        self.interruptible.load(Ordering::SeqCst)
     }
 
     #[inline]
-    pub(crate) fn in_wait_queue(&self) -> bool {
-        self.in_wait_queue.load(Ordering::Acquire)
+    pub(crate) fn set_interruptible(&self, interruptible: bool) {
+        self.interruptible.store(interruptible, Ordering::SeqCst);
     }
 
-    #[inline]
-    pub(crate) fn set_in_wait_queue(&self, in_wait_queue: bool) {
-        self.in_wait_queue.store(in_wait_queue, Ordering::Release);
+    /// Adds signal `signo` to the pending set, returns `true` if it's not
+    /// blocked.
+    pub(crate) fn add_pending_signal(&self, signo: usize) -> bool {
+        let bit = 1 << (signo - 1);
+        self.sig_pending.fetch_or(bit, Ordering::SeqCst);
+        self.blocked_signals() & bit == 0
     }
 
     #[inline]
@@ -265,13 +447,35 @@ impl TaskInner {
     fn current_check_preempt_pending() {
         let curr = crate::current();
         if curr.need_resched.load(Ordering::Acquire) && curr.can_preempt(0) {
-            let mut rq = crate::RUN_QUEUE.lock();
+            let mut rq = crate::current_run_queue();
             if curr.need_resched.load(Ordering::Acquire) {
                 rq.preempt_resched();
             }
         }
     }
 
+    #[inline]
+    pub(crate) fn set_cpu_id(&self, cpu_id: usize) {
+        self.cpu_id.store(cpu_id, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn set_priority(&self, prio: isize) {
+        self.priority.store(prio, Ordering::Relaxed);
+    }
+
+    /// Updates the CPU time when the task is switched out (`running` is
+    /// `false`) or switched in (`running` is `true`) at time `now_ns`.
+    pub(crate) fn account_cpu_time(&self, now_ns: u64, running: bool) {
+        if running {
+            self.last_run_ns.store(now_ns, Ordering::Relaxed);
+        } else {
+            let last = self.last_run_ns.load(Ordering::Relaxed);
+            self.cpu_time_ns
+                .fetch_add(now_ns.saturating_sub(last), Ordering::Relaxed);
+        }
+    }
+
     pub(crate) fn notify_exit(&self, exit_code: i32, rq: &mut AxRunQueue) {
         self.exit_code.store(exit_code, Ordering::Release);
         self.wait_for_exit.notify_all_locked(false, rq);
@@ -296,9 +500,20 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        TASK_LIST.lock().remove(&self.id.as_u64());
     }
 }
 
+/// Returns all living tasks (including idle tasks and exited but not yet
+/// dropped tasks), ordered by the task ID.
+pub fn all_tasks() -> Vec<AxTaskRef> {
+    TASK_LIST
+        .lock()
+        .values()
+        .filter_map(|task| task.upgrade())
+        .collect()
+}
+
 struct TaskStack {
     ptr: NonNull<u8>,
     layout: Layout,
@@ -380,7 +595,8 @@ impl Deref for CurrentTask {
 
 extern "C" fn task_entry() -> ! {
     // release the lock that was implicitly held across the reschedule
-    unsafe { crate::RUN_QUEUE.force_unlock() };
+    crate::run_queue::finish_migration();
+    unsafe { crate::run_queue::force_unlock_current() };
     #[cfg(feature = "irq")]
     axhal::arch::enable_irqs();
     let task = crate::current();
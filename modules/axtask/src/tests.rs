@@ -1,11 +1,66 @@
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Mutex, Once};
 
-use crate::{self as axtask, current, WaitQueue};
+use axconfig::SMP;
+use spinlock::SpinRawGuard;
+
+use crate::run_queue::{current_run_queue, load_of, AxRunQueue, RQ_LOADS, RUN_QUEUES, WAKE_LISTS};
+use crate::{self as axtask, current, AxCpuMask, AxTaskRef, TaskState, WaitQueue};
+use crate::{FutexKey, FutexWaitResult};
 
 static INIT: Once = Once::new();
 static SERIAL: Mutex<()> = Mutex::new(());
 
+/// The last CPU simulated on the host, which never runs tasks by itself.
+/// Tests move tasks to and from it, and must take all of them back before
+/// dropping it.
+///
+/// It's offline (never selected to run new tasks) when not in use.
+struct FakeCpu;
+
+impl FakeCpu {
+    const ID: usize = SMP - 1;
+
+    /// Brings the CPU online, returns `None` if there is only one CPU
+    /// (`AX_SMP=1`).
+    fn online() -> Option<Self> {
+        static INIT_RQ: Once = Once::new();
+        if SMP < 2 {
+            return None;
+        }
+        INIT_RQ.call_once(|| RUN_QUEUES[Self::ID].init_by(AxRunQueue::new(Self::ID)));
+        RQ_LOADS[Self::ID].store(0, Ordering::Relaxed);
+        Some(Self)
+    }
+
+    fn rq(&self) -> SpinRawGuard<'static, AxRunQueue> {
+        RUN_QUEUES[Self::ID].lock()
+    }
+
+    /// Moves a ready task of this CPU back to the current CPU.
+    fn take_back(&self) -> Option<AxTaskRef> {
+        let mut rq = current_run_queue();
+        let task = rq.steal_task(1)?;
+        rq.add_task_to(0, task.clone(), false);
+        Some(task)
+    }
+}
+
+impl Drop for FakeCpu {
+    fn drop(&mut self) {
+        assert_eq!(load_of(Self::ID), 0, "tasks are left on the fake CPU");
+        // pretend to be fully loaded, so no tasks are put on it
+        RQ_LOADS[Self::ID].store(usize::MAX / 2, Ordering::Relaxed);
+    }
+}
+
+/// Yields until there are no other ready tasks on this CPU.
+fn run_all_ready_tasks() {
+    while load_of(0) > 0 {
+        axtask::yield_now();
+    }
+}
+
 #[test]
 fn test_sched_fifo() {
     let _lock = SERIAL.lock();
@@ -38,6 +93,7 @@ fn test_fp_state_switch() {
     INIT.call_once(axtask::init_scheduler);
 
     const NUM_TASKS: usize = 5;
+    #[allow(clippy::approx_constant)]
     const FLOATS: [f64; NUM_TASKS] = [
         3.141592653589793,
         2.718281828459045,
@@ -128,3 +184,331 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_cross_cpu_wakeup() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WQ: WaitQueue = WaitQueue::new();
+
+    let task = axtask::spawn(|| WQ.wait());
+    while task.state() != TaskState::Blocked {
+        axtask::yield_now();
+    }
+    run_all_ready_tasks();
+    let Some(cpu1) = FakeCpu::online() else {
+        return;
+    };
+
+    // Pretend that it was blocked on CPU 1, so it's woken up there.
+    task.set_cpu_id(FakeCpu::ID);
+    assert!(WQ.notify_one(false));
+    assert_eq!(task.state(), TaskState::Ready);
+    assert_eq!(load_of(FakeCpu::ID), 1);
+    assert!(WAKE_LISTS[FakeCpu::ID]
+        .lock()
+        .iter()
+        .any(|t| alloc::sync::Arc::ptr_eq(t, &task)));
+
+    // CPU 1 receives the IPI.
+    assert!(cpu1.rq().drain_wake_list());
+    assert!(WAKE_LISTS[FakeCpu::ID].lock().is_empty());
+    assert_eq!(load_of(FakeCpu::ID), 1);
+
+    let stolen = cpu1.take_back().unwrap();
+    assert!(alloc::sync::Arc::ptr_eq(&stolen, &task));
+    assert_eq!(task.cpu_id(), 0);
+    assert_eq!(task.join(), Some(0));
+}
+
+#[test]
+fn test_spawn_to_least_loaded_cpu() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+    run_all_ready_tasks();
+    let Some(cpu1) = FakeCpu::online() else {
+        return;
+    };
+
+    let task1 = axtask::spawn(|| {});
+    let task2 = axtask::spawn(|| {});
+    // ties are broken in favor of the current CPU
+    assert_eq!(task1.cpu_id(), 0);
+    assert_eq!(task2.cpu_id(), FakeCpu::ID);
+    assert_eq!(load_of(FakeCpu::ID), 1);
+
+    cpu1.rq().drain_wake_list();
+    assert!(alloc::sync::Arc::ptr_eq(&cpu1.take_back().unwrap(), &task2));
+    assert!(cpu1.take_back().is_none());
+    task1.join();
+    task2.join();
+}
+
+#[cfg(feature = "irq")]
+#[test]
+fn test_load_balance() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+    run_all_ready_tasks();
+
+    const NUM_TASKS: usize = 3;
+    let tasks: Vec<_> = (0..NUM_TASKS).map(|_| axtask::spawn(|| {})).collect();
+    let Some(cpu1) = FakeCpu::online() else {
+        return;
+    };
+    let load = load_of(0);
+    assert_eq!(load, NUM_TASKS);
+
+    // pulls one task as CPU 0 has at least 2 more ready tasks
+    cpu1.rq().load_balance();
+    assert_eq!(load_of(0), load - 1);
+    assert_eq!(load_of(FakeCpu::ID), 1);
+    // balanced enough
+    cpu1.rq().load_balance();
+    assert_eq!(load_of(FakeCpu::ID), 1);
+
+    let migrated = cpu1.take_back().unwrap();
+    assert_eq!(migrated.cpu_id(), 0);
+    for task in tasks {
+        task.join();
+    }
+}
+
+#[cfg(feature = "irq")]
+#[test]
+fn test_wait_timeout() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    use core::sync::atomic::AtomicBool;
+    use core::time::Duration;
+
+    static WQ: WaitQueue = WaitQueue::new();
+    static READY: AtomicBool = AtomicBool::new(false);
+
+    // The clock of the dummy platform is always 0, so the alarm of a zero
+    // timeout fires as soon as the timer events are checked.
+    axtask::spawn(crate::timers::check_events);
+    assert!(WQ.wait_timeout(Duration::ZERO));
+    assert!(!current().in_wait_queue());
+    assert!(!current().in_timer_list());
+
+    axtask::spawn(|| assert!(WQ.notify_one(false)));
+    assert!(!WQ.wait_timeout(Duration::from_secs(1)));
+    assert!(!current().in_timer_list());
+
+    assert!(WQ.wait_timeout_until(Duration::ZERO, || false));
+
+    axtask::spawn(|| {
+        assert!(WQ.notify_one(false)); // spurious wakeup
+        axtask::yield_now();
+        READY.store(true, Ordering::Release);
+        assert!(WQ.notify_one(false));
+    });
+    let timeout = WQ.wait_timeout_until(Duration::from_secs(1), || {
+        assert!(!current().in_timer_list());
+        READY.load(Ordering::Acquire)
+    });
+    assert!(!timeout);
+    assert!(!current().in_wait_queue());
+    assert!(!current().in_timer_list());
+}
+
+#[test]
+fn test_cpumask() {
+    let mut mask = AxCpuMask::new();
+    assert!(mask.is_empty());
+    mask.set(SMP - 1, true);
+    mask.set(SMP, true); // not exist
+    assert_eq!(mask, AxCpuMask::one_shot(SMP - 1));
+    assert_eq!(mask.iter().collect::<Vec<_>>(), [SMP - 1]);
+    assert!(!mask.contains(SMP));
+    assert!(AxCpuMask::one_shot(SMP).is_empty());
+    assert_eq!(AxCpuMask::from_raw_bits(usize::MAX), AxCpuMask::full());
+    assert_eq!(AxCpuMask::full().iter().count(), SMP);
+    assert_eq!(AxCpuMask::full().first(), Some(0));
+}
+
+#[test]
+fn test_set_affinity() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    assert!(!axtask::set_current_affinity(AxCpuMask::new()));
+    assert_eq!(current().cpumask(), AxCpuMask::full());
+    assert!(axtask::set_current_affinity(AxCpuMask::one_shot(0)));
+    assert_eq!(current().cpumask(), AxCpuMask::one_shot(0));
+    assert!(axtask::set_current_affinity(AxCpuMask::full()));
+}
+
+#[test]
+fn test_pinned_task_not_migrated() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+    run_all_ready_tasks();
+
+    const NUM_TASKS: usize = 3;
+    let tasks: Vec<_> = (0..NUM_TASKS)
+        .map(|i| {
+            axtask::spawn_raw_with_affinity(
+                || assert_eq!(axhal::cpu::this_cpu_id(), 0),
+                format!("P{}", i),
+                0x1000,
+                AxCpuMask::one_shot(0),
+            )
+        })
+        .collect();
+    let Some(cpu1) = FakeCpu::online() else {
+        return;
+    };
+    // They are never put on CPU 1 even if it's idle.
+    let task = axtask::spawn_raw_with_affinity(|| {}, "P".into(), 0x1000, AxCpuMask::one_shot(0));
+    assert_eq!(task.cpu_id(), 0);
+    assert_eq!(load_of(0), NUM_TASKS + 1);
+
+    assert!(cpu1.rq().steal_task(1).is_none());
+    #[cfg(feature = "irq")]
+    for _ in 0..NUM_TASKS {
+        cpu1.rq().load_balance();
+    }
+    assert_eq!(load_of(0), NUM_TASKS + 1);
+    assert_eq!(load_of(FakeCpu::ID), 0);
+
+    for task in tasks.into_iter().chain([task]) {
+        assert_eq!(task.cpu_id(), 0);
+        task.join();
+    }
+}
+
+#[test]
+fn test_affinity_change_of_ready_task() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+    run_all_ready_tasks();
+    let Some(cpu1) = FakeCpu::online() else {
+        return;
+    };
+
+    let task = axtask::spawn(|| {});
+    assert_eq!(task.cpu_id(), 0);
+    assert!(task.set_cpumask(AxCpuMask::one_shot(FakeCpu::ID)));
+    // It's moved to CPU 1 when CPU 0 picks it.
+    axtask::yield_now();
+    assert_eq!(task.cpu_id(), FakeCpu::ID);
+    assert_eq!(load_of(FakeCpu::ID), 1);
+    assert_eq!(task.state(), TaskState::Ready);
+
+    // Not allowed to be stolen back.
+    cpu1.rq().drain_wake_list();
+    assert!(cpu1.take_back().is_none());
+    assert_eq!(load_of(FakeCpu::ID), 1);
+
+    task.set_cpumask(AxCpuMask::full());
+    assert!(alloc::sync::Arc::ptr_eq(&cpu1.take_back().unwrap(), &task));
+    task.join();
+}
+
+/// Spawns `n` tasks blocked on the futex `key`, they expect to be woken up.
+fn spawn_futex_waiters(key: FutexKey, word: &'static AtomicU32, n: usize) -> Vec<AxTaskRef> {
+    let tasks: Vec<_> = (0..n)
+        .map(|_| {
+            axtask::spawn(move || {
+                let res = axtask::futex_wait(key, word, 0, false);
+                assert_eq!(res, FutexWaitResult::Woken);
+            })
+        })
+        .collect();
+    while tasks.iter().any(|t| t.state() != TaskState::Blocked) {
+        axtask::yield_now();
+    }
+    tasks
+}
+
+#[test]
+fn test_futex_wait_wake() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WORD: AtomicU32 = AtomicU32::new(0);
+    let key = FutexKey::kernel(&WORD);
+
+    let res = axtask::futex_wait(key, &WORD, 1, false);
+    assert_eq!(res, FutexWaitResult::Mismatch);
+    assert_eq!(axtask::futex_wake(key, 1), 0);
+
+    let tasks = spawn_futex_waiters(key, &WORD, 3);
+    assert_eq!(axtask::futex_wake(key, 2), 2);
+    assert_eq!(axtask::futex_wake(key, 2), 1);
+    assert_eq!(axtask::futex_wake(key, 2), 0);
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+}
+
+#[test]
+fn test_futex_requeue() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WORD: AtomicU32 = AtomicU32::new(0);
+    // `from` and `to_other` are in different buckets, `from` and `to_same`
+    // are in the same bucket.
+    let from = FutexKey::new(0, 0x1000);
+    let to_other = FutexKey::new(0, 0x1004);
+    let to_same = FutexKey::new(0, 0x1100);
+
+    for to in [to_other, to_same] {
+        let tasks = spawn_futex_waiters(from, &WORD, 4);
+        assert_eq!(
+            axtask::futex_requeue(from, 1, to, 2, Some((&WORD, 1))),
+            None
+        );
+        assert_eq!(
+            axtask::futex_requeue(from, 1, to, 2, Some((&WORD, 0))),
+            Some(3)
+        );
+        axtask::yield_now();
+        assert_eq!(
+            tasks
+                .iter()
+                .filter(|t| t.state() == TaskState::Exited)
+                .count(),
+            1
+        );
+        // one is left on `from`, two are moved to `to`
+        assert_eq!(axtask::futex_wake(from, usize::MAX), 1);
+        assert_eq!(axtask::futex_wake(to, usize::MAX), 2);
+        assert_eq!(axtask::futex_wake(to, usize::MAX), 0);
+        for task in tasks {
+            assert_eq!(task.join(), Some(0));
+        }
+    }
+}
+
+#[test]
+fn test_futex_timeout() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static WORD: AtomicU32 = AtomicU32::new(0);
+    let key = FutexKey::kernel(&WORD);
+    let now = axhal::time::current_time();
+
+    let res = axtask::futex_wait_timeout(key, &WORD, 0, now, false);
+    assert_eq!(res, FutexWaitResult::TimedOut);
+    let res = axtask::futex_wait_timeout(key, &WORD, 1, now, false);
+    assert_eq!(res, FutexWaitResult::Mismatch);
+
+    // Woken up before the deadline. Without `irq`, it polls the futex word
+    // instead of blocking, and the clock of the dummy platform never moves.
+    #[cfg(feature = "irq")]
+    {
+        let waker = axtask::spawn(move || assert_eq!(axtask::futex_wake(key, 1), 1));
+        let deadline = now + core::time::Duration::from_secs(1);
+        let res = axtask::futex_wait_timeout(key, &WORD, 0, deadline, false);
+        assert_eq!(res, FutexWaitResult::Woken);
+        assert!(!current().in_timer_list());
+        waker.join();
+    }
+}
@@ -2,7 +2,7 @@ use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use spinlock::SpinRaw;
 
-use crate::{AxRunQueue, AxTaskRef, CurrentTask, RUN_QUEUE};
+use crate::{current_run_queue, AxRunQueue, AxTaskRef, CurrentTask};
 
 /// A queue to store sleeping tasks.
 ///
@@ -27,7 +27,7 @@ use crate::{AxRunQueue, AxTaskRef, CurrentTask, RUN_QUEUE};
 /// assert_eq!(VALUE.load(Ordering::Relaxed), 1);
 /// ```
 pub struct WaitQueue {
-    queue: SpinRaw<VecDeque<AxTaskRef>>, // we already disabled IRQs when lock the `RUN_QUEUE`
+    queue: SpinRaw<VecDeque<AxTaskRef>>, // we already disabled IRQs when lock the run queue
 }
 
 impl WaitQueue {
@@ -50,7 +50,7 @@ impl WaitQueue {
         // the event from another queue.
         if curr.in_wait_queue() {
             // wake up by timer (timeout).
-            // `RUN_QUEUE` is not locked here, so disable IRQs.
+            // The run queue is not locked here, so disable IRQs.
             let _guard = kernel_guard::IrqSave::new();
             self.queue.lock().retain(|t| !curr.ptr_eq(t));
             curr.set_in_wait_queue(false);
@@ -65,7 +65,7 @@ impl WaitQueue {
     /// Blocks the current task and put it into the wait queue, until other task
     /// notifies it.
     pub fn wait(&self) {
-        RUN_QUEUE.lock().block_current(|task| {
+        current_run_queue().block_current(|task| {
             task.set_in_wait_queue(true);
             self.queue.lock().push_back(task)
         });
@@ -82,18 +82,59 @@ impl WaitQueue {
         F: Fn() -> bool,
     {
         loop {
-            let mut rq = RUN_QUEUE.lock();
+            let mut rq = current_run_queue();
+            // Hold the wait queue lock while checking the condition, so a
+            // notifier on another CPU cannot miss us.
+            let mut wq = self.queue.lock();
             if condition() {
                 break;
             }
-            rq.block_current(|task| {
+            rq.block_current(move |task| {
                 task.set_in_wait_queue(true);
-                self.queue.lock().push_back(task);
+                wq.push_back(task);
             });
         }
         self.cancel_events(crate::current());
     }
 
+    /// Like [`wait_until`](Self::wait_until), but also wakes up when the
+    /// current task has pending signals that are not blocked.
+    ///
+    /// Returns `false` if it's interrupted by signals before the condition
+    /// becomes true.
+    pub fn wait_until_interruptible<F>(&self, condition: F) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let curr = crate::current();
+        let mut satisfied = true;
+        loop {
+            let mut rq = current_run_queue();
+            let mut wq = self.queue.lock();
+            if curr.in_wait_queue() {
+                // woken up by signals, but not removed from the queue
+                wq.retain(|t| !curr.ptr_eq(t));
+                curr.set_in_wait_queue(false);
+            }
+            if condition() {
+                break;
+            }
+            curr.set_interruptible(true);
+            if curr.has_pending_signal() {
+                curr.set_interruptible(false);
+                satisfied = false;
+                break;
+            }
+            rq.block_current(move |task| {
+                task.set_in_wait_queue(true);
+                wq.push_back(task);
+            });
+            curr.set_interruptible(false);
+        }
+        self.cancel_events(curr);
+        satisfied
+    }
+
     /// Blocks the current task and put it into the wait queue, until other tasks
     /// notify it, or the given duration has elapsed.
     #[cfg(feature = "irq")]
@@ -105,11 +146,13 @@ impl WaitQueue {
             curr.id_name(),
             deadline
         );
-        crate::timers::set_alarm_wakeup(deadline, curr.clone());
 
-        RUN_QUEUE.lock().block_current(|task| {
+        current_run_queue().block_current(|task| {
             task.set_in_wait_queue(true);
-            self.queue.lock().push_back(task)
+            self.queue.lock().push_back(task.clone());
+            // Set the alarm after blocked, as the timer may be fired on
+            // another CPU immediately.
+            crate::timers::set_alarm_wakeup(deadline, task);
         });
         let timeout = curr.in_wait_queue(); // still in the wait queue, must have timed out
         self.cancel_events(curr);
@@ -133,19 +176,29 @@ impl WaitQueue {
             curr.id_name(),
             deadline
         );
-        crate::timers::set_alarm_wakeup(deadline, curr.clone());
 
         let mut timeout = true;
         while axhal::time::current_time() < deadline {
-            let mut rq = RUN_QUEUE.lock();
+            let mut rq = current_run_queue();
+            let mut wq = self.queue.lock();
             if condition() {
                 timeout = false;
                 break;
             }
-            rq.block_current(|task| {
+            rq.block_current(move |task| {
                 task.set_in_wait_queue(true);
-                self.queue.lock().push_back(task);
+                wq.push_back(task.clone());
+                drop(wq);
+                // Set the alarm after blocked, as the timer may be fired on
+                // another CPU immediately.
+                crate::timers::set_alarm_wakeup(deadline, task);
             });
+            drop(rq);
+            if curr.in_timer_list() {
+                // woken up by `notify()`, the alarm is set again on the next
+                // block.
+                crate::timers::cancel_alarm(curr.as_task_ref());
+            }
         }
         self.cancel_events(curr);
         timeout
@@ -156,7 +209,7 @@ impl WaitQueue {
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
     pub fn notify_one(&self, resched: bool) -> bool {
-        let mut rq = RUN_QUEUE.lock();
+        let mut rq = current_run_queue();
         if !self.queue.lock().is_empty() {
             self.notify_one_locked(resched, &mut rq)
         } else {
@@ -170,14 +223,14 @@ impl WaitQueue {
     /// preemption is enabled.
     pub fn notify_all(&self, resched: bool) {
         loop {
-            let mut rq = RUN_QUEUE.lock();
+            let mut rq = current_run_queue();
             if let Some(task) = self.queue.lock().pop_front() {
                 task.set_in_wait_queue(false);
                 rq.unblock_task(task, resched);
             } else {
                 break;
             }
-            drop(rq); // we must unlock `RUN_QUEUE` after unlocking `self.queue`.
+            drop(rq); // we must unlock the run queue after unlocking `self.queue`.
         }
     }
 
@@ -186,7 +239,7 @@ impl WaitQueue {
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
     pub fn notify_task(&mut self, resched: bool, task: &AxTaskRef) -> bool {
-        let mut rq = RUN_QUEUE.lock();
+        let mut rq = current_run_queue();
         let mut wq = self.queue.lock();
         if let Some(index) = wq.iter().position(|t| Arc::ptr_eq(t, task)) {
             task.set_in_wait_queue(false);
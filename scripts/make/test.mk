@@ -3,6 +3,9 @@
 define unit_test
   $(call run_cmd,cargo test,-p percpu $(1) -- --nocapture)
   $(call run_cmd,cargo test,-p axfs $(1) --features "myfs" -- --nocapture)
+  $(call run_cmd,bash,modules/axfs/resources/create_test_img.sh ext2)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "ext2" -- --nocapture)
+  $(call run_cmd,AX_SMP=2 cargo test,-p axtask $(1) --features "multitask irq" -- --nocapture)
   $(call run_cmd,cargo test,--workspace --exclude "arceos-*" $(1) -- --nocapture)
 endef
 
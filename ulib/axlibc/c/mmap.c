@@ -2,6 +2,8 @@
 #include <stdio.h>
 #include <sys/mman.h>
 
+#ifndef AX_CONFIG_MMAP
+
 // TODO:
 void *mmap(void *addr, size_t len, int prot, int flags, int fildes, off_t off)
 {
@@ -16,6 +18,8 @@ int munmap(void *addr, size_t length)
     return 0;
 }
 
+#endif // AX_CONFIG_MMAP
+
 // TODO:
 void *mremap(void *old_address, size_t old_size, size_t new_size, int flags,
              ... /* void *new_address */)
@@ -24,6 +28,8 @@ void *mremap(void *old_address, size_t old_size, size_t new_size, int flags,
     return NULL;
 }
 
+#ifndef AX_CONFIG_MMAP
+
 // TODO
 int mprotect(void *addr, size_t len, int prot)
 {
@@ -31,6 +37,15 @@ int mprotect(void *addr, size_t len, int prot)
     return 0;
 }
 
+// TODO
+int msync(void *addr, size_t len, int flags)
+{
+    unimplemented();
+    return 0;
+}
+
+#endif // AX_CONFIG_MMAP
+
 // TODO
 int madvise(void *addr, size_t len, int advice)
 {
@@ -1,8 +1,10 @@
 #include <errno.h>
+#include <limits.h>
 #include <signal.h>
 #include <stddef.h>
 #include <stdio.h>
 
+#ifndef AX_CONFIG_MULTITASK
 int sigaction_helper(int signum, const struct sigaction *act, struct sigaction *oldact,
                      size_t sigsetsize)
 {
@@ -15,30 +17,45 @@ int sigaction_helper(int signum, const struct sigaction *act, struct sigaction *
     return 0;
 }
 
-void (*signal(int signum, void (*handler)(int)))(int)
+int sigaction(int sig, const struct sigaction *restrict act, struct sigaction *restrict oact)
 {
-    struct sigaction old;
-    struct sigaction act = {
-        .sa_handler = handler, .sa_flags = SA_RESTART, /* BSD signal semantics */
-    };
-
-    if (sigaction_helper(signum, &act, &old, sizeof(sigset_t)) < 0)
-        return SIG_ERR;
+    return sigaction_helper(sig, act, oact, sizeof(sigset_t));
+}
 
-    return (old.sa_flags & SA_SIGINFO) ? NULL : old.sa_handler;
+// TODO
+int kill(pid_t __pid, int __sig)
+{
+    unimplemented();
+    return 0;
 }
 
-int sigaction(int sig, const struct sigaction *restrict act, struct sigaction *restrict oact)
+// TODO
+int raise(int __sig)
 {
-    return sigaction_helper(sig, act, oact, sizeof(sigset_t));
+    unimplemented();
+    return 0;
 }
 
 // TODO
-int kill(pid_t __pid, int __sig)
+int pthread_sigmask(int __how, const sigset_t *restrict __newmask, sigset_t *restrict __oldmask)
 {
     unimplemented();
     return 0;
 }
+#endif // AX_CONFIG_MULTITASK
+
+void (*signal(int signum, void (*handler)(int)))(int)
+{
+    struct sigaction old;
+    struct sigaction act = {
+        .sa_handler = handler, .sa_flags = SA_RESTART, /* BSD signal semantics */
+    };
+
+    if (sigaction(signum, &act, &old) < 0)
+        return SIG_ERR;
+
+    return (old.sa_flags & SA_SIGINFO) ? NULL : old.sa_handler;
+}
 
 int sigemptyset(sigset_t *set)
 {
@@ -52,10 +69,20 @@ int sigemptyset(sigset_t *set)
     return 0;
 }
 
-// TODO
-int raise(int __sig)
+int sigfillset(sigset_t *set)
 {
-    unimplemented();
+#if ULONG_MAX == 0xffffffff
+    set->__bits[0] = 0x7ffffffful;
+    set->__bits[1] = 0xfffffffcul;
+    if (_NSIG > 65) {
+        set->__bits[2] = 0xfffffffful;
+        set->__bits[3] = 0xfffffffful;
+    }
+#else
+    set->__bits[0] = 0xfffffffc7ffffffful;
+    if (_NSIG > 65)
+        set->__bits[1] = 0xfffffffffffffffful;
+#endif
     return 0;
 }
 
@@ -70,18 +97,21 @@ int sigaddset(sigset_t *set, int sig)
     return 0;
 }
 
-// TODO
-int pthread_sigmask(int __how, const sigset_t *restrict __newmask, sigset_t *restrict __oldmask)
+int sigdelset(sigset_t *set, int sig)
 {
-    unimplemented();
+    unsigned s = sig - 1;
+    if (s >= _NSIG - 1 || sig - 32U < 3) {
+        errno = EINVAL;
+        return -1;
+    }
+    set->__bits[s / 8 / sizeof *set->__bits] &= ~(1UL << (s & (8 * sizeof *set->__bits - 1)));
     return 0;
 }
 
-#ifdef AX_CONFIG_MULTITASK
-// TODO
-int pthread_kill(pthread_t t, int sig)
+int sigismember(const sigset_t *set, int sig)
 {
-    unimplemented();
-    return 0;
+    unsigned s = sig - 1;
+    if (s >= _NSIG - 1)
+        return 0;
+    return !!(set->__bits[s / 8 / sizeof *set->__bits] & 1UL << (s & (8 * sizeof *set->__bits - 1)));
 }
-#endif
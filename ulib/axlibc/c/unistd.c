@@ -61,13 +61,6 @@ int access(const char *pathname, int mode)
     return 0;
 }
 
-// TODO:
-ssize_t readlink(const char *path, char *buf, size_t bufsiz)
-{
-    unimplemented();
-    return 0;
-}
-
 // TODO:
 int unlink(const char *pathname)
 {
@@ -1,6 +1,9 @@
 #ifndef _POLL_H
 #define _POLL_H
 
+#include <signal.h>
+#include <sys/time.h>
+
 struct pollfd {
     int fd;
     short events;
@@ -17,5 +20,7 @@ struct pollfd {
 typedef unsigned long nfds_t;
 
 int poll(struct pollfd *__fds, nfds_t __nfds, int __timeout);
+int ppoll(struct pollfd *__fds, nfds_t __nfds, const struct timespec *__timeout,
+          const sigset_t *__sigmask);
 
 #endif // _POLL_H
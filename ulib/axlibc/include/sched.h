@@ -2,6 +2,7 @@
 #define _SCHED_H
 
 #include <stddef.h>
+#include <sys/types.h>
 
 typedef struct cpu_set_t {
     unsigned long __bits[128 / sizeof(long)];
@@ -12,12 +13,17 @@ typedef struct cpu_set_t {
                         : (((unsigned long *)(set))[(i) / 8 / sizeof(long)] op( \
                               1UL << ((i) % (8 * sizeof(long))))))
 
-#define CPU_SET_S(i, size, set) __CPU_op_S(i, size, set, |=)
-#define CPU_ZERO_S(size, set)   memset(set, 0, size)
+#define CPU_SET_S(i, size, set)   __CPU_op_S(i, size, set, |=)
+#define CPU_CLR_S(i, size, set)   __CPU_op_S(i, size, set, &= ~)
+#define CPU_ISSET_S(i, size, set) __CPU_op_S(i, size, set, &)
+#define CPU_ZERO_S(size, set)     memset(set, 0, size)
 
-#define CPU_SET(i, set) CPU_SET_S(i, sizeof(cpu_set_t), set);
-#define CPU_ZERO(set)   CPU_ZERO_S(sizeof(cpu_set_t), set)
+#define CPU_SET(i, set)   CPU_SET_S(i, sizeof(cpu_set_t), set);
+#define CPU_CLR(i, set)   CPU_CLR_S(i, sizeof(cpu_set_t), set)
+#define CPU_ISSET(i, set) CPU_ISSET_S(i, sizeof(cpu_set_t), set)
+#define CPU_ZERO(set)     CPU_ZERO_S(sizeof(cpu_set_t), set)
 
 int sched_setaffinity(pid_t, size_t, const cpu_set_t *);
+int sched_getaffinity(pid_t, size_t, cpu_set_t *);
 
 #endif // _SCHED_H
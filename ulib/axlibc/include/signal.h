@@ -166,14 +166,20 @@ struct sigaction {
 void (*signal(int, void (*)(int)))(int);
 int sigaction(int, const struct sigaction *__restrict, struct sigaction *__restrict);
 int sigemptyset(sigset_t *);
-int raise(int);
+int sigfillset(sigset_t *);
 int sigaddset(sigset_t *, int);
+int sigdelset(sigset_t *, int);
+int sigismember(const sigset_t *, int);
+int raise(int);
 int pthread_sigmask(int, const sigset_t *__restrict, sigset_t *__restrict);
 
 int kill(pid_t, int);
 
 #ifdef AX_CONFIG_MULTITASK
 int pthread_kill(pthread_t t, int sig);
+int sigprocmask(int, const sigset_t *__restrict, sigset_t *__restrict);
+int sigpending(sigset_t *);
+int sigsuspend(const sigset_t *);
 #endif
 
 #endif // _SIGNAL_H
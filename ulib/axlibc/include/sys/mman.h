@@ -37,6 +37,11 @@
 
 #define MAP_FAILED ((void *)-1)
 
+/* Flags for msync.  */
+#define MS_ASYNC      1 /* Sync memory asynchronously.  */
+#define MS_INVALIDATE 2 /* Invalidate the caches.  */
+#define MS_SYNC       4 /* Synchronous memory sync.  */
+
 /* Flags for mremap.  */
 #define MREMAP_MAYMOVE   1
 #define MREMAP_FIXED     2
@@ -48,5 +53,6 @@ void *mremap(void *old_address, size_t old_size, size_t new_size, int flags,
              ... /* void *new_address */);
 int mprotect(void *addr, size_t len, int prot);
 int madvise(void *addr, size_t length, int advice);
+int msync(void *addr, size_t length, int flags);
 
 #endif
@@ -42,6 +42,11 @@ struct cmsghdr {
     int cmsg_type;
 };
 
+struct linger {
+    int l_onoff;
+    int l_linger;
+};
+
 struct sockaddr {
     sa_family_t sa_family;
     char sa_data[14];
@@ -54,6 +59,7 @@ struct sockaddr_storage {
 };
 
 int socket(int, int, int);
+int socketpair(int, int, int, int[2]);
 int shutdown(int, int);
 
 int bind(int, const struct sockaddr *, socklen_t);
@@ -1,7 +1,8 @@
 use core::ffi::{c_char, c_int};
 
 use arceos_posix_api::{
-    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
+    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_readlink, sys_rename, sys_stat,
+    sys_symlink,
 };
 
 use crate::{ctypes, utils::e};
@@ -65,3 +66,23 @@ pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
 pub unsafe extern "C" fn rename(old: *const c_char, new: *const c_char) -> c_int {
     e(sys_rename(old, new))
 }
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if success.
+#[no_mangle]
+pub unsafe extern "C" fn symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    e(sys_symlink(target, linkpath))
+}
+
+/// Read the target of the symbolic link `path` into `buf`.
+///
+/// Return the number of bytes placed in `buf`.
+#[no_mangle]
+pub unsafe extern "C" fn readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsiz: usize,
+) -> ctypes::ssize_t {
+    e(sys_readlink(path, buf, bufsiz) as _) as _
+}
@@ -10,6 +10,7 @@
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
 //!     - `tls`: Enable thread-local storage.
+//!     - `mmap`: Enable memory mapping ([mmap]) support.
 //! - Task management
 //!     - `multitask`: Enable multi-threading support.
 //! - Upperlayer stacks
@@ -19,11 +20,14 @@
 //!     - `fd`: Enable file descriptor table.
 //!     - `pipe`: Enable pipe support.
 //!     - `select`: Enable synchronous I/O multiplexing ([select]) support.
+//!     - `poll`: Enable synchronous I/O multiplexing ([poll]) support.
 //!     - `epoll`: Enable event polling ([epoll]) support.
 //!
 //! [ArceOS]: https://github.com/rcore-os/arceos
 //! [select]: https://man7.org/linux/man-pages/man2/select.2.html
+//! [poll]: https://man7.org/linux/man-pages/man2/poll.2.html
 //! [epoll]: https://man7.org/linux/man-pages/man7/epoll.7.html
+//! [mmap]: https://man7.org/linux/man-pages/man2/mmap.2.html
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
 #![feature(doc_cfg)]
@@ -53,16 +57,22 @@ mod utils;
 mod fd_ops;
 #[cfg(feature = "fs")]
 mod fs;
-#[cfg(any(feature = "select", feature = "epoll"))]
+#[cfg(any(feature = "select", feature = "poll", feature = "epoll"))]
 mod io_mpx;
 #[cfg(feature = "alloc")]
 mod malloc;
+#[cfg(feature = "mmap")]
+mod mmap;
 #[cfg(feature = "net")]
 mod net;
 #[cfg(feature = "pipe")]
 mod pipe;
 #[cfg(feature = "multitask")]
 mod pthread;
+#[cfg(feature = "multitask")]
+mod semaphore;
+#[cfg(feature = "multitask")]
+mod signal;
 #[cfg(feature = "alloc")]
 mod strftime;
 #[cfg(feature = "fp_simd")]
@@ -73,6 +83,7 @@ mod io;
 mod mktime;
 mod rand;
 mod resource;
+mod sched;
 mod setjmp;
 mod sys;
 mod time;
@@ -86,6 +97,7 @@ pub use self::errno::strerror;
 pub use self::mktime::mktime;
 pub use self::rand::{rand, random, srand};
 pub use self::resource::{getrlimit, setrlimit};
+pub use self::sched::{sched_getaffinity, sched_setaffinity};
 pub use self::setjmp::{longjmp, setjmp};
 pub use self::sys::sysconf;
 pub use self::time::{clock_gettime, nanosleep};
@@ -100,18 +112,41 @@ pub use self::strftime::strftime;
 pub use self::fd_ops::{ax_fcntl, close, dup, dup2, dup3};
 
 #[cfg(feature = "fs")]
-pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, rename, stat};
+pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, readlink, rename, stat, symlink};
+
+#[cfg(feature = "mmap")]
+pub use self::mmap::{mmap, mprotect, msync, munmap};
 
 #[cfg(feature = "net")]
 pub use self::net::{
-    accept, bind, connect, freeaddrinfo, getaddrinfo, getpeername, getsockname, listen, recv,
-    recvfrom, send, sendto, shutdown, socket,
+    accept, bind, connect, freeaddrinfo, getaddrinfo, getpeername, getsockname, getsockopt, listen,
+    recv, recvfrom, send, sendto, setsockopt, shutdown, socket, socketpair,
 };
 
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_cond_broadcast, pthread_cond_destroy, pthread_cond_init, pthread_cond_signal,
+    pthread_cond_timedwait, pthread_cond_wait,
+};
 #[cfg(feature = "multitask")]
 pub use self::pthread::{pthread_create, pthread_exit, pthread_join, pthread_self};
 #[cfg(feature = "multitask")]
-pub use self::pthread::{pthread_mutex_init, pthread_mutex_lock, pthread_mutex_unlock};
+pub use self::pthread::{
+    pthread_mutex_init, pthread_mutex_lock, pthread_mutex_trylock, pthread_mutex_unlock,
+};
+#[cfg(feature = "multitask")]
+pub use self::pthread::{
+    pthread_rwlock_destroy, pthread_rwlock_init, pthread_rwlock_rdlock, pthread_rwlock_tryrdlock,
+    pthread_rwlock_trywrlock, pthread_rwlock_unlock, pthread_rwlock_wrlock,
+};
+#[cfg(feature = "multitask")]
+pub use self::semaphore::{
+    sem_destroy, sem_getvalue, sem_init, sem_post, sem_timedwait, sem_trywait, sem_wait,
+};
+#[cfg(feature = "multitask")]
+pub use self::signal::{
+    kill, pthread_kill, pthread_sigmask, raise, sigaction, sigpending, sigprocmask, sigsuspend,
+};
 
 #[cfg(feature = "pipe")]
 pub use self::pipe::pipe;
@@ -120,6 +155,8 @@ pub use self::pipe::pipe;
 pub use self::io_mpx::select;
 #[cfg(feature = "epoll")]
 pub use self::io_mpx::{epoll_create, epoll_ctl, epoll_wait};
+#[cfg(feature = "poll")]
+pub use self::io_mpx::{poll, ppoll};
 
 #[cfg(feature = "fp_simd")]
 pub use self::strtod::{strtod, strtof};
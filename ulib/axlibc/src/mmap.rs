@@ -0,0 +1,50 @@
+use core::ffi::{c_int, c_void};
+
+use arceos_posix_api::{sys_mmap, sys_mprotect, sys_msync, sys_munmap};
+
+use crate::{ctypes, utils::e};
+
+/// Map files or devices into memory.
+///
+/// Return the start address of the mapping, or `MAP_FAILED` if failed.
+#[no_mangle]
+pub unsafe extern "C" fn mmap(
+    addr: *mut c_void,
+    len: ctypes::size_t,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    offset: ctypes::off_t,
+) -> *mut c_void {
+    let ret = sys_mmap(addr, len, prot, flags, fd, offset) as isize;
+    // negated error numbers are at the top of the address space
+    if (-4095..0).contains(&ret) {
+        e(ret as c_int);
+        return usize::MAX as *mut c_void; // MAP_FAILED
+    }
+    ret as *mut c_void
+}
+
+/// Unmap the pages in the range `[addr, addr + len)`.
+///
+/// Return 0 if success.
+#[no_mangle]
+pub unsafe extern "C" fn munmap(addr: *mut c_void, len: ctypes::size_t) -> c_int {
+    e(sys_munmap(addr, len))
+}
+
+/// Change the access protections of the pages in the range `[addr, addr + len)`.
+///
+/// Return 0 if success.
+#[no_mangle]
+pub unsafe extern "C" fn mprotect(addr: *mut c_void, len: ctypes::size_t, prot: c_int) -> c_int {
+    e(sys_mprotect(addr, len, prot))
+}
+
+/// Write the modified pages in the range `[addr, addr + len)` back to the file.
+///
+/// Return 0 if success.
+#[no_mangle]
+pub unsafe extern "C" fn msync(addr: *mut c_void, len: ctypes::size_t, flags: c_int) -> c_int {
+    e(sys_msync(addr, len, flags))
+}
@@ -52,8 +52,105 @@ pub unsafe extern "C" fn pthread_mutex_lock(mutex: *mut ctypes::pthread_mutex_t)
     e(api::sys_pthread_mutex_lock(mutex))
 }
 
+/// Try to lock the given mutex.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_mutex_trylock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
+    e(api::sys_pthread_mutex_trylock(mutex))
+}
+
 /// Unlock the given mutex.
 #[no_mangle]
 pub unsafe extern "C" fn pthread_mutex_unlock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
     e(api::sys_pthread_mutex_unlock(mutex))
 }
+
+/// Initialize a condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_init(
+    cond: *mut ctypes::pthread_cond_t,
+    attr: *const ctypes::pthread_condattr_t,
+) -> c_int {
+    e(api::sys_pthread_cond_init(cond, attr))
+}
+
+/// Destroy a condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_destroy(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_destroy(cond))
+}
+
+/// Wait on the condition variable, the `mutex` is unlocked during the wait.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_wait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+) -> c_int {
+    e(api::sys_pthread_cond_wait(cond, mutex))
+}
+
+/// Wait on the condition variable until the absolute time `abstime`.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_timedwait(
+    cond: *mut ctypes::pthread_cond_t,
+    mutex: *mut ctypes::pthread_mutex_t,
+    abstime: *const ctypes::timespec,
+) -> c_int {
+    e(api::sys_pthread_cond_timedwait(cond, mutex, abstime))
+}
+
+/// Wake up one thread waiting on the condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_signal(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_signal(cond))
+}
+
+/// Wake up all threads waiting on the condition variable.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_cond_broadcast(cond: *mut ctypes::pthread_cond_t) -> c_int {
+    e(api::sys_pthread_cond_broadcast(cond))
+}
+
+/// Initialize a readers-writer lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_init(
+    rwlock: *mut ctypes::pthread_rwlock_t,
+    attr: *const ctypes::pthread_rwlockattr_t,
+) -> c_int {
+    e(api::sys_pthread_rwlock_init(rwlock, attr))
+}
+
+/// Destroy a readers-writer lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_destroy(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_destroy(rwlock))
+}
+
+/// Lock the given readers-writer lock for reading.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_rdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_rdlock(rwlock))
+}
+
+/// Try to lock the given readers-writer lock for reading without blocking.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_tryrdlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_tryrdlock(rwlock))
+}
+
+/// Lock the given readers-writer lock for writing.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_wrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_wrlock(rwlock))
+}
+
+/// Try to lock the given readers-writer lock for writing without blocking.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_trywrlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_trywrlock(rwlock))
+}
+
+/// Unlock the given readers-writer lock.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_rwlock_unlock(rwlock: *mut ctypes::pthread_rwlock_t) -> c_int {
+    e(api::sys_pthread_rwlock_unlock(rwlock))
+}
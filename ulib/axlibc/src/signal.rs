@@ -0,0 +1,68 @@
+use arceos_posix_api::{
+    sys_kill, sys_pthread_kill, sys_raise, sys_sigaction, sys_sigpending, sys_sigprocmask,
+    sys_sigsuspend,
+};
+use core::ffi::c_int;
+
+use crate::{ctypes, utils::e};
+
+/// Examine and change the action of signal `signum`.
+#[no_mangle]
+pub unsafe extern "C" fn sigaction(
+    signum: c_int,
+    act: *const ctypes::sigaction,
+    oldact: *mut ctypes::sigaction,
+) -> c_int {
+    e(sys_sigaction(signum, act, oldact))
+}
+
+/// Examine and change the blocked signals of the current thread.
+#[no_mangle]
+pub unsafe extern "C" fn sigprocmask(
+    how: c_int,
+    set: *const ctypes::sigset_t,
+    oldset: *mut ctypes::sigset_t,
+) -> c_int {
+    e(sys_sigprocmask(how, set, oldset))
+}
+
+/// Examine and change the blocked signals of the current thread.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_sigmask(
+    how: c_int,
+    set: *const ctypes::sigset_t,
+    oldset: *mut ctypes::sigset_t,
+) -> c_int {
+    e(sys_sigprocmask(how, set, oldset))
+}
+
+/// Get the pending signals of the current thread.
+#[no_mangle]
+pub unsafe extern "C" fn sigpending(set: *mut ctypes::sigset_t) -> c_int {
+    e(sys_sigpending(set))
+}
+
+/// Wait for a signal with the blocked signals temporarily replaced by
+/// `mask`.
+#[no_mangle]
+pub unsafe extern "C" fn sigsuspend(mask: *const ctypes::sigset_t) -> c_int {
+    e(sys_sigsuspend(mask))
+}
+
+/// Send signal `sig` to the thread `pid`.
+#[no_mangle]
+pub unsafe extern "C" fn kill(pid: c_int, sig: c_int) -> c_int {
+    e(sys_kill(pid, sig))
+}
+
+/// Send signal `sig` to the current thread.
+#[no_mangle]
+pub unsafe extern "C" fn raise(sig: c_int) -> c_int {
+    e(sys_raise(sig))
+}
+
+/// Send signal `sig` to the given thread.
+#[no_mangle]
+pub unsafe extern "C" fn pthread_kill(thread: ctypes::pthread_t, sig: c_int) -> c_int {
+    e(sys_pthread_kill(thread, sig))
+}
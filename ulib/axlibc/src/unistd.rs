@@ -8,8 +8,13 @@ pub unsafe extern "C" fn getpid() -> c_int {
 }
 
 /// Abort the current process.
+///
+/// `SIGABRT` is raised first, so it can be caught by a handler. If the
+/// handler returns, or signals are not supported, the system is terminated.
 #[no_mangle]
 pub unsafe extern "C" fn abort() -> ! {
+    #[cfg(feature = "multitask")]
+    arceos_posix_api::sys_raise(crate::ctypes::SIGABRT as _);
     panic!()
 }
 
@@ -11,6 +11,9 @@ use alloc::{string::String, vec::Vec};
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
 
+/// A reference to a mountable filesystem.
+pub use arceos_api::fs::AxFileSystem as FileSystem;
+
 /// Read the entire contents of a file into a bytes vector.
 #[cfg(feature = "alloc")]
 pub fn read(path: &str) -> io::Result<Vec<u8>> {
@@ -42,6 +45,24 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Query the metadata about a file without following symlinks.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    arceos_api::fs::ax_symlink_attr(path).map(Metadata)
+}
+
+/// Reads a symbolic link, returning the file that the link points to.
+#[cfg(feature = "alloc")]
+pub fn read_link(path: &str) -> io::Result<String> {
+    arceos_api::fs::ax_read_link(path)
+}
+
+/// Creates a new symbolic link on the filesystem.
+///
+/// The `link` path will be a symbolic link pointing to the `original` path.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    arceos_api::fs::ax_symlink(original, link)
+}
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -75,3 +96,16 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     arceos_api::fs::ax_rename(old, new)
 }
+
+/// Mounts the filesystem `fs` at the given path.
+///
+/// The mount point is created if it does not exist, and it can be inside
+/// another mounted filesystem.
+pub fn mount(path: &str, fs: FileSystem) -> io::Result<()> {
+    arceos_api::fs::ax_mount(path, fs)
+}
+
+/// Unmounts the filesystem mounted at the given path.
+pub fn umount(path: &str) -> io::Result<()> {
+    arceos_api::fs::ax_umount(path)
+}
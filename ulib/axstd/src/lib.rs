@@ -30,7 +30,10 @@
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext2`: Use the ext2 filesystem as the root filesystem instead of FAT.
 //!     - `net`: Enable networking support.
+//!     - `net-busy-poll`: Poll the NIC in a loop instead of sleeping on NIC interrupts.
+//!     - `net-dhcp`: Configure the network by DHCP, fall back to the static configuration.
 //!     - `dns`: Enable DNS lookup support.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
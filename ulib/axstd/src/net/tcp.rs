@@ -1,5 +1,6 @@
 use super::{SocketAddr, ToSocketAddrs};
 use crate::io::{self, prelude::*};
+use core::time::Duration;
 
 use arceos_api::net::{self as api, AxTcpSocketHandle};
 
@@ -43,6 +44,61 @@ impl TcpStream {
     pub fn shutdown(&self) -> io::Result<()> {
         api::ax_tcp_shutdown(&self.0)
     }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`read`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// [`read`]: Read::read
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// If the timeout is [`None`], then [`read`] calls will block indefinitely.
+    ///
+    /// [`read`]: Read::read
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_tcp_read_timeout(&self.0))
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`write`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// [`write`]: Write::write
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// If the timeout is [`None`], then [`write`] calls will block indefinitely.
+    ///
+    /// [`write`]: Write::write
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_tcp_write_timeout(&self.0))
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// If set, this option disables the Nagle algorithm. This means that
+    /// segments are always sent as soon as possible, even if there is only a
+    /// small amount of data.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        api::ax_tcp_set_nodelay(&self.0, nodelay);
+        Ok(())
+    }
+
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        Ok(api::ax_tcp_nodelay(&self.0))
+    }
 }
 
 impl Read for TcpStream {
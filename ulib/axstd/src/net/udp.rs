@@ -1,5 +1,6 @@
 use super::{SocketAddr, ToSocketAddrs};
 use crate::io;
+use core::time::Duration;
 
 use arceos_api::net::{self as api, AxUdpSocketHandle};
 
@@ -93,4 +94,38 @@ impl UdpSocket {
     pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
         api::ax_udp_recv(&self.0, buf)
     }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`recv`] and [`recv_from`]
+    /// calls will block indefinitely. An [`Err`] is returned if the zero
+    /// [`Duration`] is passed to this method.
+    ///
+    /// [`recv`]: UdpSocket::recv
+    /// [`recv_from`]: UdpSocket::recv_from
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_udp_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_udp_read_timeout(&self.0))
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`send`] and [`send_to`]
+    /// calls will block indefinitely. An [`Err`] is returned if the zero
+    /// [`Duration`] is passed to this method.
+    ///
+    /// [`send`]: UdpSocket::send
+    /// [`send_to`]: UdpSocket::send_to
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_udp_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_udp_write_timeout(&self.0))
+    }
 }
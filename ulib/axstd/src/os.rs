@@ -4,3 +4,6 @@
 pub mod arceos {
     pub use arceos_api as api;
 }
+
+#[cfg(feature = "net")]
+pub mod unix;
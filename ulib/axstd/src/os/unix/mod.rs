@@ -0,0 +1,3 @@
+//! Unix-specific functionality.
+
+pub mod net;
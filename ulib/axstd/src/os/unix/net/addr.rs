@@ -0,0 +1,76 @@
+use crate::io;
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use arceos_api::net::AxUnixSocketAddr;
+
+/// The maximum length of a name, which is the size of `sun_path` minus the
+/// terminating null byte or the leading null byte of an abstract name.
+const MAX_NAME_LEN: usize = 107;
+
+/// An address associated with a Unix domain socket.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SocketAddr(pub(super) AxUnixSocketAddr);
+
+impl SocketAddr {
+    /// Constructs a `SocketAddr` with the family `AF_UNIX` and the provided
+    /// path.
+    ///
+    /// Returns an error if the path is empty, longer than `SUN_LEN`, or
+    /// contains null bytes.
+    pub fn from_pathname(path: &str) -> io::Result<SocketAddr> {
+        if path.is_empty() || path.contains('\0') {
+            axerrno::ax_err!(InvalidInput, "path must not be empty or contain null bytes")
+        } else if path.len() > MAX_NAME_LEN {
+            axerrno::ax_err!(InvalidInput, "path must be shorter than SUN_LEN")
+        } else {
+            Ok(SocketAddr(AxUnixSocketAddr::Pathname(String::from(path))))
+        }
+    }
+
+    /// Creates a Unix socket address in the abstract namespace.
+    ///
+    /// Returns an error if the name is longer than `SUN_LEN - 1`.
+    pub fn from_abstract_name<N: AsRef<[u8]>>(name: N) -> io::Result<SocketAddr> {
+        let name = name.as_ref();
+        if name.len() > MAX_NAME_LEN {
+            axerrno::ax_err!(InvalidInput, "abstract name must be shorter than SUN_LEN")
+        } else {
+            Ok(SocketAddr(AxUnixSocketAddr::Abstract(Vec::from(name))))
+        }
+    }
+
+    /// Returns `true` if the address is unnamed.
+    pub fn is_unnamed(&self) -> bool {
+        matches!(self.0, AxUnixSocketAddr::Unnamed)
+    }
+
+    /// Returns the contents of this address if it is a pathname address.
+    pub fn as_pathname(&self) -> Option<&str> {
+        match &self.0 {
+            AxUnixSocketAddr::Pathname(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of this address if it is an abstract namespace
+    /// address.
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        match &self.0 {
+            AxUnixSocketAddr::Abstract(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for SocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            AxUnixSocketAddr::Unnamed => write!(f, "(unnamed)"),
+            AxUnixSocketAddr::Pathname(path) => write!(f, "{path:?} (pathname)"),
+            AxUnixSocketAddr::Abstract(name) => {
+                write!(f, "\"{}\" (abstract)", name.escape_ascii())
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use super::SocketAddr;
+use crate::io;
+use core::time::Duration;
+
+use arceos_api::net::{self as api, AxUnixDatagramHandle};
+
+/// A Unix datagram socket.
+pub struct UnixDatagram(AxUnixDatagramHandle);
+
+impl UnixDatagram {
+    /// Creates a Unix datagram socket bound to the given path.
+    pub fn bind(path: &str) -> io::Result<UnixDatagram> {
+        Self::bind_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    /// Creates a Unix datagram socket bound to an address.
+    pub fn bind_addr(socket_addr: &SocketAddr) -> io::Result<UnixDatagram> {
+        let socket = api::ax_unix_dgram_socket();
+        api::ax_unix_dgram_bind(&socket, socket_addr.0.clone())?;
+        Ok(UnixDatagram(socket))
+    }
+
+    /// Creates a Unix datagram socket which is not bound to any address.
+    pub fn unbound() -> io::Result<UnixDatagram> {
+        Ok(UnixDatagram(api::ax_unix_dgram_socket()))
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
+        let (socket1, socket2) = api::ax_unix_dgram_pair();
+        Ok((UnixDatagram(socket1), UnixDatagram(socket2)))
+    }
+
+    /// Connects the socket to the specified path.
+    ///
+    /// The [`send`] method will send datagrams to this path, and the
+    /// [`recv`] method will only receive datagrams from it.
+    ///
+    /// [`send`]: UnixDatagram::send
+    /// [`recv`]: UnixDatagram::recv
+    pub fn connect(&self, path: &str) -> io::Result<()> {
+        self.connect_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    /// Connects the socket to an address.
+    pub fn connect_addr(&self, socket_addr: &SocketAddr) -> io::Result<()> {
+        api::ax_unix_dgram_connect(&self.0, socket_addr.0.clone())
+    }
+
+    /// Returns the address of this socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_unix_dgram_local_addr(&self.0).map(SocketAddr)
+    }
+
+    /// Returns the address of this socket's peer.
+    ///
+    /// The [`connect`] method will connect the socket to a peer.
+    ///
+    /// [`connect`]: UnixDatagram::connect
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_unix_dgram_peer_addr(&self.0).map(SocketAddr)
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes
+    /// read and the address from whence the data came.
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        api::ax_unix_dgram_recv_from(&self.0, buf).map(|(n, addr)| (n, SocketAddr(addr)))
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes
+    /// read.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        api::ax_unix_dgram_recv(&self.0, buf)
+    }
+
+    /// Sends data on the socket to the specified path. On success, returns
+    /// the number of bytes written.
+    pub fn send_to(&self, buf: &[u8], path: &str) -> io::Result<usize> {
+        self.send_to_addr(buf, &SocketAddr::from_pathname(path)?)
+    }
+
+    /// Sends data on the socket to the specified [`SocketAddr`]. On success,
+    /// returns the number of bytes written.
+    pub fn send_to_addr(&self, buf: &[u8], socket_addr: &SocketAddr) -> io::Result<usize> {
+        api::ax_unix_dgram_send_to(&self.0, buf, socket_addr.0.clone())
+    }
+
+    /// Sends data on the socket to the socket's peer. On success, returns the
+    /// number of bytes written.
+    ///
+    /// The peer address may be set by the [`connect`] method, and this method
+    /// will return an error if the socket has not already been connected.
+    ///
+    /// [`connect`]: UnixDatagram::connect
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        api::ax_unix_dgram_send(&self.0, buf)
+    }
+
+    /// Shuts down the socket, so that pending and future [`recv`] calls
+    /// return immediately.
+    ///
+    /// [`recv`]: UnixDatagram::recv
+    pub fn shutdown(&self) -> io::Result<()> {
+        api::ax_unix_dgram_shutdown(&self.0)
+    }
+
+    /// Sets the read timeout for the socket.
+    ///
+    /// If the provided value is [`None`], then [`recv`] and [`recv_from`]
+    /// calls will block indefinitely. An [`Err`] is returned if the zero
+    /// [`Duration`] is passed to this method.
+    ///
+    /// [`recv`]: UnixDatagram::recv
+    /// [`recv_from`]: UnixDatagram::recv_from
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_unix_dgram_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_unix_dgram_read_timeout(&self.0))
+    }
+
+    /// Sets the write timeout for the socket.
+    ///
+    /// If the provided value is [`None`], then [`send`] and [`send_to`]
+    /// calls will block indefinitely. An [`Err`] is returned if the zero
+    /// [`Duration`] is passed to this method.
+    ///
+    /// [`send`]: UnixDatagram::send
+    /// [`send_to`]: UnixDatagram::send_to
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_unix_dgram_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_unix_dgram_write_timeout(&self.0))
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        api::ax_unix_dgram_set_nonblocking(&self.0, nonblocking)
+    }
+}
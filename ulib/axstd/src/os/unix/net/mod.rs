@@ -0,0 +1,18 @@
+//! Unix domain sockets.
+//!
+//! # Organization
+//!
+//! * [`UnixListener`] and [`UnixStream`] provide functionality for
+//!   connection-oriented communication over Unix domain sockets
+//! * [`UnixDatagram`] provides functionality for datagram communication over
+//!   Unix domain sockets
+//! * [`SocketAddr`] represents the address of a Unix domain socket, which is
+//!   either unnamed, a filesystem path, or a name in the abstract namespace
+
+mod addr;
+mod datagram;
+mod stream;
+
+pub use self::addr::SocketAddr;
+pub use self::datagram::UnixDatagram;
+pub use self::stream::{UnixListener, UnixStream};
@@ -0,0 +1,158 @@
+use super::SocketAddr;
+use crate::io::{self, prelude::*};
+use core::time::Duration;
+
+use arceos_api::net::{self as api, AxUnixStreamHandle};
+
+/// A Unix stream socket.
+pub struct UnixStream(AxUnixStreamHandle);
+
+/// A structure representing a Unix domain socket server.
+pub struct UnixListener(AxUnixStreamHandle);
+
+impl UnixStream {
+    /// Connects to the socket named by `path`.
+    pub fn connect(path: &str) -> io::Result<UnixStream> {
+        Self::connect_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    /// Connects to the socket specified by [`address`].
+    ///
+    /// [`address`]: SocketAddr
+    pub fn connect_addr(socket_addr: &SocketAddr) -> io::Result<UnixStream> {
+        let socket = api::ax_unix_stream_socket();
+        api::ax_unix_stream_connect(&socket, socket_addr.0.clone())?;
+        Ok(UnixStream(socket))
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    ///
+    /// Returns two `UnixStream`s which are connected to each other.
+    pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
+        let (socket1, socket2) = api::ax_unix_stream_pair();
+        Ok((UnixStream(socket1), UnixStream(socket2)))
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_unix_stream_local_addr(&self.0).map(SocketAddr)
+    }
+
+    /// Returns the socket address of the remote half of this connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_unix_stream_peer_addr(&self.0).map(SocketAddr)
+    }
+
+    /// Shuts down the connection.
+    pub fn shutdown(&self) -> io::Result<()> {
+        api::ax_unix_stream_shutdown(&self.0)
+    }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`read`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// [`read`]: Read::read
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_unix_stream_set_read_timeout(&self.0, dur)
+    }
+
+    /// Returns the read timeout of this socket.
+    ///
+    /// If the timeout is [`None`], then [`read`] calls will block indefinitely.
+    ///
+    /// [`read`]: Read::read
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_unix_stream_read_timeout(&self.0))
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is [`None`], then [`write`] calls will block
+    /// indefinitely. An [`Err`] is returned if the zero [`Duration`] is
+    /// passed to this method.
+    ///
+    /// [`write`]: Write::write
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        api::ax_unix_stream_set_write_timeout(&self.0, dur)
+    }
+
+    /// Returns the write timeout of this socket.
+    ///
+    /// If the timeout is [`None`], then [`write`] calls will block indefinitely.
+    ///
+    /// [`write`]: Write::write
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(api::ax_unix_stream_write_timeout(&self.0))
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        api::ax_unix_stream_set_nonblocking(&self.0, nonblocking)
+    }
+}
+
+impl Read for UnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        api::ax_unix_stream_recv(&self.0, buf)
+    }
+}
+
+impl Write for UnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        api::ax_unix_stream_send(&self.0, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl UnixListener {
+    /// Creates a new `UnixListener` bound to the specified socket `path`.
+    ///
+    /// With the `fs` feature enabled, a socket file is created at `path`,
+    /// which is not removed when the listener is dropped.
+    pub fn bind(path: &str) -> io::Result<UnixListener> {
+        Self::bind_addr(&SocketAddr::from_pathname(path)?)
+    }
+
+    /// Creates a new `UnixListener` bound to the specified [`socket address`].
+    ///
+    /// [`socket address`]: SocketAddr
+    pub fn bind_addr(socket_addr: &SocketAddr) -> io::Result<UnixListener> {
+        let socket = api::ax_unix_stream_socket();
+        api::ax_unix_stream_bind(&socket, socket_addr.0.clone())?;
+        api::ax_unix_stream_listen(&socket)?;
+        Ok(UnixListener(socket))
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        api::ax_unix_stream_local_addr(&self.0).map(SocketAddr)
+    }
+
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// This function will block the calling thread until a new Unix connection
+    /// is established. When established, the corresponding [`UnixStream`] and
+    /// the remote peer's address will be returned.
+    pub fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
+        let socket = api::ax_unix_stream_accept(&self.0)?;
+        let addr = api::ax_unix_stream_peer_addr(&socket)?;
+        Ok((UnixStream(socket), SocketAddr(addr)))
+    }
+
+    /// Moves the socket into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, [`accept`] returns a [`WouldBlock`] error if
+    /// there is no pending connection.
+    ///
+    /// [`accept`]: UnixListener::accept
+    /// [`WouldBlock`]: io::Error::WouldBlock
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        api::ax_unix_stream_set_nonblocking(&self.0, nonblocking)
+    }
+}
@@ -0,0 +1,141 @@
+//! A futex-based condition variable.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use arceos_api::task as api;
+
+use super::MutexGuard;
+use crate::time::Instant;
+
+/// A type indicating whether a timed wait on a condition variable returned
+/// due to a time out or not.
+///
+/// It is returned by the [`Condvar::wait_timeout`] method.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait was known to have timed out.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// A Condition Variable, similar to
+/// [`std::sync::Condvar`](https://doc.rust-lang.org/std/sync/struct.Condvar.html).
+///
+/// Each notification bumps a sequence number, waiting threads are blocked on
+/// it as a futex until the sequence number is changed. Spurious wakeups are
+/// possible, so the caller should always check its condition in a loop (or
+/// use [`Condvar::wait_while`]).
+pub struct Condvar {
+    seq: AtomicU32,
+}
+
+impl Condvar {
+    /// Creates a new condition variable which is ready to be waited on and
+    /// notified.
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+        }
+    }
+
+    /// Blocks the current thread until this condition variable receives a
+    /// notification.
+    ///
+    /// This function will atomically unlock the mutex specified (represented
+    /// by `guard`) and block the current thread. When this function returns,
+    /// the lock will have been re-acquired.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.wait_timeout_inner(guard, None).0
+    }
+
+    /// Blocks the current thread until the provided `condition` becomes false.
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// the specified duration.
+    ///
+    /// The returned [`WaitTimeoutResult`] indicates whether the timeout is
+    /// known to have elapsed.
+    pub fn wait_timeout<'a, T: ?Sized>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        self.wait_timeout_inner(guard, Some(dur))
+    }
+
+    /// Waits on this condition variable until the provided `condition`
+    /// becomes false, or the specified duration has elapsed.
+    pub fn wait_timeout_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        dur: Duration,
+        mut condition: F,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let start = Instant::now();
+        while condition(&mut *guard) {
+            let elapsed = start.elapsed();
+            if elapsed >= dur {
+                return (guard, WaitTimeoutResult(true));
+            }
+            guard = self.wait_timeout(guard, dur - elapsed).0;
+        }
+        (guard, WaitTimeoutResult(false))
+    }
+
+    /// Wakes up one blocked thread on this condvar.
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        api::ax_futex_wake(&self.seq, 1);
+    }
+
+    /// Wakes up all blocked threads on this condvar.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        api::ax_futex_wake(&self.seq, u32::MAX);
+    }
+
+    fn wait_timeout_inner<'a, T: ?Sized>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout: Option<Duration>,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let mutex = guard.lock;
+        let seq = self.seq.load(Ordering::Acquire);
+        drop(guard);
+        let timed_out = api::ax_futex_wait(&self.seq, seq, timeout);
+        (mutex.lock(), WaitTimeoutResult(timed_out))
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
@@ -1,20 +1,26 @@
-//! A naïve sleeping mutex.
+//! A futex-based sleeping mutex.
 
 use core::cell::UnsafeCell;
 use core::fmt;
 use core::ops::{Deref, DerefMut};
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
-use arceos_api::task::{self as api, AxWaitQueueHandle};
+use arceos_api::task as api;
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked, and there may be other threads waiting for it.
+const CONTENDED: u32 = 2;
 
 /// A mutual exclusion primitive useful for protecting shared data, similar to
 /// [`std::sync::Mutex`](https://doc.rust-lang.org/std/sync/struct.Mutex.html).
 ///
-/// When the mutex is locked, the current task will block and be put into the
-/// wait queue. When the mutex is unlocked, all tasks waiting on the queue
-/// will be woken up.
+/// The lock state is a futex word. Locking and unlocking an uncontended mutex
+/// are single atomic operations, the current thread only blocks on the futex
+/// when the mutex is locked, and unlocking only wakes up one waiting thread
+/// when there may be any.
 pub struct Mutex<T: ?Sized> {
-    wq: AxWaitQueueHandle,
+    state: AtomicU32,
     owner_id: AtomicU64,
     data: UnsafeCell<T>,
 }
@@ -23,7 +29,7 @@ pub struct Mutex<T: ?Sized> {
 ///
 /// When the guard falls out of scope it will release the lock.
 pub struct MutexGuard<'a, T: ?Sized + 'a> {
-    lock: &'a Mutex<T>,
+    pub(crate) lock: &'a Mutex<T>,
     data: *mut T,
 }
 
@@ -36,7 +42,7 @@ impl<T> Mutex<T> {
     #[inline(always)]
     pub const fn new(data: T) -> Self {
         Self {
-            wq: AxWaitQueueHandle::new(),
+            state: AtomicU32::new(UNLOCKED),
             owner_id: AtomicU64::new(0),
             data: UnsafeCell::new(data),
         }
@@ -61,7 +67,7 @@ impl<T: ?Sized> Mutex<T> {
     /// the instant it is called. Do not use it for synchronization purposes. However, it may be useful as a heuristic.
     #[inline(always)]
     pub fn is_locked(&self) -> bool {
-        self.owner_id.load(Ordering::Relaxed) != 0
+        self.state.load(Ordering::Relaxed) != UNLOCKED
     }
 
     /// Locks the [`Mutex`] and returns a guard that permits access to the inner data.
@@ -70,33 +76,34 @@ impl<T: ?Sized> Mutex<T> {
     /// and the lock will be dropped when the guard falls out of scope.
     pub fn lock(&self) -> MutexGuard<T> {
         let current_id = api::ax_current_task_id();
-        loop {
-            // Can fail to lock even if the spinlock is not locked. May be more efficient than `try_lock`
-            // when called in a loop.
-            match self.owner_id.compare_exchange_weak(
-                0,
-                current_id,
-                Ordering::Acquire,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => break,
-                Err(owner_id) => {
-                    assert_ne!(
-                        owner_id, current_id,
-                        "Thread({}) tried to acquire mutex it already owns.",
-                        current_id,
-                    );
-                    // Wait until the lock looks unlocked before retrying
-                    api::ax_wait_queue_wait(&self.wq, || !self.is_locked(), None);
-                }
-            }
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            self.lock_contended(current_id);
         }
+        self.owner_id.store(current_id, Ordering::Relaxed);
         MutexGuard {
             lock: self,
             data: unsafe { &mut *self.data.get() },
         }
     }
 
+    #[cold]
+    fn lock_contended(&self, current_id: u64) {
+        assert_ne!(
+            self.owner_id.load(Ordering::Relaxed),
+            current_id,
+            "Thread({}) tried to acquire mutex it already owns.",
+            current_id,
+        );
+        // Mark it as contended, so the owner will wake us up on unlock.
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            api::ax_futex_wait(&self.state, CONTENDED, None);
+        }
+    }
+
     /// Try to lock this [`Mutex`], returning a lock guard if successful.
     #[inline(always)]
     pub fn try_lock(&self) -> Option<MutexGuard<T>> {
@@ -104,10 +111,11 @@ impl<T: ?Sized> Mutex<T> {
         // The reason for using a strong compare_exchange is explained here:
         // https://github.com/Amanieu/parking_lot/pull/207#issuecomment-575869107
         if self
-            .owner_id
-            .compare_exchange(0, current_id, Ordering::Acquire, Ordering::Relaxed)
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
             .is_ok()
         {
+            self.owner_id.store(current_id, Ordering::Relaxed);
             Some(MutexGuard {
                 lock: self,
                 data: unsafe { &mut *self.data.get() },
@@ -125,15 +133,17 @@ impl<T: ?Sized> Mutex<T> {
     /// thread. However, this can be useful in some instances for exposing
     /// the lock to FFI that doesn’t know how to deal with RAII.
     pub unsafe fn force_unlock(&self) {
-        let owner_id = self.owner_id.swap(0, Ordering::Release);
+        let owner_id = self.owner_id.swap(0, Ordering::Relaxed);
         let current_id = api::ax_current_task_id();
         assert_eq!(
             owner_id, current_id,
             "Thread({}) tried to release mutex it doesn't own",
             current_id,
         );
-        // wake up one waiting thread.
-        api::ax_wait_queue_wake(&self.wq, 1);
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            // wake up one waiting thread.
+            api::ax_futex_wake(&self.state, 1);
+        }
     }
 
     /// Returns a mutable reference to the underlying data.
@@ -0,0 +1,280 @@
+//! A naïve sleeping readers-writer lock.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use arceos_api::task::{self as api, AxWaitQueueHandle};
+
+/// The lock state when it is held by a writer, otherwise the state is the
+/// number of readers.
+const WRITER: usize = usize::MAX;
+
+/// A readers-writer lock, similar to
+/// [`std::sync::RwLock`](https://doc.rust-lang.org/std/sync/struct.RwLock.html).
+///
+/// It allows a number of readers or at most one writer at any point in time.
+/// Threads that cannot acquire the lock will block and be put into the wait
+/// queue. Note that it does not prevent readers from starving writers.
+pub struct RwLock<T: ?Sized> {
+    wq: AxWaitQueueHandle,
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+/// A guard that provides immutable data access.
+///
+/// When the guard falls out of scope it will decrement the read count,
+/// potentially releasing the lock.
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    data: *const T,
+}
+
+/// A guard that provides mutable data access.
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    data: *mut T,
+}
+
+// Same unsafe impls as `std::sync::RwLock`
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new [`RwLock`] wrapping the supplied data.
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            wq: AxWaitQueueHandle::new(),
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`RwLock`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        // We know statically that there are no outstanding references to
+        // `self` so there's no need to lock.
+        let RwLock { data, .. } = self;
+        data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Returns the number of readers that currently hold the lock.
+    ///
+    /// This function provides no synchronization guarantees and so its result
+    /// should be considered 'out of date' the instant it is called.
+    #[inline(always)]
+    pub fn reader_count(&self) -> usize {
+        match self.state.load(Ordering::Relaxed) {
+            WRITER => 0,
+            readers => readers,
+        }
+    }
+
+    /// Returns the number of writers that currently hold the lock, which is
+    /// either `0` or `1`.
+    ///
+    /// This function provides no synchronization guarantees and so its result
+    /// should be considered 'out of date' the instant it is called.
+    #[inline(always)]
+    pub fn writer_count(&self) -> usize {
+        (self.state.load(Ordering::Relaxed) == WRITER) as usize
+    }
+
+    fn try_read_raw(&self) -> bool {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state >= WRITER - 1 {
+                // held by a writer, or too many readers
+                return false;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    fn try_write_raw(&self) -> bool {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Locks this [`RwLock`] with shared read access, blocking the current
+    /// thread until it can be acquired.
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        api::ax_wait_queue_wait(&self.wq, || self.try_read_raw(), None);
+        RwLockReadGuard {
+            lock: self,
+            data: self.data.get(),
+        }
+    }
+
+    /// Attempts to acquire this [`RwLock`] with shared read access, returning
+    /// a guard if successful.
+    #[inline(always)]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        if self.try_read_raw() {
+            Some(RwLockReadGuard {
+                lock: self,
+                data: self.data.get(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Locks this [`RwLock`] with exclusive write access, blocking the current
+    /// thread until it can be acquired.
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        api::ax_wait_queue_wait(&self.wq, || self.try_write_raw(), None);
+        RwLockWriteGuard {
+            lock: self,
+            data: self.data.get(),
+        }
+    }
+
+    /// Attempts to lock this [`RwLock`] with exclusive write access, returning
+    /// a guard if successful.
+    #[inline(always)]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        if self.try_write_raw() {
+            Some(RwLockWriteGuard {
+                lock: self,
+                data: self.data.get(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Force decrement the reader count.
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if there are no readers. However, this can
+    /// be useful in some instances for exposing the lock to FFI that doesn’t
+    /// know how to deal with RAII.
+    pub unsafe fn force_read_decrement(&self) {
+        let state = self.state.fetch_sub(1, Ordering::Release);
+        assert!(
+            state != 0 && state != WRITER,
+            "tried to release a read lock that is not held"
+        );
+        if state == 1 {
+            // the last reader, wake up waiting writers
+            api::ax_wait_queue_wake(&self.wq, u32::MAX);
+        }
+    }
+
+    /// Force unlock exclusive write access.
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if the lock is not held by a writer.
+    /// However, this can be useful in some instances for exposing the lock to
+    /// FFI that doesn’t know how to deal with RAII.
+    pub unsafe fn force_write_unlock(&self) {
+        let state = self.state.swap(0, Ordering::Release);
+        assert_eq!(
+            state, WRITER,
+            "tried to release a write lock that is not held"
+        );
+        api::ax_wait_queue_wake(&self.wq, u32::MAX);
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`RwLock`] mutably, no actual locking needs
+    /// to take place -- the mutable borrow statically guarantees no locks
+    /// exist.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        // We know statically that there are no other references to `self`, so
+        // there's no need to lock the inner lock.
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<T: ?Sized + Default> Default for RwLock<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => write!(f, "RwLock {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "RwLock {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that there are no writers
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only we are referencing data
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        // We know statically that only we are referencing data
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    /// The dropping of the [`RwLockReadGuard`] will decrement the read count.
+    fn drop(&mut self) {
+        unsafe { self.lock.force_read_decrement() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    /// The dropping of the [`RwLockWriteGuard`] will release the lock.
+    fn drop(&mut self) {
+        unsafe { self.lock.force_write_unlock() }
+    }
+}
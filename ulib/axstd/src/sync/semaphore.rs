@@ -0,0 +1,78 @@
+//! A counting semaphore.
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use arceos_api::task::{self as api, AxWaitQueueHandle};
+
+/// A counting semaphore.
+///
+/// It maintains a number of permits. [`Semaphore::acquire`] blocks the
+/// current thread until a permit is available and then takes it,
+/// [`Semaphore::release`] adds a permit and wakes up a waiting thread.
+pub struct Semaphore {
+    wq: AxWaitQueueHandle,
+    count: AtomicUsize,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given number of permits.
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            wq: AxWaitQueueHandle::new(),
+            count: AtomicUsize::new(permits),
+        }
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Tries to take a permit without blocking, returns `true` if successful.
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+        loop {
+            if count == 0 {
+                return false;
+            }
+            match self.count.compare_exchange_weak(
+                count,
+                count - 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(c) => count = c,
+            }
+        }
+    }
+
+    /// Takes a permit, blocks the current thread until one is available.
+    pub fn acquire(&self) {
+        api::ax_wait_queue_wait(&self.wq, || self.try_acquire(), None);
+    }
+
+    /// Takes a permit, blocks the current thread until one is available or the
+    /// given duration has elapsed.
+    ///
+    /// Returns `true` if a permit is taken, `false` if timed out.
+    pub fn acquire_timeout(&self, dur: Duration) -> bool {
+        self.try_acquire() || !api::ax_wait_queue_wait(&self.wq, || self.try_acquire(), Some(dur))
+    }
+
+    /// Adds a permit to the semaphore and wakes up one waiting thread.
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        api::ax_wait_queue_wake(&self.wq, 1);
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}